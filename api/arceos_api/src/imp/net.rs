@@ -2,6 +2,7 @@ use crate::io::AxPollState;
 use axerrno::AxResult;
 use axnet::{UdpSocket, TcpSocket};
 use core::net::{IpAddr, SocketAddr};
+use core::time::Duration;
 
 /// A handle to a TCP socket.
 pub struct AxTcpSocketHandle(TcpSocket);
@@ -64,6 +65,61 @@ pub fn ax_tcp_shutdown(socket: &AxTcpSocketHandle) -> AxResult {
     socket.0.shutdown()
 }
 
+pub fn ax_tcp_set_reuse_address(socket: &AxTcpSocketHandle, reuse: bool) -> AxResult {
+    socket.0.set_reuse_address(reuse);
+    Ok(())
+}
+
+pub fn ax_tcp_set_nodelay(socket: &AxTcpSocketHandle, nodelay: bool) -> AxResult {
+    socket.0.set_nodelay(nodelay);
+    Ok(())
+}
+
+pub fn ax_tcp_nodelay(socket: &AxTcpSocketHandle) -> AxResult<bool> {
+    Ok(socket.0.nodelay())
+}
+
+pub fn ax_tcp_set_keepalive(socket: &AxTcpSocketHandle, interval: Option<Duration>) -> AxResult {
+    socket.0.set_keepalive(interval)
+}
+
+pub fn ax_tcp_keepalive(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.keepalive())
+}
+
+pub fn ax_tcp_set_linger(socket: &AxTcpSocketHandle, linger: Option<Duration>) -> AxResult {
+    socket.0.set_linger(linger);
+    Ok(())
+}
+
+pub fn ax_tcp_linger(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.linger())
+}
+
+pub fn ax_tcp_set_ttl(socket: &AxTcpSocketHandle, ttl: u32) -> AxResult {
+    socket.0.set_ttl(to_ttl(ttl)?)
+}
+
+pub fn ax_tcp_ttl(socket: &AxTcpSocketHandle) -> AxResult<u32> {
+    Ok(socket.0.ttl() as u32)
+}
+
+pub fn ax_tcp_set_read_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_read_timeout(timeout)
+}
+
+pub fn ax_tcp_read_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.read_timeout())
+}
+
+pub fn ax_tcp_set_write_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_write_timeout(timeout)
+}
+
+pub fn ax_tcp_write_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.write_timeout())
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // UDP socket
 ////////////////////////////////////////////////////////////////////////////////
@@ -117,6 +173,30 @@ pub fn ax_udp_poll(socket: &AxUdpSocketHandle) -> AxResult<AxPollState> {
     socket.0.poll()
 }
 
+pub fn ax_udp_set_ttl(socket: &AxUdpSocketHandle, ttl: u32) -> AxResult {
+    socket.0.set_ttl(to_ttl(ttl)?)
+}
+
+pub fn ax_udp_ttl(socket: &AxUdpSocketHandle) -> AxResult<u32> {
+    Ok(socket.0.ttl() as u32)
+}
+
+pub fn ax_udp_set_read_timeout(socket: &AxUdpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_read_timeout(timeout)
+}
+
+pub fn ax_udp_read_timeout(socket: &AxUdpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.read_timeout())
+}
+
+pub fn ax_udp_set_write_timeout(socket: &AxUdpSocketHandle, timeout: Option<Duration>) -> AxResult {
+    socket.0.set_write_timeout(timeout)
+}
+
+pub fn ax_udp_write_timeout(socket: &AxUdpSocketHandle) -> AxResult<Option<Duration>> {
+    Ok(socket.0.write_timeout())
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Miscellaneous
 ////////////////////////////////////////////////////////////////////////////////
@@ -129,3 +209,7 @@ pub fn ax_poll_interfaces() -> AxResult {
     axnet::poll_interfaces();
     Ok(())
 }
+
+fn to_ttl(ttl: u32) -> AxResult<u8> {
+    u8::try_from(ttl).map_err(|_| axerrno::ax_err_type!(InvalidInput, "TTL out of range"))
+}
@@ -18,9 +18,15 @@ pub fn ax_yield_now() {
 
 pub fn ax_exit(_exit_code: i32) -> ! {
     #[cfg(feature = "multitask")]
-    axtask::exit(_exit_code);
+    {
+        // The exit of the main task shuts down the whole system.
+        if axtask::current().is_init() {
+            axruntime::terminate();
+        }
+        axtask::exit(_exit_code);
+    }
     #[cfg(not(feature = "multitask"))]
-    axhal::misc::terminate();
+    axruntime::terminate();
 }
 
 cfg_task! {
@@ -75,7 +81,7 @@ cfg_task! {
     }
 
     pub fn ax_set_current_priority(prio: isize) -> crate::AxResult {
-        if axtask::set_priority(prio) {
+        if axtask::set_priority(axtask::current().as_task_ref(), prio) {
             Ok(())
         } else {
             axerrno::ax_err!(
@@ -96,6 +102,22 @@ cfg_task! {
         }
     }
 
+    pub fn ax_set_priority(task: &AxTaskHandle, prio: isize) -> crate::AxResult {
+        if axtask::set_priority(&task.inner, prio) {
+            Ok(())
+        } else {
+            axerrno::ax_err!(BadState, "ax_set_priority: failed to set task priority")
+        }
+    }
+
+    pub fn ax_set_affinity(task: &AxTaskHandle, cpumask: AxCpuMask) -> crate::AxResult {
+        if axtask::set_affinity(&task.inner, cpumask) {
+            Ok(())
+        } else {
+            axerrno::ax_err!(BadState, "ax_set_affinity: failed to set task affinity")
+        }
+    }
+
     pub fn ax_wait_queue_wait(wq: &AxWaitQueueHandle, timeout: Option<Duration>) -> bool {
         #[cfg(feature = "irq")]
         if let Some(dur) = timeout {
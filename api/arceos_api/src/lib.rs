@@ -158,6 +158,11 @@ pub mod task {
         pub fn ax_set_current_priority(prio: isize) -> crate::AxResult;
         /// Sets the cpu affinity of the current task.
         pub fn ax_set_current_affinity(cpumask: AxCpuMask) -> crate::AxResult;
+        /// Sets the priority of the given task.
+        pub fn ax_set_priority(task: &AxTaskHandle, prio: isize) -> crate::AxResult;
+        /// Sets the cpu affinity of the given task, the task will be migrated
+        /// if it is not allowed to run on its current CPU any more.
+        pub fn ax_set_affinity(task: &AxTaskHandle, cpumask: AxCpuMask) -> crate::AxResult;
         /// Blocks the current task and put it into the wait queue, until
         /// other tasks notify the wait queue, or the the given duration has
         /// elapsed (if specified).
@@ -255,6 +260,15 @@ pub mod fs {
         ///
         /// It will delete the original file if `old` already exists.
         pub fn ax_rename(old: &str, new: &str) -> AxResult;
+        /// Creates a symbolic link at `link` pointing to `original`.
+        pub fn ax_symlink(original: &str, link: &str) -> AxResult;
+        /// Returns the target of the symbolic link at the given path.
+        pub fn ax_read_link(path: &str) -> AxResult<alloc::string::String>;
+        /// Creates a hard link at `link` to the existing file `original`.
+        pub fn ax_hard_link(original: &str, link: &str) -> AxResult;
+        /// Returns attributes of the file at the given path, without
+        /// following a symbolic link at the last component.
+        pub fn ax_symlink_attr(path: &str) -> AxResult<AxFileAttr>;
 
         /// Returns the current working directory.
         pub fn ax_current_dir() -> AxResult<alloc::string::String>;
@@ -267,6 +281,7 @@ pub mod fs {
 pub mod net {
     use crate::{AxResult, io::AxPollState};
     use core::net::{IpAddr, SocketAddr};
+    use core::time::Duration;
 
     define_api_type! {
         @cfg "net";
@@ -310,6 +325,36 @@ pub mod net {
         /// Closes the connection on the TCP socket.
         pub fn ax_tcp_shutdown(socket: &AxTcpSocketHandle) -> AxResult;
 
+        /// Sets whether the TCP socket can listen on a port that connections
+        /// are still on.
+        pub fn ax_tcp_set_reuse_address(socket: &AxTcpSocketHandle, reuse: bool) -> AxResult;
+        /// Disables or enables the Nagle algorithm on the TCP socket.
+        pub fn ax_tcp_set_nodelay(socket: &AxTcpSocketHandle, nodelay: bool) -> AxResult;
+        /// Returns whether the Nagle algorithm is disabled on the TCP socket.
+        pub fn ax_tcp_nodelay(socket: &AxTcpSocketHandle) -> AxResult<bool>;
+        /// Sets the interval of keep-alive packets on the TCP socket, or
+        /// disables them with `None`.
+        pub fn ax_tcp_set_keepalive(socket: &AxTcpSocketHandle, interval: Option<Duration>) -> AxResult;
+        /// Returns the interval of keep-alive packets on the TCP socket.
+        pub fn ax_tcp_keepalive(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>>;
+        /// Sets how long closing the TCP socket waits for the queued data to
+        /// be sent.
+        pub fn ax_tcp_set_linger(socket: &AxTcpSocketHandle, linger: Option<Duration>) -> AxResult;
+        /// Returns the linger time of the TCP socket.
+        pub fn ax_tcp_linger(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>>;
+        /// Sets the TTL of packets sent from the TCP socket.
+        pub fn ax_tcp_set_ttl(socket: &AxTcpSocketHandle, ttl: u32) -> AxResult;
+        /// Returns the TTL of packets sent from the TCP socket.
+        pub fn ax_tcp_ttl(socket: &AxTcpSocketHandle) -> AxResult<u32>;
+        /// Sets the timeout of receives and accepts on the TCP socket.
+        pub fn ax_tcp_set_read_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult;
+        /// Returns the timeout of receives and accepts on the TCP socket.
+        pub fn ax_tcp_read_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>>;
+        /// Sets the timeout of sends on the TCP socket.
+        pub fn ax_tcp_set_write_timeout(socket: &AxTcpSocketHandle, timeout: Option<Duration>) -> AxResult;
+        /// Returns the timeout of sends on the TCP socket.
+        pub fn ax_tcp_write_timeout(socket: &AxTcpSocketHandle) -> AxResult<Option<Duration>>;
+
         // UDP socket
 
         /// Creates a new UDP socket.
@@ -346,6 +391,19 @@ pub mod net {
         /// Returns whether the UDP socket is readable or writable.
         pub fn ax_udp_poll(socket: &AxUdpSocketHandle) -> AxResult<AxPollState>;
 
+        /// Sets the TTL of packets sent from the UDP socket.
+        pub fn ax_udp_set_ttl(socket: &AxUdpSocketHandle, ttl: u32) -> AxResult;
+        /// Returns the TTL of packets sent from the UDP socket.
+        pub fn ax_udp_ttl(socket: &AxUdpSocketHandle) -> AxResult<u32>;
+        /// Sets the timeout of receives on the UDP socket.
+        pub fn ax_udp_set_read_timeout(socket: &AxUdpSocketHandle, timeout: Option<Duration>) -> AxResult;
+        /// Returns the timeout of receives on the UDP socket.
+        pub fn ax_udp_read_timeout(socket: &AxUdpSocketHandle) -> AxResult<Option<Duration>>;
+        /// Sets the timeout of sends on the UDP socket.
+        pub fn ax_udp_set_write_timeout(socket: &AxUdpSocketHandle, timeout: Option<Duration>) -> AxResult;
+        /// Returns the timeout of sends on the UDP socket.
+        pub fn ax_udp_write_timeout(socket: &AxUdpSocketHandle) -> AxResult<Option<Duration>>;
+
         // Miscellaneous
 
         /// Resolves the host name to a list of IP addresses.
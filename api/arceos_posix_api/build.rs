@@ -5,9 +5,9 @@ fn main() {
         // TODO: generate size and initial content automatically.
         let (mutex_size, mutex_init) = if cfg!(feature = "multitask") {
             if cfg!(feature = "smp") {
-                (6, "{0, 0, 8, 0, 0, 0}") // core::mem::transmute::<_, [usize; 6]>(axsync::Mutex::new(()))
+                (7, "{0, 0, 8, 0, 0, 0, 0}") // core::mem::transmute::<_, [usize; 7]>(axsync::Mutex::new(()))
             } else {
-                (5, "{0, 8, 0, 0, 0}") // core::mem::transmute::<_, [usize; 5]>(axsync::Mutex::new(()))
+                (6, "{0, 8, 0, 0, 0, 0}") // core::mem::transmute::<_, [usize; 6]>(axsync::Mutex::new(()))
             }
         } else {
             (1, "{0}")
@@ -53,6 +53,8 @@ typedef struct {{
             "clockid_t",
             "rlimit",
             "aibuf",
+            "flock",
+            "linger",
         ];
         let allow_vars = [
             "CLOCK_.*",
@@ -60,6 +62,11 @@ typedef struct {{
             "AF_.*",
             "SOCK_.*",
             "IPPROTO_.*",
+            "SOL_.*",
+            "SO_.*",
+            "TCP_.*",
+            "IP_.*",
+            "IPV6_.*",
             "FD_.*",
             "F_.*",
             "_SC_.*",
@@ -68,6 +75,8 @@ typedef struct {{
             "RLIMIT_.*",
             "EAI_.*",
             "MAXADDRS",
+            "PTHREAD_PRIO_.*",
+            "LOCK_.*",
         ];
 
         #[derive(Debug)]
@@ -99,6 +99,9 @@ pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
 /// Manipulate file descriptor.
 ///
 /// TODO: `SET/GET` command is ignored, hard-code stdin/stdout
+///
+/// Advisory record locks (`F_GETLK`, `F_SETLK` and `F_SETLKW`) are supported
+/// on regular files.
 pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
     debug!("sys_fcntl <= fd: {} cmd: {} arg: {}", fd, cmd, arg);
     syscall_body!(sys_fcntl, {
@@ -115,6 +118,10 @@ pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
                 get_file_like(fd)?.set_nonblocking(arg & (ctypes::O_NONBLOCK as usize) > 0)?;
                 Ok(0)
             }
+            #[cfg(feature = "fs")]
+            ctypes::F_GETLK | ctypes::F_SETLK | ctypes::F_SETLKW => {
+                super::fs::fcntl_lock(fd, cmd as u32, arg as *mut ctypes::flock)
+            }
             _ => {
                 warn!("unsupported fcntl parameters: cmd {}", cmd);
                 Ok(0)
@@ -1,8 +1,9 @@
 use alloc::sync::Arc;
 use core::ffi::{c_char, c_int};
+use core::ops::Range;
 
-use axerrno::{LinuxError, LinuxResult};
-use axfs::fops::OpenOptions;
+use axerrno::{AxError, LinuxError, LinuxResult};
+use axfs::fops::{FileAttr, LockKind, OpenOptions};
 use axio::{PollState, SeekFrom};
 use axsync::Mutex;
 
@@ -42,21 +43,7 @@ impl FileLike for File {
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
-        let metadata = self.inner.lock().get_attr()?;
-        let ty = metadata.file_type() as u8;
-        let perm = metadata.perm().bits() as u32;
-        let st_mode = ((ty as u32) << 12) | perm;
-        Ok(ctypes::stat {
-            st_ino: 1,
-            st_nlink: 1,
-            st_mode,
-            st_uid: 1000,
-            st_gid: 1000,
-            st_size: metadata.size() as _,
-            st_blocks: metadata.blocks() as _,
-            st_blksize: 512,
-            ..Default::default()
-        })
+        Ok(attr_to_stat(&self.inner.lock().get_attr()?))
     }
 
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
@@ -75,6 +62,46 @@ impl FileLike for File {
     }
 }
 
+/// Converts file attributes to a [`ctypes::stat`].
+fn attr_to_stat(attr: &FileAttr) -> ctypes::stat {
+    let ty = attr.file_type() as u8;
+    let perm = attr.perm().bits() as u32;
+    let st_mode = ((ty as u32) << 12) | perm;
+    ctypes::stat {
+        st_ino: 1,
+        st_nlink: 1,
+        st_mode,
+        st_uid: 1000,
+        st_gid: 1000,
+        st_size: attr.size() as _,
+        st_blocks: attr.blocks() as _,
+        st_blksize: 512,
+        st_atime: to_timespec(attr.accessed()),
+        st_mtime: to_timespec(attr.modified()),
+        st_ctime: to_timespec(attr.created()),
+        ..Default::default()
+    }
+}
+
+/// Converts an error of a path operation to [`LinuxError`], reporting too
+/// many levels of symbolic links as `ELOOP` and links or renames across
+/// filesystems as `EXDEV`.
+fn path_err(err: AxError) -> LinuxError {
+    if err == axfs::api::SYMLINK_LOOP_ERROR {
+        LinuxError::ELOOP
+    } else if err == axfs::api::CROSS_DEVICE_ERROR {
+        LinuxError::EXDEV
+    } else {
+        err.into()
+    }
+}
+
+/// Converts a file time since the UNIX epoch to a [`ctypes::timespec`], which
+/// is zero if the filesystem does not record the time.
+fn to_timespec(time: Option<core::time::Duration>) -> ctypes::timespec {
+    time.map_or_else(Default::default, Into::into)
+}
+
 /// Convert open flags to [`OpenOptions`].
 fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
     let flags = flags as u32;
@@ -111,7 +138,7 @@ pub fn sys_open(filename: *const c_char, flags: c_int, mode: ctypes::mode_t) ->
     debug!("sys_open <= {:?} {:#o} {:#o}", filename, flags, mode);
     syscall_body!(sys_open, {
         let options = flags_to_options(flags, mode);
-        let file = axfs::fops::File::open(filename?, &options)?;
+        let file = axfs::fops::File::open(filename?, &options).map_err(path_err)?;
         File::new(file).add_to_fd_table()
     })
 }
@@ -145,7 +172,7 @@ pub unsafe fn sys_stat(path: *const c_char, buf: *mut ctypes::stat) -> c_int {
         }
         let mut options = OpenOptions::new();
         options.read(true);
-        let file = axfs::fops::File::open(path?, &options)?;
+        let file = axfs::fops::File::open(path?, &options).map_err(path_err)?;
         let st = File::new(file).stat()?;
         unsafe { *buf = st };
         Ok(0)
@@ -177,7 +204,8 @@ pub unsafe fn sys_lstat(path: *const c_char, buf: *mut ctypes::stat) -> ctypes::
         if buf.is_null() {
             return Err(LinuxError::EFAULT);
         }
-        unsafe { *buf = Default::default() }; // TODO
+        let attr = axfs::fops::symlink_attr(path?).map_err(path_err)?;
+        unsafe { *buf = attr_to_stat(&attr) };
         Ok(0)
     })
 }
@@ -212,7 +240,168 @@ pub fn sys_rename(old: *const c_char, new: *const c_char) -> c_int {
         let old_path = char_ptr_to_str(old)?;
         let new_path = char_ptr_to_str(new)?;
         debug!("sys_rename <= old: {:?}, new: {:?}", old_path, new_path);
-        axfs::api::rename(old_path, new_path)?;
+        axfs::api::rename(old_path, new_path).map_err(path_err)?;
+        Ok(0)
+    })
+}
+
+/// Create a symbolic link `linkpath` which contains the string `target`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    syscall_body!(sys_symlink, {
+        let target = char_ptr_to_str(target)?;
+        let linkpath = char_ptr_to_str(linkpath)?;
+        debug!("sys_symlink <= {:?} -> {:?}", linkpath, target);
+        axfs::api::symlink(target, linkpath).map_err(path_err)?;
+        Ok(0)
+    })
+}
+
+/// Read the target of the symbolic link `path` into `buf`, which is not
+/// null-terminated and is truncated if `bufsiz` is too small.
+///
+/// Return the number of bytes placed in `buf`.
+pub fn sys_readlink(path: *const c_char, buf: *mut c_char, bufsiz: usize) -> ctypes::ssize_t {
+    let path = char_ptr_to_str(path);
+    debug!("sys_readlink <= {:?} {:#x} {}", path, buf as usize, bufsiz);
+    syscall_body!(sys_readlink, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let target = axfs::api::read_link(path?).map_err(path_err)?;
+        let len = target.len().min(bufsiz);
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, len) };
+        dst.copy_from_slice(&target.as_bytes()[..len]);
+        Ok(len as ctypes::ssize_t)
+    })
+}
+
+/// Create a new hard link `new` to the existing file `old`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_link(old: *const c_char, new: *const c_char) -> c_int {
+    syscall_body!(sys_link, {
+        let old_path = char_ptr_to_str(old)?;
+        let new_path = char_ptr_to_str(new)?;
+        debug!("sys_link <= old: {:?}, new: {:?}", old_path, new_path);
+        axfs::api::hard_link(old_path, new_path).map_err(path_err)?;
         Ok(0)
     })
 }
+
+/// Apply or remove an advisory lock on the whole file `fd`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+pub fn sys_flock(fd: c_int, operation: c_int) -> c_int {
+    debug!("sys_flock <= {} {:#x}", fd, operation);
+    syscall_body!(sys_flock, {
+        let operation = operation as u32;
+        let kind = match operation & !ctypes::LOCK_NB {
+            ctypes::LOCK_SH => Some(LockKind::Shared),
+            ctypes::LOCK_EX => Some(LockKind::Exclusive),
+            ctypes::LOCK_UN => None,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        // do not hold the file while waiting for the lock
+        let owner = File::from_fd(fd)?.inner.lock().lock_owner();
+        owner.flock(kind, operation & ctypes::LOCK_NB == 0)?;
+        Ok(0)
+    })
+}
+
+/// Perform the device-specific request `request` with the argument `arg` on
+/// the file `fd`, such as `RTC_RD_TIME` on `/dev/rtc`.
+///
+/// Return the result of the request. Return `ENOTTY` if the file is not a
+/// device that supports it.
+pub fn sys_ioctl(fd: c_int, request: c_int, arg: usize) -> c_int {
+    debug!("sys_ioctl <= {} {:#x} {:#x}", fd, request, arg);
+    syscall_body!(sys_ioctl, {
+        let file = File::from_fd(fd).map_err(|e| match e {
+            LinuxError::EINVAL => LinuxError::ENOTTY,
+            e => e,
+        })?;
+        let ret = match file.inner.lock().ioctl(request as u32, arg) {
+            Err(AxError::Unsupported) => return Err(LinuxError::ENOTTY),
+            ret => ret?,
+        };
+        Ok(ret as c_int)
+    })
+}
+
+/// Get, apply or remove an advisory lock on a byte range of the file `fd`,
+/// which is the `F_GETLK`, `F_SETLK` or `F_SETLKW` command `cmd` of `fcntl`.
+///
+/// `F_SETLKW` fails with `EDEADLK` if the wait would never end.
+pub(super) fn fcntl_lock(fd: c_int, cmd: u32, lock: *mut ctypes::flock) -> LinuxResult<c_int> {
+    if lock.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let lock = unsafe { &mut *lock };
+    let kind = match lock.l_type as u32 {
+        ctypes::F_RDLCK => Some(LockKind::Shared),
+        ctypes::F_WRLCK => Some(LockKind::Exclusive),
+        ctypes::F_UNLCK if cmd != ctypes::F_GETLK => None,
+        _ => return Err(LinuxError::EINVAL),
+    };
+
+    let file = File::from_fd(fd)?;
+    let mut inner = file.inner.lock();
+    let base = match lock.l_whence {
+        0 => 0,
+        1 => inner.seek(SeekFrom::Current(0))?,
+        2 => inner.get_attr()?.size(),
+        _ => return Err(LinuxError::EINVAL),
+    };
+    let range = lock_range(base, lock.l_start, lock.l_len)?;
+    let owner = inner.lock_owner();
+    drop(inner);
+
+    let Some(kind) = kind.filter(|_| cmd == ctypes::F_GETLK) else {
+        return match owner.set_lock(kind, range, cmd == ctypes::F_SETLKW) {
+            Err(e) if e == axfs::fops::DEADLOCK_ERROR => Err(LinuxError::EDEADLK),
+            ret => ret.map(|_| 0).map_err(LinuxError::from),
+        };
+    };
+    match owner.get_lock(kind, range) {
+        Some(held) => {
+            lock.l_type = match held.kind {
+                LockKind::Shared => ctypes::F_RDLCK,
+                LockKind::Exclusive => ctypes::F_WRLCK,
+            } as _;
+            lock.l_whence = 0;
+            lock.l_start = held.range.start as _;
+            lock.l_len = match held.range.end {
+                u64::MAX => 0,
+                end => (end - held.range.start) as _,
+            };
+            // the lock is owned by an opened file, not by a process
+            lock.l_pid = -1;
+        }
+        None => lock.l_type = ctypes::F_UNLCK as _,
+    }
+    Ok(0)
+}
+
+/// Converts the start and the length of a `struct flock`, where the start is
+/// relative to `base`, to the range of locked bytes.
+fn lock_range(base: u64, start: ctypes::off_t, len: ctypes::off_t) -> LinuxResult<Range<u64>> {
+    let start = (base as ctypes::off_t)
+        .checked_add(start)
+        .ok_or(LinuxError::EOVERFLOW)?;
+    let (start, end) = match len {
+        // the lock extends to the end of the file, however large it grows
+        0 => (start, u64::MAX),
+        len if len > 0 => {
+            let end = start.checked_add(len).ok_or(LinuxError::EOVERFLOW)?;
+            (start, end as u64)
+        }
+        // a negative length covers the bytes before the start
+        len => (start + len, start as u64),
+    };
+    if start < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(start as u64..end)
+}
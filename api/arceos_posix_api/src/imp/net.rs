@@ -1,7 +1,8 @@
 use alloc::{sync::Arc, vec, vec::Vec};
 use core::ffi::{c_char, c_int, c_void};
 use core::mem::size_of;
-use core::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use core::time::Duration;
 
 use axerrno::{LinuxError, LinuxResult};
 use axio::PollState;
@@ -194,14 +195,64 @@ impl From<ctypes::sockaddr_in> for SocketAddrV4 {
     }
 }
 
-fn into_sockaddr(addr: SocketAddr) -> (ctypes::sockaddr, ctypes::socklen_t) {
+impl From<SocketAddrV6> for ctypes::sockaddr_in6 {
+    fn from(addr: SocketAddrV6) -> ctypes::sockaddr_in6 {
+        ctypes::sockaddr_in6 {
+            sin6_family: ctypes::AF_INET6 as u16,
+            sin6_port: addr.port().to_be(),
+            sin6_flowinfo: addr.flowinfo().to_be(),
+            sin6_addr: ctypes::in6_addr {
+                __in6_union: ctypes::in6_addr__bindgen_ty_1 {
+                    __s6_addr: addr.ip().octets(),
+                },
+            },
+            sin6_scope_id: addr.scope_id(),
+        }
+    }
+}
+
+impl From<ctypes::sockaddr_in6> for SocketAddrV6 {
+    fn from(addr: ctypes::sockaddr_in6) -> SocketAddrV6 {
+        SocketAddrV6::new(
+            Ipv6Addr::from(unsafe { addr.sin6_addr.__in6_union.__s6_addr }),
+            u16::from_be(addr.sin6_port),
+            u32::from_be(addr.sin6_flowinfo),
+            addr.sin6_scope_id,
+        )
+    }
+}
+
+/// Writes `addr` to the buffer `dst` of `*addrlen` bytes, and sets `*addrlen`
+/// to the actual length of the address.
+///
+/// The address is truncated if the buffer is too small, as Linux does.
+unsafe fn write_sockaddr(
+    addr: SocketAddr,
+    dst: *mut ctypes::sockaddr,
+    addrlen: *mut ctypes::socklen_t,
+) {
     debug!("    Sockaddr: {}", addr);
-    match addr {
-        SocketAddr::V4(addr) => (
-            unsafe { *(&ctypes::sockaddr_in::from(addr) as *const _ as *const ctypes::sockaddr) },
-            size_of::<ctypes::sockaddr>() as _,
-        ),
-        SocketAddr::V6(_) => panic!("IPv6 is not supported"),
+    let (sin, sin6);
+    let (src, len) = match addr {
+        SocketAddr::V4(addr) => {
+            sin = ctypes::sockaddr_in::from(addr);
+            (
+                &sin as *const _ as *const u8,
+                size_of::<ctypes::sockaddr_in>(),
+            )
+        }
+        SocketAddr::V6(addr) => {
+            sin6 = ctypes::sockaddr_in6::from(addr);
+            (
+                &sin6 as *const _ as *const u8,
+                size_of::<ctypes::sockaddr_in6>(),
+            )
+        }
+    };
+    unsafe {
+        let copy_len = len.min(*addrlen as usize);
+        core::ptr::copy_nonoverlapping(src, dst as *mut u8, copy_len);
+        *addrlen = len as _;
     }
 }
 
@@ -212,16 +263,20 @@ fn from_sockaddr(
     if addr.is_null() {
         return Err(LinuxError::EFAULT);
     }
-    if addrlen != size_of::<ctypes::sockaddr>() as _ {
+    if (addrlen as usize) < size_of::<ctypes::sockaddr>() {
         return Err(LinuxError::EINVAL);
     }
 
-    let mid = unsafe { *(addr as *const ctypes::sockaddr_in) };
-    if mid.sin_family != ctypes::AF_INET as u16 {
-        return Err(LinuxError::EINVAL);
-    }
-
-    let res = SocketAddr::V4(mid.into());
+    let res = match unsafe { (*addr).sa_family } as u32 {
+        ctypes::AF_INET => SocketAddr::V4(unsafe { *(addr as *const ctypes::sockaddr_in) }.into()),
+        ctypes::AF_INET6 => {
+            if (addrlen as usize) < size_of::<ctypes::sockaddr_in6>() {
+                return Err(LinuxError::EINVAL);
+            }
+            SocketAddr::V6(unsafe { *(addr as *const ctypes::sockaddr_in6) }.into())
+        }
+        _ => return Err(LinuxError::EINVAL),
+    };
     debug!("    load sockaddr:{:#x} => {:?}", addr as usize, res);
     Ok(res)
 }
@@ -234,12 +289,13 @@ pub fn sys_socket(domain: c_int, socktype: c_int, protocol: c_int) -> c_int {
     let (domain, socktype, protocol) = (domain as u32, socktype as u32, protocol as u32);
     syscall_body!(sys_socket, {
         match (domain, socktype, protocol) {
-            (ctypes::AF_INET, ctypes::SOCK_STREAM, ctypes::IPPROTO_TCP)
-            | (ctypes::AF_INET, ctypes::SOCK_STREAM, 0) => {
+            // sockets are dual-stack, so `AF_INET6` ones can also use IPv4
+            (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_STREAM, ctypes::IPPROTO_TCP)
+            | (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_STREAM, 0) => {
                 Socket::Tcp(Mutex::new(TcpSocket::new())).add_to_fd_table()
             }
-            (ctypes::AF_INET, ctypes::SOCK_DGRAM, ctypes::IPPROTO_UDP)
-            | (ctypes::AF_INET, ctypes::SOCK_DGRAM, 0) => {
+            (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_DGRAM, ctypes::IPPROTO_UDP)
+            | (ctypes::AF_INET | ctypes::AF_INET6, ctypes::SOCK_DGRAM, 0) => {
                 Socket::Udp(Mutex::new(UdpSocket::new())).add_to_fd_table()
             }
             _ => Err(LinuxError::EINVAL),
@@ -356,9 +412,7 @@ pub unsafe fn sys_recvfrom(
 
         let res = socket.recvfrom(buf)?;
         if let Some(addr) = res.1 {
-            unsafe {
-                (*socket_addr, *addrlen) = into_sockaddr(addr);
-            }
+            unsafe { write_sockaddr(addr, socket_addr, addrlen) };
         }
         Ok(res.0)
     })
@@ -388,6 +442,9 @@ pub fn sys_recv(
 
 /// Listen for connections on a socket
 ///
+/// Unless `SO_REUSEADDR` is set, it fails with `EADDRINUSE` if connections
+/// (e.g., those accepted by a previous listener) are still on the port.
+///
 /// Return 0 if success.
 pub fn sys_listen(
     socket_fd: c_int,
@@ -420,9 +477,7 @@ pub unsafe fn sys_accept(
         let new_socket = socket.accept()?;
         let addr = new_socket.peer_addr()?;
         let new_fd = Socket::add_to_fd_table(Socket::Tcp(Mutex::new(new_socket)))?;
-        unsafe {
-            (*socket_addr, *socket_len) = into_sockaddr(addr);
-        }
+        unsafe { write_sockaddr(addr, socket_addr, socket_len) };
         Ok(new_fd)
     })
 }
@@ -443,14 +498,16 @@ pub fn sys_shutdown(
 
 /// Query addresses for a domain name.
 ///
-/// Only IPv4. Ports are always 0. Ignore servname and hint.
+/// Returns both IPv4 and IPv6 addresses, unless `ai_family` of the hints
+/// selects one of them. Other fields of the hints are ignored. `localhost` is
+/// resolved to `127.0.0.1` and `::1` without a DNS query.
 /// Results' ai_flags and ai_canonname are 0 or NULL.
 ///
 /// Return address number if success.
 pub unsafe fn sys_getaddrinfo(
     nodename: *const c_char,
     servname: *const c_char,
-    _hints: *const ctypes::addrinfo,
+    hints: *const ctypes::addrinfo,
     res: *mut *mut ctypes::addrinfo,
 ) -> c_int {
     let name = char_ptr_to_str(nodename);
@@ -464,16 +521,29 @@ pub unsafe fn sys_getaddrinfo(
             return Err(LinuxError::EFAULT);
         }
 
+        let family = if hints.is_null() {
+            ctypes::AF_UNSPEC
+        } else {
+            unsafe { (*hints).ai_family as u32 }
+        };
         let port = port.map_or(0, |p| p.parse::<u16>().unwrap_or(0));
-        let ip_addrs = if let Ok(domain) = name {
+        let mut ip_addrs = if let Ok(domain) = name {
             if let Ok(a) = domain.parse::<IpAddr>() {
                 vec![a]
+            } else if domain.eq_ignore_ascii_case("localhost") {
+                // as the entries of `localhost` in `/etc/hosts`
+                vec![Ipv4Addr::LOCALHOST.into(), Ipv6Addr::LOCALHOST.into()]
             } else {
                 axnet::dns_query(domain)?
             }
         } else {
             vec![Ipv4Addr::LOCALHOST.into()]
         };
+        ip_addrs.retain(|ip| match family {
+            ctypes::AF_INET => ip.is_ipv4(),
+            ctypes::AF_INET6 => ip.is_ipv6(),
+            _ => true,
+        });
 
         let len = ip_addrs.len().min(ctypes::MAXADDRS as usize);
         if len == 0 {
@@ -482,29 +552,41 @@ pub unsafe fn sys_getaddrinfo(
 
         let mut out: Vec<ctypes::aibuf> = Vec::with_capacity(len);
         for (i, &ip) in ip_addrs.iter().enumerate().take(len) {
-            let buf = match ip {
-                IpAddr::V4(ip) => ctypes::aibuf {
-                    ai: ctypes::addrinfo {
-                        ai_family: ctypes::AF_INET as _,
-                        // TODO: This is a hard-code part, only return TCP parameters
-                        ai_socktype: ctypes::SOCK_STREAM as _,
-                        ai_protocol: ctypes::IPPROTO_TCP as _,
-                        ai_addrlen: size_of::<ctypes::sockaddr_in>() as _,
-                        ai_addr: core::ptr::null_mut(),
-                        ai_canonname: core::ptr::null_mut(),
-                        ai_next: core::ptr::null_mut(),
-                        ai_flags: 0,
-                    },
-                    sa: ctypes::aibuf_sa {
+            let (family, addrlen, sa) = match ip {
+                IpAddr::V4(ip) => (
+                    ctypes::AF_INET,
+                    size_of::<ctypes::sockaddr_in>(),
+                    ctypes::aibuf_sa {
                         sin: SocketAddrV4::new(ip, port).into(),
                     },
-                    slot: i as i16,
-                    lock: [0],
-                    ref_: 0,
+                ),
+                IpAddr::V6(ip) => (
+                    ctypes::AF_INET6,
+                    size_of::<ctypes::sockaddr_in6>(),
+                    ctypes::aibuf_sa {
+                        sin6: SocketAddrV6::new(ip, port, 0, 0).into(),
+                    },
+                ),
+            };
+            let buf = ctypes::aibuf {
+                ai: ctypes::addrinfo {
+                    ai_family: family as _,
+                    // TODO: This is a hard-code part, only return TCP parameters
+                    ai_socktype: ctypes::SOCK_STREAM as _,
+                    ai_protocol: ctypes::IPPROTO_TCP as _,
+                    ai_addrlen: addrlen as _,
+                    ai_addr: core::ptr::null_mut(),
+                    ai_canonname: core::ptr::null_mut(),
+                    ai_next: core::ptr::null_mut(),
+                    ai_flags: 0,
                 },
-                _ => panic!("IPv6 is not supported"),
+                sa,
+                slot: i as i16,
+                lock: [0],
+                ref_: 0,
             };
             out.push(buf);
+            // `sin` and `sin6` share the same address in the union
             out[i].ai.ai_addr =
                 unsafe { core::ptr::addr_of_mut!(out[i].sa.sin) as *mut ctypes::sockaddr };
             if i > 0 {
@@ -549,9 +631,8 @@ pub unsafe fn sys_getsockname(
         if unsafe { *addrlen } < size_of::<ctypes::sockaddr>() as u32 {
             return Err(LinuxError::EINVAL);
         }
-        unsafe {
-            (*addr, *addrlen) = into_sockaddr(Socket::from_fd(sock_fd)?.local_addr()?);
-        }
+        let local_addr = Socket::from_fd(sock_fd)?.local_addr()?;
+        unsafe { write_sockaddr(local_addr, addr, addrlen) };
         Ok(0)
     })
 }
@@ -573,9 +654,389 @@ pub unsafe fn sys_getpeername(
         if unsafe { *addrlen } < size_of::<ctypes::sockaddr>() as u32 {
             return Err(LinuxError::EINVAL);
         }
-        unsafe {
-            (*addr, *addrlen) = into_sockaddr(Socket::from_fd(sock_fd)?.peer_addr()?);
+        let peer_addr = Socket::from_fd(sock_fd)?.peer_addr()?;
+        unsafe { write_sockaddr(peer_addr, addr, addrlen) };
+        Ok(0)
+    })
+}
+
+/// The TTL set by `IP_TTL` with -1.
+const DEFAULT_TTL: u8 = 64;
+
+/// The keep-alive interval set by `SO_KEEPALIVE`, as the default of Linux.
+const DEFAULT_KEEPALIVE_IDLE: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// Reads an option value of type `T` from the user buffer.
+unsafe fn read_optval<T: Copy>(optval: *const c_void, optlen: ctypes::socklen_t) -> LinuxResult<T> {
+    if optval.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    if (optlen as usize) < size_of::<T>() {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(unsafe { (optval as *const T).read_unaligned() })
+}
+
+/// Writes an option value to the user buffer, and sets `optlen` to the actual
+/// length of the value.
+///
+/// The value is truncated if the buffer is too small, as Linux does.
+unsafe fn write_optval<T>(
+    val: T,
+    optval: *mut c_void,
+    optlen: *mut ctypes::socklen_t,
+) -> LinuxResult {
+    if optval.is_null() || optlen.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    unsafe {
+        let copy_len = size_of::<T>().min(*optlen as usize);
+        core::ptr::copy_nonoverlapping(&val as *const T as *const u8, optval as *mut u8, copy_len);
+        *optlen = size_of::<T>() as _;
+    }
+    Ok(())
+}
+
+/// Converts a timeout in `timeval` to `Duration`, where zero means no timeout.
+fn timeout_from_timeval(tv: ctypes::timeval) -> LinuxResult<Option<Duration>> {
+    if tv.tv_sec < 0 || !(0..1_000_000).contains(&tv.tv_usec) {
+        return Err(LinuxError::EDOM);
+    }
+    let timeout = Duration::from(tv);
+    Ok((!timeout.is_zero()).then_some(timeout))
+}
+
+/// Converts a TTL in `c_int` to `u8`, where -1 means the default.
+fn ttl_from_c_int(ttl: c_int) -> LinuxResult<u8> {
+    match ttl {
+        -1 => Ok(DEFAULT_TTL),
+        1..=255 => Ok(ttl as u8),
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Set options on sockets.
+///
+/// Supported options are `SO_REUSEADDR`, `SO_RCVBUF`, `SO_SNDBUF`,
+/// `SO_KEEPALIVE`, `SO_LINGER`, `SO_RCVTIMEO`, `SO_SNDTIMEO`, `TCP_NODELAY`,
+/// `TCP_KEEPIDLE` (which also enables keep-alive), `IP_TTL` and
+/// `IPV6_UNICAST_HOPS`.
+///
+/// `SO_REUSEPORT`, `SO_BROADCAST`, `IP_TOS`, `IPV6_V6ONLY`, `TCP_KEEPINTVL`
+/// and `TCP_KEEPCNT` are accepted but ignored, as programs commonly set them
+/// and work the same without them. Other options fail with `ENOPROTOOPT`.
+///
+/// Return 0 if success.
+pub unsafe fn sys_setsockopt(
+    socket_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *const c_void,
+    optlen: ctypes::socklen_t,
+) -> c_int {
+    debug!(
+        "sys_setsockopt <= {} {} {} {:#x} {}",
+        socket_fd, level, optname, optval as usize, optlen
+    );
+    syscall_body!(sys_setsockopt, {
+        let socket = Socket::from_fd(socket_fd)?;
+        let read_int = || unsafe { read_optval::<c_int>(optval, optlen) };
+        match (level as u32, optname as u32, socket.as_ref()) {
+            (ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR, socket) => {
+                let reuse = read_int()? != 0;
+                match socket {
+                    Socket::Udp(udpsocket) => udpsocket.lock().set_reuse_address(reuse),
+                    Socket::Tcp(tcpsocket) => tcpsocket.lock().set_reuse_address(reuse),
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVBUF, socket) => {
+                let len = read_int()?.max(0) as usize;
+                match socket {
+                    Socket::Udp(udpsocket) => udpsocket.lock().set_recv_buffer_size(len)?,
+                    Socket::Tcp(tcpsocket) => tcpsocket.lock().set_recv_buffer_size(len),
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDBUF, socket) => {
+                let len = read_int()?.max(0) as usize;
+                match socket {
+                    Socket::Udp(udpsocket) => udpsocket.lock().set_send_buffer_size(len)?,
+                    Socket::Tcp(tcpsocket) => tcpsocket.lock().set_send_buffer_size(len),
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO, socket) => {
+                let timeout = timeout_from_timeval(unsafe { read_optval(optval, optlen)? })?;
+                match socket {
+                    Socket::Udp(udpsocket) => udpsocket.lock().set_read_timeout(timeout)?,
+                    Socket::Tcp(tcpsocket) => tcpsocket.lock().set_read_timeout(timeout)?,
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO, socket) => {
+                let timeout = timeout_from_timeval(unsafe { read_optval(optval, optlen)? })?;
+                match socket {
+                    Socket::Udp(udpsocket) => udpsocket.lock().set_write_timeout(timeout)?,
+                    Socket::Tcp(tcpsocket) => tcpsocket.lock().set_write_timeout(timeout)?,
+                }
+            }
+            // no effect on UDP sockets
+            (ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE | ctypes::SO_LINGER, Socket::Udp(_)) => {}
+            (ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE, Socket::Tcp(tcpsocket)) => {
+                let tcpsocket = tcpsocket.lock();
+                let interval = (read_int()? != 0)
+                    .then(|| tcpsocket.keepalive().unwrap_or(DEFAULT_KEEPALIVE_IDLE));
+                tcpsocket.set_keepalive(interval)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_LINGER, Socket::Tcp(tcpsocket)) => {
+                let linger: ctypes::linger = unsafe { read_optval(optval, optlen)? };
+                let linger = (linger.l_onoff != 0)
+                    .then(|| Duration::from_secs(linger.l_linger.max(0) as u64));
+                tcpsocket.lock().set_linger(linger)
+            }
+            (ctypes::IPPROTO_TCP, ctypes::TCP_NODELAY, Socket::Tcp(tcpsocket)) => {
+                tcpsocket.lock().set_nodelay(read_int()? != 0)
+            }
+            (ctypes::IPPROTO_TCP, ctypes::TCP_KEEPIDLE, Socket::Tcp(tcpsocket)) => {
+                let secs = read_int()?;
+                if secs < 1 {
+                    return Err(LinuxError::EINVAL);
+                }
+                tcpsocket
+                    .lock()
+                    .set_keepalive(Some(Duration::from_secs(secs as u64)))?
+            }
+            (ctypes::IPPROTO_IP, ctypes::IP_TTL, socket)
+            | (ctypes::IPPROTO_IPV6, ctypes::IPV6_UNICAST_HOPS, socket) => {
+                let ttl = ttl_from_c_int(read_int()?)?;
+                match socket {
+                    Socket::Udp(udpsocket) => udpsocket.lock().set_ttl(ttl)?,
+                    Socket::Tcp(tcpsocket) => tcpsocket.lock().set_ttl(ttl)?,
+                }
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_REUSEPORT | ctypes::SO_BROADCAST, _)
+            | (ctypes::IPPROTO_IP, ctypes::IP_TOS, _)
+            | (ctypes::IPPROTO_IPV6, ctypes::IPV6_V6ONLY, _)
+            | (ctypes::IPPROTO_TCP, ctypes::TCP_KEEPINTVL | ctypes::TCP_KEEPCNT, _) => {
+                warn!(
+                    "sys_setsockopt: ignored option {} at level {}",
+                    optname, level
+                );
+            }
+            _ => {
+                warn!(
+                    "sys_setsockopt: unsupported option {} at level {}",
+                    optname, level
+                );
+                return Err(LinuxError::ENOPROTOOPT);
+            }
+        }
+        Ok(0)
+    })
+}
+
+/// Get options on sockets.
+///
+/// Supports the options of [`sys_setsockopt`], and `SO_TYPE` and `SO_ERROR`.
+///
+/// Return 0 if success.
+pub unsafe fn sys_getsockopt(
+    socket_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *mut c_void,
+    optlen: *mut ctypes::socklen_t,
+) -> c_int {
+    debug!(
+        "sys_getsockopt <= {} {} {} {:#x} {:#x}",
+        socket_fd, level, optname, optval as usize, optlen as usize
+    );
+    syscall_body!(sys_getsockopt, {
+        let socket = Socket::from_fd(socket_fd)?;
+        let write_int = |val: c_int| unsafe { write_optval(val, optval, optlen) };
+        let write_timeout = |timeout: Option<Duration>| unsafe {
+            write_optval(
+                ctypes::timeval::from(timeout.unwrap_or(Duration::ZERO)),
+                optval,
+                optlen,
+            )
+        };
+        match (level as u32, optname as u32, socket.as_ref()) {
+            (ctypes::SOL_SOCKET, ctypes::SO_TYPE, Socket::Udp(_)) => {
+                write_int(ctypes::SOCK_DGRAM as _)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_TYPE, Socket::Tcp(_)) => {
+                write_int(ctypes::SOCK_STREAM as _)?
+            }
+            // errors are reported by the failed operations instead
+            (ctypes::SOL_SOCKET, ctypes::SO_ERROR, _) => write_int(0)?,
+            (ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR, Socket::Udp(udpsocket)) => {
+                write_int(udpsocket.lock().reuse_address() as _)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR, Socket::Tcp(tcpsocket)) => {
+                write_int(tcpsocket.lock().reuse_address() as _)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVBUF, Socket::Udp(udpsocket)) => {
+                write_int(udpsocket.lock().recv_buffer_size() as _)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVBUF, Socket::Tcp(tcpsocket)) => {
+                write_int(tcpsocket.lock().recv_buffer_size() as _)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDBUF, Socket::Udp(udpsocket)) => {
+                write_int(udpsocket.lock().send_buffer_size() as _)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDBUF, Socket::Tcp(tcpsocket)) => {
+                write_int(tcpsocket.lock().send_buffer_size() as _)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO, Socket::Udp(udpsocket)) => {
+                write_timeout(udpsocket.lock().read_timeout())?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_RCVTIMEO, Socket::Tcp(tcpsocket)) => {
+                write_timeout(tcpsocket.lock().read_timeout())?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO, Socket::Udp(udpsocket)) => {
+                write_timeout(udpsocket.lock().write_timeout())?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_SNDTIMEO, Socket::Tcp(tcpsocket)) => {
+                write_timeout(tcpsocket.lock().write_timeout())?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE, Socket::Udp(_)) => write_int(0)?,
+            (ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE, Socket::Tcp(tcpsocket)) => {
+                write_int(tcpsocket.lock().keepalive().is_some() as _)?
+            }
+            (ctypes::SOL_SOCKET, ctypes::SO_LINGER, socket) => {
+                let linger = match socket {
+                    Socket::Udp(_) => None,
+                    Socket::Tcp(tcpsocket) => tcpsocket.lock().linger(),
+                };
+                let linger = ctypes::linger {
+                    l_onoff: linger.is_some() as _,
+                    l_linger: linger.map_or(0, |linger| linger.as_secs() as _),
+                };
+                unsafe { write_optval(linger, optval, optlen)? }
+            }
+            (ctypes::IPPROTO_TCP, ctypes::TCP_NODELAY, Socket::Tcp(tcpsocket)) => {
+                write_int(tcpsocket.lock().nodelay() as _)?
+            }
+            (ctypes::IPPROTO_TCP, ctypes::TCP_KEEPIDLE, Socket::Tcp(tcpsocket)) => {
+                let interval = tcpsocket.lock().keepalive();
+                write_int(interval.unwrap_or(DEFAULT_KEEPALIVE_IDLE).as_secs() as _)?
+            }
+            (ctypes::IPPROTO_IP, ctypes::IP_TTL, socket)
+            | (ctypes::IPPROTO_IPV6, ctypes::IPV6_UNICAST_HOPS, socket) => {
+                let ttl = match socket {
+                    Socket::Udp(udpsocket) => udpsocket.lock().ttl(),
+                    Socket::Tcp(tcpsocket) => tcpsocket.lock().ttl(),
+                };
+                write_int(ttl as _)?
+            }
+            _ => {
+                warn!(
+                    "sys_getsockopt: unsupported option {} at level {}",
+                    optname, level
+                );
+                return Err(LinuxError::ENOPROTOOPT);
+            }
         }
         Ok(0)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sockaddr_in6() {
+        let addr = SocketAddr::V6(SocketAddrV6::new("fd00::15".parse().unwrap(), 8080, 1, 2));
+        let mut sin6 = core::mem::MaybeUninit::<ctypes::sockaddr_in6>::zeroed();
+        let mut len = size_of::<ctypes::sockaddr_in6>() as ctypes::socklen_t;
+        let sa = sin6.as_mut_ptr() as *mut ctypes::sockaddr;
+        unsafe { write_sockaddr(addr, sa, &mut len) };
+        assert_eq!(len as usize, size_of::<ctypes::sockaddr_in6>());
+        let sin6 = unsafe { sin6.assume_init() };
+        assert_eq!(sin6.sin6_family, ctypes::AF_INET6 as u16);
+        assert_eq!(sin6.sin6_port, 8080u16.to_be());
+        assert_eq!(sin6.sin6_scope_id, 2);
+        assert_eq!(from_sockaddr(sa, len), Ok(addr));
+        // the address is longer than `sockaddr_in`
+        let short_len = size_of::<ctypes::sockaddr_in>() as ctypes::socklen_t;
+        assert_eq!(from_sockaddr(sa, short_len), Err(LinuxError::EINVAL));
+    }
+
+    #[test]
+    fn test_getaddrinfo_family() {
+        let lookup = |family: u32| {
+            let mut hints: ctypes::addrinfo = unsafe { core::mem::zeroed() };
+            hints.ai_family = family as _;
+            let mut res = core::ptr::null_mut();
+            let n =
+                unsafe { sys_getaddrinfo(c"localhost".as_ptr(), c"80".as_ptr(), &hints, &mut res) };
+            let mut addrs = Vec::new();
+            let mut ai = res;
+            while !ai.is_null() {
+                let info = unsafe { &*ai };
+                let addr = from_sockaddr(info.ai_addr, info.ai_addrlen).unwrap();
+                let family = match addr {
+                    SocketAddr::V4(_) => ctypes::AF_INET,
+                    SocketAddr::V6(_) => ctypes::AF_INET6,
+                };
+                assert_eq!(info.ai_family as u32, family);
+                addrs.push(addr);
+                ai = info.ai_next;
+            }
+            assert_eq!(n as usize, addrs.len());
+            unsafe { sys_freeaddrinfo(res) };
+            addrs
+        };
+        let v4 = SocketAddr::from((Ipv4Addr::LOCALHOST, 80));
+        let v6 = SocketAddr::from((Ipv6Addr::LOCALHOST, 80));
+        assert_eq!(lookup(ctypes::AF_UNSPEC), [v4, v6]);
+        assert_eq!(lookup(ctypes::AF_INET), [v4]);
+        assert_eq!(lookup(ctypes::AF_INET6), [v6]);
+    }
+
+    #[test]
+    fn test_setsockopt() {
+        let fd = sys_socket(ctypes::AF_INET as _, ctypes::SOCK_STREAM as _, 0);
+        assert!(fd >= 0);
+        let set = |level: u32, optname: u32, val: c_int| unsafe {
+            let optval = &val as *const c_int as *const c_void;
+            sys_setsockopt(
+                fd,
+                level as _,
+                optname as _,
+                optval,
+                size_of::<c_int>() as _,
+            )
+        };
+        let get = |level: u32, optname: u32| {
+            let mut val: c_int = -1;
+            let mut len = size_of::<c_int>() as ctypes::socklen_t;
+            let optval = &mut val as *mut c_int as *mut c_void;
+            let ret = unsafe { sys_getsockopt(fd, level as _, optname as _, optval, &mut len) };
+            assert_eq!(ret, 0);
+            val
+        };
+
+        assert_eq!(set(ctypes::IPPROTO_TCP, ctypes::TCP_NODELAY, 1), 0);
+        assert_eq!(get(ctypes::IPPROTO_TCP, ctypes::TCP_NODELAY), 1);
+        assert_eq!(get(ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE), 0);
+        assert_eq!(set(ctypes::IPPROTO_TCP, ctypes::TCP_KEEPIDLE, 60), 0);
+        assert_eq!(get(ctypes::SOL_SOCKET, ctypes::SO_KEEPALIVE), 1);
+        assert_eq!(get(ctypes::IPPROTO_TCP, ctypes::TCP_KEEPIDLE), 60);
+        let einval = -LinuxError::EINVAL.code();
+        assert_eq!(set(ctypes::IPPROTO_TCP, ctypes::TCP_KEEPIDLE, 0), einval);
+        assert_eq!(set(ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR, 1), 0);
+        assert_eq!(get(ctypes::SOL_SOCKET, ctypes::SO_REUSEADDR), 1);
+
+        // benign options are ignored
+        for (level, optname) in [
+            (ctypes::SOL_SOCKET, ctypes::SO_REUSEPORT),
+            (ctypes::IPPROTO_IPV6, ctypes::IPV6_V6ONLY),
+            (ctypes::IPPROTO_TCP, ctypes::TCP_KEEPINTVL),
+        ] {
+            assert_eq!(set(level, optname, 1), 0);
+        }
+        let enoprotoopt = -LinuxError::ENOPROTOOPT.code();
+        assert_eq!(set(ctypes::SOL_SOCKET, 0x7fff, 1), enoprotoopt);
+        assert_eq!(crate::imp::fd_ops::sys_close(fd), 0);
+    }
+}
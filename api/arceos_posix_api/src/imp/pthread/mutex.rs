@@ -1,7 +1,10 @@
-use crate::{ctypes, utils::check_null_mut_ptr};
+use crate::{
+    ctypes,
+    utils::{check_null_mut_ptr, check_null_ptr},
+};
 
-use axerrno::LinuxResult;
-use axsync::Mutex;
+use axerrno::{LinuxError, LinuxResult};
+use axsync::{Mutex, RawMutex};
 
 use core::ffi::c_int;
 use core::mem::{ManuallyDrop, size_of};
@@ -19,6 +22,11 @@ impl PthreadMutex {
         Self(Mutex::new(()))
     }
 
+    /// Creates a mutex with priority inheritance enabled.
+    const fn new_pi() -> Self {
+        Self(Mutex::const_new(RawMutex::new_pi(), ()))
+    }
+
     fn lock(&self) -> LinuxResult {
         let _guard = ManuallyDrop::new(self.0.lock());
         Ok(())
@@ -31,16 +39,79 @@ impl PthreadMutex {
 }
 
 /// Initialize a mutex.
+///
+/// If the protocol of `attr` is `PTHREAD_PRIO_INHERIT`, the mutex will be
+/// created with priority inheritance enabled.
 pub fn sys_pthread_mutex_init(
     mutex: *mut ctypes::pthread_mutex_t,
-    _attr: *const ctypes::pthread_mutexattr_t,
+    attr: *const ctypes::pthread_mutexattr_t,
 ) -> c_int {
-    debug!("sys_pthread_mutex_init <= {:#x}", mutex as usize);
+    debug!(
+        "sys_pthread_mutex_init <= {:#x}, {:#x}",
+        mutex as usize, attr as usize
+    );
     syscall_body!(sys_pthread_mutex_init, {
         check_null_mut_ptr(mutex)?;
+        let pi = !attr.is_null() && unsafe { (*attr).__attr } == ctypes::PTHREAD_PRIO_INHERIT as _;
+        let new_mutex = if pi {
+            PthreadMutex::new_pi()
+        } else {
+            PthreadMutex::new()
+        };
+        unsafe {
+            mutex.cast::<PthreadMutex>().write(new_mutex);
+        }
+        Ok(0)
+    })
+}
+
+/// Initialize a mutex attributes object with the default values.
+pub fn sys_pthread_mutexattr_init(attr: *mut ctypes::pthread_mutexattr_t) -> c_int {
+    debug!("sys_pthread_mutexattr_init <= {:#x}", attr as usize);
+    syscall_body!(sys_pthread_mutexattr_init, {
+        check_null_mut_ptr(attr)?;
         unsafe {
-            mutex.cast::<PthreadMutex>().write(PthreadMutex::new());
+            attr.write(ctypes::pthread_mutexattr_t {
+                __attr: ctypes::PTHREAD_PRIO_NONE as _,
+            });
+        }
+        Ok(0)
+    })
+}
+
+/// Get the protocol attribute of a mutex attributes object.
+pub fn sys_pthread_mutexattr_getprotocol(
+    attr: *const ctypes::pthread_mutexattr_t,
+    protocol: *mut c_int,
+) -> c_int {
+    debug!("sys_pthread_mutexattr_getprotocol <= {:#x}", attr as usize);
+    syscall_body!(sys_pthread_mutexattr_getprotocol, {
+        check_null_ptr(attr)?;
+        check_null_mut_ptr(protocol)?;
+        unsafe { *protocol = (*attr).__attr as c_int };
+        Ok(0)
+    })
+}
+
+/// Set the protocol attribute of a mutex attributes object.
+///
+/// Only `PTHREAD_PRIO_NONE` and `PTHREAD_PRIO_INHERIT` are supported.
+pub fn sys_pthread_mutexattr_setprotocol(
+    attr: *mut ctypes::pthread_mutexattr_t,
+    protocol: c_int,
+) -> c_int {
+    debug!(
+        "sys_pthread_mutexattr_setprotocol <= {:#x}, {}",
+        attr as usize, protocol
+    );
+    syscall_body!(sys_pthread_mutexattr_setprotocol, {
+        check_null_mut_ptr(attr)?;
+        match protocol as u32 {
+            ctypes::PTHREAD_PRIO_NONE | ctypes::PTHREAD_PRIO_INHERIT => {}
+            ctypes::PTHREAD_PRIO_PROTECT => return Err(LinuxError::ENOTSUP),
+            _ => return Err(LinuxError::EINVAL),
         }
+        unsafe { (*attr).__attr = protocol as _ };
         Ok(0)
     })
 }
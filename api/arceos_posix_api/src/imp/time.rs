@@ -3,6 +3,8 @@ use core::ffi::{c_int, c_long};
 use core::time::Duration;
 
 use crate::ctypes;
+#[cfg(feature = "multitask")]
+use crate::ctypes::CLOCK_THREAD_CPUTIME_ID;
 use crate::ctypes::{CLOCK_MONOTONIC, CLOCK_REALTIME};
 
 impl From<ctypes::timespec> for Duration {
@@ -44,6 +46,10 @@ pub unsafe fn sys_clock_gettime(clk: ctypes::clockid_t, ts: *mut ctypes::timespe
         let now = match clk as u32 {
             CLOCK_REALTIME => axhal::time::wall_time().into(),
             CLOCK_MONOTONIC => axhal::time::monotonic_time().into(),
+            #[cfg(feature = "multitask")]
+            CLOCK_THREAD_CPUTIME_ID => {
+                Duration::from_nanos(axtask::current().stats().runtime_ns).into()
+            }
             _ => {
                 warn!("Called sys_clock_gettime for unsupported clock {}", clk);
                 return Err(LinuxError::EINVAL);
@@ -5,7 +5,7 @@ impl AllDevices {
     pub(crate) fn probe_bus_devices(&mut self) {
         // TODO: parse device tree
         #[cfg(feature = "virtio")]
-        for reg in axconfig::devices::VIRTIO_MMIO_REGIONS {
+        for (index, reg) in axconfig::devices::VIRTIO_MMIO_REGIONS.iter().enumerate() {
             for_each_drivers!(type Driver, {
                 if let Some(dev) = Driver::probe_mmio(reg.0, reg.1) {
                     info!(
@@ -14,6 +14,10 @@ impl AllDevices {
                         reg.0, reg.0 + reg.1,
                         dev.device_name(),
                     );
+                    #[cfg(feature = "net")]
+                    if dev.device_type() == DeviceType::Net && self.net_irq.is_none() {
+                        self.net_irq = crate::irq::probe_mmio_irq(index, reg.0);
+                    }
                     self.add_device(dev);
                     continue; // skip to the next device
                 }
@@ -107,6 +107,10 @@ impl AllDevices {
                                 bdf,
                                 dev.device_name(),
                             );
+                            #[cfg(feature = "net")]
+                            if dev.device_type() == DeviceType::Net && self.net_irq.is_none() {
+                                self.net_irq = crate::irq::probe_pci_irq(&mut root, bdf, &dev_info);
+                            }
                             self.add_device(dev);
                             continue; // skip to the next device
                         }
@@ -0,0 +1,198 @@
+//! Interrupts raised by NICs, which let the network stack wait for received
+//! packets instead of polling.
+//!
+//! Legacy (INTx) interrupts of PCI NICs and interrupts of VirtIO MMIO NICs
+//! are supported, on platforms that route them to known IRQs (i.e.,
+//! `pci-irq-base` or `virtio-mmio-irq-base` is configured). Other NICs are
+//! left to be polled.
+
+#![allow(dead_code)] // no variant is constructed for some NICs and buses
+
+/// The interrupt of a NIC.
+#[derive(Debug, Clone, Copy)]
+pub struct NetIrq {
+    /// The IRQ number of the NIC.
+    pub irq_num: usize,
+    kind: NetIrqKind,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NetIrqKind {
+    /// A VirtIO PCI device, with the virtual address of its ISR status.
+    VirtIo { isr_vaddr: usize },
+    /// A VirtIO MMIO device, with the virtual address of its registers.
+    VirtIoMmio { regs_vaddr: usize },
+    /// An Intel 82599 NIC, with the virtual address of its registers.
+    Ixgbe { regs_vaddr: usize },
+}
+
+impl NetIrq {
+    /// Acknowledges the interrupt in the NIC, so that it stops asserting the
+    /// interrupt line.
+    ///
+    /// Returns whether the interrupt was raised by this NIC, as the line may
+    /// be shared with other devices.
+    pub fn ack(&self) -> bool {
+        match self.kind {
+            // reading the ISR status clears it and deasserts the line
+            NetIrqKind::VirtIo { isr_vaddr } => unsafe {
+                (isr_vaddr as *const u8).read_volatile() != 0
+            },
+            // the MMIO transport deasserts it once the status is acknowledged
+            NetIrqKind::VirtIoMmio { regs_vaddr } => unsafe {
+                let status =
+                    ((regs_vaddr + VIRTIO_MMIO_INTERRUPT_STATUS) as *const u32).read_volatile();
+                ((regs_vaddr + VIRTIO_MMIO_INTERRUPT_ACK) as *mut u32).write_volatile(status);
+                status != 0
+            },
+            // so does reading the EICR register
+            NetIrqKind::Ixgbe { regs_vaddr } => unsafe {
+                ((regs_vaddr + ixgbe_regs::EICR) as *const u32).read_volatile() != 0
+            },
+        }
+    }
+}
+
+const VIRTIO_MMIO_INTERRUPT_STATUS: usize = 0x60;
+const VIRTIO_MMIO_INTERRUPT_ACK: usize = 0x64;
+
+#[cfg(bus = "pci")]
+pub(crate) use self::pci::probe_pci_irq;
+
+/// Finds the interrupt of the VirtIO MMIO NIC in the `index`-th region of
+/// `virtio-mmio-regions`, at physical address `paddr`.
+///
+/// Returns `None` if the interrupts of the regions are not routed to known
+/// IRQs.
+#[cfg(all(bus = "mmio", feature = "virtio"))]
+pub(crate) fn probe_mmio_irq(index: usize, paddr: usize) -> Option<NetIrq> {
+    let irq_base = axconfig::devices::VIRTIO_MMIO_IRQ_BASE;
+    if irq_base == 0 {
+        debug!("VirtIO MMIO [PA:{:#x}]: no interrupt routed", paddr);
+        return None;
+    }
+    let irq = NetIrq {
+        irq_num: irq_base + index,
+        kind: NetIrqKind::VirtIoMmio {
+            regs_vaddr: axhal::mem::phys_to_virt(paddr.into()).as_usize(),
+        },
+    };
+    info!(
+        "VirtIO MMIO [PA:{:#x}]: NIC interrupt on IRQ {}",
+        paddr, irq.irq_num
+    );
+    Some(irq)
+}
+
+#[cfg(bus = "pci")]
+mod pci {
+    use axdriver_pci::{BarInfo, DeviceFunction, DeviceFunctionInfo, PciRoot};
+    use axhal::mem::phys_to_virt;
+
+    use super::{NetIrq, NetIrqKind};
+
+    const PCI_CAPABILITY_POINTER: usize = 0x34;
+    const PCI_INTERRUPT_PIN: usize = 0x3c;
+    const PCI_STATUS_CAP_LIST: u32 = 1 << 20;
+    const PCI_CAP_ID_VENDOR: u32 = 0x09;
+
+    const VIRTIO_VENDOR_ID: u16 = 0x1af4;
+    const VIRTIO_PCI_CAP_ISR_CFG: u32 = 3;
+
+    /// Reads a 32-bit register in the configuration space of `bdf`.
+    fn config_read(bdf: DeviceFunction, offset: usize) -> u32 {
+        let paddr = axconfig::devices::PCI_ECAM_BASE
+            + ((bdf.bus as usize) << 20
+                | (bdf.device as usize) << 15
+                | (bdf.function as usize) << 12)
+            + offset;
+        unsafe { (phys_to_virt(paddr.into()).as_usize() as *const u32).read_volatile() }
+    }
+
+    /// Returns the virtual address of the memory BAR `bar` of `bdf`.
+    fn bar_vaddr(root: &mut PciRoot, bdf: DeviceFunction, bar: u8) -> Option<usize> {
+        match root.bar_info(bdf, bar).ok()? {
+            BarInfo::Memory { address, .. } if address != 0 => {
+                Some(phys_to_virt((address as usize).into()).as_usize())
+            }
+            _ => None,
+        }
+    }
+
+    /// Finds the ISR status of a VirtIO PCI device, by walking its capability
+    /// list for the ISR configuration structure.
+    fn virtio_isr_vaddr(root: &mut PciRoot, bdf: DeviceFunction) -> Option<usize> {
+        if config_read(bdf, 0x04) & PCI_STATUS_CAP_LIST == 0 {
+            return None;
+        }
+        let mut cap = config_read(bdf, PCI_CAPABILITY_POINTER) as usize & 0xfc;
+        while cap != 0 {
+            let header = config_read(bdf, cap);
+            let (id, next, cfg_type) = (header & 0xff, (header >> 8) & 0xfc, header >> 24);
+            if id == PCI_CAP_ID_VENDOR && cfg_type == VIRTIO_PCI_CAP_ISR_CFG {
+                let bar = config_read(bdf, cap + 4) as u8;
+                let offset = config_read(bdf, cap + 8) as usize;
+                return Some(bar_vaddr(root, bdf, bar)? + offset);
+            }
+            cap = next as usize;
+        }
+        None
+    }
+
+    /// Finds the interrupt of the NIC at `bdf`, and enables it in the NIC.
+    ///
+    /// Returns `None` if the interrupt of the NIC is not routed to a known IRQ,
+    /// or the NIC is not known to raise interrupts.
+    pub(crate) fn probe_pci_irq(
+        root: &mut PciRoot,
+        bdf: DeviceFunction,
+        dev_info: &DeviceFunctionInfo,
+    ) -> Option<NetIrq> {
+        let irq_base = axconfig::devices::PCI_IRQ_BASE;
+        // 1 for INTA#, ..., 4 for INTD#, 0 if the device uses no INTx
+        let pin = (config_read(bdf, PCI_INTERRUPT_PIN) >> 8) & 0xff;
+        if irq_base == 0 || !(1..=4).contains(&pin) {
+            debug!("PCI {}: no interrupt routed", bdf);
+            return None;
+        }
+        // the standard swizzle of the host bridge, as in the device tree of
+        // QEMU `virt` machines
+        let irq_num = irq_base + (bdf.device as usize + pin as usize - 1) % 4;
+
+        let kind = match dev_info.vendor_id {
+            VIRTIO_VENDOR_ID => NetIrqKind::VirtIo {
+                isr_vaddr: virtio_isr_vaddr(root, bdf)?,
+            },
+            #[cfg(net_dev = "ixgbe")]
+            axdriver_net::ixgbe::INTEL_VEND => {
+                let regs_vaddr = bar_vaddr(root, bdf, 0)?;
+                super::ixgbe_regs::enable_rx_irq(regs_vaddr);
+                NetIrqKind::Ixgbe { regs_vaddr }
+            }
+            _ => return None,
+        };
+        let irq = NetIrq { irq_num, kind };
+        info!("PCI {}: NIC interrupt on IRQ {}", bdf, irq.irq_num);
+        Some(irq)
+    }
+}
+
+#[cfg(net_dev = "ixgbe")]
+mod ixgbe_regs {
+    /// Extended interrupt cause (read to clear).
+    pub const EICR: usize = 0x00800;
+    /// Extended interrupt mask set.
+    const EIMS: usize = 0x00880;
+    /// Interrupt vector allocation of RX queue 0 and TX queue 0.
+    const IVAR0: usize = 0x00900;
+    const IVAR_ALLOC_VAL: u32 = 0x80;
+
+    /// Maps RX queue 0 to interrupt cause 0, and unmasks the cause.
+    pub fn enable_rx_irq(regs_vaddr: usize) {
+        unsafe {
+            let ivar0 = (regs_vaddr + IVAR0) as *mut u32;
+            ivar0.write_volatile((ivar0.read_volatile() & !0xff) | IVAR_ALLOC_VAL);
+            ((regs_vaddr + EIMS) as *mut u32).write_volatile(1);
+        }
+    }
+}
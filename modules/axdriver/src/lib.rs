@@ -78,12 +78,17 @@ mod virtio;
 #[cfg(feature = "ixgbe")]
 mod ixgbe;
 
+#[cfg(feature = "net")]
+mod irq;
+
 pub mod prelude;
 
 #[allow(unused_imports)]
 use self::prelude::*;
 pub use self::structs::{AxDeviceContainer, AxDeviceEnum};
 
+#[cfg(feature = "net")]
+pub use self::irq::NetIrq;
 #[cfg(feature = "block")]
 pub use self::structs::AxBlockDevice;
 #[cfg(feature = "display")]
@@ -97,6 +102,9 @@ pub struct AllDevices {
     /// All network device drivers.
     #[cfg(feature = "net")]
     pub net: AxDeviceContainer<AxNetDevice>,
+    /// The interrupt of the first network device, if it raises one.
+    #[cfg(feature = "net")]
+    pub net_irq: Option<NetIrq>,
     /// All block device drivers.
     #[cfg(feature = "block")]
     pub block: AxDeviceContainer<AxBlockDevice>,
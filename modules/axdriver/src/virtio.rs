@@ -160,12 +160,50 @@ unsafe impl VirtIoHal for VirtIoHalImpl {
         NonNull::new(phys_to_virt(paddr.into()).as_mut_ptr()).unwrap()
     }
 
-    #[inline]
-    unsafe fn share(buffer: NonNull<[u8]>, _direction: BufferDirection) -> PhysAddr {
+    unsafe fn share(buffer: NonNull<[u8]>, direction: BufferDirection) -> PhysAddr {
         let vaddr = buffer.as_ptr() as *mut u8 as usize;
-        virt_to_phys(vaddr.into()).into()
+        if in_linear_mapping(vaddr, buffer.len()) {
+            return virt_to_phys(vaddr.into()).into();
+        }
+        // Buffers out of the linear mapping (e.g., on kernel stacks) may not be
+        // physically contiguous, so they are copied to bounce buffers.
+        let bounce = global_allocator()
+            .alloc_pages(bounce_pages(buffer.len()), 0x1000)
+            .expect("failed to allocate a DMA bounce buffer");
+        if !matches!(direction, BufferDirection::DeviceToDriver) {
+            unsafe {
+                core::ptr::copy_nonoverlapping(vaddr as *const u8, bounce as *mut u8, buffer.len())
+            };
+        }
+        virt_to_phys(bounce.into()).into()
     }
 
-    #[inline]
-    unsafe fn unshare(_paddr: PhysAddr, _buffer: NonNull<[u8]>, _direction: BufferDirection) {}
+    unsafe fn unshare(paddr: PhysAddr, buffer: NonNull<[u8]>, direction: BufferDirection) {
+        let vaddr = buffer.as_ptr() as *mut u8 as usize;
+        if in_linear_mapping(vaddr, buffer.len()) {
+            return;
+        }
+        let bounce = phys_to_virt(paddr.into()).as_usize();
+        if !matches!(direction, BufferDirection::DriverToDevice) {
+            unsafe {
+                core::ptr::copy_nonoverlapping(bounce as *const u8, vaddr as *mut u8, buffer.len())
+            };
+        }
+        global_allocator().dealloc_pages(bounce, bounce_pages(buffer.len()));
+    }
+}
+
+/// Returns whether the buffer at `vaddr` is in the linear mapping, where
+/// `virt_to_phys` works.
+fn in_linear_mapping(vaddr: usize, len: usize) -> bool {
+    axhal::mem::memory_regions().any(|r| {
+        let start = phys_to_virt(r.paddr).as_usize();
+        vaddr >= start && vaddr + len <= start + r.size
+    })
+}
+
+/// Returns the number of pages of the bounce buffer for a buffer of `len`
+/// bytes.
+const fn bounce_pages(len: usize) -> usize {
+    if len == 0 { 1 } else { len.div_ceil(0x1000) }
 }
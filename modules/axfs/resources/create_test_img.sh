@@ -26,5 +26,29 @@ create_test_img() {
 	sudo umount mnt
 }
 
+# Creates an ext2/ext4 image with the same files, and some links.
+create_ext_img() {
+	local name=$1
+	local blkcount=$2
+	local fstype=$3
+	local root=$(mktemp -d)
+	for i in $(seq 1 1000); do
+	  echo "Rust is cool!" >>"$root/long.txt"
+	done
+	echo "Rust is cool!" >>"$root/short.txt"
+	mkdir -p "$root/very/long/path"
+	echo "Rust is cool!" >>"$root/very/long/path/test.txt"
+	mkdir -p "$root/very-long-dir-name"
+	echo "Rust is cool!" >>"$root/very-long-dir-name/very-long-file-name.txt"
+	ln -s short.txt "$root/short-link.txt"
+	ln -s /very/long "$root/long-link"
+	ln "$root/short.txt" "$root/very/hard-link.txt"
+	rm -f "$name"
+	mke2fs -q -t $fstype -b 1024 -d "$root" "$name" $blkcount
+	rm -rf "$root"
+}
+
 create_test_img "$CUR_DIR/fat16.img" 2500 16
 create_test_img "$CUR_DIR/fat32.img" 34000 32
+create_ext_img "$CUR_DIR/ext2.img" 2048 ext2
+create_ext_img "$CUR_DIR/ext4.img" 2048 ext4
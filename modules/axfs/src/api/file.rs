@@ -1,5 +1,6 @@
 use axio::{Result, SeekFrom, prelude::*};
 use core::fmt;
+use core::time::Duration;
 
 use crate::fops;
 
@@ -16,7 +17,7 @@ pub struct File {
 }
 
 /// Metadata information about a file.
-pub struct Metadata(fops::FileAttr);
+pub struct Metadata(pub(super) fops::FileAttr);
 
 /// Options and flags which can be used to configure how a file is opened.
 #[derive(Clone, Debug)]
@@ -96,6 +97,12 @@ impl Metadata {
         self.0.is_file()
     }
 
+    /// Returns `true` if this metadata is for a symbolic link, which is only
+    /// possible if it is returned by [`symlink_metadata`](super::symlink_metadata).
+    pub const fn is_symlink(&self) -> bool {
+        self.0.is_symlink()
+    }
+
     /// Returns the size of the file, in bytes, this metadata is for.
     #[allow(clippy::len_without_is_empty)]
     pub const fn len(&self) -> u64 {
@@ -116,6 +123,30 @@ impl Metadata {
     pub const fn blocks(&self) -> u64 {
         self.0.blocks()
     }
+
+    /// Returns the last modification time since the UNIX epoch.
+    ///
+    /// Returns [`Unsupported`](axio::Error::Unsupported) if the filesystem
+    /// does not record it.
+    pub fn modified(&self) -> Result<Duration> {
+        self.0.modified().ok_or(axio::Error::Unsupported)
+    }
+
+    /// Returns the last access time since the UNIX epoch.
+    ///
+    /// Returns [`Unsupported`](axio::Error::Unsupported) if the filesystem
+    /// does not record it.
+    pub fn accessed(&self) -> Result<Duration> {
+        self.0.accessed().ok_or(axio::Error::Unsupported)
+    }
+
+    /// Returns the creation time since the UNIX epoch.
+    ///
+    /// Returns [`Unsupported`](axio::Error::Unsupported) if the filesystem
+    /// does not record it.
+    pub fn created(&self) -> Result<Duration> {
+        self.0.created().ok_or(axio::Error::Unsupported)
+    }
 }
 
 impl fmt::Debug for Metadata {
@@ -168,6 +199,21 @@ impl File {
     pub fn metadata(&self) -> Result<Metadata> {
         self.inner.get_attr().map(Metadata)
     }
+
+    /// Changes the last access and modification times of the underlying
+    /// file, as durations since the UNIX epoch. `None` leaves a time
+    /// unchanged.
+    ///
+    /// Returns [`Unsupported`](axio::Error::Unsupported) if the filesystem
+    /// can not change them.
+    pub fn set_times(&self, accessed: Option<Duration>, modified: Option<Duration>) -> Result<()> {
+        self.inner.set_times(accessed, modified)
+    }
+
+    /// Changes the modification time of the underlying file.
+    pub fn set_modified(&self, time: Duration) -> Result<()> {
+        self.set_times(None, Some(time))
+    }
 }
 
 impl Read for File {
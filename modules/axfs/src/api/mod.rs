@@ -5,10 +5,27 @@ mod file;
 
 pub use self::dir::{DirBuilder, DirEntry, ReadDir};
 pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
+pub use crate::cache::CacheStats;
 
 use alloc::{string::String, vec::Vec};
 use axio::{self as io, prelude::*};
 
+/// The error returned when there are too many levels of symbolic links in a
+/// path (`ELOOP`).
+///
+/// [`io::Error`] has no dedicated kind for it, so
+/// [`BadState`](io::Error::BadState) is used, which path lookups never return
+/// otherwise.
+pub const SYMLINK_LOOP_ERROR: io::Error = io::Error::BadState;
+
+/// The error returned when a hard link or a rename would cross filesystems
+/// (`EXDEV`).
+///
+/// [`io::Error`] has no dedicated kind for it, so
+/// [`NotConnected`](io::Error::NotConnected) is used, which path operations
+/// never return otherwise.
+pub const CROSS_DEVICE_ERROR: io::Error = io::Error::NotConnected;
+
 /// Returns an iterator over the entries within a directory.
 pub fn read_dir(path: &str) -> io::Result<ReadDir> {
     ReadDir::new(path)
@@ -59,6 +76,11 @@ pub fn metadata(path: &str) -> io::Result<Metadata> {
     File::open(path)?.metadata()
 }
 
+/// Queries the metadata about a file without following symlinks.
+pub fn symlink_metadata(path: &str) -> io::Result<Metadata> {
+    crate::fops::symlink_attr(path).map(Metadata)
+}
+
 /// Creates a new, empty directory at the provided path.
 pub fn create_dir(path: &str) -> io::Result<()> {
     DirBuilder::new().create(path)
@@ -83,7 +105,78 @@ pub fn remove_file(path: &str) -> io::Result<()> {
 /// Rename a file or directory to a new name.
 /// Delete the original file if `old` already exists.
 ///
-/// This only works then the new path is in the same mounted fs.
+/// This only works then the new path is in the same mounted fs, otherwise
+/// [`CROSS_DEVICE_ERROR`] is returned.
 pub fn rename(old: &str, new: &str) -> io::Result<()> {
     crate::root::rename(old, new)
 }
+
+/// Creates a new symbolic link at `link` that points to `original`.
+///
+/// `original` is stored as is, and is resolved relative to the directory of
+/// the link when the link is followed. It does not need to exist.
+pub fn symlink(original: &str, link: &str) -> io::Result<()> {
+    crate::root::symlink(None, original, link)
+}
+
+/// Reads a symbolic link, returning the path that the link points to.
+///
+/// Returns [`InvalidInput`](io::Error::InvalidInput) if `path` is not a
+/// symbolic link.
+pub fn read_link(path: &str) -> io::Result<String> {
+    crate::root::read_link(None, path)
+}
+
+/// Creates a new hard link at `link` to the file at `original`.
+///
+/// Both paths must be in the same mounted filesystem, and the filesystem must
+/// support hard links (e.g., `ramfs` or `ext2`). Directories can not be
+/// linked. Links across filesystems fail with [`CROSS_DEVICE_ERROR`].
+pub fn hard_link(original: &str, link: &str) -> io::Result<()> {
+    crate::root::hard_link(original, link)
+}
+
+/// Mounts the filesystem of type `fstype` on the directory `target`.
+///
+/// `source` is the block device (e.g., `/dev/vdb`) for disk filesystems
+/// (`vfat`), and is ignored for others (`ramfs`, `devfs`, `proc`, `sysfs`).
+/// The target directory is created if it does not exist. No mount flags are
+/// supported yet, so `flags` must be `0`.
+pub fn mount(source: &str, target: &str, fstype: &str, flags: usize) -> io::Result<()> {
+    crate::root::mount(source, target, fstype, flags)
+}
+
+/// Unmounts the filesystem mounted on `target`.
+///
+/// Returns [`ResourceBusy`](io::Error::ResourceBusy) if the filesystem is
+/// busy, i.e., there are opened files or directories in it, it contains the
+/// current directory, or other filesystems are mounted under it.
+pub fn umount(target: &str) -> io::Result<()> {
+    crate::root::umount(target)
+}
+
+/// Returns the names of all block devices (e.g., `vda`, `vdb`), which can be
+/// used as the mount source in the form of `/dev/<name>`.
+pub fn block_devices() -> Vec<String> {
+    crate::dev::block_device_names()
+}
+
+/// Returns the statistics of the block cache of a block device, which is
+/// given by its path (e.g., `/dev/vda`) or name (e.g., `vda`).
+pub fn block_cache_stats(device: &str) -> io::Result<CacheStats> {
+    Ok(crate::dev::find_block_device(device)?.cache_stats())
+}
+
+/// Sets the maximum number of blocks in the block cache of a block device.
+///
+/// Dirty blocks that exceed the new capacity are written back.
+pub fn set_block_cache_capacity(device: &str, blocks: usize) -> io::Result<()> {
+    crate::dev::find_block_device(device)?
+        .set_cache_capacity(blocks)
+        .map_err(|_| io::Error::Io)
+}
+
+/// Writes all modified data in the block caches back to the block devices.
+pub fn sync() -> io::Result<()> {
+    crate::dev::sync_all()
+}
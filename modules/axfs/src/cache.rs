@@ -0,0 +1,204 @@
+//! Block cache between filesystems and block devices.
+//!
+//! Blocks are cached in memory when they are read or written, and evicted in
+//! the least recently used (LRU) order when the cache is full. Writes only
+//! modify the cached blocks and mark them dirty, the dirty blocks are written
+//! back to the device when they are evicted, the cache is flushed, or they
+//! take up more than half of the cache.
+
+use alloc::{boxed::Box, collections::BTreeMap};
+
+use axdriver::prelude::*;
+
+pub(crate) const BLOCK_SIZE: usize = 512;
+
+/// The default number of blocks cached for each device (2 MiB).
+pub(crate) const DEFAULT_CACHE_BLOCKS: usize = 4096;
+
+/// Statistics of a block cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of block accesses served by the cache.
+    pub hits: u64,
+    /// Number of block accesses that missed the cache.
+    pub misses: u64,
+    /// Number of dirty blocks written back to the device.
+    pub writebacks: u64,
+    /// Number of blocks evicted from the cache.
+    pub evictions: u64,
+    /// Number of blocks currently in the cache.
+    pub cached: usize,
+    /// Number of dirty blocks currently in the cache.
+    pub dirty: usize,
+}
+
+struct CachedBlock {
+    data: Box<[u8; BLOCK_SIZE]>,
+    dirty: bool,
+    /// The last access stamp, which is the key in [`BlockCache::lru`].
+    stamp: u64,
+}
+
+/// A write-back block cache of a block device.
+pub(crate) struct BlockCache {
+    dev: AxBlockDevice,
+    blocks: BTreeMap<u64, CachedBlock>,
+    /// Block IDs ordered by their last access stamps, the first one is the
+    /// least recently used.
+    lru: BTreeMap<u64, u64>,
+    next_stamp: u64,
+    capacity: usize,
+    stats: CacheStats,
+}
+
+impl BlockCache {
+    /// Creates a new cache of the device, which holds at most `capacity`
+    /// blocks.
+    pub fn new(dev: AxBlockDevice, capacity: usize) -> Self {
+        assert_eq!(BLOCK_SIZE, dev.block_size());
+        Self {
+            dev,
+            blocks: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            next_stamp: 0,
+            capacity: capacity.max(1),
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Returns the number of blocks of the device.
+    pub fn num_blocks(&self) -> u64 {
+        self.dev.num_blocks()
+    }
+
+    /// Returns the statistics of the cache.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            cached: self.blocks.len(),
+            ..self.stats
+        }
+    }
+
+    /// Returns the maximum number of cached blocks.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Sets the maximum number of cached blocks, and evicts the blocks that
+    /// exceed it.
+    pub fn set_capacity(&mut self, capacity: usize) -> DevResult {
+        self.capacity = capacity.max(1);
+        while self.blocks.len() > self.capacity {
+            self.evict_one()?;
+        }
+        Ok(())
+    }
+
+    /// Reads `buf.len()` bytes at `offset` of the block.
+    pub fn read(&mut self, block_id: u64, offset: usize, buf: &mut [u8]) -> DevResult {
+        let block = self.get_block(block_id, true)?;
+        buf.copy_from_slice(&block.data[offset..offset + buf.len()]);
+        Ok(())
+    }
+
+    /// Writes `buf` at `offset` of the block.
+    ///
+    /// The block is only modified in the cache and marked dirty. If the whole
+    /// block is overwritten, it will not be read from the device on a miss.
+    pub fn write(&mut self, block_id: u64, offset: usize, buf: &[u8]) -> DevResult {
+        let whole = offset == 0 && buf.len() == BLOCK_SIZE;
+        let block = self.get_block(block_id, !whole)?;
+        block.data[offset..offset + buf.len()].copy_from_slice(buf);
+        let newly_dirty = !block.dirty;
+        block.dirty = true;
+        if newly_dirty {
+            self.stats.dirty += 1;
+            // Bound the data that would be lost on a crash, and keep clean
+            // blocks to evict without writing back.
+            if self.stats.dirty > self.dirty_limit() {
+                self.write_back()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes all dirty blocks back to the device, and flushes the device.
+    pub fn flush(&mut self) -> DevResult {
+        self.write_back()?;
+        self.dev.flush()
+    }
+
+    /// Returns the maximum number of dirty blocks in the cache.
+    fn dirty_limit(&self) -> usize {
+        (self.capacity / 2).max(1)
+    }
+
+    /// Writes all dirty blocks back to the device.
+    fn write_back(&mut self) -> DevResult {
+        for (&block_id, block) in self.blocks.iter_mut().filter(|(_, b)| b.dirty) {
+            self.dev.write_block(block_id, &block.data[..])?;
+            block.dirty = false;
+            self.stats.dirty -= 1;
+            self.stats.writebacks += 1;
+        }
+        Ok(())
+    }
+
+    /// Returns the cached block, and updates its access stamp.
+    ///
+    /// On a miss, the least recently used block is evicted if the cache is
+    /// full, and the block is read from the device if `fill` is `true`.
+    fn get_block(&mut self, block_id: u64, fill: bool) -> DevResult<&mut CachedBlock> {
+        let stamp = self.next_stamp;
+        self.next_stamp += 1;
+
+        if self.blocks.contains_key(&block_id) {
+            self.stats.hits += 1;
+            let block = self.blocks.get_mut(&block_id).unwrap();
+            self.lru.remove(&block.stamp);
+            self.lru.insert(stamp, block_id);
+            block.stamp = stamp;
+            return Ok(block);
+        }
+
+        self.stats.misses += 1;
+        if self.blocks.len() >= self.capacity {
+            self.evict_one()?;
+        }
+        let mut data = Box::new([0; BLOCK_SIZE]);
+        if fill {
+            self.dev.read_block(block_id, &mut data[..])?;
+        }
+        self.lru.insert(stamp, block_id);
+        Ok(self.blocks.entry(block_id).or_insert(CachedBlock {
+            data,
+            dirty: false,
+            stamp,
+        }))
+    }
+
+    /// Evicts the least recently used block, writes it back if it is dirty.
+    fn evict_one(&mut self) -> DevResult {
+        let Some((&stamp, &block_id)) = self.lru.first_key_value() else {
+            return Ok(());
+        };
+        let block = &self.blocks[&block_id];
+        if block.dirty {
+            self.dev.write_block(block_id, &block.data[..])?;
+            self.stats.dirty -= 1;
+            self.stats.writebacks += 1;
+        }
+        self.lru.remove(&stamp);
+        self.blocks.remove(&block_id);
+        self.stats.evictions += 1;
+        Ok(())
+    }
+}
+
+impl Drop for BlockCache {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            warn!("failed to write back the block cache: {:?}", e);
+        }
+    }
+}
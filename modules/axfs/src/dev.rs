@@ -1,23 +1,256 @@
-use axdriver::prelude::*;
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicBool, Ordering};
 
-const BLOCK_SIZE: usize = 512;
+use axdriver::{AxDeviceContainer, prelude::*};
+use axerrno::{AxError, AxResult, ax_err};
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+use crate::cache::{BLOCK_SIZE, BlockCache, CacheStats, DEFAULT_CACHE_BLOCKS};
+
+/// All block devices, in the order they are probed.
+static BLOCK_DEVICES: Mutex<Vec<Arc<BlockDevice>>> = Mutex::new(Vec::new());
+
+/// A named block device.
+///
+/// Devices are named in the order they are probed, i.e., `vda`, `vdb`, etc.
+/// Each device can be used by at most one filesystem at a time. Accesses to
+/// the device go through its block cache.
+pub struct BlockDevice {
+    name: String,
+    cache: Mutex<BlockCache>,
+    in_use: AtomicBool,
+}
+
+impl BlockDevice {
+    /// Returns the name of the device (e.g., `vda`).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the statistics of the block cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.lock().stats()
+    }
+
+    /// Returns the maximum number of blocks in the block cache.
+    pub fn cache_capacity(&self) -> usize {
+        self.cache.lock().capacity()
+    }
+
+    /// Sets the maximum number of blocks in the block cache.
+    ///
+    /// The least recently used blocks that exceed the new capacity are
+    /// evicted, and written back if they are dirty.
+    pub fn set_cache_capacity(&self, blocks: usize) -> DevResult {
+        self.cache.lock().set_capacity(blocks)
+    }
+
+    /// Writes all dirty blocks in the block cache back to the device.
+    pub fn flush(&self) -> DevResult {
+        self.cache.lock().flush()
+    }
+
+    fn num_blocks(&self) -> u64 {
+        self.cache.lock().num_blocks()
+    }
+
+    /// Calls `f` with each block in the byte range `[offset, offset + len)`,
+    /// the range within the block and the range within the buffer.
+    fn for_each_block<F>(&self, offset: u64, len: usize, mut f: F) -> VfsResult
+    where
+        F: FnMut(&mut BlockCache, u64, usize, core::ops::Range<usize>) -> DevResult,
+    {
+        let mut cache = self.cache.lock();
+        let mut pos = 0;
+        while pos < len {
+            let cur = offset + pos as u64;
+            let block_offset = cur as usize % BLOCK_SIZE;
+            let count = (BLOCK_SIZE - block_offset).min(len - pos);
+            f(
+                &mut cache,
+                cur / BLOCK_SIZE as u64,
+                block_offset,
+                pos..pos + count,
+            )
+            .map_err(|_| VfsError::Io)?;
+            pos += count;
+        }
+        Ok(())
+    }
+}
+
+/// The raw device node in `/dev`, which accesses the device through its
+/// block cache.
+///
+/// The node can not be written while the device is opened as a [`Disk`]
+/// (e.g., by a mounted filesystem), so that the filesystem is not corrupted
+/// behind its back. Reads are always allowed.
+impl VfsNodeOps for BlockDevice {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = self.num_blocks() * BLOCK_SIZE as u64;
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o660),
+            VfsNodeType::BlockDevice,
+            size,
+            size / 512,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let size = self.num_blocks() * BLOCK_SIZE as u64;
+        let len = buf.len().min(size.saturating_sub(offset) as usize);
+        self.for_each_block(offset, len, |cache, block_id, block_offset, range| {
+            cache.read(block_id, block_offset, &mut buf[range])
+        })?;
+        Ok(len)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        if self.in_use.load(Ordering::Acquire) {
+            return Err(VfsError::ResourceBusy);
+        }
+        let size = self.num_blocks() * BLOCK_SIZE as u64;
+        let len = buf.len().min(size.saturating_sub(offset) as usize);
+        if len == 0 && !buf.is_empty() {
+            return Err(VfsError::StorageFull);
+        }
+        self.for_each_block(offset, len, |cache, block_id, block_offset, range| {
+            cache.write(block_id, block_offset, &buf[range])
+        })?;
+        Ok(len)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.flush().map_err(|_| VfsError::Io)
+    }
+
+    /// Does nothing, as the size of the device is fixed.
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+}
+
+/// Names and registers all block devices.
+pub(crate) fn register_block_devices(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
+    let mut devices = BLOCK_DEVICES.lock();
+    while let Some(dev) = blk_devs.take_one() {
+        // `vda`, `vdb`, ..., `vdz`, `vdaa`, `vdab`, ...
+        let idx = devices.len();
+        let name = if idx < 26 {
+            format!("vd{}", (b'a' + idx as u8) as char)
+        } else {
+            format!(
+                "vd{}{}",
+                (b'a' + (idx / 26 - 1) as u8) as char,
+                (b'a' + (idx % 26) as u8) as char
+            )
+        };
+        info!("  block device {}: {:?}", name, dev.device_name());
+        #[cfg(feature = "sysfs")]
+        let driver = String::from(dev.device_name());
+        let dev = Arc::new(BlockDevice {
+            name,
+            cache: Mutex::new(BlockCache::new(dev, DEFAULT_CACHE_BLOCKS)),
+            in_use: AtomicBool::new(false),
+        });
+        #[cfg(feature = "sysfs")]
+        register_sysfs_attrs(&dev, driver);
+        #[cfg(feature = "devfs")]
+        crate::fs::devfs::register_device(&dev.name, dev.clone());
+        devices.push(dev);
+    }
+}
+
+/// Registers the attributes of the block device in `/sys/block/<name>`.
+#[cfg(feature = "sysfs")]
+fn register_sysfs_attrs(dev: &Arc<BlockDevice>, driver: String) {
+    use crate::fs::sysfs::{register_attr, register_writable_attr};
+
+    let dir = format!("block/{}", dev.name);
+    register_attr(&format!("{}/driver", dir), move || format!("{}\n", driver));
+    let d = dev.clone();
+    // in 512-byte sectors, as Linux does
+    register_attr(&format!("{}/size", dir), move || {
+        format!("{}\n", d.num_blocks() * BLOCK_SIZE as u64 / 512)
+    });
+    let d = dev.clone();
+    register_attr(&format!("{}/cache_stats", dir), move || {
+        let stats = d.cache_stats();
+        format!(
+            "hits {}\nmisses {}\nwritebacks {}\nevictions {}\ncached {}\ndirty {}\n",
+            stats.hits, stats.misses, stats.writebacks, stats.evictions, stats.cached, stats.dirty
+        )
+    });
+    let (d, store_dev) = (dev.clone(), dev.clone());
+    register_writable_attr(
+        &format!("{}/cache_capacity", dir),
+        move || format!("{}\n", d.cache_capacity()),
+        move |value| {
+            let blocks = value.parse().map_err(|_| AxError::InvalidInput)?;
+            store_dev
+                .set_cache_capacity(blocks)
+                .map_err(|_| AxError::Io)
+        },
+    );
+}
+
+/// Returns the names of all block devices.
+pub(crate) fn block_device_names() -> Vec<String> {
+    BLOCK_DEVICES
+        .lock()
+        .iter()
+        .map(|dev| dev.name.clone())
+        .collect()
+}
+
+/// Finds the block device by its path (e.g., `/dev/vda`) or name (e.g.,
+/// `vda`).
+pub(crate) fn find_block_device(path: &str) -> AxResult<Arc<BlockDevice>> {
+    let name = path.strip_prefix("/dev/").unwrap_or(path);
+    BLOCK_DEVICES
+        .lock()
+        .iter()
+        .find(|dev| dev.name == name)
+        .cloned()
+        .ok_or(AxError::NotFound)
+}
+
+/// Returns the first block device, which holds the root filesystem.
+pub(crate) fn root_block_device() -> Option<Arc<BlockDevice>> {
+    BLOCK_DEVICES.lock().first().cloned()
+}
 
 /// A disk device with a cursor.
 pub struct Disk {
     block_id: u64,
     offset: usize,
-    dev: AxBlockDevice,
+    dev: Arc<BlockDevice>,
 }
 
 impl Disk {
-    /// Create a new disk.
-    pub fn new(dev: AxBlockDevice) -> Self {
-        assert_eq!(BLOCK_SIZE, dev.block_size());
-        Self {
+    /// Opens the block device as a disk.
+    ///
+    /// Returns [`ResourceBusy`](AxError::ResourceBusy) if the device
+    /// is already used by another disk.
+    pub fn open(dev: Arc<BlockDevice>) -> AxResult<Self> {
+        if dev.in_use.swap(true, Ordering::Acquire) {
+            return ax_err!(ResourceBusy, "block device is in use");
+        }
+        Ok(Self {
             block_id: 0,
             offset: 0,
             dev,
-        }
+        })
+    }
+
+    /// Returns the underlying block device.
+    pub fn device(&self) -> &Arc<BlockDevice> {
+        &self.dev
     }
 
     /// Get the size of the disk.
@@ -38,55 +271,54 @@ impl Disk {
 
     /// Read within one block, returns the number of bytes read.
     pub fn read_one(&mut self, buf: &mut [u8]) -> DevResult<usize> {
-        let read_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev
-                .read_block(self.block_id, &mut buf[0..BLOCK_SIZE])?;
-            self.block_id += 1;
-            BLOCK_SIZE
-        } else {
-            // partial block
-            let mut data = [0u8; BLOCK_SIZE];
-            let start = self.offset;
-            let count = buf.len().min(BLOCK_SIZE - self.offset);
-
-            self.dev.read_block(self.block_id, &mut data)?;
-            buf[..count].copy_from_slice(&data[start..start + count]);
-
-            self.offset += count;
-            if self.offset >= BLOCK_SIZE {
-                self.block_id += 1;
-                self.offset -= BLOCK_SIZE;
-            }
-            count
-        };
-        Ok(read_size)
+        let count = buf.len().min(BLOCK_SIZE - self.offset);
+        self.dev
+            .cache
+            .lock()
+            .read(self.block_id, self.offset, &mut buf[..count])?;
+        self.advance(count);
+        Ok(count)
     }
 
     /// Write within one block, returns the number of bytes written.
     pub fn write_one(&mut self, buf: &[u8]) -> DevResult<usize> {
-        let write_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev.write_block(self.block_id, &buf[0..BLOCK_SIZE])?;
+        let count = buf.len().min(BLOCK_SIZE - self.offset);
+        self.dev
+            .cache
+            .lock()
+            .write(self.block_id, self.offset, &buf[..count])?;
+        self.advance(count);
+        Ok(count)
+    }
+
+    /// Writes all dirty blocks of the disk back to the device.
+    pub fn flush(&mut self) -> DevResult {
+        self.dev.flush()
+    }
+
+    fn advance(&mut self, count: usize) {
+        self.offset += count;
+        if self.offset >= BLOCK_SIZE {
             self.block_id += 1;
-            BLOCK_SIZE
-        } else {
-            // partial block
-            let mut data = [0u8; BLOCK_SIZE];
-            let start = self.offset;
-            let count = buf.len().min(BLOCK_SIZE - self.offset);
-
-            self.dev.read_block(self.block_id, &mut data)?;
-            data[start..start + count].copy_from_slice(&buf[..count]);
-            self.dev.write_block(self.block_id, &data)?;
-
-            self.offset += count;
-            if self.offset >= BLOCK_SIZE {
-                self.block_id += 1;
-                self.offset -= BLOCK_SIZE;
-            }
-            count
-        };
-        Ok(write_size)
+            self.offset -= BLOCK_SIZE;
+        }
+    }
+}
+
+impl Drop for Disk {
+    fn drop(&mut self) {
+        if let Err(e) = self.dev.flush() {
+            warn!("failed to flush block device {}: {:?}", self.dev.name, e);
+        }
+        self.dev.in_use.store(false, Ordering::Release);
+    }
+}
+
+/// Writes the dirty blocks of all block devices back.
+pub(crate) fn sync_all() -> AxResult {
+    let devices = BLOCK_DEVICES.lock().clone();
+    for dev in devices {
+        dev.flush().map_err(|_| AxError::Io)?;
     }
+    Ok(())
 }
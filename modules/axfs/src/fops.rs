@@ -1,13 +1,19 @@
 //! Low-level filesystem operations.
 
+use alloc::sync::Arc;
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axfs_vfs::{VfsError, VfsNodeRef};
 use axio::SeekFrom;
 use cap_access::{Cap, WithCap};
 use core::fmt;
+use core::time::Duration;
+
+use crate::root::MountRef;
+
+pub use crate::lock::{DEADLOCK_ERROR, FileLock, LockKind, LockOwner};
 
 #[cfg(feature = "myfs")]
-pub use crate::dev::Disk;
+pub use crate::dev::{BlockDevice, Disk};
 #[cfg(feature = "myfs")]
 pub use crate::fs::myfs::MyFileSystemIf;
 
@@ -15,16 +21,34 @@ pub use crate::fs::myfs::MyFileSystemIf;
 pub type FileType = axfs_vfs::VfsNodeType;
 /// Alias of [`axfs_vfs::VfsDirEntry`].
 pub type DirEntry = axfs_vfs::VfsDirEntry;
-/// Alias of [`axfs_vfs::VfsNodeAttr`].
-pub type FileAttr = axfs_vfs::VfsNodeAttr;
 /// Alias of [`axfs_vfs::VfsNodePerm`].
 pub type FilePerm = axfs_vfs::VfsNodePerm;
 
+/// Timestamps of a file, as durations since the UNIX epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileTimes {
+    /// Time of the last access.
+    pub accessed: Duration,
+    /// Time of the last modification.
+    pub modified: Duration,
+    /// Time of the creation.
+    pub created: Duration,
+}
+
+/// File attributes, including the timestamps if the filesystem records them.
+#[derive(Debug, Clone, Copy)]
+pub struct FileAttr {
+    attr: axfs_vfs::VfsNodeAttr,
+    times: Option<FileTimes>,
+}
+
 /// An opened file object, with open permissions and a cursor.
 pub struct File {
     node: WithCap<VfsNodeRef>,
     is_append: bool,
     offset: u64,
+    mount: Option<MountRef>,
+    locks: Arc<LockOwner>,
 }
 
 /// An opened directory object, with open permissions and a cursor for
@@ -32,6 +56,7 @@ pub struct File {
 pub struct Directory {
     node: WithCap<VfsNodeRef>,
     entry_idx: usize,
+    mount: Option<MountRef>,
 }
 
 /// Options and flags which can be used to configure how a file is opened.
@@ -122,14 +147,19 @@ impl File {
         self.node.access_or_err(cap, AxError::PermissionDenied)
     }
 
-    fn _open_at(dir: Option<&VfsNodeRef>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
+    fn _open_at(
+        dir: Option<&VfsNodeRef>,
+        mount: Option<MountRef>,
+        path: &str,
+        opts: &OpenOptions,
+    ) -> AxResult<Self> {
         debug!("open file: {} {:?}", path, opts);
         if !opts.is_valid() {
             return ax_err!(InvalidInput);
         }
 
-        let node_option = crate::root::lookup(dir, path);
-        let node = if opts.create || opts.create_new {
+        let node_option = crate::root::lookup_mounted(dir, mount.as_ref(), path);
+        let (node, mount) = if opts.create || opts.create_new {
             match node_option {
                 Ok(node) => {
                     // already exists
@@ -139,7 +169,9 @@ impl File {
                     node
                 }
                 // not exists, create new
-                Err(VfsError::NotFound) => crate::root::create_file(dir, path)?,
+                Err(VfsError::NotFound) => {
+                    crate::root::create_file_mounted(dir, mount.as_ref(), path)?
+                }
                 Err(e) => return Err(e),
             }
         } else {
@@ -162,17 +194,21 @@ impl File {
         if opts.truncate {
             node.truncate(0)?;
         }
+        let (file_id, keep_node) = crate::root::file_id(mount.as_ref(), &node);
+        let locks = LockOwner::new(file_id, keep_node.then(|| node.clone()));
         Ok(Self {
             node: WithCap::new(node, access_cap),
             is_append: opts.append,
             offset: 0,
+            mount,
+            locks: Arc::new(locks),
         })
     }
 
     /// Opens a file at the path relative to the current directory. Returns a
     /// [`File`] object.
     pub fn open(path: &str, opts: &OpenOptions) -> AxResult<Self> {
-        Self::_open_at(None, path, opts)
+        Self::_open_at(None, crate::root::mount_ref(None, None, path), path, opts)
     }
 
     /// Truncates the file to the specified size.
@@ -250,7 +286,31 @@ impl File {
 
     /// Gets the file attributes.
     pub fn get_attr(&self) -> AxResult<FileAttr> {
-        self.access_node(Cap::empty())?.get_attr()
+        FileAttr::of(self.access_node(Cap::empty())?, self.mount.as_ref())
+    }
+
+    /// Sets the last access and modification times of the file, as durations
+    /// since the UNIX epoch. `None` leaves a time unchanged.
+    pub fn set_times(&self, accessed: Option<Duration>, modified: Option<Duration>) -> AxResult {
+        let node = self.access_node(Cap::WRITE)?;
+        crate::root::set_node_times(self.mount.as_ref(), node, accessed, modified)
+    }
+
+    /// Performs the device-specific request `cmd` with the argument `arg`
+    /// (e.g., a pointer to the data of the request) on the file.
+    ///
+    /// Only devices in `/dev` that are registered with a handler support it.
+    pub fn ioctl(&self, cmd: u32, arg: usize) -> AxResult<usize> {
+        let node = self.access_node(Cap::empty())?;
+        crate::root::node_ioctl(self.mount.as_ref(), node, cmd, arg)
+    }
+
+    /// Returns the owner of the advisory locks taken through this file.
+    ///
+    /// The locks are released when the last reference to the owner is
+    /// dropped, so it can outlive the file to wait for a lock.
+    pub fn lock_owner(&self) -> Arc<LockOwner> {
+        self.locks.clone()
     }
 }
 
@@ -259,7 +319,12 @@ impl Directory {
         self.node.access_or_err(cap, AxError::PermissionDenied)
     }
 
-    fn _open_dir_at(dir: Option<&VfsNodeRef>, path: &str, opts: &OpenOptions) -> AxResult<Self> {
+    fn _open_dir_at(
+        dir: Option<&VfsNodeRef>,
+        mount: Option<MountRef>,
+        path: &str,
+        opts: &OpenOptions,
+    ) -> AxResult<Self> {
         debug!("open dir: {}", path);
         if !opts.read {
             return ax_err!(InvalidInput);
@@ -282,6 +347,7 @@ impl Directory {
         Ok(Self {
             node: WithCap::new(node, access_cap),
             entry_idx: 0,
+            mount,
         })
     }
 
@@ -293,22 +359,33 @@ impl Directory {
         }
     }
 
+    /// Returns the reference to the mounted filesystem that `path` relative
+    /// to this directory belongs to.
+    fn mount_at(&self, path: &str) -> AxResult<Option<MountRef>> {
+        let dir = self.access_at(path)?;
+        Ok(crate::root::mount_ref(
+            dir.as_ref(),
+            self.mount.as_ref(),
+            path,
+        ))
+    }
+
     /// Opens a directory at the path relative to the current directory.
     /// Returns a [`Directory`] object.
     pub fn open_dir(path: &str, opts: &OpenOptions) -> AxResult<Self> {
-        Self::_open_dir_at(None, path, opts)
+        Self::_open_dir_at(None, crate::root::mount_ref(None, None, path), path, opts)
     }
 
     /// Opens a directory at the path relative to this directory. Returns a
     /// [`Directory`] object.
     pub fn open_dir_at(&self, path: &str, opts: &OpenOptions) -> AxResult<Self> {
-        Self::_open_dir_at(self.access_at(path)?, path, opts)
+        Self::_open_dir_at(self.access_at(path)?, self.mount_at(path)?, path, opts)
     }
 
     /// Opens a file at the path relative to this directory. Returns a [`File`]
     /// object.
     pub fn open_file_at(&self, path: &str, opts: &OpenOptions) -> AxResult<File> {
-        File::_open_at(self.access_at(path)?, path, opts)
+        File::_open_at(self.access_at(path)?, self.mount_at(path)?, path, opts)
     }
 
     /// Creates an empty file at the path relative to this directory.
@@ -353,6 +430,75 @@ impl Directory {
     }
 }
 
+/// Gets the attributes of the file at the path relative to the current
+/// directory, without following the symbolic link at the end of the path.
+pub fn symlink_attr(path: &str) -> AxResult<FileAttr> {
+    let node = crate::root::lookup_link(None, path)?;
+    FileAttr::of(&node, crate::root::mount_ref(None, None, path).as_ref())
+}
+
+impl FileAttr {
+    fn of(node: &VfsNodeRef, mount: Option<&MountRef>) -> AxResult<Self> {
+        Ok(Self {
+            attr: node.get_attr()?,
+            times: crate::root::node_times(mount, node),
+        })
+    }
+
+    /// Returns the permissions of the file.
+    pub const fn perm(&self) -> FilePerm {
+        self.attr.perm()
+    }
+
+    /// Returns the type of the file.
+    pub const fn file_type(&self) -> FileType {
+        self.attr.file_type()
+    }
+
+    /// Whether the file is a directory.
+    pub const fn is_dir(&self) -> bool {
+        self.attr.is_dir()
+    }
+
+    /// Whether the file is a regular file.
+    pub const fn is_file(&self) -> bool {
+        self.attr.is_file()
+    }
+
+    /// Whether the file is a symbolic link.
+    pub const fn is_symlink(&self) -> bool {
+        self.attr.file_type().is_symlink()
+    }
+
+    /// Returns the size of the file, in bytes.
+    pub const fn size(&self) -> u64 {
+        self.attr.size()
+    }
+
+    /// Returns the number of blocks allocated to the file, in 512-byte units.
+    pub const fn blocks(&self) -> u64 {
+        self.attr.blocks()
+    }
+
+    /// Returns the time of the last access since the UNIX epoch, or `None`
+    /// if the filesystem does not record it.
+    pub fn accessed(&self) -> Option<Duration> {
+        self.times.map(|t| t.accessed)
+    }
+
+    /// Returns the time of the last modification since the UNIX epoch, or
+    /// `None` if the filesystem does not record it.
+    pub fn modified(&self) -> Option<Duration> {
+        self.times.map(|t| t.modified)
+    }
+
+    /// Returns the time of the creation since the UNIX epoch, or `None` if
+    /// the filesystem does not record it.
+    pub fn created(&self) -> Option<Duration> {
+        self.times.map(|t| t.created)
+    }
+}
+
 impl Drop for File {
     fn drop(&mut self) {
         unsafe { self.node.access_unchecked().release().ok() };
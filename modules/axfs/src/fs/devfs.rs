@@ -0,0 +1,100 @@
+//! The device filesystem mounted on `/dev`, based on [`axfs_devfs`].
+//!
+//! Devices are provided by the modules that drive them, which register them
+//! with [`register_device`] when they are probed. Devices can be registered
+//! before or after the filesystem is mounted, and they appear in all mounted
+//! instances. Devices that take requests beyond reads and writes are
+//! registered with [`register_device_with_ioctl`] instead.
+
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use axerrno::AxResult;
+use axfs_vfs::{VfsError, VfsNodeRef, VfsResult};
+use axsync::Mutex;
+
+use super::NodeExtOps;
+
+pub use axfs_devfs::*;
+
+/// Handles the device-specific request `cmd` with the argument `arg`, which
+/// is usually a pointer to the data of the request. Returns the non-negative
+/// result of the request.
+pub type DeviceIoctlFn = fn(cmd: u32, arg: usize) -> AxResult<usize>;
+
+struct Registry {
+    devices: Vec<(&'static str, VfsNodeRef, Option<DeviceIoctlFn>)>,
+    /// The mounted instances, to which later registered devices are added.
+    instances: Vec<Weak<DeviceFileSystem>>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    devices: Vec::new(),
+    instances: Vec::new(),
+});
+
+/// Registers a device named `name` (e.g., `vda`) in `/dev`.
+///
+/// It is intended to be called once for each device when it is probed, as
+/// the name is kept for the lifetime of the kernel. An existing device with
+/// the same name is replaced.
+pub fn register_device(name: &str, dev: VfsNodeRef) {
+    register(name, dev, None)
+}
+
+/// Registers a device named `name` (e.g., `rtc`) in `/dev`, with the handler
+/// of the requests issued by `ioctl` on it.
+///
+/// See [`register_device`] for the name.
+pub fn register_device_with_ioctl(name: &str, dev: VfsNodeRef, ioctl: DeviceIoctlFn) {
+    register(name, dev, Some(ioctl))
+}
+
+fn register(name: &str, dev: VfsNodeRef, ioctl: Option<DeviceIoctlFn>) {
+    let name: &'static str = String::from(name).leak();
+    let mut registry = REGISTRY.lock();
+    registry.instances.retain(|fs| match fs.upgrade() {
+        Some(fs) => {
+            fs.add(name, dev.clone());
+            true
+        }
+        None => false,
+    });
+    registry.devices.retain(|(n, ..)| *n != name);
+    registry.devices.push((name, dev, ioctl));
+}
+
+/// Adds all registered devices to the new instance `fs`, and keeps track of
+/// it for the devices registered later.
+pub(crate) fn attach(fs: &Arc<DeviceFileSystem>) {
+    let mut registry = REGISTRY.lock();
+    for (name, dev, _) in registry.devices.iter() {
+        fs.add(name, dev.clone());
+    }
+    registry.instances.push(Arc::downgrade(fs));
+}
+
+/// The extra node operations of the device filesystem.
+pub(crate) const EXT_OPS: NodeExtOps = NodeExtOps {
+    node_times: None,
+    set_times: None,
+    hard_link: None,
+    ioctl: Some(ioctl),
+    node_id: None,
+};
+
+/// Passes the request to the handler of the device, or fails with
+/// [`Unsupported`](VfsError::Unsupported) if it has no handler (e.g., it is
+/// a directory).
+fn ioctl(node: &VfsNodeRef, cmd: u32, arg: usize) -> VfsResult<usize> {
+    let handler = REGISTRY
+        .lock()
+        .devices
+        .iter()
+        .find(|(_, dev, _)| Arc::ptr_eq(dev, node))
+        .and_then(|(.., ioctl)| *ioctl);
+    match handler {
+        Some(handler) => handler(cmd, arg),
+        None => Err(VfsError::Unsupported),
+    }
+}
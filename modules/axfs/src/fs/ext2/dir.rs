@@ -0,0 +1,262 @@
+//! Directory entries of ext2.
+//!
+//! Directories are stored as linked lists of entries in their data blocks.
+//! Hashed B-tree indexes of ext3/ext4 are compatible with the lists, they are
+//! ignored when reading, and dropped when the directory is modified.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use axfs_vfs::{VfsError, VfsNodeType, VfsResult};
+
+use super::layout::*;
+use super::volume::Volume;
+
+const MAX_NAME_LEN: usize = 255;
+
+/// An entry in a directory.
+pub struct DirEntry {
+    pub ino: u32,
+    pub name: String,
+    /// The type of the node, if the filesystem records it in entries.
+    pub ty: Option<VfsNodeType>,
+}
+
+/// A raw entry in a directory block.
+struct RawEntry {
+    /// Offset in the block.
+    off: usize,
+    ino: u32,
+    rec_len: usize,
+    name_len: usize,
+    file_type: u8,
+}
+
+impl RawEntry {
+    fn name<'a>(&self, block: &'a [u8]) -> &'a [u8] {
+        let start = self.off + DIRENT_HEADER_SIZE;
+        &block[start..start + self.name_len]
+    }
+}
+
+/// Parses the entries in a directory block.
+fn parse_block(block: &[u8], has_filetype: bool) -> VfsResult<Vec<RawEntry>> {
+    let mut entries = Vec::new();
+    let mut off = 0;
+    while off + DIRENT_HEADER_SIZE <= block.len() {
+        let rec_len = get_u16(block, off + 4) as usize;
+        let (name_len, file_type) = if has_filetype {
+            (block[off + 6] as usize, block[off + 7])
+        } else {
+            (get_u16(block, off + 6) as usize, 0)
+        };
+        if rec_len < dirent_rec_len(name_len) || off + rec_len > block.len() {
+            warn!("ext2: corrupted directory entry at offset {}", off);
+            return Err(VfsError::InvalidData);
+        }
+        entries.push(RawEntry {
+            off,
+            ino: get_u32(block, off),
+            rec_len,
+            name_len,
+            file_type,
+        });
+        off += rec_len;
+    }
+    Ok(entries)
+}
+
+/// Writes an entry at `off` of the directory block.
+fn write_entry(block: &mut [u8], off: usize, rec_len: usize, ino: u32, name: &str, file_type: u8) {
+    set_u32(block, off, ino);
+    set_u16(block, off + 4, rec_len as u16);
+    block[off + 6] = name.len() as u8;
+    block[off + 7] = file_type;
+    let start = off + DIRENT_HEADER_SIZE;
+    block[start..start + name.len()].copy_from_slice(name.as_bytes());
+}
+
+impl Volume {
+    /// Returns the physical blocks of the directory.
+    fn dir_blocks(&mut self, dir: &mut Inode) -> VfsResult<Vec<u64>> {
+        let nblocks = dir.size().div_ceil(self.block_size() as u64);
+        let mut blocks = Vec::new();
+        for lblk in 0..nblocks {
+            if let Some(block) = self.bmap(dir, lblk, false)? {
+                blocks.push(block);
+            }
+        }
+        Ok(blocks)
+    }
+
+    fn file_type_of(&self, ty: VfsNodeType) -> u8 {
+        if self.has_filetype() {
+            type_to_dirent_type(ty)
+        } else {
+            0
+        }
+    }
+
+    /// Returns all entries of the directory, including `.` and `..`.
+    pub fn read_dir(&mut self, dir: &mut Inode) -> VfsResult<Vec<DirEntry>> {
+        let has_filetype = self.has_filetype();
+        let mut entries = Vec::new();
+        for block in self.dir_blocks(dir)? {
+            let buf = self.read_block(block)?;
+            for raw in parse_block(&buf, has_filetype)? {
+                if raw.ino == 0 {
+                    continue;
+                }
+                entries.push(DirEntry {
+                    ino: raw.ino,
+                    name: String::from_utf8_lossy(raw.name(&buf)).to_string(),
+                    ty: dirent_type_to_type(raw.file_type),
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Finds the entry of `name` in the directory.
+    pub fn find_entry(&mut self, dir: &mut Inode, name: &str) -> VfsResult<Option<DirEntry>> {
+        let has_filetype = self.has_filetype();
+        for block in self.dir_blocks(dir)? {
+            let buf = self.read_block(block)?;
+            for raw in parse_block(&buf, has_filetype)? {
+                if raw.ino != 0 && raw.name(&buf) == name.as_bytes() {
+                    return Ok(Some(DirEntry {
+                        ino: raw.ino,
+                        name: name.into(),
+                        ty: dirent_type_to_type(raw.file_type),
+                    }));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns whether the directory has no entries other than `.` and `..`.
+    pub fn is_dir_empty(&mut self, dir: &mut Inode) -> VfsResult<bool> {
+        Ok(self
+            .read_dir(dir)?
+            .iter()
+            .all(|e| e.name == "." || e.name == ".."))
+    }
+
+    /// Adds an entry of `name` to the directory.
+    ///
+    /// It does not check whether the name already exists.
+    pub fn add_entry(
+        &mut self,
+        dir: &mut Inode,
+        name: &str,
+        ino: u32,
+        ty: VfsNodeType,
+    ) -> VfsResult {
+        self.check_writable()?;
+        if name.is_empty() || name.len() > MAX_NAME_LEN {
+            return Err(VfsError::InvalidInput);
+        }
+        // The index is not maintained.
+        dir.set_flags(dir.flags() & !INDEX_FL);
+
+        let has_filetype = self.has_filetype();
+        let file_type = self.file_type_of(ty);
+        let needed = dirent_rec_len(name.len());
+        for block in self.dir_blocks(dir)? {
+            let mut buf = self.read_block(block)?;
+            for raw in parse_block(&buf, has_filetype)? {
+                let used = if raw.ino == 0 {
+                    0
+                } else {
+                    dirent_rec_len(raw.name_len)
+                };
+                if raw.rec_len - used < needed {
+                    continue;
+                }
+                if used == 0 {
+                    write_entry(&mut buf, raw.off, raw.rec_len, ino, name, file_type);
+                } else {
+                    // split the free space of the entry
+                    set_u16(&mut buf, raw.off + 4, used as u16);
+                    let off = raw.off + used;
+                    write_entry(&mut buf, off, raw.rec_len - used, ino, name, file_type);
+                }
+                return self.write_block(block, &buf);
+            }
+        }
+
+        // no space in existing blocks, append a new one
+        let bs = self.block_size();
+        let lblk = dir.size().div_ceil(bs as u64);
+        let block = self.bmap(dir, lblk, true)?.unwrap();
+        let mut buf = alloc::vec![0; bs];
+        write_entry(&mut buf, 0, bs, ino, name, file_type);
+        self.write_block(block, &buf)?;
+        dir.set_size((lblk + 1) * bs as u64);
+        Ok(())
+    }
+
+    /// Removes the entry of `name` from the directory, returns its inode
+    /// number.
+    pub fn remove_entry(&mut self, dir: &mut Inode, name: &str) -> VfsResult<u32> {
+        self.check_writable()?;
+        dir.set_flags(dir.flags() & !INDEX_FL);
+
+        let has_filetype = self.has_filetype();
+        for block in self.dir_blocks(dir)? {
+            let mut buf = self.read_block(block)?;
+            let entries = parse_block(&buf, has_filetype)?;
+            let Some(i) = entries
+                .iter()
+                .position(|raw| raw.ino != 0 && raw.name(&buf) == name.as_bytes())
+            else {
+                continue;
+            };
+            let ino = entries[i].ino;
+            if i == 0 {
+                // the first entry of a block can not be merged
+                set_u32(&mut buf, 0, 0);
+            } else {
+                let prev = &entries[i - 1];
+                let rec_len = prev.rec_len + entries[i].rec_len;
+                set_u16(&mut buf, prev.off + 4, rec_len as u16);
+            }
+            self.write_block(block, &buf)?;
+            return Ok(ino);
+        }
+        Err(VfsError::NotFound)
+    }
+
+    /// Initializes a new directory with `.` and `..` entries.
+    pub fn init_dir(&mut self, dir: &mut Inode, parent: u32) -> VfsResult {
+        let bs = self.block_size();
+        let block = self.bmap(dir, 0, true)?.unwrap();
+        let file_type = self.file_type_of(VfsNodeType::Dir);
+        let mut buf = alloc::vec![0; bs];
+        let dot_len = dirent_rec_len(1);
+        write_entry(&mut buf, 0, dot_len, dir.ino, ".", file_type);
+        write_entry(&mut buf, dot_len, bs - dot_len, parent, "..", file_type);
+        self.write_block(block, &buf)?;
+        dir.set_size(bs as u64);
+        Ok(())
+    }
+
+    /// Points the `..` entry of the directory to `parent`.
+    pub fn set_parent_entry(&mut self, dir: &mut Inode, parent: u32) -> VfsResult {
+        let has_filetype = self.has_filetype();
+        let Some(&block) = self.dir_blocks(dir)?.first() else {
+            return Err(VfsError::InvalidData);
+        };
+        let mut buf = self.read_block(block)?;
+        let entries = parse_block(&buf, has_filetype)?;
+        let dotdot = entries
+            .iter()
+            .find(|raw| raw.ino != 0 && raw.name(&buf) == b"..")
+            .ok_or(VfsError::InvalidData)?;
+        set_u32(&mut buf, dotdot.off, parent);
+        self.write_block(block, &buf)
+    }
+}
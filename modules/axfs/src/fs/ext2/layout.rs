@@ -0,0 +1,476 @@
+//! On-disk structures of ext2, and the parts of ext4 that can be read.
+//!
+//! See <https://www.nongnu.org/ext2-doc/ext2.html> and
+//! <https://docs.kernel.org/filesystems/ext4/ondisk.html>.
+//!
+//! The structures keep their raw bytes, so that the fields not known by us are
+//! preserved when they are written back.
+
+use alloc::{boxed::Box, vec};
+
+use axfs_vfs::VfsNodeType;
+
+pub const SUPERBLOCK_OFFSET: u64 = 1024;
+pub const SUPERBLOCK_SIZE: usize = 1024;
+pub const EXT2_MAGIC: u16 = 0xef53;
+pub const ROOT_INO: u32 = 2;
+/// The first non-reserved inode of revision 0 filesystems.
+const GOOD_OLD_FIRST_INO: u32 = 11;
+const GOOD_OLD_INODE_SIZE: u16 = 128;
+
+pub const INCOMPAT_FILETYPE: u32 = 0x2;
+pub const INCOMPAT_RECOVER: u32 = 0x4;
+pub const INCOMPAT_EXTENTS: u32 = 0x40;
+pub const INCOMPAT_64BIT: u32 = 0x80;
+pub const INCOMPAT_FLEX_BG: u32 = 0x200;
+pub const INCOMPAT_CSUM_SEED: u32 = 0x2000;
+pub const INCOMPAT_LARGEDIR: u32 = 0x4000;
+
+pub const RO_COMPAT_SPARSE_SUPER: u32 = 0x1;
+pub const RO_COMPAT_LARGE_FILE: u32 = 0x2;
+
+/// Incompatible features that we can read.
+pub const INCOMPAT_READ: u32 = INCOMPAT_FILETYPE
+    | INCOMPAT_RECOVER
+    | INCOMPAT_EXTENTS
+    | INCOMPAT_64BIT
+    | INCOMPAT_FLEX_BG
+    | INCOMPAT_CSUM_SEED
+    | INCOMPAT_LARGEDIR;
+/// Incompatible features that we can write.
+pub const INCOMPAT_WRITE: u32 = INCOMPAT_FILETYPE;
+/// Read-only compatible features that we can write.
+pub const RO_COMPAT_WRITE: u32 = RO_COMPAT_SPARSE_SUPER | RO_COMPAT_LARGE_FILE;
+
+pub const S_IFMT: u16 = 0o170000;
+pub const S_IFREG: u16 = 0o100000;
+pub const S_IFDIR: u16 = 0o040000;
+pub const S_IFLNK: u16 = 0o120000;
+
+/// The directory is indexed by a hashed B-tree.
+pub const INDEX_FL: u32 = 0x1000;
+/// The inode uses extents to map its blocks.
+pub const EXTENTS_FL: u32 = 0x80000;
+/// The inode stores its data inline.
+pub const INLINE_DATA_FL: u32 = 0x1000_0000;
+
+/// Number of block pointers in an inode.
+pub const N_BLOCKS: usize = 15;
+/// Number of direct block pointers in an inode.
+pub const N_DIRECT: usize = 12;
+
+pub const EXTENT_MAGIC: u16 = 0xf30a;
+/// Extents longer than this are uninitialized, and read as zeros.
+pub const EXTENT_INIT_MAX_LEN: u16 = 32768;
+
+pub fn get_u16(buf: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes([buf[off], buf[off + 1]])
+}
+
+pub fn get_u32(buf: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(buf[off..off + 4].try_into().unwrap())
+}
+
+pub fn set_u16(buf: &mut [u8], off: usize, val: u16) {
+    buf[off..off + 2].copy_from_slice(&val.to_le_bytes());
+}
+
+pub fn set_u32(buf: &mut [u8], off: usize, val: u32) {
+    buf[off..off + 4].copy_from_slice(&val.to_le_bytes());
+}
+
+/// The superblock.
+pub struct SuperBlock(Box<[u8]>);
+
+impl SuperBlock {
+    pub fn new(raw: Box<[u8]>) -> Self {
+        assert_eq!(raw.len(), SUPERBLOCK_SIZE);
+        Self(raw)
+    }
+
+    pub fn raw(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn inodes_count(&self) -> u32 {
+        get_u32(&self.0, 0)
+    }
+
+    pub fn blocks_count(&self) -> u64 {
+        let lo = get_u32(&self.0, 4) as u64;
+        if self.is_64bit() {
+            lo | (get_u32(&self.0, 0x150) as u64) << 32
+        } else {
+            lo
+        }
+    }
+
+    pub fn free_blocks_count(&self) -> u64 {
+        let lo = get_u32(&self.0, 12) as u64;
+        if self.is_64bit() {
+            lo | (get_u32(&self.0, 0x158) as u64) << 32
+        } else {
+            lo
+        }
+    }
+
+    pub fn set_free_blocks_count(&mut self, count: u64) {
+        set_u32(&mut self.0, 12, count as u32);
+        if self.is_64bit() {
+            set_u32(&mut self.0, 0x158, (count >> 32) as u32);
+        }
+    }
+
+    pub fn free_inodes_count(&self) -> u32 {
+        get_u32(&self.0, 16)
+    }
+
+    pub fn set_free_inodes_count(&mut self, count: u32) {
+        set_u32(&mut self.0, 16, count);
+    }
+
+    pub fn first_data_block(&self) -> u32 {
+        get_u32(&self.0, 20)
+    }
+
+    pub fn block_size(&self) -> usize {
+        1024 << get_u32(&self.0, 24)
+    }
+
+    pub fn blocks_per_group(&self) -> u32 {
+        get_u32(&self.0, 32)
+    }
+
+    pub fn inodes_per_group(&self) -> u32 {
+        get_u32(&self.0, 40)
+    }
+
+    pub fn magic(&self) -> u16 {
+        get_u16(&self.0, 56)
+    }
+
+    fn rev_level(&self) -> u32 {
+        get_u32(&self.0, 76)
+    }
+
+    pub fn first_ino(&self) -> u32 {
+        if self.rev_level() == 0 {
+            GOOD_OLD_FIRST_INO
+        } else {
+            get_u32(&self.0, 84)
+        }
+    }
+
+    pub fn inode_size(&self) -> usize {
+        if self.rev_level() == 0 {
+            GOOD_OLD_INODE_SIZE as usize
+        } else {
+            get_u16(&self.0, 88) as usize
+        }
+    }
+
+    pub fn feature_incompat(&self) -> u32 {
+        get_u32(&self.0, 96)
+    }
+
+    pub fn feature_ro_compat(&self) -> u32 {
+        get_u32(&self.0, 100)
+    }
+
+    pub fn set_feature_ro_compat(&mut self, features: u32) {
+        set_u32(&mut self.0, 100, features);
+    }
+
+    pub fn is_64bit(&self) -> bool {
+        self.feature_incompat() & INCOMPAT_64BIT != 0
+    }
+
+    /// Returns the size of a group descriptor.
+    pub fn desc_size(&self) -> usize {
+        if self.is_64bit() {
+            get_u16(&self.0, 254) as usize
+        } else {
+            32
+        }
+    }
+
+    pub fn groups_count(&self) -> u32 {
+        (self.blocks_count() - self.first_data_block() as u64)
+            .div_ceil(self.blocks_per_group() as u64) as u32
+    }
+}
+
+/// A block group descriptor.
+pub struct GroupDesc {
+    raw: Box<[u8]>,
+    is_64bit: bool,
+}
+
+impl GroupDesc {
+    pub fn new(raw: &[u8]) -> Self {
+        Self {
+            is_64bit: raw.len() >= 64,
+            raw: raw.into(),
+        }
+    }
+
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    fn get_lo_hi32(&self, lo: usize, hi: usize) -> u64 {
+        let lo = get_u32(&self.raw, lo) as u64;
+        if self.is_64bit {
+            lo | (get_u32(&self.raw, hi) as u64) << 32
+        } else {
+            lo
+        }
+    }
+
+    fn get_lo_hi16(&self, lo: usize, hi: usize) -> u32 {
+        let lo = get_u16(&self.raw, lo) as u32;
+        if self.is_64bit {
+            lo | (get_u16(&self.raw, hi) as u32) << 16
+        } else {
+            lo
+        }
+    }
+
+    fn set_lo_hi16(&mut self, lo: usize, hi: usize, val: u32) {
+        set_u16(&mut self.raw, lo, val as u16);
+        if self.is_64bit {
+            set_u16(&mut self.raw, hi, (val >> 16) as u16);
+        }
+    }
+
+    pub fn block_bitmap(&self) -> u64 {
+        self.get_lo_hi32(0, 0x20)
+    }
+
+    pub fn inode_bitmap(&self) -> u64 {
+        self.get_lo_hi32(4, 0x24)
+    }
+
+    pub fn inode_table(&self) -> u64 {
+        self.get_lo_hi32(8, 0x28)
+    }
+
+    pub fn free_blocks_count(&self) -> u32 {
+        self.get_lo_hi16(12, 0x2c)
+    }
+
+    pub fn set_free_blocks_count(&mut self, count: u32) {
+        self.set_lo_hi16(12, 0x2c, count)
+    }
+
+    pub fn free_inodes_count(&self) -> u32 {
+        self.get_lo_hi16(14, 0x2e)
+    }
+
+    pub fn set_free_inodes_count(&mut self, count: u32) {
+        self.set_lo_hi16(14, 0x2e, count)
+    }
+
+    pub fn used_dirs_count(&self) -> u32 {
+        self.get_lo_hi16(16, 0x30)
+    }
+
+    pub fn set_used_dirs_count(&mut self, count: u32) {
+        self.set_lo_hi16(16, 0x30, count)
+    }
+}
+
+/// An inode, along with its number.
+pub struct Inode {
+    pub ino: u32,
+    raw: Box<[u8]>,
+}
+
+impl Inode {
+    pub fn new(ino: u32, raw: Box<[u8]>) -> Self {
+        Self { ino, raw }
+    }
+
+    /// Creates an empty inode of `inode_size` bytes.
+    pub fn empty(ino: u32, inode_size: usize) -> Self {
+        let mut raw = vec![0; inode_size].into_boxed_slice();
+        if inode_size > GOOD_OLD_INODE_SIZE as usize {
+            // `i_extra_isize`, covers the fields up to `i_crtime_extra`.
+            set_u16(&mut raw, 0x80, 0x18.min(inode_size as u16 - 0x80));
+        }
+        Self { ino, raw }
+    }
+
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    pub fn mode(&self) -> u16 {
+        get_u16(&self.raw, 0)
+    }
+
+    pub fn set_mode(&mut self, mode: u16) {
+        set_u16(&mut self.raw, 0, mode)
+    }
+
+    pub fn node_type(&self) -> VfsNodeType {
+        mode_to_type(self.mode())
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode() & S_IFMT == S_IFDIR
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.mode() & S_IFMT == S_IFLNK
+    }
+
+    pub fn size(&self) -> u64 {
+        get_u32(&self.raw, 4) as u64 | (get_u32(&self.raw, 108) as u64) << 32
+    }
+
+    pub fn set_size(&mut self, size: u64) {
+        set_u32(&mut self.raw, 4, size as u32);
+        set_u32(&mut self.raw, 108, (size >> 32) as u32);
+    }
+
+    pub fn atime(&self) -> u32 {
+        get_u32(&self.raw, 8)
+    }
+
+    pub fn ctime(&self) -> u32 {
+        get_u32(&self.raw, 12)
+    }
+
+    pub fn mtime(&self) -> u32 {
+        get_u32(&self.raw, 16)
+    }
+
+    /// Returns the creation time, which only exists in large inodes of ext4.
+    pub fn crtime(&self) -> Option<u32> {
+        if self.raw.len() < 0x94 {
+            return None;
+        }
+        let extra_isize = get_u16(&self.raw, 0x80) as usize;
+        (0x80 + extra_isize >= 0x94).then(|| get_u32(&self.raw, 0x90))
+    }
+
+    pub fn set_atime(&mut self, time: u32) {
+        set_u32(&mut self.raw, 8, time)
+    }
+
+    pub fn set_ctime(&mut self, time: u32) {
+        set_u32(&mut self.raw, 12, time)
+    }
+
+    pub fn set_mtime(&mut self, time: u32) {
+        set_u32(&mut self.raw, 16, time)
+    }
+
+    pub fn set_crtime(&mut self, time: u32) {
+        if self.crtime().is_some() {
+            set_u32(&mut self.raw, 0x90, time)
+        }
+    }
+
+    pub fn set_dtime(&mut self, time: u32) {
+        set_u32(&mut self.raw, 20, time)
+    }
+
+    pub fn links_count(&self) -> u16 {
+        get_u16(&self.raw, 26)
+    }
+
+    pub fn set_links_count(&mut self, count: u16) {
+        set_u16(&mut self.raw, 26, count)
+    }
+
+    /// Returns the number of 512-byte sectors allocated to the inode.
+    pub fn sectors(&self) -> u64 {
+        get_u32(&self.raw, 28) as u64
+    }
+
+    pub fn set_sectors(&mut self, sectors: u64) {
+        set_u32(&mut self.raw, 28, sectors as u32)
+    }
+
+    pub fn flags(&self) -> u32 {
+        get_u32(&self.raw, 32)
+    }
+
+    pub fn set_flags(&mut self, flags: u32) {
+        set_u32(&mut self.raw, 32, flags)
+    }
+
+    /// Returns the `i`-th block pointer.
+    pub fn block(&self, i: usize) -> u32 {
+        get_u32(&self.raw, 40 + i * 4)
+    }
+
+    pub fn set_block(&mut self, i: usize, block: u32) {
+        set_u32(&mut self.raw, 40 + i * 4, block)
+    }
+
+    /// Returns the raw bytes of the block pointers, which are the root of the
+    /// extent tree, or the target of a fast symbolic link.
+    pub fn block_area(&self) -> &[u8] {
+        &self.raw[40..40 + N_BLOCKS * 4]
+    }
+
+    pub fn block_area_mut(&mut self) -> &mut [u8] {
+        &mut self.raw[40..40 + N_BLOCKS * 4]
+    }
+
+    pub fn file_acl(&self) -> u32 {
+        get_u32(&self.raw, 104)
+    }
+}
+
+/// Returns the node type of the inode mode.
+pub fn mode_to_type(mode: u16) -> VfsNodeType {
+    match mode & S_IFMT {
+        0o010000 => VfsNodeType::Fifo,
+        0o020000 => VfsNodeType::CharDevice,
+        S_IFDIR => VfsNodeType::Dir,
+        0o060000 => VfsNodeType::BlockDevice,
+        S_IFLNK => VfsNodeType::SymLink,
+        0o140000 => VfsNodeType::Socket,
+        _ => VfsNodeType::File,
+    }
+}
+
+/// Returns the file type in directory entries of the node type.
+pub fn type_to_dirent_type(ty: VfsNodeType) -> u8 {
+    match ty {
+        VfsNodeType::File => 1,
+        VfsNodeType::Dir => 2,
+        VfsNodeType::CharDevice => 3,
+        VfsNodeType::BlockDevice => 4,
+        VfsNodeType::Fifo => 5,
+        VfsNodeType::Socket => 6,
+        VfsNodeType::SymLink => 7,
+    }
+}
+
+/// Returns the node type of the file type in directory entries, or `None` if
+/// it is unknown.
+pub fn dirent_type_to_type(ty: u8) -> Option<VfsNodeType> {
+    match ty {
+        1 => Some(VfsNodeType::File),
+        2 => Some(VfsNodeType::Dir),
+        3 => Some(VfsNodeType::CharDevice),
+        4 => Some(VfsNodeType::BlockDevice),
+        5 => Some(VfsNodeType::Fifo),
+        6 => Some(VfsNodeType::Socket),
+        7 => Some(VfsNodeType::SymLink),
+        _ => None,
+    }
+}
+
+/// Size of the fixed part of a directory entry.
+pub const DIRENT_HEADER_SIZE: usize = 8;
+
+/// Returns the minimum record length of a directory entry with the name.
+pub const fn dirent_rec_len(name_len: usize) -> usize {
+    (DIRENT_HEADER_SIZE + name_len).next_multiple_of(4)
+}
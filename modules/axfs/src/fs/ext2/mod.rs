@@ -0,0 +1,121 @@
+//! A native [ext2] filesystem, which can also read ext3 and ext4 volumes.
+//!
+//! Plain ext2 volumes are readable and writable, with permissions, symbolic
+//! links, hard links and files up to the triple indirect blocks. Volumes with
+//! features that we can not write (e.g., extents, 64-bit block numbers and
+//! metadata checksums of ext4) are read-only, and writes fail with
+//! [`PermissionDenied`](axfs_vfs::VfsError::PermissionDenied). The journal of ext3 and
+//! ext4 is ignored.
+//!
+//! [ext2]: https://www.nongnu.org/ext2-doc/ext2.html
+
+mod dir;
+mod layout;
+mod node;
+mod volume;
+
+use alloc::sync::{Arc, Weak};
+use core::time::Duration;
+
+use axfs_vfs::{VfsError, VfsNodeRef, VfsOps, VfsResult};
+use axsync::Mutex;
+
+use self::layout::ROOT_INO;
+use self::node::Ext2Node;
+use self::volume::Volume;
+use super::NodeExtOps;
+use crate::dev::Disk;
+use crate::fops::FileTimes;
+
+pub struct Ext2FileSystem {
+    vol: Mutex<Volume>,
+    this: Weak<Ext2FileSystem>,
+}
+
+impl Ext2FileSystem {
+    pub fn new(disk: Disk) -> VfsResult<Arc<Self>> {
+        let vol = Volume::open(disk)?;
+        Ok(Arc::new_cyclic(|this| Self {
+            vol: Mutex::new(vol),
+            this: this.clone(),
+        }))
+    }
+}
+
+impl VfsOps for Ext2FileSystem {
+    fn root_dir(&self) -> VfsNodeRef {
+        Arc::new(Ext2Node {
+            ino: ROOT_INO,
+            fs: self.this.upgrade().unwrap(),
+        })
+    }
+}
+
+/// Returns the current time in seconds since the UNIX epoch.
+fn now() -> u32 {
+    axhal::time::wall_time().as_secs() as u32
+}
+
+/// Returns the timestamps of a node in an ext2 filesystem.
+///
+/// The creation time is only recorded by ext4, the inode change time is used
+/// for others.
+fn node_times(node: &VfsNodeRef) -> Option<FileTimes> {
+    let node = node.as_any().downcast_ref::<Ext2Node>()?;
+    let inode = node.fs.vol.lock().read_inode(node.ino).ok()?;
+    let secs = |t: u32| Duration::from_secs(t as u64);
+    Some(FileTimes {
+        accessed: secs(inode.atime()),
+        modified: secs(inode.mtime()),
+        created: secs(inode.crtime().unwrap_or(inode.ctime())),
+    })
+}
+
+/// Sets the access and modification times of a node in an ext2 filesystem,
+/// which are stored in seconds.
+fn set_times(
+    node: &VfsNodeRef,
+    accessed: Option<Duration>,
+    modified: Option<Duration>,
+) -> VfsResult {
+    let node = node
+        .as_any()
+        .downcast_ref::<Ext2Node>()
+        .ok_or(VfsError::Unsupported)?;
+    let mut vol = node.fs.vol.lock();
+    vol.check_writable()?;
+    let mut inode = vol.read_inode(node.ino)?;
+    if let Some(accessed) = accessed {
+        inode.set_atime(accessed.as_secs() as u32);
+    }
+    if let Some(modified) = modified {
+        inode.set_mtime(modified.as_secs() as u32);
+    }
+    inode.set_ctime(now());
+    vol.write_inode(&inode)
+}
+
+/// Creates a hard link named `name` in the directory `dir` to `node`.
+fn hard_link(dir: &VfsNodeRef, name: &str, node: &VfsNodeRef) -> VfsResult {
+    let dir = dir.as_any().downcast_ref::<Ext2Node>();
+    let node = node.as_any().downcast_ref::<Ext2Node>();
+    match (dir, node) {
+        (Some(dir), Some(node)) if Arc::ptr_eq(&dir.fs, &node.fs) => dir.link(name, node.ino),
+        _ => Err(VfsError::Unsupported), // not in this filesystem
+    }
+}
+
+/// Returns the inode number of a node in an ext2 filesystem.
+fn node_id(node: &VfsNodeRef) -> Option<u64> {
+    let node = node.as_any().downcast_ref::<Ext2Node>()?;
+    Some(node.ino as u64)
+}
+
+/// Operations of ext2 nodes that are not in [`VfsNodeOps`](axfs_vfs::VfsNodeOps).
+pub(crate) const EXT_OPS: NodeExtOps = NodeExtOps {
+    node_times: Some(node_times),
+    set_times: Some(set_times),
+    hard_link: Some(hard_link),
+    ioctl: None,
+    node_id: Some(node_id),
+};
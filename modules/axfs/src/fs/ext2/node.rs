@@ -0,0 +1,401 @@
+//! Files and directories of ext2.
+
+use alloc::{string::String, sync::Arc};
+
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef};
+use axfs_vfs::{VfsNodeType, VfsResult};
+
+use super::layout::*;
+use super::volume::{Volume, is_fast_symlink};
+use super::{Ext2FileSystem, now};
+
+/// The maximum number of symbolic links followed in a lookup.
+const MAX_SYMLINK_DEPTH: usize = 8;
+
+/// A file or directory of ext2, identified by its inode number.
+pub struct Ext2Node {
+    pub(super) ino: u32,
+    pub(super) fs: Arc<Ext2FileSystem>,
+}
+
+impl Ext2Node {
+    fn node(&self, ino: u32) -> VfsNodeRef {
+        Arc::new(Ext2Node {
+            ino,
+            fs: self.fs.clone(),
+        })
+    }
+
+    /// Adds a hard link named `name` in this directory to the inode `ino`.
+    pub(super) fn link(&self, name: &str, ino: u32) -> VfsResult {
+        debug!("link at ext2: {} -> inode {}", name, ino);
+        let mut vol = self.fs.vol.lock();
+        vol.check_writable()?;
+        let mut dir = vol.read_inode(self.ino)?;
+        if !dir.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        if vol.find_entry(&mut dir, name)?.is_some() {
+            return Err(VfsError::AlreadyExists);
+        }
+        let mut inode = vol.read_inode(ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::PermissionDenied);
+        }
+        vol.add_entry(&mut dir, name, ino, inode.node_type())?;
+        let now = now();
+        inode.set_links_count(inode.links_count() + 1);
+        inode.set_ctime(now);
+        vol.write_inode(&inode)?;
+        dir.set_mtime(now);
+        dir.set_ctime(now);
+        vol.write_inode(&dir)
+    }
+}
+
+/// Splits the path into the parent path and the last component.
+fn split_path(path: &str) -> VfsResult<(&str, &str)> {
+    let path = path.trim_end_matches('/');
+    let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(VfsError::InvalidInput);
+    }
+    Ok((parent, name))
+}
+
+/// Sets the target of the new symbolic link, which is stored in the inode if
+/// it is short enough.
+fn write_link(vol: &mut Volume, inode: &mut Inode, target: &[u8]) -> VfsResult {
+    if inode.size() != 0 {
+        return Err(VfsError::PermissionDenied); // can not be changed
+    }
+    if target.len() < N_BLOCKS * 4 {
+        inode.block_area_mut()[..target.len()].copy_from_slice(target);
+        inode.set_size(target.len() as u64);
+    } else {
+        vol.write_data(inode, 0, target)?;
+    }
+    Ok(())
+}
+
+/// Returns the target of the symbolic link.
+fn read_link(vol: &mut Volume, inode: &mut Inode) -> VfsResult<String> {
+    let size = inode.size() as usize;
+    let target = if is_fast_symlink(inode, vol.block_size()) {
+        inode.block_area()[..size.min(N_BLOCKS * 4)].to_vec()
+    } else {
+        let mut buf = alloc::vec![0; size];
+        let len = vol.read_data(inode, 0, &mut buf)?;
+        buf.truncate(len);
+        buf
+    };
+    String::from_utf8(target).map_err(|_| VfsError::InvalidData)
+}
+
+/// Resolves `path` relative to the directory `start`, returns the inode
+/// number.
+///
+/// Symbolic links are followed, except the last component if `follow` is
+/// `false`. Absolute targets of symbolic links are resolved from the root of
+/// this filesystem.
+///
+/// Paths from the root directory have been resolved before they get here, so
+/// the links are only followed for the lookups that start in this filesystem.
+fn resolve(vol: &mut Volume, start: u32, path: &str, follow: bool, depth: usize) -> VfsResult<u32> {
+    let mut cur = start;
+    let mut components = path
+        .split('/')
+        .filter(|c| !c.is_empty() && *c != ".")
+        .peekable();
+    while let Some(name) = components.next() {
+        let mut dir = vol.read_inode(cur)?;
+        if !dir.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        let entry = vol.find_entry(&mut dir, name)?.ok_or(VfsError::NotFound)?;
+        let is_last = components.peek().is_none();
+        let mut inode = vol.read_inode(entry.ino)?;
+        if inode.is_symlink() && (follow || !is_last) {
+            if depth >= MAX_SYMLINK_DEPTH {
+                warn!("ext2: too many levels of symbolic links");
+                return Err(VfsError::InvalidInput);
+            }
+            let target = read_link(vol, &mut inode)?;
+            let base = if target.starts_with('/') {
+                ROOT_INO
+            } else {
+                cur
+            };
+            cur = resolve(vol, base, &target, true, depth + 1)?;
+        } else {
+            cur = entry.ino;
+        }
+    }
+    Ok(cur)
+}
+
+/// Resolves the parent directory of `path` relative to `start`, returns its
+/// inode and the last component of the path.
+fn resolve_parent<'a>(vol: &mut Volume, start: u32, path: &'a str) -> VfsResult<(Inode, &'a str)> {
+    let (parent, name) = split_path(path)?;
+    let ino = resolve(vol, start, parent, true, 0)?;
+    let dir = vol.read_inode(ino)?;
+    if !dir.is_dir() {
+        return Err(VfsError::NotADirectory);
+    }
+    Ok((dir, name))
+}
+
+/// Removes the entry of `name` from the directory `parent`, and deletes the
+/// inode after its last link is removed.
+///
+/// Directories can only be removed if they are empty.
+fn unlink(vol: &mut Volume, parent: &mut Inode, name: &str) -> VfsResult {
+    let entry = vol.find_entry(parent, name)?.ok_or(VfsError::NotFound)?;
+    let mut inode = vol.read_inode(entry.ino)?;
+    if inode.is_dir() && !vol.is_dir_empty(&mut inode)? {
+        return Err(VfsError::DirectoryNotEmpty);
+    }
+    vol.remove_entry(parent, name)?;
+    let now = now();
+    if inode.is_dir() {
+        // the `..` entry of the removed directory
+        parent.set_links_count(parent.links_count().saturating_sub(1));
+        vol.delete_inode(&mut inode, now)?;
+    } else {
+        let links = inode.links_count().saturating_sub(1);
+        inode.set_links_count(links);
+        inode.set_ctime(now);
+        if links == 0 {
+            vol.delete_inode(&mut inode, now)?;
+        } else {
+            vol.write_inode(&inode)?;
+        }
+    }
+    parent.set_mtime(now);
+    parent.set_ctime(now);
+    vol.write_inode(parent)
+}
+
+impl VfsNodeOps for Ext2Node {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let inode = self.fs.vol.lock().read_inode(self.ino)?;
+        let perm = VfsNodePerm::from_bits_truncate(inode.mode() & 0o777);
+        Ok(VfsNodeAttr::new(
+            perm,
+            inode.node_type(),
+            inode.size(),
+            inode.sectors(),
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let mut vol = self.fs.vol.lock();
+        let mut inode = vol.read_inode(self.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::IsADirectory);
+        } else if inode.is_symlink() {
+            let target = read_link(&mut vol, &mut inode)?;
+            let start = target.len().min(offset as usize);
+            let len = buf.len().min(target.len() - start);
+            buf[..len].copy_from_slice(&target.as_bytes()[start..start + len]);
+            return Ok(len);
+        }
+        vol.read_data(&mut inode, offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut vol = self.fs.vol.lock();
+        let mut inode = vol.read_inode(self.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::IsADirectory);
+        } else if inode.is_symlink() {
+            if offset != 0 {
+                return Err(VfsError::PermissionDenied);
+            }
+            write_link(&mut vol, &mut inode, buf)?;
+            vol.write_inode(&inode)?;
+            return Ok(buf.len());
+        }
+        let write_len = vol.write_data(&mut inode, offset, buf);
+        // the blocks may be allocated even if it fails
+        let now = now();
+        inode.set_mtime(now);
+        inode.set_ctime(now);
+        vol.write_inode(&inode)?;
+        write_len
+    }
+
+    fn fsync(&self) -> VfsResult {
+        self.fs.vol.lock().flush()
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        let mut vol = self.fs.vol.lock();
+        let mut inode = vol.read_inode(self.ino)?;
+        if inode.is_dir() {
+            return Err(VfsError::IsADirectory);
+        }
+        vol.truncate(&mut inode, size)?;
+        let now = now();
+        inode.set_mtime(now);
+        inode.set_ctime(now);
+        vol.write_inode(&inode)
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        let ino = resolve(&mut self.fs.vol.lock(), self.ino, "..", false, 0).ok()?;
+        Some(self.node(ino))
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        debug!("lookup at ext2: {}", path);
+        let ino = resolve(&mut self.fs.vol.lock(), self.ino, path, false, 0)?;
+        Ok(self.node(ino))
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at ext2: {}", ty, path);
+        let mode = match ty {
+            VfsNodeType::File => S_IFREG | 0o644,
+            VfsNodeType::Dir => S_IFDIR | 0o755,
+            VfsNodeType::SymLink => S_IFLNK | 0o777,
+            _ => return Err(VfsError::Unsupported),
+        };
+        let mut vol = self.fs.vol.lock();
+        vol.check_writable()?;
+        let (mut parent, name) = resolve_parent(&mut vol, self.ino, path)?;
+        if vol.find_entry(&mut parent, name)?.is_some() {
+            return Err(VfsError::AlreadyExists);
+        }
+
+        let is_dir = ty == VfsNodeType::Dir;
+        let goal = vol.inode_group(parent.ino);
+        let ino = vol.alloc_inode(goal, is_dir)?;
+        let mut inode = vol.empty_inode(ino);
+        let now = now();
+        inode.set_mode(mode);
+        inode.set_atime(now);
+        inode.set_ctime(now);
+        inode.set_mtime(now);
+        inode.set_crtime(now);
+        inode.set_links_count(if is_dir { 2 } else { 1 });
+        let res = if is_dir {
+            vol.init_dir(&mut inode, parent.ino)
+        } else {
+            Ok(())
+        };
+        let res = res
+            .and_then(|_| vol.write_inode(&inode))
+            .and_then(|_| vol.add_entry(&mut parent, name, ino, ty));
+        if let Err(e) = res {
+            vol.delete_inode(&mut inode, now)?;
+            return Err(e);
+        }
+
+        if is_dir {
+            parent.set_links_count(parent.links_count() + 1);
+        }
+        parent.set_mtime(now);
+        parent.set_ctime(now);
+        vol.write_inode(&parent)
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at ext2: {}", path);
+        let mut vol = self.fs.vol.lock();
+        vol.check_writable()?;
+        let (mut parent, name) = resolve_parent(&mut vol, self.ino, path)?;
+        unlink(&mut vol, &mut parent, name)
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let mut vol = self.fs.vol.lock();
+        let mut dir = vol.read_inode(self.ino)?;
+        if !dir.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        let entries = vol.read_dir(&mut dir)?;
+        let mut count = 0;
+        for (entry, out) in entries.iter().skip(start_idx).zip(dirents.iter_mut()) {
+            let ty = match entry.ty {
+                Some(ty) => ty,
+                None => vol.read_inode(entry.ino)?.node_type(),
+            };
+            *out = VfsDirEntry::new(&entry.name, ty);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        debug!("rename at ext2: {} -> {}", src_path, dst_path);
+        let mut vol = self.fs.vol.lock();
+        vol.check_writable()?;
+        let (mut src_dir, src_name) = resolve_parent(&mut vol, self.ino, src_path)?;
+        let (mut dst_dir, dst_name) = resolve_parent(&mut vol, self.ino, dst_path)?;
+        let (src_ino, dst_ino) = (src_dir.ino, dst_dir.ino);
+        if src_ino == dst_ino && src_name == dst_name {
+            return Ok(());
+        }
+        let entry = vol
+            .find_entry(&mut src_dir, src_name)?
+            .ok_or(VfsError::NotFound)?;
+        let mut inode = vol.read_inode(entry.ino)?;
+        let moves_dir = inode.is_dir() && src_ino != dst_ino;
+
+        if inode.is_dir() {
+            // a directory can not be moved into itself
+            let mut cur = dst_ino;
+            while cur != ROOT_INO {
+                if cur == inode.ino {
+                    return Err(VfsError::InvalidInput);
+                }
+                cur = resolve(&mut vol, cur, "..", false, 0)?;
+            }
+        }
+        if let Some(old) = vol.find_entry(&mut dst_dir, dst_name)? {
+            if old.ino == inode.ino {
+                // hard links of the same file
+                return unlink(&mut vol, &mut src_dir, src_name);
+            }
+            let old_is_dir = vol.read_inode(old.ino)?.is_dir();
+            if old_is_dir && !inode.is_dir() {
+                return Err(VfsError::IsADirectory);
+            } else if !old_is_dir && inode.is_dir() {
+                return Err(VfsError::NotADirectory);
+            }
+            unlink(&mut vol, &mut dst_dir, dst_name)?;
+        }
+
+        let now = now();
+        // Re-read the directories, as they may be the same one.
+        let mut dst_dir = vol.read_inode(dst_ino)?;
+        vol.add_entry(&mut dst_dir, dst_name, inode.ino, inode.node_type())?;
+        if moves_dir {
+            dst_dir.set_links_count(dst_dir.links_count() + 1);
+        }
+        dst_dir.set_mtime(now);
+        dst_dir.set_ctime(now);
+        vol.write_inode(&dst_dir)?;
+
+        let mut src_dir = vol.read_inode(src_ino)?;
+        vol.remove_entry(&mut src_dir, src_name)?;
+        if moves_dir {
+            src_dir.set_links_count(src_dir.links_count().saturating_sub(1));
+        }
+        src_dir.set_mtime(now);
+        src_dir.set_ctime(now);
+        vol.write_inode(&src_dir)?;
+
+        if moves_dir {
+            vol.set_parent_entry(&mut inode, dst_ino)?;
+        }
+        inode.set_ctime(now);
+        vol.write_inode(&inode)
+    }
+}
@@ -0,0 +1,619 @@
+//! Block, inode and bitmap management of an ext2 volume.
+
+use alloc::{vec, vec::Vec};
+
+use axfs_vfs::{VfsError, VfsResult};
+
+use super::layout::*;
+use crate::dev::Disk;
+
+/// The maximum depth of extent trees.
+const MAX_EXTENT_DEPTH: u16 = 5;
+
+/// An opened ext2 volume.
+///
+/// Methods taking `&mut Inode` only modify the inode in memory, the caller
+/// should write it back by [`Volume::write_inode`].
+pub struct Volume {
+    disk: Disk,
+    sb: SuperBlock,
+    groups: Vec<GroupDesc>,
+    block_size: usize,
+    read_only: bool,
+}
+
+impl Volume {
+    /// Opens the ext2 volume on the disk.
+    ///
+    /// The volume is read-only if it uses features that we can not write
+    /// (e.g., extents and metadata checksums of ext4).
+    pub fn open(mut disk: Disk) -> VfsResult<Self> {
+        let mut raw = vec![0; SUPERBLOCK_SIZE].into_boxed_slice();
+        disk_read(&mut disk, SUPERBLOCK_OFFSET, &mut raw)?;
+        let sb = SuperBlock::new(raw);
+        if sb.magic() != EXT2_MAGIC {
+            warn!("ext2: bad magic number {:#x}", sb.magic());
+            return Err(VfsError::InvalidData);
+        }
+        let incompat = sb.feature_incompat();
+        if incompat & !INCOMPAT_READ != 0 {
+            warn!(
+                "ext2: unsupported incompatible features {:#x}",
+                incompat & !INCOMPAT_READ
+            );
+            return Err(VfsError::Unsupported);
+        }
+        if incompat & INCOMPAT_RECOVER != 0 {
+            warn!("ext2: the journal needs recovery, the filesystem may be inconsistent");
+        }
+        let read_only =
+            incompat & !INCOMPAT_WRITE != 0 || sb.feature_ro_compat() & !RO_COMPAT_WRITE != 0;
+        if read_only {
+            info!("ext2: mounted read-only due to unsupported features");
+        }
+
+        let block_size = sb.block_size();
+        let desc_size = sb.desc_size();
+        let gdt_pos = (sb.first_data_block() as u64 + 1) * block_size as u64;
+        let mut gdt = vec![0; sb.groups_count() as usize * desc_size];
+        disk_read(&mut disk, gdt_pos, &mut gdt)?;
+        let groups = gdt.chunks(desc_size).map(GroupDesc::new).collect();
+        debug!(
+            "ext2: block size {}, {} blocks, {} inodes, {} groups",
+            block_size,
+            sb.blocks_count(),
+            sb.inodes_count(),
+            sb.groups_count()
+        );
+        Ok(Self {
+            disk,
+            sb,
+            groups,
+            block_size,
+            read_only,
+        })
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Creates an empty inode in memory.
+    pub fn empty_inode(&self, ino: u32) -> Inode {
+        Inode::empty(ino, self.sb.inode_size())
+    }
+
+    pub fn has_filetype(&self) -> bool {
+        self.sb.feature_incompat() & INCOMPAT_FILETYPE != 0
+    }
+
+    /// Returns [`PermissionDenied`](VfsError::PermissionDenied) if the volume
+    /// is read-only.
+    pub fn check_writable(&self) -> VfsResult {
+        if self.read_only {
+            Err(VfsError::PermissionDenied)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn flush(&mut self) -> VfsResult {
+        self.disk.flush().map_err(|_| VfsError::Io)
+    }
+
+    fn read_bytes(&mut self, pos: u64, buf: &mut [u8]) -> VfsResult {
+        disk_read(&mut self.disk, pos, buf)
+    }
+
+    fn write_bytes(&mut self, pos: u64, buf: &[u8]) -> VfsResult {
+        disk_write(&mut self.disk, pos, buf)
+    }
+
+    fn block_pos(&self, block: u64) -> u64 {
+        block * self.block_size as u64
+    }
+
+    pub fn read_block(&mut self, block: u64) -> VfsResult<Vec<u8>> {
+        let mut buf = vec![0; self.block_size];
+        self.read_bytes(self.block_pos(block), &mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn write_block(&mut self, block: u64, buf: &[u8]) -> VfsResult {
+        debug_assert_eq!(buf.len(), self.block_size);
+        self.write_bytes(self.block_pos(block), buf)
+    }
+
+    fn write_super(&mut self) -> VfsResult {
+        disk_write(&mut self.disk, SUPERBLOCK_OFFSET, self.sb.raw())
+    }
+
+    fn write_group(&mut self, group: usize) -> VfsResult {
+        let gdt_pos = self.block_pos(self.sb.first_data_block() as u64 + 1);
+        let pos = gdt_pos + (group * self.sb.desc_size()) as u64;
+        disk_write(&mut self.disk, pos, self.groups[group].raw())
+    }
+
+    fn inode_pos(&self, ino: u32) -> VfsResult<u64> {
+        if ino == 0 || ino > self.sb.inodes_count() {
+            return Err(VfsError::InvalidData);
+        }
+        let ipg = self.sb.inodes_per_group();
+        let group = ((ino - 1) / ipg) as usize;
+        let index = ((ino - 1) % ipg) as u64;
+        Ok(self.block_pos(self.groups[group].inode_table()) + index * self.sb.inode_size() as u64)
+    }
+
+    pub fn read_inode(&mut self, ino: u32) -> VfsResult<Inode> {
+        let pos = self.inode_pos(ino)?;
+        let mut raw = vec![0; self.sb.inode_size()].into_boxed_slice();
+        self.read_bytes(pos, &mut raw)?;
+        Ok(Inode::new(ino, raw))
+    }
+
+    pub fn write_inode(&mut self, inode: &Inode) -> VfsResult {
+        let pos = self.inode_pos(inode.ino)?;
+        self.write_bytes(pos, inode.raw())
+    }
+
+    /// Returns the group of the inode.
+    pub fn inode_group(&self, ino: u32) -> usize {
+        ((ino - 1) / self.sb.inodes_per_group()) as usize
+    }
+
+    /// Finds a zero bit in the bitmap block of a group, sets it and returns
+    /// its index.
+    fn alloc_bit(&mut self, bitmap: u64, nbits: usize) -> VfsResult<Option<usize>> {
+        let mut buf = self.read_block(bitmap)?;
+        let Some(bit) = (0..nbits).find(|&i| buf[i / 8] & (1 << (i % 8)) == 0) else {
+            return Ok(None);
+        };
+        buf[bit / 8] |= 1 << (bit % 8);
+        self.write_block(bitmap, &buf)?;
+        Ok(Some(bit))
+    }
+
+    /// Clears the bit in the bitmap block, returns whether it was set.
+    fn free_bit(&mut self, bitmap: u64, bit: usize) -> VfsResult<bool> {
+        let pos = self.block_pos(bitmap) + (bit / 8) as u64;
+        let mut byte = [0];
+        self.read_bytes(pos, &mut byte)?;
+        let was_set = byte[0] & (1 << (bit % 8)) != 0;
+        byte[0] &= !(1 << (bit % 8));
+        self.write_bytes(pos, &byte)?;
+        Ok(was_set)
+    }
+
+    /// Allocates a zeroed block, preferably in the group `goal`.
+    pub fn alloc_block(&mut self, goal: usize) -> VfsResult<u64> {
+        let ngroups = self.groups.len();
+        let bpg = self.sb.blocks_per_group() as u64;
+        let first = self.sb.first_data_block() as u64;
+        for group in (0..ngroups).map(|i| (goal + i) % ngroups) {
+            if self.groups[group].free_blocks_count() == 0 {
+                continue;
+            }
+            // The last group may be smaller than others.
+            let nbits = bpg.min(self.sb.blocks_count() - first - group as u64 * bpg);
+            let bitmap = self.groups[group].block_bitmap();
+            let Some(bit) = self.alloc_bit(bitmap, nbits as usize)? else {
+                continue;
+            };
+            let desc = &mut self.groups[group];
+            desc.set_free_blocks_count(desc.free_blocks_count() - 1);
+            self.write_group(group)?;
+            self.sb
+                .set_free_blocks_count(self.sb.free_blocks_count().saturating_sub(1));
+            self.write_super()?;
+
+            let block = first + group as u64 * bpg + bit as u64;
+            self.write_block(block, &vec![0; self.block_size])?;
+            return Ok(block);
+        }
+        Err(VfsError::StorageFull)
+    }
+
+    pub fn free_block(&mut self, block: u64) -> VfsResult {
+        let bpg = self.sb.blocks_per_group() as u64;
+        let rel = block
+            .checked_sub(self.sb.first_data_block() as u64)
+            .filter(|_| block < self.sb.blocks_count())
+            .ok_or(VfsError::InvalidData)?;
+        let group = (rel / bpg) as usize;
+        if !self.free_bit(self.groups[group].block_bitmap(), (rel % bpg) as usize)? {
+            warn!("ext2: freeing free block {}", block);
+            return Ok(());
+        }
+        let desc = &mut self.groups[group];
+        desc.set_free_blocks_count(desc.free_blocks_count() + 1);
+        self.write_group(group)?;
+        self.sb
+            .set_free_blocks_count(self.sb.free_blocks_count() + 1);
+        self.write_super()
+    }
+
+    /// Allocates an inode, preferably in the group `goal`, returns its
+    /// number.
+    pub fn alloc_inode(&mut self, goal: usize, is_dir: bool) -> VfsResult<u32> {
+        let ngroups = self.groups.len();
+        let ipg = self.sb.inodes_per_group();
+        for group in (0..ngroups).map(|i| (goal + i) % ngroups) {
+            if self.groups[group].free_inodes_count() == 0 {
+                continue;
+            }
+            let bitmap = self.groups[group].inode_bitmap();
+            let Some(bit) = self.alloc_bit(bitmap, ipg as usize)? else {
+                continue;
+            };
+            let ino = group as u32 * ipg + bit as u32 + 1;
+            if ino < self.sb.first_ino() {
+                // The reserved inodes should have been marked in the bitmap.
+                warn!("ext2: reserved inode {} is not in use", ino);
+                continue;
+            }
+            let desc = &mut self.groups[group];
+            desc.set_free_inodes_count(desc.free_inodes_count() - 1);
+            if is_dir {
+                desc.set_used_dirs_count(desc.used_dirs_count() + 1);
+            }
+            self.write_group(group)?;
+            self.sb
+                .set_free_inodes_count(self.sb.free_inodes_count().saturating_sub(1));
+            self.write_super()?;
+            return Ok(ino);
+        }
+        Err(VfsError::StorageFull)
+    }
+
+    pub fn free_inode(&mut self, ino: u32, is_dir: bool) -> VfsResult {
+        let ipg = self.sb.inodes_per_group();
+        let group = self.inode_group(ino);
+        let bit = ((ino - 1) % ipg) as usize;
+        if !self.free_bit(self.groups[group].inode_bitmap(), bit)? {
+            warn!("ext2: freeing free inode {}", ino);
+            return Ok(());
+        }
+        let desc = &mut self.groups[group];
+        desc.set_free_inodes_count(desc.free_inodes_count() + 1);
+        if is_dir {
+            desc.set_used_dirs_count(desc.used_dirs_count().saturating_sub(1));
+        }
+        self.write_group(group)?;
+        self.sb
+            .set_free_inodes_count(self.sb.free_inodes_count() + 1);
+        self.write_super()
+    }
+
+    /// Number of block pointers in an indirect block.
+    fn ptrs_per_block(&self) -> u64 {
+        (self.block_size / 4) as u64
+    }
+
+    /// Returns the indices of block pointers from the inode to the data block
+    /// `lblk`. The first index is in the inode, and the rest are in indirect
+    /// blocks.
+    fn block_path(&self, lblk: u64) -> VfsResult<Vec<usize>> {
+        let ppb = self.ptrs_per_block();
+        if lblk < N_DIRECT as u64 {
+            return Ok(vec![lblk as usize]);
+        }
+        let mut rel = lblk - N_DIRECT as u64;
+        let mut span = ppb;
+        for level in 1..=3 {
+            if rel < span {
+                let mut path = vec![N_DIRECT + level - 1];
+                for _ in 0..level {
+                    span /= ppb;
+                    path.push((rel / span) as usize);
+                    rel %= span;
+                }
+                return Ok(path);
+            }
+            rel -= span;
+            span *= ppb;
+        }
+        // larger than the maximum file size
+        Err(VfsError::StorageFull)
+    }
+
+    /// Returns the physical block of the data block `lblk` of the inode, or
+    /// `None` if it is a hole.
+    ///
+    /// If `create` is `true`, the holes are filled with new blocks.
+    pub fn bmap(&mut self, inode: &mut Inode, lblk: u64, create: bool) -> VfsResult<Option<u64>> {
+        if inode.flags() & EXTENTS_FL != 0 {
+            if create {
+                return Err(VfsError::PermissionDenied);
+            }
+            return self.extent_bmap(inode, lblk);
+        }
+        let path = self.block_path(lblk)?;
+        let goal = self.inode_group(inode.ino);
+        let sectors_per_block = (self.block_size / 512) as u64;
+
+        let mut block = inode.block(path[0]) as u64;
+        if block == 0 {
+            if !create {
+                return Ok(None);
+            }
+            block = self.alloc_block(goal)?;
+            inode.set_block(path[0], block as u32);
+            inode.set_sectors(inode.sectors() + sectors_per_block);
+        }
+        for &idx in &path[1..] {
+            let pos = self.block_pos(block) + idx as u64 * 4;
+            let mut ptr = [0; 4];
+            self.read_bytes(pos, &mut ptr)?;
+            let mut next = u32::from_le_bytes(ptr) as u64;
+            if next == 0 {
+                if !create {
+                    return Ok(None);
+                }
+                next = self.alloc_block(goal)?;
+                self.write_bytes(pos, &(next as u32).to_le_bytes())?;
+                inode.set_sectors(inode.sectors() + sectors_per_block);
+            }
+            block = next;
+        }
+        Ok(Some(block))
+    }
+
+    fn extent_bmap(&mut self, inode: &Inode, lblk: u64) -> VfsResult<Option<u64>> {
+        let mut node = inode.block_area().to_vec();
+        for _ in 0..=MAX_EXTENT_DEPTH {
+            if get_u16(&node, 0) != EXTENT_MAGIC {
+                return Err(VfsError::InvalidData);
+            }
+            let entries = (get_u16(&node, 2) as usize).min(node.len() / 12 - 1);
+            let depth = get_u16(&node, 6);
+            let entry = |i: usize| &node[12 + i * 12..24 + i * 12];
+            if depth == 0 {
+                for i in 0..entries {
+                    let e = entry(i);
+                    let first = get_u32(e, 0) as u64;
+                    let len = get_u16(e, 4);
+                    let (len, init) = if len > EXTENT_INIT_MAX_LEN {
+                        (len - EXTENT_INIT_MAX_LEN, false)
+                    } else {
+                        (len, true)
+                    };
+                    if (first..first + len as u64).contains(&lblk) {
+                        if !init {
+                            return Ok(None);
+                        }
+                        let start = get_u32(e, 8) as u64 | (get_u16(e, 6) as u64) << 32;
+                        return Ok(Some(start + lblk - first));
+                    }
+                }
+                return Ok(None);
+            }
+            // the last index that covers `lblk`
+            let Some(idx) = (0..entries)
+                .rev()
+                .find(|&i| get_u32(entry(i), 0) as u64 <= lblk)
+            else {
+                return Ok(None);
+            };
+            let e = entry(idx);
+            let child = get_u32(e, 4) as u64 | (get_u16(e, 8) as u64) << 32;
+            node = self.read_block(child)?;
+        }
+        Err(VfsError::InvalidData)
+    }
+
+    /// Reads the data of the inode at `offset`, returns the number of bytes
+    /// read.
+    pub fn read_data(
+        &mut self,
+        inode: &mut Inode,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> VfsResult<usize> {
+        if inode.flags() & INLINE_DATA_FL != 0 {
+            return Err(VfsError::Unsupported);
+        }
+        let size = inode.size();
+        if offset >= size {
+            return Ok(0);
+        }
+        let len = buf.len().min((size - offset) as usize);
+        let bs = self.block_size as u64;
+        let mut done = 0;
+        while done < len {
+            let pos = offset + done as u64;
+            let in_block = (pos % bs) as usize;
+            let count = (len - done).min(self.block_size - in_block);
+            let out = &mut buf[done..done + count];
+            match self.bmap(inode, pos / bs, false)? {
+                Some(block) => self.read_bytes(self.block_pos(block) + in_block as u64, out)?,
+                None => out.fill(0),
+            }
+            done += count;
+        }
+        Ok(len)
+    }
+
+    /// Writes the data of the inode at `offset`, and extends the file if
+    /// needed. Returns the number of bytes written.
+    pub fn write_data(&mut self, inode: &mut Inode, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.check_writable()?;
+        let bs = self.block_size as u64;
+        let mut done = 0;
+        while done < buf.len() {
+            let pos = offset + done as u64;
+            let in_block = (pos % bs) as usize;
+            let count = (buf.len() - done).min(self.block_size - in_block);
+            let block = match self.bmap(inode, pos / bs, true) {
+                Ok(block) => block.unwrap(),
+                Err(VfsError::StorageFull) if done > 0 => break,
+                Err(e) => return Err(e),
+            };
+            self.write_bytes(
+                self.block_pos(block) + in_block as u64,
+                &buf[done..done + count],
+            )?;
+            done += count;
+        }
+        let end = offset + done as u64;
+        if end > inode.size() {
+            self.set_size(inode, end)?;
+        }
+        Ok(done)
+    }
+
+    /// Sets the size of the inode, and sets the large file feature if the
+    /// size needs more than 31 bits.
+    fn set_size(&mut self, inode: &mut Inode, size: u64) -> VfsResult {
+        let ro_compat = self.sb.feature_ro_compat();
+        if size > i32::MAX as u64 && ro_compat & RO_COMPAT_LARGE_FILE == 0 {
+            self.sb
+                .set_feature_ro_compat(ro_compat | RO_COMPAT_LARGE_FILE);
+            self.write_super()?;
+        }
+        inode.set_size(size);
+        Ok(())
+    }
+
+    /// Truncates or extends the data of the inode to `size`.
+    ///
+    /// Extended parts are holes, and the blocks beyond the new size are
+    /// freed.
+    pub fn truncate(&mut self, inode: &mut Inode, size: u64) -> VfsResult {
+        self.check_writable()?;
+        let old_size = inode.size();
+        if size < old_size {
+            let bs = self.block_size as u64;
+            // Clear the tail of the last block, so it reads as zeros if the
+            // file is extended later.
+            let in_block = size % bs;
+            let last = match in_block {
+                0 => None,
+                _ => self.bmap(inode, size / bs, false)?,
+            };
+            if let Some(block) = last {
+                let zeros = vec![0; (bs - in_block) as usize];
+                self.write_bytes(self.block_pos(block) + in_block, &zeros)?;
+            }
+            self.free_blocks_from(inode, size.div_ceil(bs))?;
+        }
+        self.set_size(inode, size)
+    }
+
+    /// Frees all data blocks of the inode from the data block `first`.
+    fn free_blocks_from(&mut self, inode: &mut Inode, first: u64) -> VfsResult {
+        if inode.flags() & EXTENTS_FL != 0 {
+            return Err(VfsError::PermissionDenied);
+        }
+        for i in (first as usize).min(N_DIRECT)..N_DIRECT {
+            let block = inode.block(i) as u64;
+            if block != 0 {
+                self.release_block(inode, block)?;
+                inode.set_block(i, 0);
+            }
+        }
+        let ppb = self.ptrs_per_block();
+        let (mut base, mut span) = (N_DIRECT as u64, ppb);
+        for level in 1..=3 {
+            let slot = N_DIRECT + level - 1;
+            let block = inode.block(slot) as u64;
+            if block != 0 && first < base + span {
+                let rel = first.saturating_sub(base);
+                if self.free_tree(inode, block, level as u32, rel)? {
+                    self.release_block(inode, block)?;
+                    inode.set_block(slot, 0);
+                }
+            }
+            base += span;
+            span *= ppb;
+        }
+        Ok(())
+    }
+
+    /// Frees the data blocks from the relative data block `first` in the tree
+    /// of the indirect block, returns whether the indirect block itself
+    /// should be freed.
+    fn free_tree(
+        &mut self,
+        inode: &mut Inode,
+        block: u64,
+        level: u32,
+        first: u64,
+    ) -> VfsResult<bool> {
+        let mut buf = self.read_block(block)?;
+        let child_span = self.ptrs_per_block().pow(level - 1);
+        for (i, ptr) in buf.chunks_exact_mut(4).enumerate() {
+            let child_first = i as u64 * child_span;
+            let child = get_u32(ptr, 0) as u64;
+            if child == 0 || child_first + child_span <= first {
+                continue;
+            }
+            if level == 1
+                || self.free_tree(inode, child, level - 1, first.saturating_sub(child_first))?
+            {
+                self.release_block(inode, child)?;
+                set_u32(ptr, 0, 0);
+            }
+        }
+        if first == 0 {
+            return Ok(true);
+        }
+        self.write_block(block, &buf)?;
+        Ok(false)
+    }
+
+    /// Frees a block of the inode.
+    fn release_block(&mut self, inode: &mut Inode, block: u64) -> VfsResult {
+        self.free_block(block)?;
+        let sectors_per_block = (self.block_size / 512) as u64;
+        inode.set_sectors(inode.sectors().saturating_sub(sectors_per_block));
+        Ok(())
+    }
+
+    /// Frees the inode and all its data blocks, after its last link is
+    /// removed.
+    pub fn delete_inode(&mut self, inode: &mut Inode, now: u32) -> VfsResult {
+        if !is_fast_symlink(inode, self.block_size) {
+            self.free_blocks_from(inode, 0)?;
+        }
+        inode.set_size(0);
+        inode.set_links_count(0);
+        inode.set_dtime(now);
+        self.write_inode(inode)?;
+        self.free_inode(inode.ino, inode.is_dir())
+    }
+}
+
+/// Returns whether the inode is a symbolic link whose target is stored in the
+/// inode.
+pub fn is_fast_symlink(inode: &Inode, block_size: usize) -> bool {
+    let acl_sectors = if inode.file_acl() != 0 {
+        (block_size / 512) as u64
+    } else {
+        0
+    };
+    inode.is_symlink() && inode.sectors() == acl_sectors
+}
+
+fn disk_read(disk: &mut Disk, pos: u64, mut buf: &mut [u8]) -> VfsResult {
+    disk.set_position(pos);
+    while !buf.is_empty() {
+        match disk.read_one(buf) {
+            Ok(0) => return Err(VfsError::UnexpectedEof),
+            Ok(n) => buf = &mut buf[n..],
+            Err(_) => return Err(VfsError::Io),
+        }
+    }
+    Ok(())
+}
+
+fn disk_write(disk: &mut Disk, pos: u64, mut buf: &[u8]) -> VfsResult {
+    disk.set_position(pos);
+    while !buf.is_empty() {
+        match disk.write_one(buf) {
+            Ok(0) => return Err(VfsError::WriteZero),
+            Ok(n) => buf = &buf[n..],
+            Err(_) => return Err(VfsError::Io),
+        }
+    }
+    Ok(())
+}
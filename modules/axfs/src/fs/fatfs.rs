@@ -1,22 +1,220 @@
-use alloc::sync::Arc;
-use core::cell::UnsafeCell;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::time::Duration;
 
 use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
-use fatfs::{Dir, File, LossyOemCpConverter, NullTimeProvider, Read, Seek, SeekFrom, Write};
+use fatfs::{Date, DateTime, Dir, File, LossyOemCpConverter, Read, Seek, SeekFrom, Time, Write};
 
+use super::{NodeExtOps, PathIds};
 use crate::dev::Disk;
+use crate::fops::FileTimes;
 
 const BLOCK_SIZE: usize = 512;
 
+type FatFile<'a> = File<'a, Disk, AxTimeProvider, LossyOemCpConverter>;
+type FatDir<'a> = Dir<'a, Disk, AxTimeProvider, LossyOemCpConverter>;
+type FatDirEntry<'a> = fatfs::DirEntry<'a, Disk, AxTimeProvider, LossyOemCpConverter>;
+
 pub struct FatFileSystem {
-    inner: fatfs::FileSystem<Disk, NullTimeProvider, LossyOemCpConverter>,
-    root_dir: UnsafeCell<Option<VfsNodeRef>>,
+    inner: fatfs::FileSystem<Disk, AxTimeProvider, LossyOemCpConverter>,
+    this: Weak<FatFileSystem>,
+    /// The numbers of the files, as FAT has no inode numbers.
+    ids: PathIds,
+    /// The timestamps of the files by their numbers, shared by all the nodes
+    /// of a file.
+    times: Mutex<BTreeMap<u64, FileTimes>>,
+}
+
+// The file and directory wrappers borrow `FatFileSystem::inner`, so they hold
+// a strong reference of the filesystem to keep it alive. The fields are
+// dropped in order, the borrowing one is dropped first.
+//
+// A file wrapper also has the number of the file in `FatFileSystem::ids`, and
+// a directory wrapper has its path, in lowercase as FAT is case-insensitive.
+//
+// FAT keeps the timestamps in the directory entries rather than the files, so
+// they are read from the entry when the file is first looked up, and kept in
+// `FatFileSystem::times` by the number of the file from then on, where the
+// modification time is updated when the file is written. The root directory
+// has no entry, so it has no timestamps.
+pub struct FileWrapper<'a>(Mutex<FatFile<'a>>, Arc<FatFileSystem>, u64);
+pub struct DirWrapper<'a>(FatDir<'a>, Arc<FatFileSystem>, String);
+
+/// Provides the current time of the FAT filesystem from the wall clock, which
+/// is in UTC.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AxTimeProvider;
+
+impl fatfs::TimeProvider for AxTimeProvider {
+    fn get_current_date(&self) -> Date {
+        self.get_current_date_time().date
+    }
+
+    fn get_current_date_time(&self) -> DateTime {
+        duration_to_fat(axhal::time::wall_time())
+    }
+}
+
+/// The first and the last days that FAT can represent, since the UNIX epoch.
+const FAT_MIN_DAYS: i64 = days_from_civil(1980, 1, 1);
+const FAT_MAX_DAYS: i64 = days_from_civil(2107, 12, 31);
+
+/// Returns the number of days since the UNIX epoch of the given date.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>.
+const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Returns the date of the given number of days since the UNIX epoch.
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+const fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Converts a FAT date and time to the duration since the UNIX epoch.
+fn fat_to_duration(date: Date, time: Time) -> Duration {
+    let days = days_from_civil(date.year as _, date.month as _, date.day as _);
+    let secs = time.hour as u64 * 3600 + time.min as u64 * 60 + time.sec as u64;
+    Duration::from_secs(days.max(0) as u64 * 86400 + secs)
+        + Duration::from_millis(time.millis as u64)
+}
+
+/// Converts the duration since the UNIX epoch to a FAT date and time, which
+/// is clamped to the range that FAT can represent.
+fn duration_to_fat(dur: Duration) -> DateTime {
+    let days = (dur.as_secs() / 86400) as i64;
+    let (days, secs, millis) = if days < FAT_MIN_DAYS {
+        (FAT_MIN_DAYS, 0, 0)
+    } else if days > FAT_MAX_DAYS {
+        (FAT_MAX_DAYS, 86399, 999)
+    } else {
+        (days, dur.as_secs() % 86400, dur.subsec_millis())
+    };
+    let (year, month, day) = civil_from_days(days);
+    DateTime::new(
+        Date::new(year as u16, month as u16, day as u16),
+        Time::new(
+            (secs / 3600) as u16,
+            (secs / 60 % 60) as u16,
+            (secs % 60) as u16,
+            millis as u16,
+        ),
+    )
+}
+
+fn entry_times(entry: &FatDirEntry<'_>) -> FileTimes {
+    let created = entry.created();
+    let modified = entry.modified();
+    FileTimes {
+        accessed: fat_to_duration(entry.accessed(), Time::new(0, 0, 0, 0)),
+        modified: fat_to_duration(modified.date, modified.time),
+        created: fat_to_duration(created.date, created.time),
+    }
+}
+
+/// Returns the timestamps of a node in a FAT filesystem.
+fn node_times(node: &VfsNodeRef) -> Option<FileTimes> {
+    let id = node_id(node)?;
+    let fs = match node.as_any().downcast_ref::<FileWrapper<'static>>() {
+        Some(file) => &file.1,
+        None => &node.as_any().downcast_ref::<DirWrapper<'static>>()?.1,
+    };
+    fs.times.lock().get(&id).copied()
+}
+
+/// Returns the number of a node in a FAT filesystem, which stands in for the
+/// inode number.
+fn node_id(node: &VfsNodeRef) -> Option<u64> {
+    let any = node.as_any();
+    if let Some(file) = any.downcast_ref::<FileWrapper<'static>>() {
+        Some(file.2)
+    } else {
+        let dir = any.downcast_ref::<DirWrapper<'static>>()?;
+        Some(dir.1.ids.get(&dir.2))
+    }
+}
+
+/// Returns the path of `path` relative to the directory `dir`, both of which
+/// are relative to the root, in lowercase.
+fn join(dir: &str, path: &str) -> String {
+    let mut joined = String::from(dir);
+    for name in path.split('/') {
+        match name {
+            "" | "." => {}
+            ".." => joined.truncate(joined.rfind('/').unwrap_or(0)),
+            _ => {
+                if !joined.is_empty() {
+                    joined.push('/');
+                }
+                joined += &name.to_ascii_lowercase();
+            }
+        }
+    }
+    joined
+}
+
+/// Sets the access and modification times of a file in a FAT filesystem,
+/// which are written to its directory entry when the file is flushed.
+///
+/// FAT only records the date of the last access, and the modification time
+/// in 2 seconds. Directories are not supported, as their entries can not be
+/// changed through [`Dir`].
+fn set_times(
+    node: &VfsNodeRef,
+    accessed: Option<Duration>,
+    modified: Option<Duration>,
+) -> VfsResult {
+    let file = node
+        .as_any()
+        .downcast_ref::<FileWrapper<'static>>()
+        .ok_or(VfsError::Unsupported)?;
+    let mut inner = file.0.lock();
+    let mut times = file.1.times.lock();
+    let times = times.get_mut(&file.2).ok_or(VfsError::Unsupported)?;
+    if let Some(accessed) = accessed {
+        let accessed = duration_to_fat(accessed).date;
+        inner.set_accessed(accessed);
+        times.accessed = fat_to_duration(accessed, Time::new(0, 0, 0, 0));
+    }
+    if let Some(modified) = modified {
+        let DateTime { date, time } = duration_to_fat(modified);
+        let time = Time::new(time.hour, time.min, time.sec / 2 * 2, 0);
+        let modified = DateTime::new(date, time);
+        inner.set_modified(modified);
+        times.modified = fat_to_duration(modified.date, modified.time);
+    }
+    Ok(())
 }
 
-pub struct FileWrapper<'a>(Mutex<File<'a, Disk, NullTimeProvider, LossyOemCpConverter>>);
-pub struct DirWrapper<'a>(Dir<'a, Disk, NullTimeProvider, LossyOemCpConverter>);
+/// Operations of FAT nodes that are not in [`VfsNodeOps`]. FAT has no links.
+pub(crate) const EXT_OPS: NodeExtOps = NodeExtOps {
+    node_times: Some(node_times),
+    set_times: Some(set_times),
+    hard_link: None,
+    ioctl: None,
+    node_id: Some(node_id),
+};
 
 unsafe impl Sync for FatFileSystem {}
 unsafe impl Send for FatFileSystem {}
@@ -27,44 +225,87 @@ unsafe impl Sync for DirWrapper<'_> {}
 
 impl FatFileSystem {
     #[cfg(feature = "use-ramdisk")]
-    pub fn new(mut disk: Disk) -> Self {
+    pub fn new(mut disk: Disk) -> VfsResult<Arc<Self>> {
         let opts = fatfs::FormatVolumeOptions::new();
-        fatfs::format_volume(&mut disk, opts).expect("failed to format volume");
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
-        Self {
-            inner,
-            root_dir: UnsafeCell::new(None),
-        }
+        fatfs::format_volume(&mut disk, opts).map_err(as_vfs_err)?;
+        Self::from_disk(disk)
     }
 
     #[cfg(not(feature = "use-ramdisk"))]
-    pub fn new(disk: Disk) -> Self {
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
-        Self {
+    pub fn new(disk: Disk) -> VfsResult<Arc<Self>> {
+        Self::from_disk(disk)
+    }
+
+    fn from_disk(disk: Disk) -> VfsResult<Arc<Self>> {
+        let opts = fatfs::FsOptions::new().time_provider(AxTimeProvider);
+        let inner = fatfs::FileSystem::new(disk, opts).map_err(as_vfs_err)?;
+        Ok(Arc::new_cyclic(|this| Self {
             inner,
-            root_dir: UnsafeCell::new(None),
-        }
+            this: this.clone(),
+            ids: PathIds::new(),
+            times: Mutex::new(BTreeMap::new()),
+        }))
     }
 
-    pub fn init(&'static self) {
-        // must be called before later operations
-        unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir())) }
+    fn new_file(&self, file: FatFile<'_>, path: &str) -> Arc<FileWrapper<'static>> {
+        // SAFETY: the wrapper keeps `self` alive, see `FileWrapper`.
+        let file = unsafe { core::mem::transmute::<FatFile<'_>, FatFile<'static>>(file) };
+        Arc::new(FileWrapper(
+            Mutex::new(file),
+            self.this.upgrade().unwrap(),
+            self.ids.get(path),
+        ))
     }
 
-    fn new_file(file: File<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<FileWrapper> {
-        Arc::new(FileWrapper(Mutex::new(file)))
+    fn new_dir(&self, dir: FatDir<'_>, path: String) -> Arc<DirWrapper<'static>> {
+        // SAFETY: the wrapper keeps `self` alive, see `DirWrapper`.
+        let dir = unsafe { core::mem::transmute::<FatDir<'_>, FatDir<'static>>(dir) };
+        Arc::new(DirWrapper(dir, self.this.upgrade().unwrap(), path))
     }
 
-    fn new_dir(dir: Dir<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<DirWrapper> {
-        Arc::new(DirWrapper(dir))
+    /// Forgets the timestamps of the files numbered `ids`.
+    fn forget_times(&self, ids: Vec<u64>) {
+        let mut times = self.times.lock();
+        for id in ids {
+            times.remove(&id);
+        }
+    }
+}
+
+impl FileWrapper<'_> {
+    /// Updates the modification time after the file is modified, as FAT does
+    /// on the directory entry when the file is flushed.
+    fn touch(&self) {
+        if let Some(times) = self.1.times.lock().get_mut(&self.2) {
+            let now = duration_to_fat(axhal::time::wall_time());
+            times.modified = fat_to_duration(now.date, now.time);
+        }
+    }
+}
+
+impl DirWrapper<'_> {
+    /// Finds the entry at `path`, which is relative to this directory.
+    fn find_entry(&self, path: &str) -> Option<FatDirEntry<'_>> {
+        let (parent, name) = match path.rsplit_once('/') {
+            Some((parent, name)) => (Some(self.0.open_dir(parent).ok()?), name),
+            None => (None, path),
+        };
+        parent
+            .as_ref()
+            .unwrap_or(&self.0)
+            .iter()
+            .filter_map(Result::ok)
+            .find(|entry| entry.file_name().eq_ignore_ascii_case(name))
     }
 }
 
 impl VfsNodeOps for FileWrapper<'static> {
     axfs_vfs::impl_vfs_non_dir_default! {}
 
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
         let size = self.0.lock().seek(SeekFrom::End(0)).map_err(as_vfs_err)?;
         let blocks = size.div_ceil(BLOCK_SIZE as u64);
@@ -82,19 +323,33 @@ impl VfsNodeOps for FileWrapper<'static> {
     fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
         let mut file = self.0.lock();
         file.seek(SeekFrom::Start(offset)).map_err(as_vfs_err)?; // TODO: more efficient
-        file.write(buf).map_err(as_vfs_err)
+        let write_len = file.write(buf).map_err(as_vfs_err)?;
+        self.touch();
+        Ok(write_len)
+    }
+
+    /// Writes the directory entry of the file and all cached blocks of the
+    /// filesystem back to the disk.
+    fn fsync(&self) -> VfsResult {
+        self.0.lock().flush().map_err(as_vfs_err)
     }
 
     fn truncate(&self, size: u64) -> VfsResult {
         let mut file = self.0.lock();
         file.seek(SeekFrom::Start(size)).map_err(as_vfs_err)?; // TODO: more efficient
-        file.truncate().map_err(as_vfs_err)
+        file.truncate().map_err(as_vfs_err)?;
+        self.touch();
+        Ok(())
     }
 }
 
 impl VfsNodeOps for DirWrapper<'static> {
     axfs_vfs::impl_vfs_dir_default! {}
 
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
         // FAT fs doesn't support permissions, we just set everything to 755
         Ok(VfsNodeAttr::new(
@@ -108,7 +363,7 @@ impl VfsNodeOps for DirWrapper<'static> {
     fn parent(&self) -> Option<VfsNodeRef> {
         self.0
             .open_dir("..")
-            .map_or(None, |dir| Some(FatFileSystem::new_dir(dir)))
+            .map_or(None, |dir| Some(self.1.new_dir(dir, join(&self.2, ".."))))
     }
 
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
@@ -121,11 +376,26 @@ impl VfsNodeOps for DirWrapper<'static> {
             return self.lookup(rest);
         }
 
-        // TODO: use `fatfs::Dir::find_entry`, but it's not public.
-        if let Ok(file) = self.0.open_file(path) {
-            Ok(FatFileSystem::new_file(file))
+        let full_path = join(&self.2, path);
+        if let Some(entry) = self.find_entry(path) {
+            let id = self.1.ids.get(&full_path);
+            self.1
+                .times
+                .lock()
+                .entry(id)
+                .or_insert_with(|| entry_times(&entry));
+            if entry.is_dir() {
+                Ok(self.1.new_dir(entry.to_dir(), full_path))
+            } else {
+                Ok(self.1.new_file(entry.to_file(), &full_path))
+            }
+        } else if let Ok(file) = self.0.open_file(path) {
+            // "." and ".." in the middle of the path are not handled by
+            // `find_entry`, the timestamps are only there if the file has
+            // been looked up before.
+            Ok(self.1.new_file(file, &full_path))
         } else if let Ok(dir) = self.0.open_dir(path) {
-            Ok(FatFileSystem::new_dir(dir))
+            Ok(self.1.new_dir(dir, full_path))
         } else {
             Err(VfsError::NotFound)
         }
@@ -161,7 +431,10 @@ impl VfsNodeOps for DirWrapper<'static> {
         if let Some(rest) = path.strip_prefix("./") {
             return self.remove(rest);
         }
-        self.0.remove(path).map_err(as_vfs_err)
+        self.0.remove(path).map_err(as_vfs_err)?;
+        let ids = self.1.ids.remove(&join(&self.2, path));
+        self.1.forget_times(ids);
+        Ok(())
     }
 
     fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
@@ -194,14 +467,19 @@ impl VfsNodeOps for DirWrapper<'static> {
 
         self.0
             .rename(src_path, &self.0, dst_path)
-            .map_err(as_vfs_err)
+            .map_err(as_vfs_err)?;
+        let replaced = self
+            .1
+            .ids
+            .rename(&join(&self.2, src_path), &join(&self.2, dst_path));
+        self.1.forget_times(replaced);
+        Ok(())
     }
 }
 
 impl VfsOps for FatFileSystem {
     fn root_dir(&self) -> VfsNodeRef {
-        let root_dir = unsafe { (*self.root_dir.get()).as_ref().unwrap() };
-        root_dir.clone()
+        self.new_dir(self.inner.root_dir(), String::new())
     }
 }
 
@@ -243,7 +521,7 @@ impl Write for Disk {
         Ok(write_len)
     }
     fn flush(&mut self) -> Result<(), Self::Error> {
-        Ok(())
+        Disk::flush(self).map_err(|_| ())
     }
 }
 
@@ -1,3 +1,42 @@
+use core::time::Duration;
+
+use axfs_vfs::{VfsNodeRef, VfsResult};
+
+use crate::fops::FileTimes;
+
+/// Gets the timestamps of a node, which belongs to the filesystem that the
+/// function is provided for.
+pub(crate) type NodeTimesFn = fn(&VfsNodeRef) -> Option<FileTimes>;
+
+/// Sets the access and modification times of a node, which belongs to the
+/// filesystem that the function is provided for. `None` leaves a time as is.
+pub(crate) type SetTimesFn =
+    fn(&VfsNodeRef, accessed: Option<Duration>, modified: Option<Duration>) -> VfsResult;
+
+/// Creates a hard link named `name` in the directory `dir` to `node`, all of
+/// which belong to the filesystem that the function is provided for.
+pub(crate) type HardLinkFn = fn(dir: &VfsNodeRef, name: &str, node: &VfsNodeRef) -> VfsResult;
+
+/// Gets the inode number of a node, which belongs to the filesystem that the
+/// function is provided for. It is the same for all the nodes and all the
+/// names of a file.
+pub(crate) type NodeIdFn = fn(&VfsNodeRef) -> Option<u64>;
+
+/// Performs the device-specific request `cmd` with the argument `arg` on a
+/// node, which belongs to the filesystem that the function is provided for.
+pub(crate) type IoctlFn = fn(&VfsNodeRef, cmd: u32, arg: usize) -> VfsResult<usize>;
+
+/// Operations on nodes that are not in [`axfs_vfs::VfsNodeOps`], provided by
+/// the filesystems that support them. The default supports none of them.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct NodeExtOps {
+    pub node_times: Option<NodeTimesFn>,
+    pub set_times: Option<SetTimesFn>,
+    pub hard_link: Option<HardLinkFn>,
+    pub ioctl: Option<IoctlFn>,
+    pub node_id: Option<NodeIdFn>,
+}
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "myfs")] {
         pub mod myfs;
@@ -6,8 +45,28 @@ cfg_if::cfg_if! {
     }
 }
 
+#[cfg(feature = "ext2fs")]
+pub mod ext2;
+
+#[cfg(any(all(feature = "fatfs", not(feature = "myfs")), feature = "overlayfs"))]
+mod path_ids;
+#[cfg(any(all(feature = "fatfs", not(feature = "myfs")), feature = "overlayfs"))]
+pub(crate) use path_ids::PathIds;
+
 #[cfg(feature = "devfs")]
-pub use axfs_devfs as devfs;
+pub mod devfs;
 
 #[cfg(feature = "ramfs")]
-pub use axfs_ramfs as ramfs;
+pub mod ramfs;
+
+#[cfg(feature = "overlayfs")]
+pub mod overlayfs;
+
+#[cfg(any(feature = "procfs", feature = "sysfs"))]
+mod pseudo;
+
+#[cfg(feature = "procfs")]
+pub mod procfs;
+
+#[cfg(feature = "sysfs")]
+pub mod sysfs;
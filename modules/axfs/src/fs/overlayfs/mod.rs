@@ -0,0 +1,128 @@
+//! An overlay filesystem, which layers a writable RAM filesystem over a
+//! read-only filesystem (e.g., the FAT or ext2 volume of the root).
+//!
+//! Lookups see the union of both layers, where the upper layer takes
+//! precedence. The lower layer is never written: a file is copied to the
+//! upper layer when it is modified for the first time (copy-up), and an entry
+//! of the lower layer is deleted by a whiteout in the upper layer.
+//!
+//! Whiteouts follow the convention of AUFS: a file named `.wh.<name>` hides
+//! `<name>` of the lower layer, and a file named `.wh..wh..opq` in a directory
+//! hides all the lower entries in it (e.g., when the directory is created
+//! again after being removed). Names starting with `.wh.` are reserved, and
+//! they are never visible.
+
+mod node;
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use axfs_vfs::{VfsError, VfsNodeRef, VfsOps, VfsResult};
+use axsync::Mutex;
+
+use self::node::OverlayNode;
+use super::ramfs::RamFileSystem;
+use super::{NodeExtOps, PathIds};
+use crate::fops::FileTimes;
+
+/// The prefix of the names of whiteouts.
+const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// The name of the whiteout that makes a directory opaque.
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+/// Returns the name of the whiteout of `name`.
+fn whiteout(name: &str) -> String {
+    String::from(WHITEOUT_PREFIX) + name
+}
+
+/// The layers shared by all nodes of an overlay filesystem.
+struct Layers {
+    lower: Arc<dyn VfsOps>,
+    /// The operations of the lower filesystem beyond [`axfs_vfs::VfsNodeOps`].
+    lower_ops: NodeExtOps,
+    upper: RamFileSystem,
+    /// The parent of the mount point, which is the parent of the root.
+    parent: Mutex<Option<VfsNodeRef>>,
+    /// Serializes the operations that modify the upper layer in several
+    /// steps, such as copy-up and whiteouts.
+    lock: Mutex<()>,
+    /// The numbers of the files. The inode numbers of the lower layer are
+    /// not used, as a file gets a new node when it is copied up.
+    ids: PathIds,
+}
+
+/// An overlay filesystem that implements [`axfs_vfs::VfsOps`].
+pub(crate) struct OverlayFileSystem {
+    layers: Arc<Layers>,
+    root: Arc<OverlayNode>,
+}
+
+impl OverlayFileSystem {
+    /// Creates a new instance over the read-only filesystem `lower`, which
+    /// supports the extra node operations `lower_ops`, with an empty RAM
+    /// filesystem as the upper layer.
+    pub(crate) fn new(lower: Arc<dyn VfsOps>, lower_ops: NodeExtOps) -> Self {
+        let layers = Arc::new(Layers {
+            lower,
+            lower_ops,
+            upper: RamFileSystem::new(),
+            parent: Mutex::new(None),
+            lock: Mutex::new(()),
+            ids: PathIds::new(),
+        });
+        Self {
+            root: OverlayNode::root(&layers),
+            layers,
+        }
+    }
+}
+
+impl VfsOps for OverlayFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        *self.layers.parent.lock() = mount_point.parent();
+        Ok(())
+    }
+
+    fn umount(&self) -> VfsResult {
+        self.layers.upper.umount()?;
+        self.layers.lower.umount()
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+/// Returns the timestamps of `node` from the lower filesystem, if it has not
+/// been copied up.
+fn node_times(node: &VfsNodeRef) -> Option<FileTimes> {
+    let node = node.as_any().downcast_ref::<OverlayNode>()?;
+    node.lower_times()
+}
+
+/// Returns the number of `node`, which stands in for the inode number.
+fn node_id(node: &VfsNodeRef) -> Option<u64> {
+    let node = node.as_any().downcast_ref::<OverlayNode>()?;
+    Some(node.id())
+}
+
+/// Creates a hard link named `name` in the directory `dir` to `node`, both of
+/// which are copied up first, as hard links are only supported by the upper
+/// layer.
+fn hard_link(dir: &VfsNodeRef, name: &str, node: &VfsNodeRef) -> VfsResult {
+    let dir = dir.as_any().downcast_ref::<OverlayNode>();
+    let node = node.as_any().downcast_ref::<OverlayNode>();
+    match (dir, node) {
+        (Some(dir), Some(node)) => dir.link(name, node),
+        _ => Err(VfsError::Unsupported),
+    }
+}
+
+/// Operations of overlayfs nodes that are not in [`axfs_vfs::VfsNodeOps`].
+pub(crate) const EXT_OPS: NodeExtOps = NodeExtOps {
+    node_times: Some(node_times),
+    set_times: None,
+    hard_link: Some(hard_link),
+    ioctl: None,
+    node_id: Some(node_id),
+};
@@ -0,0 +1,520 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsOps, VfsResult};
+use axsync::Mutex;
+
+use super::{Layers, OPAQUE_WHITEOUT, WHITEOUT_PREFIX, whiteout};
+use crate::fops::FileTimes;
+
+/// The size of the chunks in which files are copied up.
+const COPY_CHUNK_SIZE: usize = 4096;
+
+/// A node in overlayfs, which merges the nodes at the same path of both
+/// layers.
+///
+/// Nodes are created on lookup, and identified by the path relative to the
+/// root of overlayfs.
+pub struct OverlayNode {
+    this: Weak<OverlayNode>,
+    layers: Arc<Layers>,
+    path: String,
+    ty: VfsNodeType,
+    /// The node in the lower layer, unless it is hidden by the upper layer.
+    lower: Option<VfsNodeRef>,
+    /// The node in the upper layer. If it does not exist when the node is
+    /// created, it is looked up again when needed, as the node may have been
+    /// copied up through another node at the same path.
+    upper: Mutex<Option<VfsNodeRef>>,
+}
+
+impl OverlayNode {
+    fn new(
+        layers: Arc<Layers>,
+        path: String,
+        ty: VfsNodeType,
+        lower: Option<VfsNodeRef>,
+        upper: Option<VfsNodeRef>,
+    ) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            this: this.clone(),
+            layers,
+            path,
+            ty,
+            lower,
+            upper: Mutex::new(upper),
+        })
+    }
+
+    pub(super) fn root(layers: &Arc<Layers>) -> Arc<Self> {
+        Self::new(
+            layers.clone(),
+            String::new(),
+            VfsNodeType::Dir,
+            Some(layers.lower.root_dir()),
+            Some(layers.upper.root_dir()),
+        )
+    }
+
+    fn upper(&self) -> Option<VfsNodeRef> {
+        let mut upper = self.upper.lock();
+        if upper.is_none() {
+            *upper = self
+                .layers
+                .upper
+                .root_dir()
+                .lookup(&self.path)
+                .ok()
+                .filter(|node| {
+                    node.get_attr()
+                        .is_ok_and(|attr| attr.file_type() == self.ty)
+                });
+        }
+        upper.clone()
+    }
+
+    /// Returns the node of the layer that takes precedence.
+    fn top(&self) -> VfsResult<VfsNodeRef> {
+        self.upper()
+            .or_else(|| self.lower.clone())
+            .ok_or(VfsError::NotFound)
+    }
+
+    /// Returns the timestamps from the lower layer, if the node has not been
+    /// copied up.
+    pub(super) fn lower_times(&self) -> Option<FileTimes> {
+        if self.upper().is_some() {
+            return None;
+        }
+        (self.layers.lower_ops.node_times?)(self.lower.as_ref()?)
+    }
+
+    /// Returns the number of the file, which stands in for the inode number.
+    pub(super) fn id(&self) -> u64 {
+        self.layers.ids.get(&self.path)
+    }
+
+    /// Returns the path of `name` in this directory.
+    fn join(&self, name: &str) -> String {
+        if self.path.is_empty() {
+            name.into()
+        } else {
+            format!("{}/{}", self.path, name)
+        }
+    }
+
+    /// Returns whether the lower layer has `name` in this directory, even if
+    /// it is hidden.
+    fn lower_has(&self, name: &str) -> VfsResult<bool> {
+        match &self.lower {
+            Some(dir) => Ok(lookup_opt(dir, name)?.is_some()),
+            None => Ok(false),
+        }
+    }
+
+    /// Looks up `name` in this directory of both layers.
+    fn child(&self, name: &str) -> VfsResult<Arc<Self>> {
+        if name.starts_with(WHITEOUT_PREFIX) {
+            return Err(VfsError::NotFound);
+        }
+        let (upper, hidden) = match self.upper() {
+            Some(dir) => match lookup_opt(&dir, name)? {
+                Some(node) if node.get_attr()?.is_dir() => {
+                    let opaque = lookup_opt(&node, OPAQUE_WHITEOUT)?.is_some();
+                    (Some(node), opaque)
+                }
+                Some(node) => (Some(node), true),
+                None => (None, lookup_opt(&dir, &whiteout(name))?.is_some()),
+            },
+            None => (None, false),
+        };
+        let mut lower = match &self.lower {
+            Some(dir) if !hidden => lookup_opt(dir, name)?,
+            _ => None,
+        };
+        // an upper directory is only merged with a lower directory
+        if upper.is_some()
+            && let Some(node) = &lower
+            && !node.get_attr()?.is_dir()
+        {
+            lower = None;
+        }
+        let ty = match upper.as_ref().or(lower.as_ref()) {
+            Some(node) => node.get_attr()?.file_type(),
+            None => return Err(VfsError::NotFound),
+        };
+        Ok(Self::new(
+            self.layers.clone(),
+            self.join(name),
+            ty,
+            lower,
+            upper,
+        ))
+    }
+
+    fn walk(self: Arc<Self>, path: &str) -> VfsResult<Arc<Self>> {
+        let mut node = self;
+        for name in path.split('/') {
+            if !node.ty.is_dir() {
+                return Err(VfsError::NotADirectory);
+            }
+            node = match name {
+                "" | "." => node,
+                ".." if node.path.is_empty() => node, // `..` of the root is itself
+                ".." => node.parent_node()?,
+                _ => node.child(name)?,
+            };
+        }
+        Ok(node)
+    }
+
+    fn parent_node(&self) -> VfsResult<Arc<Self>> {
+        let parent = self.path.rsplit_once('/').map_or("", |(parent, _)| parent);
+        Self::root(&self.layers).walk(parent)
+    }
+
+    /// Returns the directory that contains the last component of `path`, and
+    /// the name of the component.
+    fn parent_of<'a>(&self, path: &'a str) -> VfsResult<(Arc<Self>, &'a str)> {
+        let path = path.trim_end_matches('/');
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let this = self.this.upgrade().ok_or(VfsError::NotFound)?;
+        let dir = this.walk(parent)?;
+        if !dir.ty.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        Ok((dir, name))
+    }
+
+    /// Copies the node and its ancestors to the upper layer if they are only
+    /// in the lower layer, returns the node in the upper layer.
+    ///
+    /// Directories are copied without their entries. The caller should hold
+    /// the lock of the layers.
+    fn copy_up(&self) -> VfsResult<VfsNodeRef> {
+        if let Some(upper) = self.upper() {
+            return Ok(upper);
+        }
+        let lower = self.lower.as_ref().ok_or(VfsError::NotFound)?;
+        debug!("copy up at overlayfs: {}", self.path);
+        let parent = self.parent_node()?.copy_up()?;
+        let name = self
+            .path
+            .rsplit_once('/')
+            .map_or(&*self.path, |(_, name)| name);
+        parent.create(name, self.ty)?;
+        let upper = parent.clone().lookup(name)?;
+        if !self.ty.is_dir()
+            && let Err(e) = copy_data(lower, &upper, self.ty)
+        {
+            parent.remove(name).ok();
+            return Err(e);
+        }
+        *self.upper.lock() = Some(upper.clone());
+        Ok(upper)
+    }
+
+    /// Returns the node in the upper layer to be modified, which is copied
+    /// up if needed.
+    fn writable(&self) -> VfsResult<VfsNodeRef> {
+        match self.upper() {
+            Some(upper) => Ok(upper),
+            None => {
+                let _guard = self.layers.lock.lock();
+                self.copy_up()
+            }
+        }
+    }
+
+    /// Returns the names and types of the visible entries in this directory.
+    fn entries(&self) -> VfsResult<BTreeMap<String, VfsNodeType>> {
+        let mut entries = BTreeMap::new();
+        let mut whiteouts = BTreeSet::new();
+        if let Some(upper) = self.upper() {
+            for (name, ty) in read_all(&upper)? {
+                match name.strip_prefix(WHITEOUT_PREFIX) {
+                    Some(name) => whiteouts.insert(String::from(name)),
+                    None => entries.insert(name, ty).is_none(),
+                };
+            }
+        }
+        // the lower directory is absent if this directory is opaque
+        if let Some(lower) = &self.lower {
+            for (name, ty) in read_all(lower)? {
+                if !name.starts_with(WHITEOUT_PREFIX) && !whiteouts.contains(&name) {
+                    entries.entry(name).or_insert(ty);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Removes `name` in this directory, and hides it in the lower layer with
+    /// a whiteout. The caller should hold the lock of the layers.
+    fn remove_child(&self, name: &str) -> VfsResult {
+        let node = self.child(name)?;
+        if node.ty.is_dir() && !node.entries()?.is_empty() {
+            return Err(VfsError::DirectoryNotEmpty);
+        }
+        if let Some(upper) = node.upper() {
+            if node.ty.is_dir() {
+                for (name, _) in read_all(&upper)? {
+                    if name.starts_with(WHITEOUT_PREFIX) {
+                        upper.remove(&name)?;
+                    }
+                }
+            }
+            self.copy_up()?.remove(name)?;
+        }
+        if self.lower_has(name)? {
+            let upper = self.copy_up()?;
+            let whiteout = whiteout(name);
+            if lookup_opt(&upper, &whiteout)?.is_none() {
+                upper.create(&whiteout, VfsNodeType::File)?;
+            }
+        }
+        self.layers.ids.remove(&node.path);
+        Ok(())
+    }
+
+    /// Creates a hard link named `name` in this directory to `node`, both of
+    /// which are copied up first.
+    pub(super) fn link(&self, name: &str, node: &Self) -> VfsResult {
+        if name.starts_with(WHITEOUT_PREFIX) {
+            return Err(VfsError::InvalidInput);
+        }
+        let _guard = self.layers.lock.lock();
+        match self.child(name) {
+            Ok(_) => return Err(VfsError::AlreadyExists),
+            Err(VfsError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+        let target = node.copy_up()?;
+        let dir = self.copy_up()?;
+        remove_whiteout(&dir, name)?;
+        crate::fs::ramfs::hard_link(&dir, name, &target)
+    }
+}
+
+impl VfsNodeOps for OverlayNode {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        self.top()?.get_attr()
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        if self.path.is_empty() {
+            self.layers.parent.lock().clone()
+        } else {
+            self.parent_node().ok().map(|node| node as _)
+        }
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        Ok(self.walk(path)?)
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        if !self.ty.is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        let entries = self.entries()?;
+        let mut entries = entries.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => {
+                    if let Some((name, ty)) = entries.next() {
+                        *ent = VfsDirEntry::new(name, *ty);
+                    } else {
+                        return Ok(i);
+                    }
+                }
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.top()?.read_at(offset, buf)
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        self.writable()?.write_at(offset, buf)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        match self.upper() {
+            Some(upper) => upper.fsync(),
+            None => Ok(()), // the lower layer is never written
+        }
+    }
+
+    fn truncate(&self, size: u64) -> VfsResult {
+        self.writable()?.truncate(size)
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at overlayfs: {}", ty, path);
+        let (dir, name) = self.parent_of(path)?;
+        if name.is_empty() || name == "." || name == ".." {
+            return Ok(()); // already exists
+        } else if name.starts_with(WHITEOUT_PREFIX) {
+            return Err(VfsError::InvalidInput);
+        }
+        let _guard = self.layers.lock.lock();
+        match dir.child(name) {
+            Ok(_) => return Err(VfsError::AlreadyExists),
+            Err(VfsError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+        let upper = dir.copy_up()?;
+        upper.create(name, ty)?;
+        // a new directory should not show the entries of a removed one
+        if ty == VfsNodeType::Dir && dir.lower_has(name)? {
+            upper.create(&format!("{}/{}", name, OPAQUE_WHITEOUT), VfsNodeType::File)?;
+        }
+        remove_whiteout(&upper, name)
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at overlayfs: {}", path);
+        let (dir, name) = self.parent_of(path)?;
+        if name.is_empty() || name == "." || name == ".." {
+            return Err(VfsError::InvalidInput); // remove '.' or '..
+        }
+        let _guard = self.layers.lock.lock();
+        dir.remove_child(name)
+    }
+
+    /// Renames files and symbolic links, and directories that are only in
+    /// the upper layer. Renaming a directory that has entries in the lower
+    /// layer is not supported, as Linux returns `EXDEV` for it.
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        debug!("rename at overlayfs: {} -> {}", src_path, dst_path);
+        let (src_dir, src_name) = self.parent_of(src_path)?;
+        let (dst_dir, dst_name) = self.parent_of(dst_path)?;
+        for name in [src_name, dst_name] {
+            if name.is_empty() || name == "." || name == ".." {
+                return Err(VfsError::InvalidInput);
+            }
+        }
+        if dst_name.starts_with(WHITEOUT_PREFIX) {
+            return Err(VfsError::InvalidInput);
+        }
+        let _guard = self.layers.lock.lock();
+        let node = src_dir.child(src_name)?;
+        let dst = dst_dir.join(dst_name);
+        if node.path == dst {
+            return Ok(());
+        }
+        if node.ty.is_dir() {
+            if node.lower.is_some() {
+                return Err(VfsError::Unsupported);
+            }
+            // a directory can not be moved into itself
+            if dst.starts_with(&format!("{}/", node.path)) {
+                return Err(VfsError::InvalidInput);
+            }
+        }
+        match dst_dir.child(dst_name) {
+            Ok(old) => {
+                match (old.ty.is_dir(), node.ty.is_dir()) {
+                    (true, false) => return Err(VfsError::IsADirectory),
+                    (false, true) => return Err(VfsError::NotADirectory),
+                    _ => {}
+                }
+                dst_dir.remove_child(dst_name)?;
+            }
+            Err(VfsError::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+
+        let upper = node.copy_up()?;
+        let dst_upper = dst_dir.copy_up()?;
+        remove_whiteout(&dst_upper, dst_name)?;
+        self.layers.upper.root_dir().rename(&node.path, &dst)?;
+        if node.ty.is_dir()
+            && dst_dir.lower_has(dst_name)?
+            && lookup_opt(&upper, OPAQUE_WHITEOUT)?.is_none()
+        {
+            upper.create(OPAQUE_WHITEOUT, VfsNodeType::File)?;
+        }
+        if src_dir.lower_has(src_name)? {
+            src_dir
+                .copy_up()?
+                .create(&whiteout(src_name), VfsNodeType::File)?;
+        }
+        self.layers.ids.rename(&node.path, &dst);
+        Ok(())
+    }
+}
+
+/// Looks up `name` in the directory `dir` of a layer, returns `None` if it
+/// does not exist.
+fn lookup_opt(dir: &VfsNodeRef, name: &str) -> VfsResult<Option<VfsNodeRef>> {
+    match dir.clone().lookup(name) {
+        Ok(node) => Ok(Some(node)),
+        Err(VfsError::NotFound) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Removes the whiteout of `name` in the upper directory `dir` if it exists.
+fn remove_whiteout(dir: &VfsNodeRef, name: &str) -> VfsResult {
+    let whiteout = whiteout(name);
+    if lookup_opt(dir, &whiteout)?.is_some() {
+        dir.remove(&whiteout)?;
+    }
+    Ok(())
+}
+
+/// Returns the names and types of the entries in the directory `dir` of a
+/// layer, without `.` and `..`.
+fn read_all(dir: &VfsNodeRef) -> VfsResult<Vec<(String, VfsNodeType)>> {
+    let mut entries = Vec::new();
+    let mut dirents: [VfsDirEntry; 16] = core::array::from_fn(|_| VfsDirEntry::default());
+    let mut idx = 0;
+    loop {
+        let len = dir.read_dir(idx, &mut dirents)?;
+        if len == 0 {
+            return Ok(entries);
+        }
+        idx += len;
+        for ent in &dirents[..len] {
+            let name =
+                core::str::from_utf8(ent.name_as_bytes()).map_err(|_| VfsError::InvalidData)?;
+            if name != "." && name != ".." {
+                entries.push((name.into(), ent.entry_type()));
+            }
+        }
+    }
+}
+
+/// Copies the content of the lower node `src` to the upper node `dst`.
+fn copy_data(src: &VfsNodeRef, dst: &VfsNodeRef, ty: VfsNodeType) -> VfsResult {
+    if ty == VfsNodeType::SymLink {
+        // the target of a symbolic link is written at once
+        let mut target = vec![0; src.get_attr()?.size() as usize];
+        let len = src.read_at(0, &mut target)?;
+        dst.write_at(0, &target[..len])?;
+        return Ok(());
+    }
+    let mut buf = vec![0; COPY_CHUNK_SIZE];
+    let mut offset = 0;
+    loop {
+        let len = src.read_at(offset, &mut buf)?;
+        if len == 0 {
+            return Ok(());
+        }
+        dst.write_at(offset, &buf[..len])?;
+        offset += len as u64;
+    }
+}
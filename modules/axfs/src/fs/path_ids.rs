@@ -0,0 +1,77 @@
+//! Numbers that stand in for the inode numbers of the files.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axsync::Mutex;
+
+/// Numbers that stand in for the inode numbers of a filesystem that has none
+/// and creates new nodes on each lookup (e.g., FAT).
+///
+/// They are kept by the paths relative to the root of the filesystem, and
+/// follow the files when they are renamed. A number is assigned when a path
+/// is first asked for, and forgotten when the file is removed.
+pub(crate) struct PathIds {
+    ids: Mutex<BTreeMap<String, u64>>,
+    next: AtomicU64,
+}
+
+impl PathIds {
+    pub(crate) const fn new() -> Self {
+        Self {
+            ids: Mutex::new(BTreeMap::new()),
+            next: AtomicU64::new(1),
+        }
+    }
+
+    /// Returns the number of the file at `path`.
+    pub(crate) fn get(&self, path: &str) -> u64 {
+        *self
+            .ids
+            .lock()
+            .entry(String::from(path))
+            .or_insert_with(|| self.next.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Moves the numbers of the file at `src`, and of the files under it if
+    /// it is a directory, to `dst`. The file replaced at `dst` is forgotten.
+    ///
+    /// Returns the forgotten numbers.
+    pub(crate) fn rename(&self, src: &str, dst: &str) -> Vec<u64> {
+        let mut ids = self.ids.lock();
+        let replaced = remove_tree(&mut ids, dst);
+        for (path, id) in remove_tree(&mut ids, src) {
+            ids.insert(String::from(dst) + &path[src.len()..], id);
+        }
+        replaced.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// Forgets the number of the file at `path`, and returns the forgotten
+    /// numbers.
+    pub(crate) fn remove(&self, path: &str) -> Vec<u64> {
+        remove_tree(&mut self.ids.lock(), path)
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect()
+    }
+}
+
+/// Removes the entries of `path` and of the paths under it from `ids`, and
+/// returns them.
+fn remove_tree(ids: &mut BTreeMap<String, u64>, path: &str) -> Vec<(String, u64)> {
+    let prefix = String::from(path) + "/";
+    // the paths starting with `path` are together in order
+    let paths = ids
+        .range::<str, _>(path..)
+        .map(|(p, _)| p)
+        .take_while(|p| p.starts_with(path))
+        .filter(|p| *p == path || p.starts_with(&prefix))
+        .cloned()
+        .collect::<Vec<_>>();
+    paths
+        .into_iter()
+        .filter_map(|p| ids.remove(&p).map(|id| (p, id)))
+        .collect()
+}
@@ -0,0 +1,199 @@
+//! A synthetic filesystem for `/proc`, whose files are generated from the live
+//! kernel state each time they are read from the start. The rest of the file
+//! is read from the same snapshot, until it is read from the start again.
+//!
+//! The files are provided by other modules (usually the runtime), which
+//! register them by path with [`register_file`], [`register_symlink`] and
+//! [`register_dir`]. Entries can be registered before or after the filesystem
+//! is mounted, and they are shared by all mounted instances.
+//!
+//! All files and directories are read-only.
+
+mod node;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use axfs_vfs::{VfsNodeRef, VfsOps};
+use axsync::RwLock;
+
+use self::node::{Kind, ProcNode};
+use super::pseudo::{add_dirs, split_last};
+
+/// Generates the content of a file, or the target of a symbolic link.
+pub type ProcFileFn = fn() -> String;
+
+/// Lists the names of the subdirectories of a dynamic directory.
+pub type ProcDirFn = fn() -> Vec<String>;
+
+/// Generates the content of a file in the subdirectory `name` of a dynamic
+/// directory. Returns `None` if the subdirectory no longer exists.
+pub type ProcEntryFileFn = fn(name: &str) -> Option<String>;
+
+/// A registered entry at a fixed path.
+#[derive(Clone, Copy)]
+enum Entry {
+    Dir,
+    File(ProcFileFn),
+    Symlink(ProcFileFn),
+}
+
+impl Entry {
+    const fn kind(self) -> Kind {
+        match self {
+            Self::Dir => Kind::Dir,
+            Self::File(f) => Kind::File(f),
+            Self::Symlink(f) => Kind::Symlink(f),
+        }
+    }
+}
+
+/// Subdirectories that are listed on demand, all of which contain the same
+/// set of files.
+#[derive(Clone, Copy)]
+struct DynamicDir {
+    list: ProcDirFn,
+    files: &'static [(&'static str, ProcEntryFileFn)],
+}
+
+struct Registry {
+    /// Entries indexed by the path relative to the root, without the leading
+    /// and trailing `/`.
+    entries: BTreeMap<String, Entry>,
+    /// Dynamic subdirectories indexed by the path of the parent directory.
+    dynamic: BTreeMap<String, Vec<DynamicDir>>,
+}
+
+static REGISTRY: RwLock<Registry> = RwLock::new(Registry {
+    entries: BTreeMap::new(),
+    dynamic: BTreeMap::new(),
+});
+
+/// Registers a file at `path`, whose content is generated by `generate` each
+/// time it is read.
+///
+/// `path` is relative to the root of procfs (e.g., `meminfo`). The missing
+/// parent directories are created, and an existing entry at `path` is
+/// replaced.
+pub fn register_file(path: &str, generate: ProcFileFn) {
+    register(path, Entry::File(generate));
+}
+
+/// Registers a symbolic link at `path`, whose target is generated by `target`
+/// each time it is read.
+///
+/// It is used for links that depend on the caller, such as `self`.
+pub fn register_symlink(path: &str, target: ProcFileFn) {
+    register(path, Entry::Symlink(target));
+}
+
+/// Registers dynamic subdirectories in the directory `path`.
+///
+/// The names of the subdirectories are listed by `list` on every lookup, and
+/// each of them contains `files`, whose contents are generated by calling the
+/// functions with the name of the subdirectory. For example, per-task
+/// directories like `/proc/<tid>/status` are registered with `path` being
+/// empty.
+pub fn register_dir(
+    path: &str,
+    list: ProcDirFn,
+    files: &'static [(&'static str, ProcEntryFileFn)],
+) {
+    let path = path.trim_matches('/');
+    let mut registry = REGISTRY.write();
+    add_dirs(&mut registry.entries, path, Entry::Dir);
+    registry
+        .dynamic
+        .entry(path.into())
+        .or_default()
+        .push(DynamicDir { list, files });
+}
+
+fn register(path: &str, entry: Entry) {
+    let path = path.trim_matches('/');
+    let mut registry = REGISTRY.write();
+    if let Some((parent, _)) = path.rsplit_once('/') {
+        add_dirs(&mut registry.entries, parent, Entry::Dir);
+    }
+    registry.entries.insert(path.into(), entry);
+}
+
+/// Returns the dynamic subdirectories in the directory `path`.
+fn dynamic_dirs(path: &str) -> Vec<DynamicDir> {
+    // copied so that the list functions are called without the lock
+    REGISTRY
+        .read()
+        .dynamic
+        .get(path)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Finds the node at `path`, which is relative to the root.
+fn find(path: &str) -> Option<Kind> {
+    if path.is_empty() {
+        return Some(Kind::Dir);
+    }
+    if let Some(entry) = REGISTRY.read().entries.get(path) {
+        return Some(entry.kind());
+    }
+
+    let (parent, name) = split_last(path);
+    for dir in dynamic_dirs(parent) {
+        if (dir.list)().iter().any(|n| n == name) {
+            return Some(Kind::EntryDir(dir.files));
+        }
+    }
+    if parent.is_empty() {
+        return None;
+    }
+    let (grandparent, entry_name) = split_last(parent);
+    for dir in dynamic_dirs(grandparent) {
+        if let Some(&(_, f)) = dir.files.iter().find(|(n, _)| *n == name)
+            && (dir.list)().iter().any(|n| n == entry_name)
+        {
+            return Some(Kind::EntryFile(f));
+        }
+    }
+    None
+}
+
+/// Returns the names and kinds of the children of the directory `path`.
+fn children(path: &str) -> Vec<(String, Kind)> {
+    let mut children: Vec<_> = REGISTRY
+        .read()
+        .entries
+        .iter()
+        .filter(|(p, _)| split_last(p).0 == path)
+        .map(|(p, entry)| (String::from(split_last(p).1), entry.kind()))
+        .collect();
+    for dir in dynamic_dirs(path) {
+        children.extend(
+            (dir.list)()
+                .into_iter()
+                .map(|name| (name, Kind::EntryDir(dir.files))),
+        );
+    }
+    children
+}
+
+/// The procfs that implements [`axfs_vfs::VfsOps`].
+pub(crate) struct ProcFileSystem {
+    root: Arc<ProcNode>,
+}
+
+impl ProcFileSystem {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(ProcNode::new(String::new(), Kind::Dir)),
+        }
+    }
+}
+
+impl VfsOps for ProcFileSystem {
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
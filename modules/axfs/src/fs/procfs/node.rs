@@ -0,0 +1,170 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef};
+use axfs_vfs::{VfsNodeType, VfsResult};
+
+use super::{ProcEntryFileFn, ProcFileFn, children, find};
+use crate::fs::pseudo::{self, Snapshot, child_path, split_last};
+
+/// The kind of a node in procfs.
+#[derive(Clone, Copy)]
+pub(super) enum Kind {
+    Dir,
+    File(ProcFileFn),
+    Symlink(ProcFileFn),
+    /// A dynamic subdirectory that contains the given files.
+    EntryDir(&'static [(&'static str, ProcEntryFileFn)]),
+    /// A file in a dynamic subdirectory.
+    EntryFile(ProcEntryFileFn),
+}
+
+impl Kind {
+    const fn node_type(&self) -> VfsNodeType {
+        match self {
+            Self::Dir | Self::EntryDir(_) => VfsNodeType::Dir,
+            Self::File(_) | Self::EntryFile(_) => VfsNodeType::File,
+            Self::Symlink(_) => VfsNodeType::SymLink,
+        }
+    }
+}
+
+/// A file, directory or symbolic link in procfs.
+///
+/// Nodes are created on lookup, and identified by the path relative to the
+/// root of procfs.
+pub struct ProcNode {
+    path: String,
+    kind: Kind,
+    snapshot: Snapshot,
+}
+
+impl ProcNode {
+    pub(super) const fn new(path: String, kind: Kind) -> Self {
+        Self {
+            path,
+            kind,
+            snapshot: Snapshot::new(),
+        }
+    }
+
+    /// Generates the content of the file, or the target of the symbolic link.
+    fn generate(&self) -> VfsResult<String> {
+        match self.kind {
+            Kind::File(f) | Kind::Symlink(f) => Ok(f()),
+            Kind::EntryFile(f) => {
+                let entry_name = split_last(split_last(&self.path).0).1;
+                f(entry_name).ok_or(VfsError::NotFound) // the entry has gone
+            }
+            Kind::Dir | Kind::EntryDir(_) => Err(VfsError::IsADirectory),
+        }
+    }
+
+    fn parent_node(&self) -> Option<Arc<Self>> {
+        if self.path.is_empty() {
+            return None;
+        }
+        let path = split_last(&self.path).0;
+        Some(Arc::new(Self::new(path.into(), find(path)?)))
+    }
+}
+
+impl VfsNodeOps for ProcNode {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let (perm, size) = match self.kind {
+            Kind::Dir | Kind::EntryDir(_) => (0o555, 0),
+            // the size of generated files is unknown until they are read
+            Kind::File(_) | Kind::EntryFile(_) => (0o444, 0),
+            Kind::Symlink(f) => (0o777, f().len() as u64),
+        };
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(perm),
+            self.kind.node_type(),
+            size,
+            0,
+        ))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.parent_node().map(|node| node as _)
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let mut node = self;
+        for name in path.split('/') {
+            if !node.kind.node_type().is_dir() {
+                return Err(VfsError::NotADirectory);
+            }
+            node = match name {
+                "" | "." => node,
+                ".." if node.path.is_empty() => node, // `..` of the root is itself
+                ".." => node.parent_node().ok_or(VfsError::NotFound)?,
+                _ => match node.kind {
+                    Kind::EntryDir(files) => {
+                        let &(_, f) = files
+                            .iter()
+                            .find(|(n, _)| *n == name)
+                            .ok_or(VfsError::NotFound)?;
+                        Arc::new(Self::new(child_path(&node.path, name), Kind::EntryFile(f)))
+                    }
+                    _ => {
+                        let path = child_path(&node.path, name);
+                        let kind = find(&path).ok_or(VfsError::NotFound)?;
+                        Arc::new(Self::new(path, kind))
+                    }
+                },
+            };
+        }
+        Ok(node)
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let children: Vec<_> = match self.kind {
+            Kind::Dir => children(&self.path)
+                .into_iter()
+                .map(|(name, kind)| (name, kind.node_type()))
+                .collect(),
+            Kind::EntryDir(files) => files
+                .iter()
+                .map(|(name, _)| (String::from(*name), VfsNodeType::File))
+                .collect(),
+            _ => return Err(VfsError::NotADirectory),
+        };
+        let children = children.iter().map(|(name, ty)| (name.as_str(), *ty));
+        Ok(pseudo::read_dir(children, start_idx, dirents))
+    }
+
+    /// Reads the content generated at offset 0, or at the first read of the
+    /// node if it starts elsewhere.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        self.snapshot.read_at(offset, buf, || self.generate())
+    }
+
+    fn write_at(&self, _offset: u64, _buf: &[u8]) -> VfsResult<usize> {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn create(&self, _path: &str, _ty: VfsNodeType) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn remove(&self, _path: &str) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn rename(&self, _src_path: &str, _dst_path: &str) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+}
@@ -0,0 +1,98 @@
+//! Building blocks shared by the synthetic filesystems ([`procfs`] and
+//! [`sysfs`]), whose entries are registered by path and whose file contents
+//! are generated on read.
+//!
+//! [`procfs`]: super::procfs
+//! [`sysfs`]: super::sysfs
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use axfs_vfs::{VfsDirEntry, VfsNodeType, VfsResult};
+use axsync::Mutex;
+
+/// Splits `path` into the parent path and the last component.
+pub(super) fn split_last(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+/// Returns the path of the child `name` of the directory `parent`, both
+/// relative to the root.
+pub(super) fn child_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.into()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+/// Adds the directory `path` and all its ancestors as `dir` if they do not
+/// exist.
+pub(super) fn add_dirs<E: Clone>(entries: &mut BTreeMap<String, E>, path: &str, dir: E) {
+    if path.is_empty() {
+        return;
+    }
+    let ends = path.match_indices('/').map(|(i, _)| i);
+    for end in ends.chain([path.len()]) {
+        entries
+            .entry(path[..end].into())
+            .or_insert_with(|| dir.clone());
+    }
+}
+
+/// Fills `dirents` with `.`, `..` and then the names and types of `children`,
+/// starting from the `start_idx`-th entry. Returns the number of entries
+/// filled.
+pub(super) fn read_dir<'a>(
+    children: impl IntoIterator<Item = (&'a str, VfsNodeType)>,
+    start_idx: usize,
+    dirents: &mut [VfsDirEntry],
+) -> usize {
+    let mut children = children.into_iter().skip(start_idx.max(2) - 2);
+    for (i, ent) in dirents.iter_mut().enumerate() {
+        match i + start_idx {
+            0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+            1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+            _ => {
+                if let Some((name, ty)) = children.next() {
+                    *ent = VfsDirEntry::new(name, ty);
+                } else {
+                    return i;
+                }
+            }
+        }
+    }
+    dirents.len()
+}
+
+/// The content of a generated file, which is generated when the file is read
+/// from the start, so that reading it in several calls returns a consistent
+/// snapshot.
+pub(super) struct Snapshot(Mutex<Option<String>>);
+
+impl Snapshot {
+    pub const fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+
+    /// Reads the content generated by `generate` at offset 0, or at the first
+    /// read if it starts elsewhere.
+    pub fn read_at(
+        &self,
+        offset: u64,
+        buf: &mut [u8],
+        generate: impl FnOnce() -> VfsResult<String>,
+    ) -> VfsResult<usize> {
+        let mut snapshot = self.0.lock();
+        let content = match &mut *snapshot {
+            Some(content) if offset != 0 => content,
+            snapshot => snapshot.insert(generate()?),
+        };
+        let content = content.as_bytes();
+        let start = content.len().min(offset as usize);
+        let end = content.len().min(offset as usize + buf.len());
+        let src = &content[start..end];
+        buf[..src.len()].copy_from_slice(src);
+        Ok(src.len())
+    }
+}
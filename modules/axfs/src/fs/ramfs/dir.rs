@@ -0,0 +1,260 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsError, VfsResult};
+use axsync::RwLock;
+
+use super::{FileNode, SymlinkNode};
+
+/// The directory node in the RAM filesystem.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct DirNode {
+    this: Weak<DirNode>,
+    parent: RwLock<Weak<dyn VfsNodeOps>>,
+    children: RwLock<BTreeMap<String, VfsNodeRef>>,
+}
+
+impl DirNode {
+    pub(super) fn new(parent: Option<Weak<dyn VfsNodeOps>>) -> Arc<Self> {
+        Arc::new_cyclic(|this| Self {
+            this: this.clone(),
+            parent: RwLock::new(parent.unwrap_or_else(|| Weak::<Self>::new())),
+            children: RwLock::new(BTreeMap::new()),
+        })
+    }
+
+    pub(super) fn set_parent(&self, parent: Option<&VfsNodeRef>) {
+        *self.parent.write() = match parent {
+            Some(parent) => Arc::downgrade(parent),
+            None => Weak::<Self>::new(),
+        };
+    }
+
+    /// Checks whether a node with the given name exists in this directory.
+    pub fn exist(&self, name: &str) -> bool {
+        self.children.read().contains_key(name)
+    }
+
+    /// Creates a new node with the given name and type in this directory.
+    pub fn create_node(&self, name: &str, ty: VfsNodeType) -> VfsResult {
+        if self.exist(name) {
+            error!("AlreadyExists {}", name);
+            return Err(VfsError::AlreadyExists);
+        }
+        let node: VfsNodeRef = match ty {
+            VfsNodeType::File => Arc::new(FileNode::new()),
+            VfsNodeType::Dir => Self::new(Some(self.this.clone() as Weak<dyn VfsNodeOps>)),
+            VfsNodeType::SymLink => Arc::new(SymlinkNode::new()),
+            _ => return Err(VfsError::Unsupported),
+        };
+        self.children.write().insert(name.into(), node);
+        Ok(())
+    }
+
+    /// Inserts an existing node with the given name in this directory.
+    pub(super) fn link_node(&self, name: &str, node: VfsNodeRef) -> VfsResult {
+        if name.is_empty() || name == "." || name == ".." {
+            return Err(VfsError::InvalidInput);
+        }
+        let mut children = self.children.write();
+        if children.contains_key(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        children.insert(name.into(), node);
+        Ok(())
+    }
+
+    /// Removes a node by the given name in this directory.
+    pub fn remove_node(&self, name: &str) -> VfsResult {
+        let mut children = self.children.write();
+        let node = children.get(name).ok_or(VfsError::NotFound)?;
+        if let Some(dir) = node.as_any().downcast_ref::<DirNode>()
+            && !dir.children.read().is_empty()
+        {
+            return Err(VfsError::DirectoryNotEmpty);
+        }
+        children.remove(name);
+        Ok(())
+    }
+}
+
+impl VfsNodeOps for DirNode {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_dir(4096, 0))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.parent.read().upgrade()
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let (name, rest) = split_path(path);
+        let node = match name {
+            "" | "." => Ok(self.clone() as VfsNodeRef),
+            ".." => self.parent().ok_or(VfsError::NotFound),
+            _ => self
+                .children
+                .read()
+                .get(name)
+                .cloned()
+                .ok_or(VfsError::NotFound),
+        }?;
+
+        if let Some(rest) = rest {
+            node.lookup(rest)
+        } else {
+            Ok(node)
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let children = self.children.read();
+        let mut children = children.iter().skip(start_idx.max(2) - 2);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match i + start_idx {
+                0 => *ent = VfsDirEntry::new(".", VfsNodeType::Dir),
+                1 => *ent = VfsDirEntry::new("..", VfsNodeType::Dir),
+                _ => {
+                    if let Some((name, node)) = children.next() {
+                        *ent = VfsDirEntry::new(name, node.get_attr()?.file_type());
+                    } else {
+                        return Ok(i);
+                    }
+                }
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
+        debug!("create {:?} at ramfs: {}", ty, path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.create(rest, ty),
+                ".." => self.parent().ok_or(VfsError::NotFound)?.create(rest, ty),
+                _ => {
+                    let subdir = self
+                        .children
+                        .read()
+                        .get(name)
+                        .ok_or(VfsError::NotFound)?
+                        .clone();
+                    subdir.create(rest, ty)
+                }
+            }
+        } else if name.is_empty() || name == "." || name == ".." {
+            Ok(()) // already exists
+        } else {
+            self.create_node(name, ty)
+        }
+    }
+
+    fn remove(&self, path: &str) -> VfsResult {
+        debug!("remove at ramfs: {}", path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            match name {
+                "" | "." => self.remove(rest),
+                ".." => self.parent().ok_or(VfsError::NotFound)?.remove(rest),
+                _ => {
+                    let subdir = self
+                        .children
+                        .read()
+                        .get(name)
+                        .ok_or(VfsError::NotFound)?
+                        .clone();
+                    subdir.remove(rest)
+                }
+            }
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::InvalidInput) // remove '.' or '..
+        } else {
+            self.remove_node(name)
+        }
+    }
+
+    fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
+        debug!("rename at ramfs: {} -> {}", src_path, dst_path);
+        let (src_dir, src_name) = self.parent_of(src_path)?;
+        let (dst_dir, dst_name) = self.parent_of(dst_path)?;
+        let node = src_dir
+            .children
+            .read()
+            .get(src_name)
+            .cloned()
+            .ok_or(VfsError::NotFound)?;
+        if Arc::ptr_eq(&src_dir, &dst_dir) && src_name == dst_name {
+            return Ok(());
+        }
+        if node.get_attr()?.is_dir() {
+            // a directory can not be moved into itself
+            let mut cur = Some(dst_dir.clone());
+            while let Some(dir) = cur {
+                if Arc::ptr_eq(&(dir.clone() as VfsNodeRef), &node) {
+                    return Err(VfsError::InvalidInput);
+                }
+                // stop at the root of this filesystem, whose parent is the
+                // mount point in another filesystem
+                cur = dir
+                    .parent
+                    .read()
+                    .upgrade()
+                    .and_then(|p| p.as_any().downcast_ref::<DirNode>()?.this.upgrade());
+            }
+        }
+        if let Some(old) = dst_dir.children.read().get(dst_name) {
+            if Arc::ptr_eq(old, &node) {
+                return Ok(()); // hard links of the same file
+            }
+            match (old.get_attr()?.is_dir(), node.get_attr()?.is_dir()) {
+                (true, false) => return Err(VfsError::IsADirectory),
+                (false, true) => return Err(VfsError::NotADirectory),
+                _ => {}
+            }
+        }
+        if dst_dir.exist(dst_name) {
+            dst_dir.remove_node(dst_name)?;
+        }
+        src_dir.children.write().remove(src_name);
+        if let Some(dir) = node.as_any().downcast_ref::<DirNode>() {
+            *dir.parent.write() = dst_dir.this.clone();
+        }
+        dst_dir.children.write().insert(dst_name.into(), node);
+        Ok(())
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
+
+impl DirNode {
+    /// Returns the directory that contains the last component of `path`, and
+    /// the name of the component.
+    fn parent_of<'a>(&self, path: &'a str) -> VfsResult<(Arc<DirNode>, &'a str)> {
+        let path = path.trim_end_matches('/');
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        if name.is_empty() || name == "." || name == ".." {
+            return Err(VfsError::InvalidInput);
+        }
+        let this = self.this.upgrade().ok_or(VfsError::NotFound)?;
+        let dir = this.lookup(parent)?;
+        match dir.as_any().downcast_ref::<DirNode>() {
+            Some(dir) => Ok((dir.this.upgrade().ok_or(VfsError::NotFound)?, name)),
+            None => Err(VfsError::NotADirectory),
+        }
+    }
+}
+
+fn split_path(path: &str) -> (&str, Option<&str>) {
+    let trimmed_path = path.trim_start_matches('/');
+    trimmed_path.find('/').map_or((trimmed_path, None), |n| {
+        (&trimmed_path[..n], Some(&trimmed_path[n + 1..]))
+    })
+}
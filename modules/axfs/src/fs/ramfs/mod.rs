@@ -0,0 +1,82 @@
+//! RAM filesystem used by [ArceOS](https://github.com/arceos-org/arceos).
+//!
+//! Besides regular files and directories, it supports symbolic links and hard
+//! links. A hard link is the same node inserted into another directory, so
+//! the data is freed after the last link is removed.
+
+mod dir;
+mod file;
+mod symlink;
+
+pub use self::dir::DirNode;
+pub use self::file::FileNode;
+pub use self::symlink::SymlinkNode;
+
+use alloc::sync::Arc;
+use axfs_vfs::{VfsError, VfsNodeRef, VfsOps, VfsResult};
+use axsync::Mutex;
+
+use super::NodeExtOps;
+
+/// A RAM filesystem that implements [`axfs_vfs::VfsOps`].
+pub struct RamFileSystem {
+    /// Keeps the parent of the mount point alive, which is weakly referenced
+    /// by the root directory.
+    parent: Mutex<Option<VfsNodeRef>>,
+    root: Arc<DirNode>,
+}
+
+impl RamFileSystem {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            parent: Mutex::new(None),
+            root: DirNode::new(None),
+        }
+    }
+}
+
+impl VfsOps for RamFileSystem {
+    fn mount(&self, _path: &str, mount_point: VfsNodeRef) -> VfsResult {
+        let parent = mount_point.parent();
+        self.root.set_parent(parent.as_ref());
+        *self.parent.lock() = parent;
+        Ok(())
+    }
+
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+impl Default for RamFileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Creates a hard link named `name` in the directory `dir` to `node`.
+///
+/// Directories can not be linked.
+pub(super) fn hard_link(dir: &VfsNodeRef, name: &str, node: &VfsNodeRef) -> VfsResult {
+    let dir = dir
+        .as_any()
+        .downcast_ref::<DirNode>()
+        .ok_or(VfsError::Unsupported)?;
+    let any = node.as_any();
+    if any.is::<DirNode>() {
+        return Err(VfsError::PermissionDenied);
+    } else if !any.is::<FileNode>() && !any.is::<SymlinkNode>() {
+        return Err(VfsError::Unsupported); // not a node of ramfs
+    }
+    dir.link_node(name, node.clone())
+}
+
+/// Operations of ramfs nodes that are not in [`VfsNodeOps`].
+pub(crate) const EXT_OPS: NodeExtOps = NodeExtOps {
+    node_times: None,
+    set_times: None,
+    hard_link: Some(hard_link),
+    ioctl: None,
+    node_id: None,
+};
@@ -0,0 +1,54 @@
+use alloc::vec::Vec;
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+use axsync::RwLock;
+
+/// The symbolic link node in the RAM filesystem.
+///
+/// Reading it returns the target path. The target is set by writing it at
+/// offset 0 after the link is created, and can not be changed later.
+pub struct SymlinkNode {
+    target: RwLock<Vec<u8>>,
+}
+
+impl SymlinkNode {
+    pub(super) const fn new() -> Self {
+        Self {
+            target: RwLock::new(Vec::new()),
+        }
+    }
+}
+
+impl VfsNodeOps for SymlinkNode {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o777),
+            VfsNodeType::SymLink,
+            self.target.read().len() as _,
+            0,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let target = self.target.read();
+        let start = target.len().min(offset as usize);
+        let end = target.len().min(offset as usize + buf.len());
+        let src = &target[start..end];
+        buf[..src.len()].copy_from_slice(src);
+        Ok(src.len())
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let mut target = self.target.write();
+        if offset != 0 || !target.is_empty() {
+            return Err(VfsError::PermissionDenied);
+        }
+        target.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
@@ -0,0 +1,136 @@
+//! A synthetic filesystem for `/sys`, whose files are attributes of the
+//! kernel that can be read and, if supported, written at runtime.
+//!
+//! The attributes are provided by other modules, which register them by path
+//! with [`register_attr`] and [`register_writable_attr`]. Reading an attribute
+//! from the start calls its `show` callback, and the rest of it is read from
+//! the same snapshot, as procfs does. Writing to an attribute calls its
+//! `store` callback with the written value, so that kernel tunables can be
+//! changed without rebuilding. Entries can be registered before or after the
+//! filesystem is mounted, and they are shared by all mounted instances.
+//!
+//! Module parameters are conventionally registered as
+//! `module/<module>/parameters/<name>`, as Linux does.
+
+mod node;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use axerrno::AxResult;
+use axfs_vfs::{VfsNodeRef, VfsOps};
+use axsync::RwLock;
+
+use self::node::SysNode;
+use super::pseudo::{add_dirs, split_last};
+
+/// Generates the content of an attribute.
+pub type SysShowFn = dyn Fn() -> String + Send + Sync;
+
+/// Stores the value written to an attribute, with the surrounding whitespace
+/// (e.g., the trailing newline of `echo`) trimmed.
+///
+/// Returns an error (usually [`InvalidInput`](axerrno::AxError::InvalidInput))
+/// if the value is not accepted, which is returned to the writer.
+pub type SysStoreFn = dyn Fn(&str) -> AxResult + Send + Sync;
+
+/// A registered attribute.
+struct Attr {
+    show: Arc<SysShowFn>,
+    store: Option<Arc<SysStoreFn>>,
+}
+
+/// A registered entry, which is cheap to clone.
+#[derive(Clone)]
+enum Entry {
+    Dir,
+    Attr(Arc<Attr>),
+}
+
+/// Entries indexed by the path relative to the root, without the leading and
+/// trailing `/`.
+static REGISTRY: RwLock<BTreeMap<String, Entry>> = RwLock::new(BTreeMap::new());
+
+/// Registers a read-only attribute at `path`, whose content is generated by
+/// `show` each time it is read.
+///
+/// `path` is relative to the root of sysfs (e.g., `kernel/mm/foo`). The
+/// missing parent directories are created, and an existing attribute at
+/// `path` is replaced.
+pub fn register_attr<S>(path: &str, show: S)
+where
+    S: Fn() -> String + Send + Sync + 'static,
+{
+    register(
+        path,
+        Attr {
+            show: Arc::new(show),
+            store: None,
+        },
+    );
+}
+
+/// Registers a writable attribute at `path`, whose content is generated by
+/// `show` each time it is read, and whose written values are passed to
+/// `store`.
+pub fn register_writable_attr<S, T>(path: &str, show: S, store: T)
+where
+    S: Fn() -> String + Send + Sync + 'static,
+    T: Fn(&str) -> AxResult + Send + Sync + 'static,
+{
+    register(
+        path,
+        Attr {
+            show: Arc::new(show),
+            store: Some(Arc::new(store)),
+        },
+    );
+}
+
+fn register(path: &str, attr: Attr) {
+    let path = path.trim_matches('/');
+    let mut registry = REGISTRY.write();
+    if let Some((parent, _)) = path.rsplit_once('/') {
+        add_dirs(&mut *registry, parent, Entry::Dir);
+    }
+    registry.insert(path.into(), Entry::Attr(Arc::new(attr)));
+}
+
+/// Finds the entry at `path`, which is relative to the root.
+fn find(path: &str) -> Option<Entry> {
+    if path.is_empty() {
+        return Some(Entry::Dir);
+    }
+    REGISTRY.read().get(path).cloned()
+}
+
+/// Returns the names and entries of the children of the directory `path`.
+fn children(path: &str) -> Vec<(String, Entry)> {
+    REGISTRY
+        .read()
+        .iter()
+        .filter(|(p, _)| split_last(p).0 == path)
+        .map(|(p, entry)| (String::from(split_last(p).1), entry.clone()))
+        .collect()
+}
+
+/// The sysfs that implements [`axfs_vfs::VfsOps`].
+pub(crate) struct SysFileSystem {
+    root: Arc<SysNode>,
+}
+
+impl SysFileSystem {
+    /// Create a new instance.
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(SysNode::new(String::new(), Entry::Dir)),
+        }
+    }
+}
+
+impl VfsOps for SysFileSystem {
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
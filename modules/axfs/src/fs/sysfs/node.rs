@@ -0,0 +1,149 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use axfs_vfs::{VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef};
+use axfs_vfs::{VfsNodeType, VfsResult};
+
+use super::{Attr, Entry, children, find};
+use crate::fs::pseudo::{self, Snapshot, child_path, split_last};
+
+impl Entry {
+    const fn node_type(&self) -> VfsNodeType {
+        match self {
+            Self::Dir => VfsNodeType::Dir,
+            Self::Attr(_) => VfsNodeType::File,
+        }
+    }
+}
+
+/// A directory or attribute in sysfs.
+///
+/// Nodes are created on lookup, and identified by the path relative to the
+/// root of sysfs.
+pub struct SysNode {
+    path: String,
+    entry: Entry,
+    snapshot: Snapshot,
+}
+
+impl SysNode {
+    pub(super) const fn new(path: String, entry: Entry) -> Self {
+        Self {
+            path,
+            entry,
+            snapshot: Snapshot::new(),
+        }
+    }
+
+    fn attr(&self) -> VfsResult<&Attr> {
+        match &self.entry {
+            Entry::Attr(attr) => Ok(attr),
+            Entry::Dir => Err(VfsError::IsADirectory),
+        }
+    }
+
+    fn parent_node(&self) -> Option<Arc<Self>> {
+        if self.path.is_empty() {
+            return None;
+        }
+        let path = split_last(&self.path).0;
+        Some(Arc::new(Self::new(path.into(), find(path)?)))
+    }
+}
+
+impl VfsNodeOps for SysNode {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = match &self.entry {
+            Entry::Dir => 0o555,
+            Entry::Attr(attr) if attr.store.is_some() => 0o644,
+            Entry::Attr(_) => 0o444,
+        };
+        // the size of attributes is unknown until they are read
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(perm),
+            self.entry.node_type(),
+            0,
+            0,
+        ))
+    }
+
+    fn parent(&self) -> Option<VfsNodeRef> {
+        self.parent_node().map(|node| node as _)
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let mut node = self;
+        for name in path.split('/') {
+            if !node.entry.node_type().is_dir() {
+                return Err(VfsError::NotADirectory);
+            }
+            node = match name {
+                "" | "." => node,
+                ".." if node.path.is_empty() => node, // `..` of the root is itself
+                ".." => node.parent_node().ok_or(VfsError::NotFound)?,
+                _ => {
+                    let path = child_path(&node.path, name);
+                    let entry = find(&path).ok_or(VfsError::NotFound)?;
+                    Arc::new(Self::new(path, entry))
+                }
+            };
+        }
+        Ok(node)
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        if !self.entry.node_type().is_dir() {
+            return Err(VfsError::NotADirectory);
+        }
+        let children = children(&self.path);
+        let children = children
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry.node_type()));
+        Ok(pseudo::read_dir(children, start_idx, dirents))
+    }
+
+    /// Reads the content shown at offset 0, or at the first read of the node
+    /// if it starts elsewhere.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let show = &self.attr()?.show;
+        self.snapshot.read_at(offset, buf, || Ok(show()))
+    }
+
+    /// Passes the whole `buf` to the `store` callback of the attribute,
+    /// regardless of `offset`.
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let store = self.attr()?.store.as_ref();
+        let store = store.ok_or(VfsError::PermissionDenied)?;
+        let value = core::str::from_utf8(buf).map_err(|_| VfsError::InvalidInput)?;
+        store(value.trim())?;
+        Ok(buf.len())
+    }
+
+    fn fsync(&self) -> VfsResult {
+        Ok(())
+    }
+
+    /// Does nothing for writable attributes, so that they can be opened with
+    /// `O_TRUNC` (e.g., by `echo 1 > attr`).
+    fn truncate(&self, _size: u64) -> VfsResult {
+        match self.attr()?.store {
+            Some(_) => Ok(()),
+            None => Err(VfsError::PermissionDenied),
+        }
+    }
+
+    fn create(&self, _path: &str, _ty: VfsNodeType) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn remove(&self, _path: &str) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn rename(&self, _src_path: &str, _dst_path: &str) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+}
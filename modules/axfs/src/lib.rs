@@ -6,10 +6,29 @@
 //!
 //! - `fatfs`: Use [FAT] as the main filesystem and mount it on `/`. This feature
 //!   is **enabled** by default.
-//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`. This feature is
-//!   **enabled** by default.
-//! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`. This feature is
-//!   **enabled** by default.
+//! - `ext2fs`: Use [ext2] as the main filesystem and mount it on `/`, which
+//!   takes precedence over `fatfs` if both are enabled. ext3 and ext4 volumes
+//!   can also be mounted read-only. The disk must already contain a volume
+//!   (e.g., made by `mke2fs`), `use-ramdisk` does not format it. This feature
+//!   is **disabled** by default.
+//! - `devfs`: Mount [`axfs_devfs::DeviceFileSystem`] on `/dev`, where the
+//!   block devices and the devices registered by other modules with
+//!   [`devfs::register_device`] appear. This feature is **enabled** by
+//!   default.
+//! - `ramfs`: Mount a RAM filesystem on `/tmp`, which supports symbolic links
+//!   and hard links. This feature is **enabled** by default.
+//! - `overlayfs`: Layer a RAM filesystem over the main filesystem on `/`, so
+//!   that the main filesystem is never written. Modified files are copied to
+//!   the RAM filesystem, and the changes are lost on reboot. This feature is
+//!   **disabled** by default.
+//! - `procfs`: Mount a synthetic filesystem on `/proc`, whose files are
+//!   generated from the kernel state when they are read. Other modules
+//!   provide the files by [`procfs::register_file`] and its siblings. This
+//!   feature is **enabled** by default.
+//! - `sysfs`: Mount a synthetic filesystem on `/sys`, whose files are kernel
+//!   attributes that can be tuned at runtime. Other modules provide them by
+//!   [`sysfs::register_attr`] and [`sysfs::register_writable_attr`]. This
+//!   feature is **enabled** by default.
 //! - `myfs`: Allow users to define their custom filesystems to override the
 //!   default. In this case, [`MyFileSystemIf`] is required to be implemented
 //!   to create and initialize other filesystems. This feature is **disabled** by
@@ -17,6 +36,7 @@
 //!   both are enabled.
 //!
 //! [FAT]: https://en.wikipedia.org/wiki/File_Allocation_Table
+//! [ext2]: https://en.wikipedia.org/wiki/Ext2
 //! [`MyFileSystemIf`]: fops::MyFileSystemIf
 
 #![cfg_attr(all(not(test), not(doc)), no_std)]
@@ -26,21 +46,38 @@
 extern crate log;
 extern crate alloc;
 
+mod cache;
 mod dev;
 mod fs;
+mod lock;
 mod mounts;
 mod root;
 
 pub mod api;
 pub mod fops;
 
+#[cfg(feature = "devfs")]
+pub use fs::devfs;
+#[cfg(feature = "procfs")]
+pub use fs::procfs;
+#[cfg(feature = "ramfs")]
+pub use fs::ramfs;
+#[cfg(feature = "sysfs")]
+pub use fs::sysfs;
+
 use axdriver::{AxDeviceContainer, prelude::*};
 
 /// Initializes filesystems by block devices.
-pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
+///
+/// The block devices are named `vda`, `vdb`, etc. in order, and the first one
+/// is used for the root filesystem. Others can be mounted later by
+/// [`api::mount`].
+pub fn init_filesystems(blk_devs: AxDeviceContainer<AxBlockDevice>) {
     info!("Initialize filesystems...");
 
-    let dev = blk_devs.take_one().expect("No block device found!");
-    info!("  use block device 0: {:?}", dev.device_name());
-    self::root::init_rootfs(self::dev::Disk::new(dev));
+    self::dev::register_block_devices(blk_devs);
+    let dev = self::dev::root_block_device().expect("No block device found!");
+    info!("  use block device {} as root", dev.name());
+    let disk = self::dev::Disk::open(dev).expect("failed to open the root block device");
+    self::root::init_rootfs(disk);
 }
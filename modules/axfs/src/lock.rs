@@ -0,0 +1,372 @@
+//! Advisory file locks, which are taken by `flock` on whole files and by
+//! `fcntl` on byte ranges.
+//!
+//! The two kinds of locks do not interact with each other, as on Linux. All
+//! tasks belong to the same process in ArceOS, so the byte-range locks are
+//! owned by opened files (i.e., open file descriptions) like the `flock`
+//! ones, otherwise they would never conflict. This is the same as the "open
+//! file description locks" of Linux. The locks of an opened file are released
+//! when it is closed.
+//!
+//! Files are identified by their filesystems and inode numbers, so all the
+//! names (hard links) of a file share the locks, and the locks follow the
+//! file when it is renamed. The nodes of the filesystems without inode
+//! numbers are identified by their addresses, as the nodes are kept for the
+//! lifetime of the files (e.g., ramfs).
+//!
+//! A byte-range lock request that waits fails with [`DEADLOCK_ERROR`] if it
+//! would wait for itself, i.e., an owner that blocks it waits, directly or
+//! through other owners, for a lock held by the requesting owner. A `flock`
+//! request that waits releases the existing lock of the owner first, as on
+//! Linux, so owners converting their shared locks to exclusive ones at the
+//! same time do not wait for each other.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use axerrno::{AxError, AxResult, ax_err};
+use axfs_vfs::VfsNodeRef;
+use axsync::spin::SpinNoIrq;
+use core::ops::Range;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The error returned when waiting for a lock would deadlock.
+///
+/// [`AxError`] has no dedicated kind for it, so
+/// [`ResourceBusy`](AxError::ResourceBusy) is used, which lock requests never
+/// return otherwise.
+pub const DEADLOCK_ERROR: AxError = AxError::ResourceBusy;
+
+/// The identity of a file for the advisory locks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct FileId {
+    /// The address of the information of the mounted filesystem.
+    pub fs: usize,
+    /// The inode number of the file, or the address of the node.
+    pub ino: u64,
+}
+
+/// The kind of an advisory lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    /// A shared (read) lock, which can be held by multiple owners.
+    Shared,
+    /// An exclusive (write) lock, which can be held by only one owner.
+    Exclusive,
+}
+
+/// An advisory lock on a byte range of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileLock {
+    /// The kind of the lock.
+    pub kind: LockKind,
+    /// The locked bytes, where the end of [`u64::MAX`] means that the lock
+    /// extends to the end of the file, however large it grows.
+    pub range: Range<u64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Flock,
+    Range,
+}
+
+#[derive(Clone)]
+struct Held {
+    owner: u64,
+    class: Class,
+    lock: FileLock,
+}
+
+/// A lock that an owner waits for.
+#[cfg(feature = "multitask")]
+struct Waiting {
+    file: FileId,
+    class: Class,
+    kind: LockKind,
+    range: Range<u64>,
+}
+
+struct Locks {
+    /// The locks held on each file.
+    held: BTreeMap<FileId, Vec<Held>>,
+    /// The lock that each waiting owner waits for.
+    #[cfg(feature = "multitask")]
+    waiting: BTreeMap<u64, Waiting>,
+}
+
+/// The advisory locks of all files.
+///
+/// It is a spinlock, as it is also checked in the wait condition.
+static LOCKS: SpinNoIrq<Locks> = SpinNoIrq::new(Locks {
+    held: BTreeMap::new(),
+    #[cfg(feature = "multitask")]
+    waiting: BTreeMap::new(),
+});
+
+/// The number of wait queues in [`WAIT_QUEUES`].
+#[cfg(feature = "multitask")]
+const NUM_WAIT_QUEUES: usize = 64;
+
+/// The tasks waiting for the locks of files to be released, hashed by the
+/// files, so that a change of the locks of a file only wakes up the tasks
+/// that may wait for it.
+#[cfg(feature = "multitask")]
+static WAIT_QUEUES: [axtask::WaitQueue; NUM_WAIT_QUEUES] =
+    [const { axtask::WaitQueue::new() }; NUM_WAIT_QUEUES];
+
+static NEXT_OWNER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// The owner of the advisory locks taken through an opened file, which
+/// releases them when it is dropped.
+pub struct LockOwner {
+    file: FileId,
+    id: u64,
+    /// The node of the file if it is identified by the address of the node,
+    /// which is kept so that the address is not reused by another node.
+    _node: Option<VfsNodeRef>,
+}
+
+impl LockOwner {
+    /// Creates an owner of the locks on the file identified by `file`, which
+    /// keeps `node` alive if it is given.
+    pub(crate) fn new(file: FileId, node: Option<VfsNodeRef>) -> Self {
+        Self {
+            file,
+            id: NEXT_OWNER_ID.fetch_add(1, Ordering::Relaxed),
+            _node: node,
+        }
+    }
+
+    /// Applies a lock of `kind` on the whole file, or removes it if `kind` is
+    /// `None`, like `flock`. An existing lock of this owner is converted.
+    ///
+    /// If the lock conflicts with the locks of other owners, it waits until
+    /// they are released if `wait` is `true`, or returns
+    /// [`WouldBlock`](axerrno::AxError::WouldBlock) otherwise. Like Linux, the
+    /// existing lock is released before waiting, so it is not kept if the
+    /// wait is interrupted.
+    pub fn flock(&self, kind: Option<LockKind>, wait: bool) -> AxResult {
+        self.set(Class::Flock, kind, 0..u64::MAX, wait)
+    }
+
+    /// Applies a lock of `kind` on `range` of the file, or removes the locks
+    /// on `range` if `kind` is `None`, like `fcntl` with `F_SETLK`, or with
+    /// `F_SETLKW` if `wait` is `true`.
+    ///
+    /// The locks of this owner on `range` are replaced, and the ones partially
+    /// in `range` are split. If `wait` is `true`, it returns [`DEADLOCK_ERROR`]
+    /// instead of waiting if the wait would never end.
+    pub fn set_lock(&self, kind: Option<LockKind>, range: Range<u64>, wait: bool) -> AxResult {
+        if range.is_empty() {
+            return ax_err!(InvalidInput);
+        }
+        self.set(Class::Range, kind, range, wait)
+    }
+
+    /// Returns a lock of other owners that conflicts with a lock of `kind` on
+    /// `range` of the file, like `fcntl` with `F_GETLK`.
+    pub fn get_lock(&self, kind: LockKind, range: Range<u64>) -> Option<FileLock> {
+        let locks = LOCKS.lock();
+        locks
+            .blockers(self.file, self.id, Class::Range, kind, &range)
+            .next()
+            .map(|h| h.lock.clone())
+    }
+
+    #[cfg(feature = "multitask")]
+    fn is_blocked(&self, class: Class, kind: LockKind, range: &Range<u64>) -> bool {
+        LOCKS
+            .lock()
+            .blockers(self.file, self.id, class, kind, range)
+            .next()
+            .is_some()
+    }
+
+    fn set(&self, class: Class, kind: Option<LockKind>, range: Range<u64>, wait: bool) -> AxResult {
+        let Some(kind) = kind else {
+            LOCKS.lock().remove_owned(self, class, &range);
+            notify_waiters(self.file);
+            return Ok(());
+        };
+
+        loop {
+            let mut locks = LOCKS.lock();
+            let blocked = locks
+                .blockers(self.file, self.id, class, kind, &range)
+                .next()
+                .is_some();
+            if !blocked {
+                #[cfg(feature = "multitask")]
+                locks.waiting.remove(&self.id);
+                let held = locks.held.entry(self.file).or_default();
+                self.remove(held, class, &range);
+                held.push(Held {
+                    owner: self.id,
+                    class,
+                    lock: FileLock { kind, range },
+                });
+                drop(locks);
+                // others may wait for a lock converted to shared
+                notify_waiters(self.file);
+                return Ok(());
+            }
+            if !wait {
+                return ax_err!(WouldBlock);
+            }
+            #[cfg(not(feature = "multitask"))]
+            {
+                // without other tasks, the locks will never be released
+                drop(locks);
+                return ax_err!(WouldBlock);
+            }
+            #[cfg(feature = "multitask")]
+            {
+                let waiting = Waiting {
+                    file: self.file,
+                    class,
+                    kind,
+                    range: range.clone(),
+                };
+                if class == Class::Range && locks.waits_for(&waiting, self.id) {
+                    locks.waiting.remove(&self.id);
+                    return Err(DEADLOCK_ERROR);
+                }
+                locks.waiting.insert(self.id, waiting);
+                // an existing `flock` is released rather than kept while
+                // waiting, otherwise two owners converting their shared locks
+                // would wait for each other forever
+                let released = class == Class::Flock && locks.remove_owned(self, class, &range);
+                drop(locks);
+                if released {
+                    notify_waiters(self.file);
+                }
+                wait_queue(self.file).wait_until(|| !self.is_blocked(class, kind, &range));
+            }
+        }
+    }
+
+    /// Removes the locks of this owner on `range`, and splits the ones that
+    /// are partially on it.
+    fn remove(&self, held: &mut Vec<Held>, class: Class, range: &Range<u64>) {
+        let mut rest = Vec::new();
+        held.retain(|h| {
+            if h.owner != self.id || h.class != class || !overlaps(&h.lock.range, range) {
+                return true;
+            }
+            let lock = &h.lock.range;
+            if lock.start < range.start {
+                rest.push(with_range(h, lock.start..range.start));
+            }
+            if range.end < lock.end {
+                rest.push(with_range(h, range.end..lock.end));
+            }
+            false
+        });
+        held.extend(rest);
+    }
+}
+
+impl Drop for LockOwner {
+    fn drop(&mut self) {
+        let mut locks = LOCKS.lock();
+        let Some(held) = locks.held.get_mut(&self.file) else {
+            return;
+        };
+        held.retain(|h| h.owner != self.id);
+        if held.is_empty() {
+            locks.held.remove(&self.file);
+        }
+        drop(locks);
+        notify_waiters(self.file);
+    }
+}
+
+impl Locks {
+    /// Removes the locks of `owner` on `range` of its file, and returns
+    /// whether there were any.
+    fn remove_owned(&mut self, owner: &LockOwner, class: Class, range: &Range<u64>) -> bool {
+        let Some(held) = self.held.get_mut(&owner.file) else {
+            return false;
+        };
+        let removed = held
+            .iter()
+            .any(|h| h.owner == owner.id && h.class == class && overlaps(&h.lock.range, range));
+        owner.remove(held, class, range);
+        if held.is_empty() {
+            self.held.remove(&owner.file);
+        }
+        removed
+    }
+
+    /// Returns the locks of other owners than `owner` that conflict with a
+    /// lock of `kind` on `range` of `file`.
+    fn blockers<'a>(
+        &'a self,
+        file: FileId,
+        owner: u64,
+        class: Class,
+        kind: LockKind,
+        range: &'a Range<u64>,
+    ) -> impl Iterator<Item = &'a Held> {
+        self.held.get(&file).into_iter().flatten().filter(move |h| {
+            h.owner != owner
+                && h.class == class
+                && (kind == LockKind::Exclusive || h.lock.kind == LockKind::Exclusive)
+                && overlaps(&h.lock.range, range)
+        })
+    }
+
+    /// Returns whether an owner that blocks `waiting` waits, directly or
+    /// through other owners, for a lock held by `owner`.
+    #[cfg(feature = "multitask")]
+    fn waits_for(&self, waiting: &Waiting, owner: u64) -> bool {
+        let blockers_of = |w: &Waiting, o: u64| {
+            self.blockers(w.file, o, w.class, w.kind, &w.range)
+                .map(|h| h.owner)
+                .collect::<Vec<_>>()
+        };
+        let mut visited = alloc::collections::BTreeSet::new();
+        let mut pending = blockers_of(waiting, owner);
+        while let Some(blocker) = pending.pop() {
+            if blocker == owner {
+                return true;
+            }
+            if !visited.insert(blocker) {
+                continue;
+            }
+            if let Some(w) = self.waiting.get(&blocker) {
+                pending.extend(blockers_of(w, blocker));
+            }
+        }
+        false
+    }
+}
+
+fn overlaps(a: &Range<u64>, b: &Range<u64>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+fn with_range(held: &Held, range: Range<u64>) -> Held {
+    Held {
+        lock: FileLock {
+            kind: held.lock.kind,
+            range,
+        },
+        ..held.clone()
+    }
+}
+
+/// Returns the wait queue of the tasks waiting for the locks of `file`.
+#[cfg(feature = "multitask")]
+fn wait_queue(file: FileId) -> &'static axtask::WaitQueue {
+    let hash = (file.fs as u64).rotate_left(17) ^ file.ino;
+    &WAIT_QUEUES[(hash % NUM_WAIT_QUEUES as u64) as usize]
+}
+
+/// Wakes up the tasks waiting for the locks of `file`, since they changed.
+fn notify_waiters(file: FileId) {
+    #[cfg(feature = "multitask")]
+    wait_queue(file).notify_all(false);
+    #[cfg(not(feature = "multitask"))]
+    let _ = file;
+}
@@ -1,7 +1,41 @@
 use alloc::sync::Arc;
-use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
+use axerrno::{AxResult, ax_err};
+use axfs_vfs::VfsOps;
 
-use crate::fs;
+use crate::fs::{self, NodeExtOps};
+
+/// Creates a new filesystem of type `fstype` on `source`.
+///
+/// `source` is the path of the block device (e.g., `/dev/vdb`) for disk
+/// filesystems, and is ignored for others.
+/// It also returns the operations on the nodes that the filesystem supports
+/// beyond [`axfs_vfs::VfsNodeOps`].
+pub(crate) fn new_fs(source: &str, fstype: &str) -> AxResult<(Arc<dyn VfsOps>, NodeExtOps)> {
+    let _ = source;
+    match fstype {
+        #[cfg(all(feature = "fatfs", not(feature = "myfs")))]
+        "vfat" | "fat" => {
+            let disk = crate::dev::Disk::open(crate::dev::find_block_device(source)?)?;
+            let fs = fs::fatfs::FatFileSystem::new(disk)?;
+            Ok((fs, fs::fatfs::EXT_OPS))
+        }
+        #[cfg(feature = "ext2fs")]
+        "ext2" | "ext3" | "ext4" => {
+            let disk = crate::dev::Disk::open(crate::dev::find_block_device(source)?)?;
+            let fs = fs::ext2::Ext2FileSystem::new(disk)?;
+            Ok((fs, fs::ext2::EXT_OPS))
+        }
+        #[cfg(feature = "devfs")]
+        "devfs" => Ok((devfs(), fs::devfs::EXT_OPS)),
+        #[cfg(feature = "ramfs")]
+        "ramfs" | "tmpfs" => Ok((ramfs(), fs::ramfs::EXT_OPS)),
+        #[cfg(feature = "procfs")]
+        "proc" => Ok((procfs(), NodeExtOps::default())),
+        #[cfg(feature = "sysfs")]
+        "sysfs" => Ok((sysfs(), NodeExtOps::default())),
+        _ => ax_err!(Unsupported, "unknown filesystem type"),
+    }
+}
 
 #[cfg(feature = "devfs")]
 pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
@@ -13,7 +47,9 @@ pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     devfs.add("null", Arc::new(null));
     devfs.add("zero", Arc::new(zero));
     foo_dir.add("bar", Arc::new(bar));
-    Arc::new(devfs)
+    let devfs = Arc::new(devfs);
+    fs::devfs::attach(&devfs);
+    devfs
 }
 
 #[cfg(feature = "ramfs")]
@@ -22,59 +58,27 @@ pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
 }
 
 #[cfg(feature = "procfs")]
-pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
-    let procfs = fs::ramfs::RamFileSystem::new();
-    let proc_root = procfs.root_dir();
-
-    // Create /proc/sys/net/core/somaxconn
-    proc_root.create("sys", VfsNodeType::Dir)?;
-    proc_root.create("sys/net", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core", VfsNodeType::Dir)?;
-    proc_root.create("sys/net/core/somaxconn", VfsNodeType::File)?;
-    let file_somaxconn = proc_root.clone().lookup("./sys/net/core/somaxconn")?;
-    file_somaxconn.write_at(0, b"4096\n")?;
-
-    // Create /proc/sys/vm/overcommit_memory
-    proc_root.create("sys/vm", VfsNodeType::Dir)?;
-    proc_root.create("sys/vm/overcommit_memory", VfsNodeType::File)?;
-    let file_over = proc_root.clone().lookup("./sys/vm/overcommit_memory")?;
-    file_over.write_at(0, b"0\n")?;
+pub(crate) fn procfs() -> Arc<fs::procfs::ProcFileSystem> {
+    use alloc::string::String;
+    use fs::procfs::register_file;
 
-    // Create /proc/self/stat
-    proc_root.create("self", VfsNodeType::Dir)?;
-    proc_root.create("self/stat", VfsNodeType::File)?;
-
-    Ok(Arc::new(procfs))
+    register_file("mounts", crate::root::proc_mounts);
+    register_file("sys/net/core/somaxconn", || String::from("4096\n"));
+    register_file("sys/vm/overcommit_memory", || String::from("0\n"));
+    Arc::new(fs::procfs::ProcFileSystem::new())
 }
 
 #[cfg(feature = "sysfs")]
-pub(crate) fn sysfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
-    let sysfs = fs::ramfs::RamFileSystem::new();
-    let sys_root = sysfs.root_dir();
-
-    // Create /sys/kernel/mm/transparent_hugepage/enabled
-    sys_root.create("kernel", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm/transparent_hugepage", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm/transparent_hugepage/enabled", VfsNodeType::File)?;
-    let file_hp = sys_root
-        .clone()
-        .lookup("./kernel/mm/transparent_hugepage/enabled")?;
-    file_hp.write_at(0, b"always [madvise] never\n")?;
+pub(crate) fn sysfs() -> Arc<fs::sysfs::SysFileSystem> {
+    use alloc::string::String;
+    use fs::sysfs::register_attr;
 
-    // Create /sys/devices/system/clocksource/clocksource0/current_clocksource
-    sys_root.create("devices", VfsNodeType::Dir)?;
-    sys_root.create("devices/system", VfsNodeType::Dir)?;
-    sys_root.create("devices/system/clocksource", VfsNodeType::Dir)?;
-    sys_root.create("devices/system/clocksource/clocksource0", VfsNodeType::Dir)?;
-    sys_root.create(
+    register_attr("kernel/mm/transparent_hugepage/enabled", || {
+        String::from("always [madvise] never\n")
+    });
+    register_attr(
         "devices/system/clocksource/clocksource0/current_clocksource",
-        VfsNodeType::File,
-    )?;
-    let file_cc = sys_root
-        .clone()
-        .lookup("devices/system/clocksource/clocksource0/current_clocksource")?;
-    file_cc.write_at(0, b"tsc\n")?;
-
-    Ok(Arc::new(sysfs))
+        || String::from("tsc\n"),
+    );
+    Arc::new(fs::sysfs::SysFileSystem::new())
 }
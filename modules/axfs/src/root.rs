@@ -2,13 +2,18 @@
 //!
 //! TODO: it doesn't work very well if the mount points have containment relationships.
 
-use alloc::{string::String, sync::Arc, vec::Vec};
+use alloc::{format, string::String, sync::Arc, vec, vec::Vec};
 use axerrno::{AxError, AxResult, ax_err};
-use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsOps, VfsResult};
 use axns::{ResArc, def_resource};
-use axsync::Mutex;
+use axsync::{Mutex, RwLock};
+use core::time::Duration;
 use lazyinit::LazyInit;
 
+use crate::fops::FileTimes;
+use crate::fs::NodeExtOps;
+use crate::lock::FileId;
 use crate::{api::FileType, fs, mounts};
 
 def_resource! {
@@ -17,20 +22,62 @@ def_resource! {
 }
 
 struct MountPoint {
-    path: &'static str,
+    path: String,
     fs: Arc<dyn VfsOps>,
+    /// Cloned by every [`MountRef`] of this mount point.
+    info: Arc<MountInfo>,
 }
 
+/// Information of a mounted filesystem, shared with the opened files.
+struct MountInfo {
+    /// The device or the name that the filesystem is mounted from.
+    source: String,
+    fstype: String,
+    ext_ops: NodeExtOps,
+}
+
+/// A reference to a mounted filesystem, held by opened files and directories
+/// in it, so that the filesystem will not be unmounted when it is busy.
+#[derive(Clone)]
+pub(crate) struct MountRef(Arc<MountInfo>);
+
 struct RootDirectory {
     main_fs: Arc<dyn VfsOps>,
-    mounts: Vec<MountPoint>,
+    main_info: MountInfo,
+    mounts: RwLock<Vec<MountPoint>>,
 }
 
 static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
 
+/// The maximum number of symbolic links followed when resolving a path.
+const MAX_SYMLINKS: usize = 40;
+
+impl MountInfo {
+    fn new(source: &str, fstype: &str, ext_ops: NodeExtOps) -> Self {
+        Self {
+            source: source.into(),
+            fstype: fstype.into(),
+            ext_ops,
+        }
+    }
+}
+
 impl MountPoint {
-    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
-        Self { path, fs }
+    pub fn new(path: String, fs: Arc<dyn VfsOps>, info: MountInfo) -> Self {
+        Self {
+            path,
+            fs,
+            info: Arc::new(info),
+        }
+    }
+
+    /// Returns whether the mount point is `path` or an ancestor of `path`,
+    /// where `path` is relative to the root without the leading `/`.
+    fn covers(&self, path: &str) -> bool {
+        // skip the first '/'
+        let mp_path = &self.path[1..];
+        path.strip_prefix(mp_path)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
     }
 }
 
@@ -41,36 +88,94 @@ impl Drop for MountPoint {
 }
 
 impl RootDirectory {
-    pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
+    pub const fn new(main_fs: Arc<dyn VfsOps>, main_info: MountInfo) -> Self {
         Self {
             main_fs,
-            mounts: Vec::new(),
+            main_info,
+            mounts: RwLock::new(Vec::new()),
         }
     }
 
-    pub fn mount(&mut self, path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    pub fn mount(&self, path: &str, fs: Arc<dyn VfsOps>, info: MountInfo) -> AxResult {
         if path == "/" {
             return ax_err!(InvalidInput, "cannot mount root filesystem");
         }
         if !path.starts_with('/') {
             return ax_err!(InvalidInput, "mount path must start with '/'");
         }
-        if self.mounts.iter().any(|mp| mp.path == path) {
+        if self.contains(path) {
             return ax_err!(InvalidInput, "mount point already exists");
         }
-        // create the mount point in the main filesystem if it does not exist
-        self.main_fs.root_dir().create(path, FileType::Dir)?;
-        fs.mount(path, self.main_fs.root_dir().lookup(path)?)?;
-        self.mounts.push(MountPoint::new(path, fs));
+        // create the mount point in the parent filesystem if it does not exist
+        match self.create(path, FileType::Dir) {
+            Ok(()) | Err(AxError::AlreadyExists) => {}
+            Err(e) => return Err(e),
+        }
+        let mount_point =
+            self.lookup_mounted_fs(path, |fs, rest_path| fs.root_dir().lookup(rest_path))?;
+        if !mount_point.get_attr()?.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+
+        let mut mounts = self.mounts.write();
+        if mounts.iter().any(|mp| mp.path == path) {
+            return ax_err!(InvalidInput, "mount point already exists");
+        }
+        fs.mount(path, mount_point)?;
+        mounts.push(MountPoint::new(path.into(), fs, info));
         Ok(())
     }
 
-    pub fn _umount(&mut self, path: &str) {
-        self.mounts.retain(|mp| mp.path != path);
+    pub fn umount(&self, path: &str) -> AxResult {
+        let mut mounts = self.mounts.write();
+        let Some(idx) = mounts.iter().position(|mp| mp.path == path) else {
+            return ax_err!(InvalidInput, "not a mount point");
+        };
+        if Arc::strong_count(&mounts[idx].info) > 1 {
+            return ax_err!(ResourceBusy, "filesystem has opened files");
+        }
+        let nested = mounts.iter().any(|mp| {
+            mp.path
+                .strip_prefix(path)
+                .is_some_and(|rest| rest.starts_with('/'))
+        });
+        if nested {
+            return ax_err!(ResourceBusy, "other filesystems are mounted under it");
+        }
+        let mp = mounts.remove(idx);
+        drop(mounts);
+        drop(mp); // umount the filesystem
+        Ok(())
     }
 
     pub fn contains(&self, path: &str) -> bool {
-        self.mounts.iter().any(|mp| mp.path == path)
+        self.mounts.read().iter().any(|mp| mp.path == path)
+    }
+
+    /// Returns the reference to the mounted filesystem that `path` belongs to,
+    /// or `None` if it belongs to the main filesystem.
+    fn mount_ref(&self, path: &str) -> Option<MountRef> {
+        let mounts = self.mounts.read();
+        let idx = Self::find_mount(&mounts, path.trim_matches('/'))?;
+        Some(MountRef(mounts[idx].info.clone()))
+    }
+
+    /// Returns the filesystem mounted exactly on `path`, which is absolute.
+    fn mounted_fs(&self, path: &str) -> Option<(Arc<dyn VfsOps>, MountRef)> {
+        let mounts = self.mounts.read();
+        let mp = mounts.iter().find(|mp| mp.path == path)?;
+        Some((mp.fs.clone(), MountRef(mp.info.clone())))
+    }
+
+    /// Finds the mount point that has the longest path match.
+    fn find_mount(mounts: &[MountPoint], path: &str) -> Option<usize> {
+        // TODO: more efficient, e.g. trie
+        mounts
+            .iter()
+            .enumerate()
+            .filter(|(_, mp)| mp.covers(path))
+            .max_by_key(|(_, mp)| mp.path.len())
+            .map(|(i, _)| i)
     }
 
     fn lookup_mounted_fs<F, T>(&self, path: &str, f: F) -> AxResult<T>
@@ -83,23 +188,15 @@ impl RootDirectory {
             return self.lookup_mounted_fs(rest, f);
         }
 
-        let mut idx = 0;
-        let mut max_len = 0;
-
-        // Find the filesystem that has the longest mounted path match
-        // TODO: more efficient, e.g. trie
-        for (i, mp) in self.mounts.iter().enumerate() {
-            // skip the first '/'
-            if path.starts_with(&mp.path[1..]) && mp.path.len() - 1 > max_len {
-                max_len = mp.path.len() - 1;
-                idx = i;
-            }
-        }
-
-        if max_len == 0 {
-            f(self.main_fs.clone(), path) // not matched any mount point
+        let mounts = self.mounts.read();
+        if let Some(idx) = Self::find_mount(&mounts, path) {
+            let fs = mounts[idx].fs.clone();
+            let max_len = mounts[idx].path.len() - 1;
+            drop(mounts);
+            f(fs, &path[max_len..]) // matched at `idx`
         } else {
-            f(self.mounts[idx].fs.clone(), &path[max_len..]) // matched at `idx`
+            drop(mounts);
+            f(self.main_fs.clone(), path) // not matched any mount point
         }
     }
 }
@@ -111,6 +208,10 @@ impl VfsNodeOps for RootDirectory {
         self.main_fs.root_dir().get_attr()
     }
 
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        self.main_fs.root_dir().read_dir(start_idx, dirents)
+    }
+
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
         self.lookup_mounted_fs(path, |fs, rest_path| fs.root_dir().lookup(rest_path))
     }
@@ -136,50 +237,89 @@ impl VfsNodeOps for RootDirectory {
     }
 
     fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
-        self.lookup_mounted_fs(src_path, |fs, rest_path| {
-            if rest_path.is_empty() {
-                ax_err!(PermissionDenied) // cannot rename mount points
-            } else {
-                fs.root_dir().rename(rest_path, dst_path)
-            }
+        self.lookup_mounted_fs(src_path, |src_fs, src_rest| {
+            self.lookup_mounted_fs(dst_path, |dst_fs, dst_rest| {
+                if src_rest.is_empty() || dst_rest.is_empty() {
+                    ax_err!(PermissionDenied) // cannot rename mount points
+                } else if !Arc::ptr_eq(&src_fs, &dst_fs) {
+                    warn!("cannot rename across filesystems");
+                    Err(crate::api::CROSS_DEVICE_ERROR)
+                } else {
+                    src_fs.root_dir().rename(src_rest, dst_rest)
+                }
+            })
         })
     }
 }
 
 pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
+    let source = format!("/dev/{}", disk.device().name());
     cfg_if::cfg_if! {
         if #[cfg(feature = "myfs")] { // override the default filesystem
             let main_fs = fs::myfs::new_myfs(disk);
+            // custom filesystems built on ramfs can link their nodes,
+            // the links fail as unsupported in others
+            #[cfg(feature = "ramfs")]
+            let ext_ops = fs::ramfs::EXT_OPS;
+            #[cfg(not(feature = "ramfs"))]
+            let ext_ops = NodeExtOps::default();
+            let main_info = MountInfo::new(&source, "myfs", ext_ops);
+        } else if #[cfg(feature = "ext2fs")] {
+            let main_fs = fs::ext2::Ext2FileSystem::new(disk)
+                .expect("failed to initialize ext2 filesystem");
+            let main_info = MountInfo::new(&source, "ext2", fs::ext2::EXT_OPS);
         } else if #[cfg(feature = "fatfs")] {
-            static FAT_FS: LazyInit<Arc<fs::fatfs::FatFileSystem>> = LazyInit::new();
-            FAT_FS.init_once(Arc::new(fs::fatfs::FatFileSystem::new(disk)));
-            FAT_FS.init();
-            let main_fs = FAT_FS.clone();
+            let main_fs = fs::fatfs::FatFileSystem::new(disk)
+                .expect("failed to initialize FAT filesystem");
+            let main_info = MountInfo::new(&source, "vfat", fs::fatfs::EXT_OPS);
         }
     }
 
-    let mut root_dir = RootDirectory::new(main_fs);
+    #[cfg(feature = "overlayfs")]
+    let (main_fs, main_info) = (
+        Arc::new(fs::overlayfs::OverlayFileSystem::new(
+            main_fs,
+            main_info.ext_ops,
+        )),
+        MountInfo::new("overlay", "overlay", fs::overlayfs::EXT_OPS),
+    );
+
+    let root_dir = RootDirectory::new(main_fs, main_info);
 
     #[cfg(feature = "devfs")]
     root_dir
-        .mount("/dev", mounts::devfs())
+        .mount(
+            "/dev",
+            mounts::devfs(),
+            MountInfo::new("devfs", "devfs", crate::fs::devfs::EXT_OPS),
+        )
         .expect("failed to mount devfs at /dev");
 
     #[cfg(feature = "ramfs")]
     root_dir
-        .mount("/tmp", mounts::ramfs())
+        .mount(
+            "/tmp",
+            mounts::ramfs(),
+            MountInfo::new("ramfs", "ramfs", fs::ramfs::EXT_OPS),
+        )
         .expect("failed to mount ramfs at /tmp");
 
-    // Mount another ramfs as procfs
     #[cfg(feature = "procfs")]
     root_dir // should not fail
-        .mount("/proc", mounts::procfs().unwrap())
+        .mount(
+            "/proc",
+            mounts::procfs(),
+            MountInfo::new("proc", "proc", NodeExtOps::default()),
+        )
         .expect("fail to mount procfs at /proc");
 
-    // Mount another ramfs as sysfs
     #[cfg(feature = "sysfs")]
     root_dir // should not fail
-        .mount("/sys", mounts::sysfs().unwrap())
+        .mount(
+            "/sys",
+            mounts::sysfs(),
+            MountInfo::new("sysfs", "sysfs", NodeExtOps::default()),
+        )
         .expect("fail to mount sysfs at /sys");
 
     ROOT_DIR.init_once(Arc::new(root_dir));
@@ -187,11 +327,127 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     CURRENT_DIR_PATH.init_new(Mutex::new("/".into()));
 }
 
-fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
-    if path.starts_with('/') {
-        ROOT_DIR.clone()
-    } else {
-        dir.cloned().unwrap_or_else(|| CURRENT_DIR.lock().clone())
+/// Converts `path` to the absolute path of a mount point, without the
+/// trailing `/`.
+fn mount_path(path: &str) -> AxResult<String> {
+    let mut path = absolute_path(path)?;
+    while path.len() > 1 && path.ends_with('/') {
+        path.pop();
+    }
+    Ok(path)
+}
+
+pub(crate) fn mount(source: &str, target: &str, fstype: &str, flags: usize) -> AxResult {
+    if flags != 0 {
+        return ax_err!(InvalidInput, "unsupported mount flags");
+    }
+    let target = mount_path(target)?;
+    let (fs, ext_ops) = mounts::new_fs(source, fstype)?;
+    info!("mount {} ({}) at {}", source, fstype, target);
+    ROOT_DIR.mount(&target, fs, MountInfo::new(source, fstype, ext_ops))
+}
+
+pub(crate) fn umount(target: &str) -> AxResult {
+    let target = mount_path(target)?;
+    if CURRENT_DIR_PATH.lock().starts_with(&format!("{}/", target)) {
+        return ax_err!(ResourceBusy, "current directory is in the filesystem");
+    }
+    info!("umount {}", target);
+    ROOT_DIR.umount(&target)
+}
+
+/// Generates the content of `/proc/mounts`, one mounted filesystem per line.
+#[cfg(feature = "procfs")]
+pub(crate) fn proc_mounts() -> String {
+    let line =
+        |info: &MountInfo, path: &str| format!("{} {} {} rw 0 0\n", info.source, path, info.fstype);
+    let mut content = line(&ROOT_DIR.main_info, "/");
+    for mp in ROOT_DIR.mounts.read().iter() {
+        content += &line(&mp.info, &mp.path);
+    }
+    content
+}
+
+/// Returns the reference to the mounted filesystem that `path` belongs to, or
+/// `None` if it belongs to the root filesystem.
+///
+/// A relative `path` is relative to `dir`, which belongs to the filesystem of
+/// `mount`, or the current directory if `dir` is `None`. Like [`resolve`],
+/// the mount points are only crossed from the root, so a path relative to
+/// `dir` other than the root belongs to `mount`.
+pub(crate) fn mount_ref(
+    dir: Option<&VfsNodeRef>,
+    mount: Option<&MountRef>,
+    path: &str,
+) -> Option<MountRef> {
+    match dir {
+        Some(dir) if !path.starts_with('/') => {
+            if is_root_dir(dir) {
+                ROOT_DIR.mount_ref(&axfs_vfs::path::canonicalize(&format!("/{}", path)))
+            } else {
+                mount.cloned()
+            }
+        }
+        _ => ROOT_DIR.mount_ref(&absolute_path(path).ok()?),
+    }
+}
+
+/// Returns the extra node operations of the filesystem of `mount`, or the root
+/// filesystem if `mount` is `None`.
+fn ext_ops(mount: Option<&MountRef>) -> NodeExtOps {
+    match mount {
+        Some(mount) => mount.0.ext_ops,
+        None => ROOT_DIR.main_info.ext_ops,
+    }
+}
+
+/// Returns the timestamps of `node`, which belongs to the filesystem of
+/// `mount`, or the root filesystem if `mount` is `None`.
+pub(crate) fn node_times(mount: Option<&MountRef>, node: &VfsNodeRef) -> Option<FileTimes> {
+    ext_ops(mount).node_times.and_then(|f| f(node))
+}
+
+/// Sets the access and modification times of `node`, which belongs to the
+/// filesystem of `mount`, or the root filesystem if `mount` is `None`.
+pub(crate) fn set_node_times(
+    mount: Option<&MountRef>,
+    node: &VfsNodeRef,
+    accessed: Option<Duration>,
+    modified: Option<Duration>,
+) -> AxResult {
+    let set_times = ext_ops(mount).set_times.ok_or(AxError::Unsupported)?;
+    Ok(set_times(node, accessed, modified)?)
+}
+
+/// Performs the device-specific request `cmd` on `node`, which belongs to the
+/// filesystem of `mount`, or the root filesystem if `mount` is `None`.
+pub(crate) fn node_ioctl(
+    mount: Option<&MountRef>,
+    node: &VfsNodeRef,
+    cmd: u32,
+    arg: usize,
+) -> AxResult<usize> {
+    let ioctl = ext_ops(mount).ioctl.ok_or(AxError::Unsupported)?;
+    Ok(ioctl(node, cmd, arg)?)
+}
+
+/// Returns the identity of `node`, which belongs to the filesystem of `mount`,
+/// or the root filesystem if `mount` is `None`, for the advisory locks.
+///
+/// The nodes of the filesystems without inode numbers are identified by their
+/// addresses, so it also returns whether the node must be kept alive while the
+/// identity is used.
+pub(crate) fn file_id(mount: Option<&MountRef>, node: &VfsNodeRef) -> (FileId, bool) {
+    let fs = match mount {
+        Some(mount) => Arc::as_ptr(&mount.0) as usize,
+        None => &ROOT_DIR.main_info as *const MountInfo as usize,
+    };
+    match ext_ops(mount).node_id.and_then(|node_id| node_id(node)) {
+        Some(ino) => (FileId { fs, ino }, false),
+        None => {
+            let ino = Arc::as_ptr(node) as *const () as usize as u64;
+            (FileId { fs, ino }, true)
+        }
     }
 }
 
@@ -204,46 +460,346 @@ pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     }
 }
 
-pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+/// A path whose symbolic links have been resolved.
+struct Resolved {
+    /// The directory that the path is relative to.
+    base: VfsNodeRef,
+    /// Whether `base` is the root directory, so that the path is absolute.
+    at_root: bool,
+    /// The components of the path relative to `base`, without `.`, and
+    /// without `..` unless they go above a `base` other than the root.
+    path: Vec<String>,
+    /// The node at the path.
+    node: VfsNodeRef,
+    /// The node in its own filesystem, which differs from `node` only for the
+    /// root directory.
+    fs_node: VfsNodeRef,
+    /// The mounted filesystem that the node belongs to, or `None` if it
+    /// belongs to the root filesystem or the path is not absolute.
+    mount: Option<MountRef>,
+}
+
+impl Resolved {
+    /// Returns the path of `name` in this directory, relative to `base`.
+    fn join(&self, name: &str) -> String {
+        let mut path = self.path.join("/");
+        if !path.is_empty() {
+            path.push('/');
+        }
+        path + name
+    }
+}
+
+/// A directory that [`Walk`] has entered.
+struct Step {
+    node: VfsNodeRef,
+    mount: Option<MountRef>,
+    /// The length of [`Walk::abs_path`] before entering the directory.
+    abs_len: usize,
+}
+
+/// The state of [`resolve`], which looks up the components of a path one by
+/// one from the directory that it has reached.
+struct Walk {
+    base: VfsNodeRef,
+    at_root: bool,
+    path: Vec<String>,
+    /// The nodes of `base` and of each component in `path`.
+    steps: Vec<Step>,
+    /// The absolute path of the current node if `at_root`, to find the
+    /// filesystems mounted on it.
+    abs_path: String,
+}
+
+impl Walk {
+    fn new(base: VfsNodeRef) -> Self {
+        Self {
+            base: base.clone(),
+            at_root: false,
+            path: Vec::new(),
+            steps: vec![Step {
+                node: base,
+                mount: None,
+                abs_len: 0,
+            }],
+            abs_path: String::new(),
+        }
+    }
+
+    fn root() -> Self {
+        let mut walk = Self::new(ROOT_DIR.clone());
+        walk.restart_at_root();
+        walk
+    }
+
+    /// Goes back to the root directory.
+    fn restart_at_root(&mut self) {
+        self.base = ROOT_DIR.clone();
+        self.at_root = true;
+        self.path.clear();
+        self.steps.truncate(1);
+        self.steps[0] = Step {
+            node: ROOT_DIR.main_fs.root_dir(),
+            mount: None,
+            abs_len: 0,
+        };
+        self.abs_path.clear();
+    }
+
+    fn current(&self) -> &Step {
+        self.steps.last().unwrap()
+    }
+
+    /// Looks up `name` in the current directory and enters it, or the root
+    /// of the filesystem mounted on it.
+    fn enter(&mut self, name: String) -> AxResult<&VfsNodeRef> {
+        let abs_len = self.abs_path.len();
+        let mounted = if self.at_root {
+            self.abs_path.push('/');
+            self.abs_path += &name;
+            ROOT_DIR.mounted_fs(&self.abs_path)
+        } else {
+            None
+        };
+        let step = match mounted {
+            Some((fs, mount)) => Step {
+                node: fs.root_dir(),
+                mount: Some(mount),
+                abs_len,
+            },
+            None => {
+                let current = self.current();
+                Step {
+                    node: current.node.clone().lookup(&name)?,
+                    mount: current.mount.clone(),
+                    abs_len,
+                }
+            }
+        };
+        self.path.push(name);
+        self.steps.push(step);
+        Ok(&self.current().node)
+    }
+
+    /// Goes back to the parent of the current directory.
+    fn leave(&mut self) {
+        let step = self.steps.pop().unwrap();
+        self.path.pop();
+        self.abs_path.truncate(step.abs_len);
+    }
+
+    fn finish(mut self) -> Resolved {
+        let step = self.steps.pop().unwrap();
+        let node = if self.at_root && self.path.is_empty() {
+            ROOT_DIR.clone() // to look up across the mount points
+        } else {
+            step.node.clone()
+        };
+        Resolved {
+            base: self.base,
+            at_root: self.at_root,
+            path: self.path,
+            node,
+            fs_node: step.node,
+            mount: step.mount,
+        }
+    }
+}
+
+/// Returns whether `node` is the root directory.
+fn is_root_dir(node: &VfsNodeRef) -> bool {
+    let root: &Arc<RootDirectory> = &ROOT_DIR;
+    core::ptr::addr_eq(Arc::as_ptr(node), Arc::as_ptr(root))
+}
+
+/// Pushes the components of `path` to `pending` in reverse order, so that
+/// they can be popped in order.
+fn push_components(pending: &mut Vec<String>, path: &str) {
+    pending.extend(
+        path.rsplit('/')
+            .filter(|c| !c.is_empty() && *c != ".")
+            .map(String::from),
+    );
+}
+
+/// Reads the target of the symbolic link `node`.
+fn read_link_target(node: &VfsNodeRef) -> AxResult<String> {
+    let size = node.get_attr()?.size() as usize;
+    let mut buf = vec![0; size];
+    let mut read_len = 0;
+    while read_len < size {
+        match node.read_at(read_len as u64, &mut buf[read_len..])? {
+            0 => break,
+            n => read_len += n,
+        }
+    }
+    buf.truncate(read_len);
+    String::from_utf8(buf).map_err(|_| AxError::InvalidData)
+}
+
+/// Resolves `path` relative to `dir`, or the current directory if `dir` is
+/// `None`.
+///
+/// The symbolic links in the path are followed, including the last component
+/// if `follow` is `true`. The lookups inside a filesystem never follow the
+/// links, so that the links can point across mount points.
+fn resolve(dir: Option<&VfsNodeRef>, path: &str, follow: bool) -> AxResult<Resolved> {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
-    let node = parent_node_of(dir, path).lookup(path)?;
-    if path.ends_with('/') && !node.get_attr()?.is_dir() {
+    let mut walk = match dir {
+        Some(dir) if !path.starts_with('/') && !is_root_dir(dir) => Walk::new(dir.clone()),
+        _ => Walk::root(),
+    };
+    let mut pending = Vec::new();
+    push_components(&mut pending, path);
+    if dir.is_none() && !path.starts_with('/') {
+        push_components(&mut pending, &CURRENT_DIR_PATH.lock());
+    }
+
+    let mut links = 0;
+    while let Some(name) = pending.pop() {
+        if name == ".." {
+            if walk.path.last().is_some_and(|c| c != "..") {
+                walk.leave();
+            } else if !walk.at_root {
+                walk.enter(name)?;
+            }
+            continue;
+        }
+
+        let node = walk.enter(name)?;
+        let attr = node.get_attr()?;
+        if attr.file_type() == VfsNodeType::SymLink && (follow || !pending.is_empty()) {
+            links += 1;
+            if links > MAX_SYMLINKS {
+                warn!("too many levels of symbolic links: {}", path);
+                return Err(crate::api::SYMLINK_LOOP_ERROR);
+            }
+            let target = read_link_target(node)?;
+            if target.is_empty() {
+                return ax_err!(NotFound);
+            }
+            walk.leave();
+            if target.starts_with('/') {
+                walk.restart_at_root();
+            }
+            push_components(&mut pending, &target);
+        } else if !attr.is_dir() && !pending.is_empty() {
+            return ax_err!(NotADirectory);
+        }
+    }
+    Ok(walk.finish())
+}
+
+/// Resolves the parent directory of `path`, returns it and the last component
+/// of the path, which is not followed.
+fn resolve_parent<'a>(dir: Option<&VfsNodeRef>, path: &'a str) -> AxResult<(Resolved, &'a str)> {
+    if path.is_empty() {
+        return ax_err!(NotFound);
+    }
+    let trimmed = path.trim_end_matches('/');
+    let (parent, name) = match trimmed.rfind('/') {
+        Some(idx) => (&trimmed[..=idx], &trimmed[idx + 1..]),
+        None => (".", trimmed),
+    };
+    if name.is_empty() || name == "." || name == ".." {
+        return ax_err!(InvalidInput);
+    }
+    let parent = resolve(dir, parent, true)?;
+    if !parent.node.get_attr()?.is_dir() {
+        return ax_err!(NotADirectory);
+    }
+    Ok((parent, name))
+}
+
+pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+    Ok(lookup_mounted(dir, None, path)?.0)
+}
+
+/// Looks up `path` like [`lookup`], and also returns the mounted filesystem
+/// that the node belongs to, with the symbolic links resolved.
+///
+/// The mount points are only crossed from the root, so `mount` is returned
+/// if `path` is relative to `dir` other than the root, which is in `mount`.
+pub(crate) fn lookup_mounted(
+    dir: Option<&VfsNodeRef>,
+    mount: Option<&MountRef>,
+    path: &str,
+) -> AxResult<(VfsNodeRef, Option<MountRef>)> {
+    let resolved = resolve(dir, path, true)?;
+    if path.ends_with('/') && !resolved.node.get_attr()?.is_dir() {
         ax_err!(NotADirectory)
+    } else if resolved.at_root {
+        Ok((resolved.node, resolved.mount))
+    } else {
+        Ok((resolved.node, mount.cloned()))
+    }
+}
+
+/// Looks up `path` like [`lookup`], but returns the symbolic link itself if
+/// the last component is a link.
+pub(crate) fn lookup_link(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+    if path.ends_with('/') {
+        // a trailing `/` requires the link to be followed to a directory
+        lookup(dir, path)
     } else {
-        Ok(node)
+        Ok(resolve(dir, path, false)?.node)
     }
 }
 
 pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+    Ok(create_file_mounted(dir, None, path)?.0)
+}
+
+/// Creates a file like [`create_file`], and also returns the mounted
+/// filesystem that it belongs to like [`lookup_mounted`].
+pub(crate) fn create_file_mounted(
+    dir: Option<&VfsNodeRef>,
+    mount: Option<&MountRef>,
+    path: &str,
+) -> AxResult<(VfsNodeRef, Option<MountRef>)> {
     if path.is_empty() {
         return ax_err!(NotFound);
     } else if path.ends_with('/') {
         return ax_err!(NotADirectory);
     }
-    let parent = parent_node_of(dir, path);
-    parent.create(path, VfsNodeType::File)?;
-    parent.lookup(path)
+    let (parent, name) = resolve_parent(dir, path)?;
+    let path = parent.join(name);
+    parent.base.create(&path, VfsNodeType::File)?;
+    let node = parent.base.lookup(&path)?;
+    if parent.at_root {
+        Ok((node, parent.mount))
+    } else {
+        Ok((node, mount.cloned()))
+    }
 }
 
 pub(crate) fn create_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    match lookup(dir, path) {
+    match lookup_link(dir, path) {
         Ok(_) => ax_err!(AlreadyExists),
-        Err(AxError::NotFound) => parent_node_of(dir, path).create(path, VfsNodeType::Dir),
+        Err(AxError::NotFound) => {
+            let (parent, name) = resolve_parent(dir, path)?;
+            parent.base.create(&parent.join(name), VfsNodeType::Dir)
+        }
         Err(e) => Err(e),
     }
 }
 
 pub(crate) fn remove_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    let node = lookup(dir, path)?;
+    if path.ends_with('/') {
+        lookup(dir, path)?;
+    }
+    let (parent, name) = resolve_parent(dir, path)?;
+    let path = parent.join(name);
+    let node = parent.base.clone().lookup(&path)?;
     let attr = node.get_attr()?;
     if attr.is_dir() {
         ax_err!(IsADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        parent.base.remove(&path)
     }
 }
 
@@ -261,18 +817,20 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
     {
         return ax_err!(InvalidInput);
     }
-    if ROOT_DIR.contains(&absolute_path(path)?) {
+
+    let (parent, name) = resolve_parent(dir, path)?;
+    let path = parent.join(name);
+    if parent.at_root && ROOT_DIR.contains(&format!("/{}", path)) {
         return ax_err!(PermissionDenied);
     }
-
-    let node = lookup(dir, path)?;
+    let node = parent.base.clone().lookup(&path)?;
     let attr = node.get_attr()?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        parent.base.remove(&path)
     }
 }
 
@@ -281,33 +839,92 @@ pub(crate) fn current_dir() -> AxResult<String> {
 }
 
 pub(crate) fn set_current_dir(path: &str) -> AxResult {
-    let mut abs_path = absolute_path(path)?;
-    if !abs_path.ends_with('/') {
-        abs_path += "/";
-    }
-    if abs_path == "/" {
-        *CURRENT_DIR.lock() = ROOT_DIR.clone();
-        *CURRENT_DIR_PATH.lock() = "/".into();
-        return Ok(());
-    }
-
-    let node = lookup(None, &abs_path)?;
-    let attr = node.get_attr()?;
+    let resolved = resolve(None, path, true)?;
+    let attr = resolved.node.get_attr()?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
     } else if !attr.perm().owner_executable() {
         ax_err!(PermissionDenied)
     } else {
-        *CURRENT_DIR.lock() = node;
+        let mut abs_path = String::from("/");
+        for name in &resolved.path {
+            abs_path += name;
+            abs_path.push('/');
+        }
+        *CURRENT_DIR.lock() = resolved.node;
         *CURRENT_DIR_PATH.lock() = abs_path;
         Ok(())
     }
 }
 
 pub(crate) fn rename(old: &str, new: &str) -> AxResult {
-    if parent_node_of(None, new).lookup(new).is_ok() {
+    let (src, src_name) = resolve_parent(None, old)?;
+    let (dst, dst_name) = resolve_parent(None, new)?;
+    let (src_path, dst_path) = (src.join(src_name), dst.join(dst_name));
+    if src_path == dst_path {
+        return Ok(());
+    }
+    if ROOT_DIR.clone().lookup(&dst_path).is_ok() {
         warn!("dst file already exist, now remove it");
-        remove_file(None, new)?;
+        remove_file(None, &format!("/{}", dst_path))?;
+    }
+    ROOT_DIR.rename(&src_path, &dst_path)
+}
+
+/// Creates a symbolic link at `path` relative to `dir`, which points to
+/// `target`.
+pub(crate) fn symlink(dir: Option<&VfsNodeRef>, target: &str, path: &str) -> AxResult {
+    if target.is_empty() {
+        return ax_err!(NotFound);
+    }
+    let (parent, name) = resolve_parent(dir, path)?;
+    let path = parent.join(name);
+    if parent.base.clone().lookup(&path).is_ok() {
+        return ax_err!(AlreadyExists);
+    }
+    parent.base.create(&path, VfsNodeType::SymLink)?;
+    let node = parent.base.clone().lookup(&path)?;
+    if let Err(e) = node.write_at(0, target.as_bytes()) {
+        parent.base.remove(&path).ok();
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Returns the target of the symbolic link at `path` relative to `dir`.
+pub(crate) fn read_link(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<String> {
+    let node = lookup_link(dir, path)?;
+    if node.get_attr()?.file_type() != VfsNodeType::SymLink {
+        return ax_err!(InvalidInput, "not a symbolic link");
+    }
+    read_link_target(&node)
+}
+
+/// Creates a hard link at `new` to the file at `old`.
+///
+/// Both of them must be in the same filesystem, which supports hard links.
+/// If `old` is a symbolic link, the link itself is linked.
+pub(crate) fn hard_link(old: &str, new: &str) -> AxResult {
+    let src = resolve(None, old, false)?;
+    if src.node.get_attr()?.is_dir() {
+        return ax_err!(PermissionDenied, "cannot link directories");
+    }
+    let (parent, name) = resolve_parent(None, new)?;
+    if parent.fs_node.clone().lookup(name).is_ok() {
+        return ax_err!(AlreadyExists);
+    }
+
+    let same_fs = match (&src.mount, &parent.mount) {
+        (Some(src), Some(dst)) => Arc::ptr_eq(&src.0, &dst.0),
+        (None, None) => true,
+        _ => false,
+    };
+    if !same_fs {
+        warn!("cannot link across filesystems");
+        return Err(crate::api::CROSS_DEVICE_ERROR);
     }
-    parent_node_of(None, old).rename(old, new)
+    let Some(link) = ext_ops(parent.mount.as_ref()).hard_link else {
+        return ax_err!(Unsupported, "hard links are not supported");
+    };
+    link(&parent.fs_node, name, &src.fs_node)
 }
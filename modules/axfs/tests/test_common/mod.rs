@@ -252,6 +252,389 @@ fn test_devfs_ramfs() -> Result<()> {
     Ok(())
 }
 
+fn test_mount_umount() -> Result<()> {
+    println!("test mount and umount ...");
+
+    fs::mount("", "/mnt", "ramfs", 0)?;
+    assert_err!(fs::mount("", "/mnt/", "ramfs", 0), InvalidInput);
+    assert_err!(fs::mount("", "/mnt2", "unknownfs", 0), Unsupported);
+    assert_err!(fs::mount("/dev/vdz", "/mnt2", "vfat", 0));
+    assert_eq!(fs::read_dir("/mnt")?.count(), 0);
+    fs::write("/mnt/test.txt", "test")?;
+
+    // busy if there are opened files
+    let file = File::open("/mnt//test.txt")?;
+    assert_err!(fs::umount("/mnt"), ResourceBusy);
+    drop(file);
+    // including the files opened relative to a directory outside of it
+    let mut opts = axfs::fops::OpenOptions::new();
+    opts.read(true);
+    let root = axfs::fops::Directory::open_dir("/", &opts)?;
+    let file = root.open_file_at("mnt/test.txt", &opts)?;
+    assert_err!(fs::umount("/mnt"), ResourceBusy);
+    drop((file, root));
+
+    // busy if other filesystems are mounted under it
+    fs::mount("", "/mnt/sub", "ramfs", 0)?;
+    assert_err!(fs::umount("/mnt"), ResourceBusy);
+    assert_eq!(fs::umount("/mnt/sub/"), Ok(()));
+
+    // busy if the current directory is in it
+    fs::set_current_dir("/mnt")?;
+    assert_err!(fs::umount("/mnt"), ResourceBusy);
+    fs::set_current_dir("/")?;
+
+    assert_eq!(fs::umount("/mnt"), Ok(()));
+    assert_err!(fs::umount("/mnt"), InvalidInput);
+    assert_err!(fs::metadata("/mnt/test.txt"), NotFound);
+
+    // mount again
+    fs::mount("", "/mnt", "ramfs", 0)?;
+    assert_eq!(fs::read_dir("/mnt")?.count(), 0);
+    fs::umount("/mnt")?;
+    fs::remove_dir("/mnt")?;
+
+    println!("test_mount_umount() OK!");
+    Ok(())
+}
+
+fn test_links() -> Result<()> {
+    println!("test links in /tmp ...");
+
+    // symbolic links, relative to the directory of the link
+    fs::create_dir("/tmp/links")?;
+    fs::write("/tmp/links/file.txt", "link")?;
+    fs::symlink("file.txt", "/tmp/links/sym")?;
+    fs::symlink("/tmp/links", "/tmp/dir-sym")?;
+    assert_eq!(fs::read_link("/tmp/links/sym")?, "file.txt");
+    assert_err!(fs::read_link("/tmp/links/file.txt"), InvalidInput);
+    assert!(fs::symlink_metadata("/tmp/links/sym")?.is_symlink());
+    assert!(fs::metadata("/tmp/links/sym")?.is_file());
+    assert_eq!(fs::read_to_string("/tmp/links/sym")?, "link");
+    assert_eq!(fs::read_to_string("/tmp/dir-sym/sym")?, "link");
+    assert_err!(fs::symlink("other", "/tmp/links/sym"), AlreadyExists);
+
+    // hard links share the data, which outlives the removed names
+    fs::hard_link("/tmp/links/file.txt", "/tmp/links/hard.txt")?;
+    fs::write("/tmp/links/hard.txt", "hard link")?;
+    assert_eq!(fs::read_to_string("/tmp/links/file.txt")?, "hard link");
+    fs::remove_file("/tmp/links/file.txt")?;
+    assert_eq!(fs::read_to_string("/tmp/links/hard.txt")?, "hard link");
+    assert_err!(fs::metadata("/tmp/links/sym"), NotFound);
+    fs::hard_link("/tmp/links/sym", "/tmp/links/sym2")?; // the link itself
+    assert_eq!(fs::read_link("/tmp/links/sym2")?, "file.txt");
+    assert_err!(
+        fs::hard_link("/tmp/links", "/tmp/dir-link"),
+        PermissionDenied
+    );
+    assert_err!(
+        fs::hard_link("/tmp/links/hard.txt", "/tmp/links/sym"),
+        AlreadyExists
+    );
+
+    // up to 40 levels of symbolic links are followed
+    fs::symlink("hard.txt", "/tmp/links/l0")?;
+    for i in 1..=40 {
+        fs::symlink(&format!("l{}", i - 1), &format!("/tmp/links/l{}", i))?;
+    }
+    assert_eq!(fs::read_to_string("/tmp/links/l39")?, "hard link");
+    assert_eq!(
+        fs::metadata("/tmp/links/l40").err(),
+        Some(fs::SYMLINK_LOOP_ERROR)
+    );
+    fs::symlink("loop-b", "/tmp/links/loop-a")?;
+    fs::symlink("loop-a", "/tmp/links/loop-b")?;
+    assert_eq!(
+        fs::read("/tmp/links/loop-a").err(),
+        Some(fs::SYMLINK_LOOP_ERROR)
+    );
+
+    // hard links and renames can not cross filesystems
+    assert_eq!(
+        fs::hard_link("/tmp/links/hard.txt", "/hard.txt").err(),
+        Some(fs::CROSS_DEVICE_ERROR)
+    );
+    assert_eq!(
+        fs::rename("/tmp/links/hard.txt", "/hard.txt").err(),
+        Some(fs::CROSS_DEVICE_ERROR)
+    );
+    assert_err!(fs::metadata("/hard.txt"), NotFound);
+
+    let names = fs::read_dir("/tmp/links")?
+        .map(|e| e.unwrap().file_name())
+        .collect::<Vec<_>>();
+    for name in names {
+        fs::remove_file(&format!("/tmp/links/{}", name))?;
+    }
+    fs::remove_dir("/tmp/links")?;
+    fs::remove_file("/tmp/dir-sym")?;
+    assert_eq!(fs::read_dir("/tmp")?.count(), 0);
+
+    println!("test_links() OK!");
+    Ok(())
+}
+
+fn test_block_device() -> Result<()> {
+    println!("test block device ...");
+
+    let fname = "/dev/vda";
+    let md = fs::metadata(fname)?;
+    assert_eq!(md.file_type(), FileType::BlockDevice);
+
+    // the first sector can be read, but not written back, as the device
+    // holds the mounted root filesystem
+    let mut file = File::options().read(true).write(true).open(fname)?;
+    let mut buf = [0; 512];
+    let len = file.read(&mut buf)?;
+    assert_eq!(len as u64, md.len().min(512));
+    file.seek(io::SeekFrom::Start(0))?;
+    assert_err!(file.write(&buf[..len]), ResourceBusy);
+    file.seek(io::SeekFrom::Start(md.len()))?;
+    assert_eq!(file.read(&mut buf)?, 0);
+
+    println!("test_block_device() OK!");
+    Ok(())
+}
+
+fn test_device_ioctl() -> Result<()> {
+    use axfs::fops;
+    use std::sync::Arc;
+
+    println!("test device ioctl ...");
+
+    fn ioctl(cmd: u32, arg: usize) -> Result<usize> {
+        match cmd {
+            1 => {
+                unsafe { *(arg as *mut u32) = 42 };
+                Ok(0)
+            }
+            _ => Err(Error::InvalidInput),
+        }
+    }
+    axfs::devfs::register_device_with_ioctl("ioctl", Arc::new(axfs::devfs::NullDev), ioctl);
+
+    let mut opts = fops::OpenOptions::new();
+    opts.read(true);
+    let file = fops::File::open("/dev/ioctl", &opts)?;
+    let mut value = 0u32;
+    assert_eq!(file.ioctl(1, &mut value as *mut u32 as usize), Ok(0));
+    assert_eq!(value, 42);
+    assert_err!(file.ioctl(2, 0), InvalidInput);
+
+    // devices without a handler and regular files do not support it
+    let file = fops::File::open("/dev/null", &opts)?;
+    assert_err!(file.ioctl(1, 0), Unsupported);
+    let file = fops::File::open("/very/long/path/test.txt", &opts)?;
+    assert_err!(file.ioctl(1, 0), Unsupported);
+
+    println!("test_device_ioctl() OK!");
+    Ok(())
+}
+
+fn test_procfs() -> Result<()> {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    println!("test procfs ...");
+
+    let mounts = fs::read_to_string("/proc/mounts")?;
+    let root = mounts.lines().next().unwrap();
+    assert_eq!(root.split(' ').nth(1), Some("/"));
+    assert!(mounts.contains("proc /proc proc rw 0 0\n"));
+    assert_eq!(
+        fs::read_to_string("/proc/sys/net/core/somaxconn")?,
+        "4096\n"
+    );
+    assert_eq!(fs::metadata("/proc/sys")?.file_type(), FileType::Dir);
+    assert_err!(fs::metadata("/proc/nonexistent"), NotFound);
+    assert_err!(fs::metadata("/proc/mounts/"), NotADirectory);
+    assert_err!(fs::write("/proc/mounts", "test"), PermissionDenied);
+    assert_err!(fs::create_dir("/proc/test"), PermissionDenied);
+
+    // a file is generated once when it is read from the start, so it is
+    // consistent across reads of small chunks
+    static GENERATED: AtomicUsize = AtomicUsize::new(0);
+    axfs::procfs::register_file("test/snapshot", || {
+        let n = GENERATED.fetch_add(1, Ordering::Relaxed);
+        char::from(b'a' + (n % 26) as u8).to_string().repeat(16)
+    });
+    let read_in_chunks = || -> Result<String> {
+        let mut file = File::open("/proc/test/snapshot")?;
+        let mut content = String::new();
+        let mut buf = [0; 5];
+        loop {
+            match file.read(&mut buf)? {
+                0 => return Ok(content),
+                n => content += core::str::from_utf8(&buf[..n]).unwrap(),
+            }
+        }
+    };
+    let first = read_in_chunks()?;
+    assert_eq!(first, "a".repeat(16));
+    assert_eq!(GENERATED.load(Ordering::Relaxed), 1);
+    // and generated again when it is read from the start again
+    assert_eq!(read_in_chunks()?, "b".repeat(16));
+
+    println!("test_procfs() OK!");
+    Ok(())
+}
+
+fn test_sysfs() -> Result<()> {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    static VALUE: AtomicUsize = AtomicUsize::new(1);
+
+    println!("test sysfs ...");
+
+    axfs::sysfs::register_writable_attr(
+        "module/test/parameters/value",
+        || format!("{}\n", VALUE.load(Ordering::Relaxed)),
+        |value| {
+            let value = value.parse().map_err(|_| Error::InvalidInput)?;
+            VALUE.store(value, Ordering::Relaxed);
+            Ok(())
+        },
+    );
+    let fname = "/sys/module/test/parameters/value";
+    assert_eq!(fs::read_to_string(fname)?, "1\n");
+    assert_eq!(fs::write(fname, "42\n"), Ok(()));
+    assert_eq!(VALUE.load(Ordering::Relaxed), 42);
+    assert_eq!(fs::read_to_string(fname)?, "42\n");
+    assert_err!(fs::write(fname, "foo"), InvalidInput);
+    assert_eq!(fs::read_dir("/sys/module/test/parameters")?.count(), 1);
+
+    // an attribute is shown once when it is read from the start, so a value
+    // read in small chunks is not torn
+    static SHOWN: AtomicUsize = AtomicUsize::new(0);
+    axfs::sysfs::register_attr("module/test/parameters/snapshot", || {
+        let n = SHOWN.fetch_add(1, Ordering::Relaxed);
+        format!("{}\n", 1000 + n * 1111)
+    });
+    let mut file = File::open("/sys/module/test/parameters/snapshot")?;
+    let mut content = String::new();
+    let mut buf = [0; 2];
+    loop {
+        match file.read(&mut buf)? {
+            0 => break,
+            n => content += core::str::from_utf8(&buf[..n]).unwrap(),
+        }
+    }
+    assert_eq!(content, "1000\n");
+    assert_eq!(SHOWN.load(Ordering::Relaxed), 1);
+
+    let fname = "/sys/kernel/mm/transparent_hugepage/enabled";
+    assert_eq!(fs::read_to_string(fname)?, "always [madvise] never\n");
+    assert_err!(fs::write(fname, "never"), PermissionDenied);
+    assert_err!(fs::create_dir("/sys/test"), PermissionDenied);
+    assert_err!(
+        fs::remove_file("/sys/module/test/parameters/value"),
+        PermissionDenied
+    );
+
+    println!("test_sysfs() OK!");
+    Ok(())
+}
+
+fn test_file_locks() -> Result<()> {
+    use axfs::fops::{self, LockKind};
+
+    println!("test file locks ...");
+
+    let mut opts = fops::OpenOptions::new();
+    opts.read(true);
+    let fname = "/very/long/path/test.txt";
+    let file1 = fops::File::open(fname, &opts)?;
+    let file2 = fops::File::open("///very/long/./path/../path/test.txt", &opts)?;
+    let (owner1, owner2) = (file1.lock_owner(), file2.lock_owner());
+
+    // whole-file locks
+    owner1.flock(Some(LockKind::Shared), false)?;
+    owner2.flock(Some(LockKind::Shared), false)?;
+    assert_err!(owner2.flock(Some(LockKind::Exclusive), false), WouldBlock);
+    owner1.flock(None, false)?;
+    owner2.flock(Some(LockKind::Exclusive), false)?;
+
+    // byte-range locks are independent of them, and they are split
+    owner1.set_lock(Some(LockKind::Exclusive), 0..10, false)?;
+    owner1.set_lock(None, 3..5, false)?;
+    assert!(owner2.get_lock(LockKind::Exclusive, 3..5).is_none());
+    let lock = owner2.get_lock(LockKind::Shared, 4..8).unwrap();
+    assert_eq!(lock.range, 5..10);
+    assert_err!(
+        owner2.set_lock(Some(LockKind::Shared), 8..20, false),
+        WouldBlock
+    );
+
+    // the locks are released when the file is closed
+    drop((file1, owner1));
+    owner2.set_lock(Some(LockKind::Exclusive), 0..u64::MAX, false)?;
+    let file3 = fops::File::open(fname, &opts)?;
+    assert_err!(
+        file3.lock_owner().flock(Some(LockKind::Shared), false),
+        WouldBlock
+    );
+
+    // the locks follow the file, however it is opened or renamed
+    let dir = fops::Directory::open_dir("/very", &opts)?;
+    let file4 = dir.open_file_at("long/path/test.txt", &opts)?;
+    assert_err!(
+        file4
+            .lock_owner()
+            .set_lock(Some(LockKind::Shared), 0..1, false),
+        WouldBlock
+    );
+    fs::rename(fname, "/very/long/path/renamed.txt")?;
+    let file = fops::File::open("/very/long/path/renamed.txt", &opts)?;
+    assert_err!(
+        file.lock_owner()
+            .set_lock(Some(LockKind::Shared), 0..1, false),
+        WouldBlock
+    );
+    fs::rename("/very/long/path/renamed.txt", fname)?;
+    drop((file2, owner2, file3));
+
+    // waiting for a lock held by an owner that waits for this one deadlocks
+    #[cfg(feature = "multitask")]
+    {
+        let file5 = fops::File::open(fname, &opts)?;
+        let (owner4, owner5) = (file4.lock_owner(), file5.lock_owner());
+        owner4.set_lock(Some(LockKind::Exclusive), 0..10, false)?;
+        owner5.set_lock(Some(LockKind::Exclusive), 10..20, false)?;
+        let waiter = owner4.clone();
+        let task = axtask::spawn(move || {
+            waiter
+                .set_lock(Some(LockKind::Exclusive), 10..20, true)
+                .unwrap();
+        });
+        axtask::yield_now(); // let the task wait for the lock
+        assert_eq!(
+            owner5
+                .set_lock(Some(LockKind::Exclusive), 0..10, true)
+                .err(),
+            Some(fops::DEADLOCK_ERROR)
+        );
+        owner5.set_lock(None, 10..20, false)?;
+        task.join();
+        assert!(owner5.get_lock(LockKind::Shared, 10..20).is_some());
+
+        // owners converting their shared `flock`s to exclusive ones at the
+        // same time do not wait for each other
+        owner4.flock(Some(LockKind::Shared), false)?;
+        owner5.flock(Some(LockKind::Shared), false)?;
+        let waiter = owner5.clone();
+        let task = axtask::spawn(move || {
+            waiter.flock(Some(LockKind::Exclusive), true).unwrap();
+        });
+        axtask::yield_now(); // let the task wait for the lock
+        owner4.flock(Some(LockKind::Exclusive), true)?;
+        owner4.flock(None, false)?;
+        task.join();
+        assert_err!(owner4.flock(Some(LockKind::Shared), false), WouldBlock);
+        owner5.flock(None, false)?;
+    }
+
+    println!("test_file_locks() OK!");
+    Ok(())
+}
+
 pub fn test_all() {
     test_read_write_file().expect("test_read_write_file() failed");
     test_read_dir().expect("test_read_dir() failed");
@@ -259,4 +642,11 @@ pub fn test_all() {
     test_create_file_dir().expect("test_create_file_dir() failed");
     test_remove_file_dir().expect("test_remove_file_dir() failed");
     test_devfs_ramfs().expect("test_devfs_ramfs() failed");
+    test_mount_umount().expect("test_mount_umount() failed");
+    test_links().expect("test_links() failed");
+    test_block_device().expect("test_block_device() failed");
+    test_device_ioctl().expect("test_device_ioctl() failed");
+    test_procfs().expect("test_procfs() failed");
+    test_sysfs().expect("test_sysfs() failed");
+    test_file_locks().expect("test_file_locks() failed");
 }
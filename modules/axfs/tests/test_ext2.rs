@@ -0,0 +1,112 @@
+#![cfg(all(feature = "ext2fs", not(any(feature = "myfs", feature = "overlayfs"))))]
+
+mod test_common;
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+use axfs::fops::{self, LockKind};
+use axio::Write;
+use core::time::Duration;
+
+const IMG_PATH: &str = "resources/ext2.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let data = std::fs::read(path)?;
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+fn test_links() -> axio::Result<()> {
+    // symbolic links are followed
+    assert_eq!(fs::read_to_string("/short-link.txt")?, "Rust is cool!\n");
+    assert_eq!(
+        fs::read_to_string("/long-link/path/test.txt")?,
+        "Rust is cool!\n"
+    );
+    // a hard link still has the data after another name is removed
+    fs::remove_file("/short.txt")?;
+    assert_eq!(
+        fs::read_to_string("/very/hard-link.txt")?,
+        "Rust is cool!\n"
+    );
+    assert_eq!(
+        fs::metadata("/short-link.txt").err(),
+        Some(axio::Error::NotFound)
+    );
+
+    // new links on ext2
+    fs::symlink("very/long/path", "/path-link")?;
+    assert_eq!(fs::read_link("/path-link")?, "very/long/path");
+    assert!(fs::symlink_metadata("/path-link")?.is_symlink());
+    assert!(fs::metadata("/path-link")?.is_dir());
+    fs::hard_link("/path-link/test.txt", "/test-link.txt")?;
+    assert_eq!(fs::read_to_string("/test-link.txt")?, "Rust is cool!\n");
+    // the names of a file share its locks
+    let mut opts = fops::OpenOptions::new();
+    opts.read(true);
+    let file1 = fops::File::open("/test-link.txt", &opts)?;
+    let file2 = fops::File::open("/very/long/path/test.txt", &opts)?;
+    file1.lock_owner().flock(Some(LockKind::Exclusive), false)?;
+    assert_eq!(
+        file2
+            .lock_owner()
+            .flock(Some(LockKind::Shared), false)
+            .err(),
+        Some(axio::Error::WouldBlock)
+    );
+    // relative paths are resolved from the current directory
+    fs::set_current_dir("/very")?;
+    fs::hard_link("long/path/test.txt", "rel-link.txt")?;
+    fs::set_current_dir("/")?;
+    assert_eq!(fs::read_to_string("/very/rel-link.txt")?, "Rust is cool!\n");
+
+    // symbolic links can point across mount points, hard links can not
+    fs::write("/tmp/a.txt", "tmp")?;
+    fs::symlink("/tmp/a.txt", "/tmp-link")?;
+    assert_eq!(fs::read_to_string("/tmp-link")?, "tmp");
+    assert_eq!(
+        fs::hard_link("/tmp/a.txt", "/a.txt").err(),
+        Some(fs::CROSS_DEVICE_ERROR)
+    );
+
+    // a symbolic link loop is detected
+    fs::symlink("loop-b", "/loop-a")?;
+    fs::symlink("loop-a", "/loop-b")?;
+    assert_eq!(fs::metadata("/loop-a").err(), Some(fs::SYMLINK_LOOP_ERROR));
+    Ok(())
+}
+
+#[test]
+fn test_ext2() {
+    println!("Testing ext2 with ramdisk ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    test_common::test_all();
+
+    // permissions and timestamps are recorded
+    let metadata = fs::metadata("/long.txt").unwrap();
+    assert_eq!(metadata.permissions().bits(), 0o644);
+    assert_eq!(metadata.len(), 14000);
+    assert!(metadata.modified().is_ok());
+    // the times can be changed, and a write moves the modification time
+    // forward
+    let old = Duration::from_secs(946_684_800); // 2000-01-01
+    let file = fs::File::options().write(true).open("/long.txt").unwrap();
+    file.set_times(Some(old), Some(old)).unwrap();
+    drop(file);
+    let metadata = fs::metadata("/long.txt").unwrap();
+    assert_eq!(metadata.modified().unwrap(), old);
+    assert_eq!(metadata.accessed().unwrap(), old);
+    let mut file = fs::File::options().write(true).open("/long.txt").unwrap();
+    file.write_all(b"Rust").unwrap();
+    drop(file);
+    assert!(fs::metadata("/long.txt").unwrap().modified().unwrap() > old);
+
+    test_links().expect("test_links() failed");
+}
@@ -0,0 +1,87 @@
+#![cfg(all(feature = "ext2fs", not(any(feature = "myfs", feature = "overlayfs"))))]
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api as fs;
+use axio::{Error, Result};
+
+const IMG_PATH: &str = "resources/ext4.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let data = std::fs::read(path)?;
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+fn test_read() -> Result<()> {
+    // files are mapped by extents
+    let long = fs::read_to_string("/long.txt")?;
+    assert_eq!(long.len(), 14000);
+    assert!(long.lines().all(|line| line == "Rust is cool!"));
+    assert_eq!(
+        fs::read_to_string("/very-long-dir-name/very-long-file-name.txt")?,
+        "Rust is cool!\n"
+    );
+    let mut names = fs::read_dir("/very")?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<Result<Vec<_>>>()?;
+    names.sort();
+    assert_eq!(names, ["hard-link.txt", "long"]);
+
+    // links are followed
+    assert_eq!(fs::read_to_string("/short-link.txt")?, "Rust is cool!\n");
+    assert_eq!(
+        fs::read_to_string("/long-link/path/test.txt")?,
+        "Rust is cool!\n"
+    );
+    assert_eq!(
+        fs::read_to_string("/very/hard-link.txt")?,
+        "Rust is cool!\n"
+    );
+
+    // ext4 records the creation time
+    let metadata = fs::metadata("/short.txt")?;
+    assert!(metadata.created().is_ok());
+    assert_eq!(metadata.permissions().bits(), 0o644);
+    Ok(())
+}
+
+fn test_read_only() -> Result<()> {
+    // the volume can not be written, as it uses features of ext4
+    assert_eq!(
+        fs::write("/short.txt", "changed").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert_eq!(
+        fs::write("/new.txt", "new").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert_eq!(
+        fs::create_dir("/new-dir").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert_eq!(
+        fs::remove_file("/short.txt").err(),
+        Some(Error::PermissionDenied)
+    );
+    assert_eq!(fs::read_to_string("/short.txt")?, "Rust is cool!\n");
+
+    // but other filesystems can
+    fs::write("/tmp/new.txt", "new")?;
+    assert_eq!(fs::read_to_string("/tmp/new.txt")?, "new");
+    Ok(())
+}
+
+#[test]
+fn test_ext4() {
+    println!("Testing ext4 with ramdisk ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    test_read().expect("test_read() failed");
+    test_read_only().expect("test_read_only() failed");
+}
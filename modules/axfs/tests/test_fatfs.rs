@@ -1,9 +1,11 @@
-#![cfg(not(feature = "myfs"))]
+#![cfg(not(any(feature = "myfs", feature = "ext2fs", feature = "overlayfs")))]
 
 mod test_common;
 
 use axdriver::AxDeviceContainer;
 use axdriver_block::ramdisk::RamDisk;
+use axio::Write;
+use core::time::Duration;
 
 const IMG_PATH: &str = "resources/fat16.img";
 
@@ -24,4 +26,67 @@ fn test_fatfs() {
     axfs::init_filesystems(AxDeviceContainer::from_one(disk));
 
     test_common::test_all();
+
+    // FAT records timestamps, which are not earlier than 1980-01-01
+    const FAT_EPOCH_SECS: u64 = 315_532_800;
+    axfs::api::write("/times.txt", "hello").expect("failed to write file");
+    let metadata = axfs::api::metadata("/times.txt").unwrap();
+    assert!(metadata.modified().unwrap().as_secs() >= FAT_EPOCH_SECS);
+    assert!(metadata.created().unwrap().as_secs() >= FAT_EPOCH_SECS);
+    assert!(metadata.accessed().is_ok());
+    // the times can be changed, and a write moves the modification time
+    // forward; FAT stores it in 2 seconds, and only the date of the access
+    let old = Duration::from_secs(946_684_800); // 2000-01-01
+    let file = axfs::api::File::options()
+        .write(true)
+        .open("/times.txt")
+        .unwrap();
+    let other = axfs::api::File::open("/times.txt").unwrap();
+    file.set_times(Some(old), Some(old)).unwrap();
+    drop(file);
+    let metadata = axfs::api::metadata("/times.txt").unwrap();
+    assert_eq!(metadata.modified().unwrap(), old);
+    assert_eq!(metadata.accessed().unwrap(), old);
+    // the times are shared by all the handles of the file
+    assert_eq!(other.metadata().unwrap().modified().unwrap(), old);
+    axfs::api::write("/times.txt", "world").unwrap();
+    let metadata = axfs::api::metadata("/times.txt").unwrap();
+    assert!(metadata.modified().unwrap() > old);
+    assert!(other.metadata().unwrap().modified().unwrap() > old);
+    drop(other);
+    axfs::api::remove_file("/times.txt").unwrap();
+    // but other filesystems do not
+    let metadata = axfs::api::metadata("/dev/null").unwrap();
+    assert!(metadata.modified().is_err());
+
+    // dirty blocks take up at most half of the cache
+    const CACHE_BLOCKS: usize = 16;
+    axfs::api::set_block_cache_capacity("/dev/vda", CACHE_BLOCKS).unwrap();
+    let mut file = axfs::api::File::create("/dirty.bin").unwrap();
+    for _ in 0..CACHE_BLOCKS * 2 {
+        file.write_all(&[0xa5; 512]).unwrap();
+        let stats = axfs::api::block_cache_stats("/dev/vda").unwrap();
+        assert!(stats.dirty <= CACHE_BLOCKS / 2);
+    }
+    // and are written back on fsync, but not when the file is closed
+    file.flush().unwrap();
+    let stats = axfs::api::block_cache_stats("/dev/vda").unwrap();
+    assert_eq!(stats.dirty, 0);
+    file.write_all(&[0xa5; 512]).unwrap();
+    drop(file);
+    let stats = axfs::api::block_cache_stats("/dev/vda").unwrap();
+    assert!(stats.dirty > 0);
+    assert_eq!(
+        axfs::api::read("/dirty.bin").unwrap().len(),
+        CACHE_BLOCKS * 1024 + 512
+    );
+    axfs::api::remove_file("/dirty.bin").unwrap();
+
+    // all modified blocks are written back after sync
+    axfs::api::sync().expect("failed to sync");
+    let stats = axfs::api::block_cache_stats("/dev/vda").unwrap();
+    println!("block cache stats: {:?}", stats);
+    assert!(stats.hits > 0);
+    assert!(stats.cached > 0);
+    assert_eq!(stats.dirty, 0);
 }
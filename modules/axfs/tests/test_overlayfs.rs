@@ -0,0 +1,104 @@
+#![cfg(all(feature = "overlayfs", not(feature = "myfs")))]
+
+mod test_common;
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axfs::api::{self as fs, OpenOptions};
+use axio::{Error, Result, Write};
+
+// the lower layer is the ext2 volume if `ext2fs` is enabled, otherwise FAT
+#[cfg(feature = "ext2fs")]
+const IMG_PATH: &str = "resources/ext2.img";
+#[cfg(not(feature = "ext2fs"))]
+const IMG_PATH: &str = "resources/fat16.img";
+
+fn make_disk() -> std::io::Result<RamDisk> {
+    let path = std::env::current_dir()?.join(IMG_PATH);
+    println!("Loading disk image from {:?} ...", path);
+    let data = std::fs::read(path)?;
+    println!("size = {} bytes", data.len());
+    Ok(RamDisk::from(&data))
+}
+
+fn test_copy_up_whiteout() -> Result<()> {
+    let mounts = fs::read_to_string("/proc/mounts")?;
+    assert!(mounts.starts_with("overlay / overlay rw 0 0\n"));
+
+    // a file of the lower layer is copied up when it is modified
+    let content = fs::read_to_string("/long.txt")?;
+    let mut file = OpenOptions::new().append(true).open("/long.txt")?;
+    file.write_all(b"more\n")?;
+    drop(file);
+    assert_eq!(fs::read_to_string("/long.txt")?, content + "more\n");
+
+    // a removed file is hidden by a whiteout, which is not visible
+    fs::remove_file("/long.txt")?;
+    assert_eq!(fs::metadata("/long.txt").err(), Some(Error::NotFound));
+    for entry in fs::read_dir("/")? {
+        let name = entry?.file_name();
+        assert!(name != "long.txt" && !name.starts_with(".wh."));
+    }
+    fs::write("/long.txt", "new")?;
+    assert_eq!(fs::read_to_string("/long.txt")?, "new");
+    assert_eq!(fs::create_dir("/.wh.dir").err(), Some(Error::InvalidInput));
+
+    // a directory created again does not show the removed entries
+    fs::remove_file("/very/long/path/test.txt")?;
+    assert_eq!(
+        fs::remove_dir("/very/long").err(),
+        Some(Error::DirectoryNotEmpty)
+    );
+    fs::remove_dir("/very/long/path")?;
+    fs::create_dir("/very/long/path")?;
+    assert_eq!(fs::read_dir("/very/long/path")?.count(), 0);
+
+    // files are moved in the upper layer
+    fs::rename("/short.txt", "/very/long/path/short.txt")?;
+    assert_eq!(fs::metadata("/short.txt").err(), Some(Error::NotFound));
+    assert_eq!(fs::read_dir("/very/long/path")?.count(), 1);
+    Ok(())
+}
+
+#[cfg(feature = "ext2fs")]
+fn test_lower_links() -> Result<()> {
+    // symbolic links of the lower layer are followed
+    assert_eq!(fs::read_to_string("/short-link.txt")?, "Rust is cool!\n");
+    assert_eq!(
+        fs::read_to_string("/long-link/path/test.txt")?,
+        "Rust is cool!\n"
+    );
+    assert!(fs::symlink_metadata("/short-link.txt")?.is_symlink());
+
+    // a hard link of the lower layer is copied up alone, so the other names
+    // of the file keep the old content
+    fs::write("/very/hard-link.txt", "changed\n")?;
+    assert_eq!(fs::read_to_string("/very/hard-link.txt")?, "changed\n");
+    assert_eq!(fs::read_to_string("/short.txt")?, "Rust is cool!\n");
+
+    // new links are created in the upper layer
+    fs::symlink("short.txt", "/new-link")?;
+    assert_eq!(fs::read_link("/new-link")?, "short.txt");
+    assert_eq!(fs::read_to_string("/new-link")?, "Rust is cool!\n");
+    fs::hard_link("/short.txt", "/new-hard-link.txt")?;
+    fs::write("/new-hard-link.txt", "linked\n")?;
+    assert_eq!(fs::read_to_string("/short.txt")?, "linked\n");
+    Ok(())
+}
+
+#[test]
+fn test_overlayfs() {
+    #[cfg(feature = "ext2fs")]
+    println!("Testing overlayfs over ext2 with ramdisk ...");
+    #[cfg(not(feature = "ext2fs"))]
+    println!("Testing overlayfs over fatfs with ramdisk ...");
+
+    let disk = make_disk().expect("failed to load disk image");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+    axfs::init_filesystems(AxDeviceContainer::from_one(disk));
+
+    test_common::test_all();
+    #[cfg(feature = "ext2fs")]
+    test_lower_links().expect("test_lower_links() failed");
+    test_copy_up_whiteout().expect("test_copy_up_whiteout() failed");
+}
@@ -8,7 +8,7 @@ use axdriver::AxDeviceContainer;
 use axdriver_block::ramdisk::RamDisk;
 use axfs::api::{self as fs, File};
 use axfs::fops::{Disk, MyFileSystemIf};
-use axfs_ramfs::RamFileSystem;
+use axfs::ramfs::RamFileSystem;
 use axfs_vfs::VfsOps;
 use axio::{Result, Write};
 
@@ -53,4 +53,12 @@ fn test_ramfs() {
     }
 
     test_common::test_all();
+
+    // the root is a ramfs as well, whose nodes can be linked
+    fs::hard_link("/very/long/path/test.txt", "/test-link.txt").unwrap();
+    fs::symlink("very/long", "/long-link").unwrap();
+    assert_eq!(
+        fs::read_to_string("/long-link/path/test.txt").unwrap(),
+        fs::read_to_string("/test-link.txt").unwrap()
+    );
 }
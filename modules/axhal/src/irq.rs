@@ -1,5 +1,7 @@
 //! Interrupt management.
 
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use axcpu::trap::{IRQ, register_trap_handler};
 use handler_table::HandlerTable;
 
@@ -12,10 +14,34 @@ pub type IrqHandler = handler_table::Handler;
 
 static IRQ_HANDLER_TABLE: HandlerTable<MAX_IRQ_COUNT> = HandlerTable::new();
 
+/// The number of times that each IRQ has occurred.
+static IRQ_COUNTS: [AtomicUsize; MAX_IRQ_COUNT] = [const { AtomicUsize::new(0) }; MAX_IRQ_COUNT];
+
+/// Calls `f` with the IRQ number and the number of times it has occurred, for
+/// every IRQ that has occurred at least once, in ascending order of the IRQ
+/// numbers.
+///
+/// Only the IRQs dispatched by the IRQ handler table are counted, e.g., the
+/// timer interrupt on RISC-V is not.
+pub fn for_each_irq_count<F>(mut f: F)
+where
+    F: FnMut(usize, usize),
+{
+    for (irq_num, count) in IRQ_COUNTS.iter().enumerate() {
+        let count = count.load(Ordering::Relaxed);
+        if count > 0 {
+            f(irq_num, count);
+        }
+    }
+}
+
 /// Platform-independent IRQ dispatching.
 #[allow(dead_code)]
 pub(crate) fn dispatch_irq_common(irq_num: usize) {
     trace!("IRQ {}", irq_num);
+    if let Some(count) = IRQ_COUNTS.get(irq_num) {
+        count.fetch_add(1, Ordering::Relaxed);
+    }
     if !IRQ_HANDLER_TABLE.handle(irq_num) {
         warn!("Unhandled IRQ {}", irq_num);
     }
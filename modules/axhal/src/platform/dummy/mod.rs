@@ -56,8 +56,11 @@ pub mod time {
     pub fn set_oneshot_timer(deadline_ns: u64) {}
 
     /// Return epoch offset in nanoseconds (wall time offset to monotonic clock start).
+    ///
+    /// The clock starts at 2024-01-01 00:00:00 UTC, so that the timestamps of
+    /// filesystems (e.g., FAT which starts from 1980) are valid.
     pub fn epochoffset_nanos() -> u64 {
-        0
+        1_704_067_200 * 1_000_000_000
     }
 }
 
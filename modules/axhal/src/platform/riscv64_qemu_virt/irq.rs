@@ -1,4 +1,5 @@
-//! TODO: PLIC
+//! Interrupts of the local interrupt controller, identified by `scause`, and
+//! of the devices behind the PLIC, identified by their PLIC source numbers.
 
 use crate::irq::IrqHandler;
 use lazyinit::LazyInit;
@@ -35,10 +36,73 @@ macro_rules! with_cause {
     };
 }
 
+/// The platform-level interrupt controller, which routes the interrupts of
+/// devices to the supervisor external interrupt of each hart.
+mod plic {
+    use axconfig::devices::PLIC_PADDR;
+    use memory_addr::PhysAddr;
+
+    use crate::mem::phys_to_virt;
+
+    const PLIC_BASE: PhysAddr = pa!(PLIC_PADDR);
+
+    /// Priority of each source.
+    const PRIORITY: usize = 0;
+    /// Enable bits of each source, per context.
+    const ENABLE: usize = 0x2000;
+    const ENABLE_STRIDE: usize = 0x80;
+    /// Priority threshold and claim/complete registers, per context.
+    const CONTEXT: usize = 0x20_0000;
+    const CONTEXT_STRIDE: usize = 0x1000;
+    const CLAIM: usize = 4;
+
+    fn reg(offset: usize) -> *mut u32 {
+        (phys_to_virt(PLIC_BASE).as_usize() + offset) as *mut u32
+    }
+
+    /// The supervisor context of the current hart (the machine context of
+    /// each hart comes first).
+    fn context() -> usize {
+        2 * crate::cpu::this_cpu_id() + 1
+    }
+
+    /// Enables or disables `source` on the current hart.
+    pub fn set_enable(source: usize, enabled: bool) {
+        let enable = reg(ENABLE + context() * ENABLE_STRIDE + source / 32 * 4);
+        let bit = 1 << (source % 32);
+        unsafe {
+            reg(PRIORITY + source * 4).write_volatile(enabled as u32);
+            let bits = enable.read_volatile();
+            enable.write_volatile(if enabled { bits | bit } else { bits & !bit });
+        }
+    }
+
+    /// Lets all enabled sources interrupt the current hart.
+    pub fn init_percpu() {
+        unsafe { reg(CONTEXT + context() * CONTEXT_STRIDE).write_volatile(0) };
+    }
+
+    /// Claims the pending source with the highest priority, if any.
+    pub fn claim() -> Option<usize> {
+        let source = unsafe { reg(CONTEXT + context() * CONTEXT_STRIDE + CLAIM).read_volatile() };
+        (source != 0).then_some(source as usize)
+    }
+
+    /// Signals that the handler of `source` has completed.
+    pub fn complete(source: usize) {
+        unsafe {
+            reg(CONTEXT + context() * CONTEXT_STRIDE + CLAIM).write_volatile(source as u32);
+        }
+    }
+}
+
 /// Enables or disables the given IRQ.
-pub fn set_enable(scause: usize, _enabled: bool) {
-    if scause == S_EXT {
-        // TODO: set enable in PLIC
+///
+/// IRQ numbers below [`INTC_IRQ_BASE`] are PLIC sources, which are enabled
+/// on the current hart.
+pub fn set_enable(irq_num: usize, enabled: bool) {
+    if irq_num != 0 && irq_num < INTC_IRQ_BASE {
+        plic::set_enable(irq_num, enabled);
     }
 }
 
@@ -47,6 +111,9 @@ pub fn set_enable(scause: usize, _enabled: bool) {
 /// It also enables the IRQ if the registration succeeds. It returns `false` if
 /// the registration failed.
 pub fn register_handler(scause: usize, handler: IrqHandler) -> bool {
+    if scause < INTC_IRQ_BASE {
+        return crate::irq::register_handler_common(scause, handler);
+    }
     with_cause!(
         scause,
         @TIMER => if !TIMER_HANDLER.is_inited() {
@@ -55,7 +122,8 @@ pub fn register_handler(scause: usize, handler: IrqHandler) -> bool {
         } else {
             false
         },
-        @EXT => crate::irq::register_handler_common(scause & !INTC_IRQ_BASE, handler),
+        // handlers of external interrupts are registered by PLIC sources
+        @EXT => false,
     )
 }
 
@@ -71,7 +139,12 @@ pub fn dispatch_irq(scause: usize) {
             trace!("IRQ: timer");
             TIMER_HANDLER();
         },
-        @EXT => crate::irq::dispatch_irq_common(0), // TODO: get IRQ number from PLIC
+        @EXT => {
+            while let Some(source) = plic::claim() {
+                crate::irq::dispatch_irq_common(source);
+                plic::complete(source);
+            }
+        },
     );
 }
 
@@ -82,4 +155,5 @@ pub(super) fn init_percpu() {
         sie::set_stimer();
         sie::set_sext();
     }
+    plic::init_percpu();
 }
@@ -5,7 +5,7 @@ use core::{cell::SyncUnsafeCell, mem::MaybeUninit};
 use kspin::SpinNoIrq;
 use lazyinit::LazyInit;
 use memory_addr::PhysAddr;
-use x2apic::ioapic::IoApic;
+use x2apic::ioapic::{IoApic, IrqFlags};
 use x2apic::lapic::{LocalApic, LocalApicBuilder, xapic_base};
 use x86_64::instructions::port::Port;
 
@@ -13,6 +13,8 @@ use self::vectors::*;
 use crate::mem::phys_to_virt;
 
 pub(super) mod vectors {
+    /// The vector of IO APIC pin 0, followed by the other pins.
+    pub const IO_APIC_VECTOR_BASE: u8 = 0x20;
     pub const APIC_TIMER_VECTOR: u8 = 0xf0;
     pub const APIC_SPURIOUS_VECTOR: u8 = 0xf1;
     pub const APIC_ERROR_VECTOR: u8 = 0xf2;
@@ -26,21 +28,31 @@ pub const TIMER_IRQ_NUM: usize = APIC_TIMER_VECTOR as usize;
 
 const IO_APIC_BASE: PhysAddr = pa!(0xFEC0_0000);
 
+/// The number of IO APIC pins, where pins 16 and above (GSI 16 to 23) are the
+/// level-triggered, active-low PCI interrupts `PIRQA#` to `PIRQH#`.
+const IO_APIC_PINS: u8 = 24;
+const IO_APIC_PCI_PIN_BASE: u8 = 16;
+
 static LOCAL_APIC: SyncUnsafeCell<MaybeUninit<LocalApic>> =
     SyncUnsafeCell::new(MaybeUninit::uninit());
 static mut IS_X2APIC: bool = false;
 static IO_APIC: LazyInit<SpinNoIrq<IoApic>> = LazyInit::new();
 
 /// Enables or disables the given IRQ.
+///
+/// Only the vectors of IO APIC pins are affected, i.e., vector
+/// `IO_APIC_VECTOR_BASE + n` for GSI `n`.
 #[cfg(feature = "irq")]
 pub fn set_enable(vector: usize, enabled: bool) {
     // should not affect LAPIC interrupts
-    if vector < APIC_TIMER_VECTOR as _ {
+    let pins = IO_APIC_VECTOR_BASE as usize..(IO_APIC_VECTOR_BASE + IO_APIC_PINS) as usize;
+    if pins.contains(&vector) {
+        let pin = (vector - IO_APIC_VECTOR_BASE as usize) as u8;
         unsafe {
             if enabled {
-                IO_APIC.lock().enable_irq(vector as u8);
+                IO_APIC.lock().enable_irq(pin);
             } else {
-                IO_APIC.lock().disable_irq(vector as u8);
+                IO_APIC.lock().disable_irq(pin);
             }
         }
     }
@@ -117,7 +129,16 @@ pub(super) fn init_primary() {
     }
 
     info!("Initialize IO APIC...");
-    let io_apic = unsafe { IoApic::new(phys_to_virt(IO_APIC_BASE).as_usize() as u64) };
+    let mut io_apic = unsafe { IoApic::new(phys_to_virt(IO_APIC_BASE).as_usize() as u64) };
+    unsafe {
+        // all pins are masked, and delivered to the BSP
+        io_apic.init(IO_APIC_VECTOR_BASE);
+        for pin in IO_APIC_PCI_PIN_BASE..IO_APIC_PINS {
+            let mut entry = io_apic.table_entry(pin);
+            entry.set_flags(IrqFlags::LEVEL_TRIGGERED | IrqFlags::LOW_ACTIVE | IrqFlags::MASKED);
+            io_apic.set_table_entry(pin, entry);
+        }
+    }
     IO_APIC.init_once(SpinNoIrq::new(io_apic));
 }
 
@@ -1,7 +1,7 @@
 //! [ArceOS](https://github.com/arceos-org/arceos) network module.
 //!
-//! It provides unified networking primitives for TCP/UDP communication
-//! using various underlying network stacks. Currently, only [smoltcp] is
+//! It provides unified networking primitives for TCP/UDP communication over
+//! IPv4 and IPv6, using various underlying network stacks. Currently, only [smoltcp] is
 //! supported.
 //!
 //! # Organization
@@ -9,11 +9,31 @@
 //! - [`TcpSocket`]: A TCP socket that provides POSIX-like APIs.
 //! - [`UdpSocket`]: A UDP socket that provides POSIX-like APIs.
 //! - [`dns_query`]: Function for DNS query.
+//! - [`ipv4_config`]: Function to query the IPv4 configuration.
+//! - [`ipv6_config`]: Function to query the IPv6 configuration.
+//!
+//! # Interfaces
+//!
+//! - `lo`: The loopback interface with `127.0.0.1/8` and `::1/128`, on a
+//!   software device. Packets to any address of this host are routed through
+//!   it rather than the NIC, so local clients and servers can talk without a
+//!   NIC.
+//! - `eth0`: The interface of the first NIC, if there is one.
+//!
+//! Each socket is bound to the interface that its packets are routed through
+//! by their destinations, except that UDP sockets receive from both.
 //!
 //! # Cargo Features
 //!
 //! - `smoltcp`: Use [smoltcp] as the underlying network stack. This is enabled
 //!   by default.
+//! - `multitask` and `irq`: When both are enabled, the interface is polled by
+//!   a background task when the NIC raises an interrupt or a timer of the
+//!   stack expires, and tasks blocked on sockets sleep until they are woken.
+//!   Otherwise, blocked tasks poll the interface in a loop.
+//! - `dhcp`: Acquire the IPv4 configuration from a DHCP server, and renew the
+//!   lease in the background without delaying the boot. The static address
+//!   from `AX_IP` and `AX_GW` is used if no lease is acquired in 10 seconds.
 //!
 //! [smoltcp]: https://github.com/smoltcp-rs/smoltcp
 
@@ -32,16 +52,27 @@ cfg_if::cfg_if! {
 
 pub use self::net_impl::TcpSocket;
 pub use self::net_impl::UdpSocket;
+pub use self::net_impl::{BufferKind, buffer_len, set_buffer_len};
+pub use self::net_impl::{InterfaceInfo, interfaces};
+pub use self::net_impl::{Ipv4Config, ipv4_config};
+pub use self::net_impl::{Ipv6Config, ipv6_config};
+pub use self::net_impl::{TcpSocketInfo, tcp_socket_table};
 pub use self::net_impl::{bench_receive, bench_transmit};
 pub use self::net_impl::{dns_query, poll_interfaces};
 
-use axdriver::{AxDeviceContainer, prelude::*};
+use axdriver::{AxDeviceContainer, NetIrq, prelude::*};
 
 /// Initializes the network subsystem by NIC devices.
-pub fn init_network(mut net_devs: AxDeviceContainer<AxNetDevice>) {
+///
+/// `net_irq` is the interrupt of the first NIC, if it raises one. Without any
+/// NIC, only the loopback interface is available.
+pub fn init_network(mut net_devs: AxDeviceContainer<AxNetDevice>, net_irq: Option<NetIrq>) {
     info!("Initialize network subsystem...");
 
-    let dev = net_devs.take_one().expect("No NIC device found!");
-    info!("  use NIC 0: {:?}", dev.device_name());
-    net_impl::init(dev);
+    let dev = net_devs.take_one();
+    match &dev {
+        Some(dev) => info!("  use NIC 0: {:?}", dev.device_name()),
+        None => warn!("  no NIC device found, use the loopback interface only"),
+    }
+    net_impl::init(dev, net_irq);
 }
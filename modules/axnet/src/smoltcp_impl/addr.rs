@@ -1,10 +1,10 @@
-use core::net::{IpAddr, Ipv4Addr, SocketAddr};
-use smoltcp::wire::{IpAddress, IpEndpoint, Ipv4Address};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use smoltcp::wire::{IpAddress, IpEndpoint, Ipv4Address, Ipv6Address};
 
 pub const fn from_core_ipaddr(ip: IpAddr) -> IpAddress {
     match ip {
         IpAddr::V4(ipv4) => IpAddress::Ipv4(Ipv4Address(ipv4.octets())),
-        _ => panic!("IPv6 not supported"),
+        IpAddr::V6(ipv6) => IpAddress::Ipv6(Ipv6Address(ipv6.octets())),
     }
 }
 
@@ -12,7 +12,10 @@ pub const fn into_core_ipaddr(ip: IpAddress) -> IpAddr {
     match ip {
         IpAddress::Ipv4(ipv4) => {
             IpAddr::V4(unsafe { core::mem::transmute::<[u8; 4], Ipv4Addr>(ipv4.0) })
-        } // _ => panic!("IPv6 not supported"),
+        }
+        IpAddress::Ipv6(ipv6) => {
+            IpAddr::V6(unsafe { core::mem::transmute::<[u8; 16], Ipv6Addr>(ipv6.0) })
+        }
     }
 }
 
@@ -28,8 +31,9 @@ pub const fn into_core_sockaddr(addr: IpEndpoint) -> SocketAddr {
 }
 
 pub fn is_unspecified(ip: IpAddress) -> bool {
-    ip.as_bytes() == [0, 0, 0, 0]
+    ip.is_unspecified()
 }
 
 pub const UNSPECIFIED_IP: IpAddress = IpAddress::v4(0, 0, 0, 0);
+pub const UNSPECIFIED_IP6: IpAddress = IpAddress::Ipv6(Ipv6Address::UNSPECIFIED);
 pub const UNSPECIFIED_ENDPOINT: IpEndpoint = IpEndpoint::new(UNSPECIFIED_IP, 0);
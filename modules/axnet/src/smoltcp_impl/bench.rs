@@ -1,5 +1,5 @@
 use super::{AxNetRxToken, AxNetTxToken, STANDARD_MTU};
-use super::{DeviceWrapper, InterfaceWrapper};
+use super::{DeviceWrapper, current_time};
 use smoltcp::phy::{Device, RxToken, TxToken};
 
 const GB: usize = 1000 * MB;
@@ -12,11 +12,11 @@ impl DeviceWrapper {
         const MAX_SEND_BYTES: usize = 10 * GB;
         let mut send_bytes: usize = 0;
         let mut past_send_bytes: usize = 0;
-        let mut past_time = InterfaceWrapper::current_time();
+        let mut past_time = current_time();
 
         // Send bytes
         while send_bytes < MAX_SEND_BYTES {
-            if let Some(tx_token) = self.transmit(InterfaceWrapper::current_time()) {
+            if let Some(tx_token) = self.transmit(current_time()) {
                 AxNetTxToken::consume(tx_token, STANDARD_MTU, |tx_buf| {
                     tx_buf[0..12].fill(1);
                     // ether type: IPv4
@@ -26,7 +26,7 @@ impl DeviceWrapper {
                 send_bytes += STANDARD_MTU;
             }
 
-            let current_time = InterfaceWrapper::current_time();
+            let current_time = current_time();
             if (current_time - past_time).secs() == 1 {
                 let gb = ((send_bytes - past_send_bytes) * 8) / GB;
                 let mb = (((send_bytes - past_send_bytes) * 8) % GB) / MB;
@@ -47,16 +47,16 @@ impl DeviceWrapper {
         const MAX_RECEIVE_BYTES: usize = 10 * GB;
         let mut receive_bytes: usize = 0;
         let mut past_receive_bytes: usize = 0;
-        let mut past_time = InterfaceWrapper::current_time();
+        let mut past_time = current_time();
         // Receive bytes
         while receive_bytes < MAX_RECEIVE_BYTES {
-            if let Some(rx_token) = self.receive(InterfaceWrapper::current_time()) {
+            if let Some(rx_token) = self.receive(current_time()) {
                 AxNetRxToken::consume(rx_token.0, |rx_buf| {
                     receive_bytes += rx_buf.len();
                 });
             }
 
-            let current_time = InterfaceWrapper::current_time();
+            let current_time = current_time();
             if (current_time - past_time).secs() == 1 {
                 let gb = ((receive_bytes - past_receive_bytes) * 8) / GB;
                 let mb = (((receive_bytes - past_receive_bytes) * 8) % GB) / MB;
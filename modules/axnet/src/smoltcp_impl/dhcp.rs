@@ -0,0 +1,104 @@
+//! DHCPv4 client that configures the IPv4 address, the default gateway and
+//! the DNS servers of `eth0`.
+//!
+//! The boot does not wait for the lease: it is acquired and renewed as the
+//! interface is polled by the background task, and the static configuration
+//! is only applied if no lease is acquired in time.
+
+use core::net::{IpAddr, Ipv4Addr};
+
+use axsync::Mutex;
+use lazyinit::LazyInit;
+use smoltcp::iface::{Interface, SocketSet};
+use smoltcp::socket::dhcpv4::{self, Event};
+use smoltcp::time::{Duration, Instant};
+
+use super::{
+    IPV4_CONFIG, Iface, Ipv4Config, SOCKET_SET, SocketHandle, current_time, set_ipv4_config,
+};
+
+/// How long to wait for the first lease before applying the static
+/// configuration.
+const BOOT_TIMEOUT: Duration = Duration::from_secs(10);
+
+static DHCP_HANDLE: LazyInit<SocketHandle> = LazyInit::new();
+
+/// The static configuration, and when to apply it if no lease is acquired by
+/// then. It is taken once either happens.
+static FALLBACK: Mutex<Option<(Ipv4Config, Instant)>> = Mutex::new(None);
+
+/// Handles the event of the DHCP socket, after each poll of the interface.
+pub fn process_event(iface: &mut Interface, sockets: &mut SocketSet<'_>, now: Instant) {
+    if !DHCP_HANDLE.is_inited() {
+        return;
+    }
+    let mut fallback = FALLBACK.lock();
+    if fallback
+        .as_ref()
+        .is_some_and(|(_, deadline)| now >= *deadline)
+    {
+        let (config, _) = fallback.take().unwrap();
+        warn!("DHCP: no lease in {}, use {}", BOOT_TIMEOUT, config.address);
+        set_ipv4_config(iface, Some(config));
+    }
+    match sockets.get_mut::<dhcpv4::Socket>(DHCP_HANDLE.inner).poll() {
+        None => {}
+        Some(Event::Configured(lease)) => {
+            let config = Ipv4Config {
+                address: Ipv4Addr::from(lease.address.address().0),
+                prefix_len: lease.address.prefix_len(),
+                gateway: lease.router.map(|router| Ipv4Addr::from(router.0)),
+                dns_servers: lease
+                    .dns_servers
+                    .iter()
+                    .map(|server| IpAddr::V4(Ipv4Addr::from(server.0)))
+                    .collect(),
+                dhcp: true,
+            };
+            info!(
+                "DHCP: leased {}/{} from {}, gateway {:?}, DNS {:?}",
+                config.address,
+                config.prefix_len,
+                lease.server.address,
+                config.gateway,
+                config.dns_servers
+            );
+            set_ipv4_config(iface, Some(config));
+            *fallback = None;
+        }
+        Some(Event::Deconfigured) => {
+            // only drop the configuration acquired by us
+            if IPV4_CONFIG
+                .lock()
+                .as_ref()
+                .is_some_and(|config| config.dhcp)
+            {
+                warn!("DHCP: lease lost");
+                set_ipv4_config(iface, None);
+            }
+        }
+    }
+}
+
+/// Returns how long until the static configuration is applied, or `None` if
+/// it is not pending.
+pub fn poll_delay(now: Instant) -> Option<Duration> {
+    FALLBACK.lock().as_ref().map(|&(_, deadline)| {
+        if deadline > now {
+            deadline - now
+        } else {
+            Duration::ZERO
+        }
+    })
+}
+
+/// Starts the DHCP client without waiting for a lease.
+///
+/// `fallback` is applied if no lease is acquired in 10 seconds, until which
+/// `eth0` has no IPv4 address.
+pub fn start(fallback: Ipv4Config) {
+    let deadline = current_time() + BOOT_TIMEOUT;
+    *FALLBACK.lock() = Some((fallback, deadline));
+    DHCP_HANDLE.init_once(SOCKET_SET.add(Iface::Eth0, dhcpv4::Socket::new()));
+    info!("DHCP: discovering...");
+}
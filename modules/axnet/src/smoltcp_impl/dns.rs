@@ -2,12 +2,12 @@ use alloc::vec::Vec;
 use axerrno::{AxError, AxResult, ax_err_type};
 use core::net::IpAddr;
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::dns::{self, GetQueryResultError, StartQueryError};
 use smoltcp::wire::DnsQueryType;
 
 use super::addr::into_core_ipaddr;
-use super::{ETH0, SOCKET_SET, SocketSetWrapper};
+use super::wait::SocketWaiter;
+use super::{SOCKET_SET, SocketHandle, SocketSetWrapper};
 
 /// A DNS socket.
 struct DnsSocket {
@@ -18,8 +18,8 @@ impl DnsSocket {
     #[allow(clippy::new_without_default)]
     /// Creates a new DNS socket.
     pub fn new() -> Self {
-        let socket = SocketSetWrapper::new_dns_socket();
-        let handle = Some(SOCKET_SET.add(socket));
+        let (iface, socket) = SocketSetWrapper::new_dns_socket();
+        let handle = Some(SOCKET_SET.add(iface, socket));
         Self { handle }
     }
 
@@ -31,47 +31,61 @@ impl DnsSocket {
         });
     }
 
-    /// Query a address with given DNS query type.
-    pub fn query(&self, name: &str, query_type: DnsQueryType) -> AxResult<Vec<IpAddr>> {
-        // let local_addr = self.local_addr.unwrap_or_else(f);
+    /// Queries the addresses of `name` with each of `query_types` at the same
+    /// time, and returns the result of each query.
+    pub fn query<const N: usize>(
+        &self,
+        name: &str,
+        query_types: [DnsQueryType; N],
+    ) -> AxResult<[AxResult<Vec<IpAddr>>; N]> {
         let handle = self.handle.ok_or_else(|| ax_err_type!(InvalidInput))?;
-        let iface = &ETH0.iface;
-        let query_handle = SOCKET_SET
-            .with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
-                socket.start_query(iface.lock().context(), name, query_type)
-            })
-            .map_err(|e| match e {
-                StartQueryError::NoFreeSlot => {
-                    ax_err_type!(ResourceBusy, "socket query() failed: no free slot")
-                }
-                StartQueryError::InvalidName => {
-                    ax_err_type!(InvalidInput, "socket query() failed: invalid name")
-                }
-                StartQueryError::NameTooLong => {
-                    ax_err_type!(InvalidInput, "socket query() failed: too long name")
-                }
-            })?;
-        loop {
-            SOCKET_SET.poll_interfaces();
-            match SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
-                socket.get_query_result(query_handle).map_err(|e| match e {
-                    GetQueryResultError::Pending => AxError::WouldBlock,
-                    GetQueryResultError::Failed => {
-                        ax_err_type!(ConnectionRefused, "socket query() failed")
-                    }
+        let mut queries = Vec::with_capacity(N);
+        for query_type in query_types {
+            let query_handle = SOCKET_SET
+                .with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
+                    handle
+                        .iface
+                        .with_context(|cx| socket.start_query(cx, name, query_type))
                 })
-            }) {
-                Ok(n) => {
-                    let mut res = Vec::with_capacity(n.capacity());
-                    for ip in n {
-                        res.push(into_core_ipaddr(ip))
+                .map_err(|e| match e {
+                    StartQueryError::NoFreeSlot => {
+                        ax_err_type!(ResourceBusy, "socket query() failed: no free slot")
+                    }
+                    StartQueryError::InvalidName => {
+                        ax_err_type!(InvalidInput, "socket query() failed: invalid name")
+                    }
+                    StartQueryError::NameTooLong => {
+                        ax_err_type!(InvalidInput, "socket query() failed: too long name")
                     }
-                    return Ok(res);
+                })?;
+            queries.push(query_handle);
+        }
+
+        let mut results: [Option<AxResult<Vec<IpAddr>>>; N] = core::array::from_fn(|_| None);
+        SocketWaiter::new().block_on(false, None, |waker| {
+            SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
+                for (&query_handle, result) in queries.iter().zip(results.iter_mut()) {
+                    if result.is_some() {
+                        continue; // the handle is freed when the result is taken
+                    }
+                    socket.register_query_waker(query_handle, waker);
+                    *result = match socket.get_query_result(query_handle) {
+                        Ok(addrs) => Some(Ok(addrs.into_iter().map(into_core_ipaddr).collect())),
+                        Err(GetQueryResultError::Pending) => None,
+                        Err(GetQueryResultError::Failed) => Some(Err(ax_err_type!(
+                            ConnectionRefused,
+                            "socket query() failed"
+                        ))),
+                    };
                 }
-                Err(AxError::WouldBlock) => axtask::yield_now(),
-                Err(e) => return Err(e),
+            });
+            if results.iter().all(Option::is_some) {
+                Ok(())
+            } else {
+                Err(AxError::WouldBlock)
             }
-        }
+        })?;
+        Ok(results.map(Option::unwrap))
     }
 }
 
@@ -84,7 +98,18 @@ impl Drop for DnsSocket {
 }
 
 /// Public function for DNS query.
+///
+/// It looks up both IPv4 (`A`) and IPv6 (`AAAA`) addresses at the same time,
+/// and returns IPv4 ones first. Fails only if both lookups fail.
 pub fn dns_query(name: &str) -> AxResult<alloc::vec::Vec<IpAddr>> {
     let socket = DnsSocket::new();
-    socket.query(name, DnsQueryType::A)
+    let [v4, v6] = socket.query(name, [DnsQueryType::A, DnsQueryType::Aaaa])?;
+    match (v4, v6) {
+        (Ok(mut v4), Ok(v6)) => {
+            v4.extend(v6);
+            Ok(v4)
+        }
+        (Ok(res), Err(_)) | (Err(_), Ok(res)) => Ok(res),
+        (Err(e), Err(_)) => Err(e),
+    }
 }
@@ -0,0 +1,295 @@
+//! IPv6 link-local addressing and stateless address autoconfiguration
+//! (SLAAC, RFC 4862).
+//!
+//! Neighbor discovery itself is handled by smoltcp. Router advertisements are
+//! received by a raw ICMPv6 socket of `eth0`, and the advertised prefix and
+//! default router are applied to the interface after the poll that received
+//! them.
+//!
+//! The autoconfigured addresses are removed when their valid lifetimes end,
+//! and the learned default route when the router lifetime ends. The preferred
+//! lifetimes are not tracked, as smoltcp has no deprecated addresses.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axsync::Mutex;
+use lazyinit::LazyInit;
+use smoltcp::iface::{Interface, SocketSet};
+use smoltcp::socket::{icmp, raw};
+use smoltcp::time::{Duration, Instant};
+use smoltcp::wire::{
+    EthernetAddress, Icmpv6Message, Icmpv6Packet, IpAddress, IpCidr, IpProtocol, IpVersion,
+    Ipv6Address, Ipv6Packet, NdiscPrefixInfoFlags, NdiscRepr,
+};
+
+use super::{Iface, SOCKET_SET, SocketHandle};
+
+/// The prefix length of link-local and autoconfigured addresses.
+pub const SLAAC_PREFIX_LEN: u8 = 64;
+
+/// The all-routers multicast address `ff02::2`.
+const ALL_ROUTERS: Ipv6Address = Ipv6Address::new(0xff02, 0, 0, 0, 0, 0, 0, 2);
+
+/// Lifetimes of this length or longer never end (RFC 4861, section 4.6.2).
+const INFINITE_LIFETIME: Duration = Duration::from_secs(0xffff_ffff);
+
+/// A valid lifetime below this is only accepted if it extends the remaining
+/// one, so that forged advertisements can not remove addresses at once
+/// (RFC 4862, section 5.5.3 e).
+const MIN_VALID_LIFETIME: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// The maximum number of advertisements received in a poll.
+const ADVERT_QUEUE_SIZE: usize = 4;
+
+/// The minimum IPv6 MTU, which advertisements must fit in.
+const IPV6_MIN_MTU: usize = 1280;
+
+/// The raw socket that receives ICMPv6 packets, including advertisements.
+static ICMPV6_SOCKET: LazyInit<SocketHandle> = LazyInit::new();
+
+/// The addresses and the default router learned from advertisements.
+static SLAAC: Mutex<Slaac> = Mutex::new(Slaac {
+    addrs: Vec::new(),
+    router: None,
+});
+
+/// Whether the default IPv6 route is configured statically, in which case
+/// routers learned from advertisements do not replace it.
+static STATIC_GATEWAY: AtomicBool = AtomicBool::new(false);
+
+struct Slaac {
+    /// The autoconfigured addresses, and when they become invalid (`None` if
+    /// never).
+    addrs: Vec<(Ipv6Address, Option<Instant>)>,
+    /// The default router, and when its lifetime ends (`None` if never).
+    router: Option<(Ipv6Address, Option<Instant>)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RouterAdvert {
+    router: Ipv6Address,
+    router_lifetime: Duration,
+    /// The advertised `/64` prefix that allows autonomous configuration, and
+    /// its valid lifetime.
+    prefix: Option<(Ipv6Address, Duration)>,
+}
+
+/// Forms an address from the upper 64 bits of `prefix` and the modified
+/// EUI-64 interface identifier of `mac` (RFC 4291, appendix A).
+fn eui64_address(prefix: &Ipv6Address, mac: EthernetAddress) -> Ipv6Address {
+    let m = mac.0;
+    let mut bytes = prefix.0;
+    bytes[8..].copy_from_slice(&[m[0] ^ 0x02, m[1], m[2], 0xff, 0xfe, m[3], m[4], m[5]]);
+    Ipv6Address(bytes)
+}
+
+/// Returns the `fe80::/64` link-local address of the interface with `mac`.
+pub fn link_local_address(mac: EthernetAddress) -> Ipv6Address {
+    eui64_address(&Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 0), mac)
+}
+
+/// Marks the default IPv6 route as statically configured.
+pub fn set_static_gateway() {
+    STATIC_GATEWAY.store(true, Ordering::Release);
+}
+
+/// Returns the instant when a lifetime that starts `now` ends, or `None` if
+/// it never ends.
+fn expiry(now: Instant, lifetime: Duration) -> Option<Instant> {
+    (lifetime < INFINITE_LIFETIME).then(|| now + lifetime)
+}
+
+/// Creates the raw socket that receives router advertisements.
+pub fn init() {
+    let rx_buffer = raw::PacketBuffer::new(
+        vec![raw::PacketMetadata::EMPTY; ADVERT_QUEUE_SIZE],
+        vec![0; ADVERT_QUEUE_SIZE * IPV6_MIN_MTU],
+    );
+    let tx_buffer = raw::PacketBuffer::new(vec![], vec![]);
+    let socket = raw::Socket::new(IpVersion::Ipv6, IpProtocol::Icmpv6, rx_buffer, tx_buffer);
+    ICMPV6_SOCKET.init_once(SOCKET_SET.add(Iface::Eth0, socket));
+}
+
+/// Parses the router advertisement in the given IPv6 packet, if any.
+fn parse_router_advert(buf: &[u8]) -> Result<Option<RouterAdvert>, smoltcp::wire::Error> {
+    let ipv6_packet = Ipv6Packet::new_checked(buf)?;
+    // routers always send with a hop limit of 255, anything else is forged
+    if ipv6_packet.next_header() != IpProtocol::Icmpv6
+        || ipv6_packet.hop_limit() != 255
+        || !ipv6_packet.src_addr().is_link_local()
+    {
+        return Ok(None);
+    }
+    let icmp_packet = Icmpv6Packet::new_checked(ipv6_packet.payload())?;
+    if icmp_packet.msg_type() != Icmpv6Message::RouterAdvert {
+        return Ok(None);
+    }
+    let NdiscRepr::RouterAdvert {
+        router_lifetime,
+        prefix_info,
+        ..
+    } = NdiscRepr::parse(&icmp_packet)?
+    else {
+        return Ok(None);
+    };
+    let prefix = prefix_info
+        .filter(|info| {
+            info.flags.contains(NdiscPrefixInfoFlags::ADDRCONF)
+                && info.prefix_len == SLAAC_PREFIX_LEN
+                && !info.prefix.is_link_local()
+        })
+        .map(|info| (info.prefix, info.valid_lifetime));
+    Ok(Some(RouterAdvert {
+        router: ipv6_packet.src_addr(),
+        router_lifetime,
+        prefix,
+    }))
+}
+
+/// Applies the router advertisements received in the last poll to `iface`,
+/// and removes the addresses and the default route that have expired.
+pub fn process_router_adverts(
+    iface: &mut Interface,
+    sockets: &mut SocketSet,
+    mac: EthernetAddress,
+    now: Instant,
+) {
+    let Some(&handle) = ICMPV6_SOCKET.get() else {
+        return;
+    };
+    let socket = sockets.get_mut::<raw::Socket>(handle.inner);
+    let mut slaac = SLAAC.lock();
+    while let Ok(packet) = socket.recv() {
+        if let Ok(Some(advert)) = parse_router_advert(packet) {
+            slaac.apply(iface, advert, mac, now);
+        }
+    }
+    slaac.expire(iface, now);
+}
+
+/// Returns how long until an autoconfigured address or the default route
+/// expires, or `None` if none will.
+pub fn poll_delay(now: Instant) -> Option<Duration> {
+    let slaac = SLAAC.lock();
+    let router = slaac.router.and_then(|(_, expiry)| expiry);
+    let addrs = slaac.addrs.iter().filter_map(|&(_, expiry)| expiry);
+    addrs.chain(router).min().map(|expiry| {
+        if expiry > now {
+            expiry - now
+        } else {
+            Duration::ZERO
+        }
+    })
+}
+
+impl Slaac {
+    fn apply(
+        &mut self,
+        iface: &mut Interface,
+        advert: RouterAdvert,
+        mac: EthernetAddress,
+        now: Instant,
+    ) {
+        if let Some((prefix, valid_lifetime)) = advert.prefix {
+            let addr = eui64_address(&prefix, mac);
+            match self.addrs.iter_mut().find(|(a, _)| *a == addr) {
+                Some((_, expires)) => {
+                    let remaining = expires.map_or(INFINITE_LIFETIME, |e| {
+                        if e > now { e - now } else { Duration::ZERO }
+                    });
+                    if valid_lifetime > MIN_VALID_LIFETIME || valid_lifetime > remaining {
+                        *expires = expiry(now, valid_lifetime);
+                    } else if remaining > MIN_VALID_LIFETIME {
+                        *expires = expiry(now, MIN_VALID_LIFETIME);
+                    }
+                }
+                None if valid_lifetime > Duration::ZERO => {
+                    let cidr = IpCidr::new(IpAddress::Ipv6(addr), SLAAC_PREFIX_LEN);
+                    let mut added = false;
+                    iface.update_ip_addrs(|ip_addrs| {
+                        added = ip_addrs.push(cidr).is_ok();
+                    });
+                    if added {
+                        info!("SLAAC: configured {}", cidr);
+                        self.addrs.push((addr, expiry(now, valid_lifetime)));
+                    } else {
+                        warn!("SLAAC: no room for address {}", addr);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        if STATIC_GATEWAY.load(Ordering::Acquire) {
+            return;
+        }
+        if advert.router_lifetime > Duration::ZERO {
+            let old = iface.routes_mut().add_default_ipv6_route(advert.router);
+            if !matches!(old, Ok(Some(route)) if route.via_router == IpAddress::Ipv6(advert.router))
+            {
+                info!("SLAAC: default router {}", advert.router);
+            }
+            self.router = Some((advert.router, expiry(now, advert.router_lifetime)));
+        } else if self
+            .router
+            .is_some_and(|(router, _)| router == advert.router)
+        {
+            iface.routes_mut().remove_default_ipv6_route();
+            self.router = None;
+            info!("SLAAC: router {} withdrawn", advert.router);
+        }
+    }
+
+    fn expire(&mut self, iface: &mut Interface, now: Instant) {
+        let expired = |expires: &Option<Instant>| expires.is_some_and(|e| e <= now);
+        if self.addrs.iter().any(|(_, expires)| expired(expires)) {
+            let addrs = &self.addrs;
+            iface.update_ip_addrs(|ip_addrs| {
+                ip_addrs.retain(|cidr| {
+                    !addrs.iter().any(|(addr, expires)| {
+                        expired(expires) && cidr.address() == IpAddress::Ipv6(*addr)
+                    })
+                });
+            });
+            self.addrs.retain(|(addr, expires)| {
+                if expired(expires) {
+                    info!("SLAAC: address {} expired", addr);
+                }
+                !expired(expires)
+            });
+        }
+        if let Some((router, _)) = self.router.filter(|(_, expires)| expired(expires)) {
+            iface.routes_mut().remove_default_ipv6_route();
+            self.router = None;
+            info!("SLAAC: router {} expired", router);
+        }
+    }
+}
+
+/// Sends a router solicitation to `ff02::2`, so that routers advertise
+/// themselves now rather than at their next periodic advertisement.
+pub fn solicit_routers() {
+    // type 133, code 0, checksum (filled in by smoltcp), reserved
+    const ROUTER_SOLICIT: [u8; 8] = [133, 0, 0, 0, 0, 0, 0, 0];
+
+    let rx_buffer = icmp::PacketBuffer::new(vec![icmp::PacketMetadata::EMPTY], vec![]);
+    let tx_buffer = icmp::PacketBuffer::new(
+        vec![icmp::PacketMetadata::EMPTY],
+        vec![0; ROUTER_SOLICIT.len()],
+    );
+    let mut socket = icmp::Socket::new(rx_buffer, tx_buffer);
+    // neighbor discovery messages must not have been forwarded
+    socket.set_hop_limit(Some(255));
+    if socket
+        .send_slice(&ROUTER_SOLICIT, IpAddress::Ipv6(ALL_ROUTERS))
+        .is_err()
+    {
+        warn!("failed to send router solicitation");
+        return;
+    }
+    let handle = SOCKET_SET.add(Iface::Eth0, socket);
+    SOCKET_SET.poll_interfaces();
+    SOCKET_SET.remove(handle);
+}
@@ -1,26 +1,34 @@
-use alloc::{boxed::Box, collections::VecDeque};
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
 use core::ops::{Deref, DerefMut};
+use core::task::Waker;
 
 use axerrno::{AxError, AxResult, ax_err};
 use axsync::Mutex;
-use smoltcp::iface::{SocketHandle, SocketSet};
+use smoltcp::iface::SocketSet;
 use smoltcp::socket::tcp::{self, State};
 use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
-use super::{LISTEN_QUEUE_SIZE, SOCKET_SET, SocketSetWrapper};
+use super::{Iface, LISTEN_QUEUE_SIZE, SOCKET_SET, SocketHandle, SocketSetWrapper};
 
 const PORT_NUM: usize = 65536;
 
 struct ListenTableEntry {
     listen_endpoint: IpListenEndpoint,
+    /// Buffer lengths of the sockets in the SYN queue.
+    buf_lens: (usize, usize),
     syn_queue: VecDeque<SocketHandle>,
+    /// The waker of the task blocked in `accept()`, registered on all sockets
+    /// in the SYN queue.
+    waker: Option<Waker>,
 }
 
 impl ListenTableEntry {
-    pub fn new(listen_endpoint: IpListenEndpoint) -> Self {
+    pub fn new(listen_endpoint: IpListenEndpoint, buf_lens: (usize, usize)) -> Self {
         Self {
             listen_endpoint,
+            buf_lens,
             syn_queue: VecDeque::with_capacity(LISTEN_QUEUE_SIZE),
+            waker: None,
         }
     }
 
@@ -61,12 +69,22 @@ impl ListenTable {
         self.tcp[port as usize].lock().is_none()
     }
 
-    pub fn listen(&self, listen_endpoint: IpListenEndpoint) -> AxResult {
+    /// Starts listening on the given endpoint, where new connections get
+    /// receive and send buffers of the given lengths.
+    pub fn listen(
+        &self,
+        listen_endpoint: IpListenEndpoint,
+        rx_len: usize,
+        tx_len: usize,
+    ) -> AxResult {
         let port = listen_endpoint.port;
         assert_ne!(port, 0);
         let mut entry = self.tcp[port as usize].lock();
         if entry.is_none() {
-            *entry = Some(Box::new(ListenTableEntry::new(listen_endpoint)));
+            *entry = Some(Box::new(ListenTableEntry::new(
+                listen_endpoint,
+                (rx_len, tx_len),
+            )));
             Ok(())
         } else {
             ax_err!(AddrInUse, "socket listen() failed")
@@ -75,7 +93,19 @@ impl ListenTable {
 
     pub fn unlisten(&self, port: u16) {
         debug!("TCP socket unlisten on {}", port);
-        *self.tcp[port as usize].lock() = None;
+        let entry = self.tcp[port as usize].lock().take();
+        // let the task blocked in `accept()` fail
+        if let Some(waker) = entry.as_ref().and_then(|entry| entry.waker.as_ref()) {
+            waker.wake_by_ref();
+        }
+    }
+
+    /// Returns the endpoints of all listening sockets, in the order of ports.
+    pub fn listen_endpoints(&self) -> Vec<IpListenEndpoint> {
+        self.tcp
+            .iter()
+            .filter_map(|entry| entry.lock().as_ref().map(|e| e.listen_endpoint))
+            .collect()
     }
 
     pub fn can_accept(&self, port: u16) -> AxResult<bool> {
@@ -86,6 +116,22 @@ impl ListenTable {
         }
     }
 
+    /// Registers the waker to be woken when a socket in the SYN queue changes
+    /// its state, including the sockets created later.
+    pub fn register_waker(&self, port: u16, waker: &Waker) -> AxResult {
+        if let Some(entry) = self.tcp[port as usize].lock().deref_mut() {
+            for &handle in &entry.syn_queue {
+                SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                    socket.register_recv_waker(waker)
+                });
+            }
+            entry.waker = Some(waker.clone());
+            Ok(())
+        } else {
+            ax_err!(InvalidInput, "socket accept() failed: not listen")
+        }
+    }
+
     pub fn accept(&self, port: u16) -> AxResult<(SocketHandle, (IpEndpoint, IpEndpoint))> {
         if let Some(entry) = self.tcp[port as usize].lock().deref_mut() {
             let syn_queue = &mut entry.syn_queue;
@@ -110,8 +156,11 @@ impl ListenTable {
         }
     }
 
+    /// Creates a socket in the SYN queue for the first packet of a connection
+    /// received by `iface`, whose socket set is `sockets`.
     pub fn incoming_tcp_packet(
         &self,
+        iface: Iface,
         src: IpEndpoint,
         dst: IpEndpoint,
         sockets: &mut SocketSet<'_>,
@@ -126,9 +175,16 @@ impl ListenTable {
                 warn!("SYN queue overflow!");
                 return;
             }
-            let mut socket = SocketSetWrapper::new_tcp_socket();
+            let (rx_len, tx_len) = entry.buf_lens;
+            let mut socket = SocketSetWrapper::new_tcp_socket(rx_len, tx_len);
             if socket.listen(entry.listen_endpoint).is_ok() {
-                let handle = sockets.add(socket);
+                if let Some(waker) = &entry.waker {
+                    socket.register_recv_waker(waker);
+                }
+                let handle = SocketHandle {
+                    iface,
+                    inner: sockets.add(socket),
+                };
                 debug!(
                     "TCP socket {}: prepare for connection {} -> {}",
                     handle, src, entry.listen_endpoint
@@ -0,0 +1,135 @@
+//! The software loopback device of `lo`.
+//!
+//! `lo` is an interface of its own, whose device queues the IP packets sent
+//! through it to be received in the next poll. Besides `127.0.0.1/8` and
+//! `::1/128`, it owns the addresses of `eth0`, as packets to any address of
+//! this host are routed through `lo` rather than the NIC.
+
+use alloc::{collections::VecDeque, vec, vec::Vec};
+
+use smoltcp::iface::{Interface, SocketSet};
+use smoltcp::phy::{self, Device, DeviceCapabilities, Medium};
+use smoltcp::time::Instant;
+use smoltcp::wire::{IpAddress, IpCidr, Ipv4Address, Ipv4Cidr, Ipv6Address, Ipv6Cidr};
+
+use super::{Iface, snoop_tcp_packet};
+
+/// The loopback address `127.0.0.1/8`.
+pub const IPV4_CIDR: IpCidr = IpCidr::Ipv4(Ipv4Cidr::new(Ipv4Address([127, 0, 0, 1]), 8));
+
+/// The loopback address `::1/128`.
+pub const IPV6_CIDR: IpCidr = IpCidr::Ipv6(Ipv6Cidr::new(Ipv6Address::LOOPBACK, 128));
+
+/// The MTU of `lo`, which is the maximum length of IP packets.
+const MTU: usize = 65535;
+
+/// The maximum number of packets waiting to be received, beyond which the
+/// device stops transmitting like a full NIC queue.
+const QUEUE_SIZE: usize = 256;
+
+pub struct Loopback {
+    /// Packets sent through `lo`, to be received in the next poll.
+    queue: VecDeque<Vec<u8>>,
+}
+
+pub struct LoopbackRxToken(Vec<u8>);
+
+pub struct LoopbackTxToken<'a>(&'a mut VecDeque<Vec<u8>>);
+
+impl Loopback {
+    pub const fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl Device for Loopback {
+    type RxToken<'a>
+        = LoopbackRxToken
+    where
+        Self: 'a;
+    type TxToken<'a>
+        = LoopbackTxToken<'a>
+    where
+        Self: 'a;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let packet = self.queue.pop_front()?;
+        Some((LoopbackRxToken(packet), LoopbackTxToken(&mut self.queue)))
+    }
+
+    fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        if self.queue.len() < QUEUE_SIZE {
+            Some(LoopbackTxToken(&mut self.queue))
+        } else {
+            None
+        }
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = DeviceCapabilities::default();
+        caps.max_transmission_unit = MTU;
+        caps.max_burst_size = None;
+        caps.medium = Medium::Ip;
+        caps
+    }
+}
+
+impl phy::RxToken for LoopbackRxToken {
+    fn preprocess(&self, sockets: &mut SocketSet<'_>) {
+        snoop_tcp_packet(Iface::Lo, &self.0, sockets).ok();
+    }
+
+    fn consume<R, F>(mut self, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        f(&mut self.0)
+    }
+}
+
+impl phy::TxToken for LoopbackTxToken<'_> {
+    fn consume<R, F>(self, len: usize, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let mut packet = vec![0; len];
+        let ret = f(&mut packet);
+        // a token returned with a received packet may find the queue full
+        if self.0.len() < QUEUE_SIZE {
+            self.0.push_back(packet);
+        } else {
+            warn!("loopback queue overflow!");
+        }
+        ret
+    }
+}
+
+/// Sets the addresses of `lo` to the loopback ones, followed by `host_addrs`
+/// (the addresses of `eth0`) with full-length prefixes.
+///
+/// The loopback ones come first, as smoltcp sends from the first address of
+/// each version.
+pub fn update_addrs(iface: &mut Interface, host_addrs: &[IpCidr]) {
+    let host_addrs = host_addrs.iter().map(|cidr| match cidr.address() {
+        addr @ IpAddress::Ipv4(_) => IpCidr::new(addr, 32),
+        addr @ IpAddress::Ipv6(_) => IpCidr::new(addr, 128),
+    });
+    let addrs = [IPV4_CIDR, IPV6_CIDR].into_iter().chain(host_addrs);
+    if iface.ip_addrs().iter().copied().eq(addrs.clone()) {
+        return;
+    }
+    iface.update_ip_addrs(|ip_addrs| {
+        ip_addrs.clear();
+        for cidr in addrs {
+            if ip_addrs.push(cidr).is_err() {
+                warn!("too many addresses on lo, {} is unreachable locally", cidr);
+            }
+        }
+    });
+}
@@ -1,29 +1,44 @@
 mod addr;
 mod bench;
+#[cfg(feature = "dhcp")]
+mod dhcp;
 mod dns;
+mod ipv6;
 mod listen_table;
+mod loopback;
+mod options;
 mod tcp;
 mod udp;
+mod wait;
 
-use alloc::vec;
+use alloc::string::String;
+use alloc::{vec, vec::Vec};
 use core::cell::RefCell;
-use core::ops::DerefMut;
+use core::fmt;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
 
-use axdriver::prelude::*;
+use axdriver::{NetIrq, prelude::*};
 use axdriver_net::{DevError, NetBufPtr};
+use axerrno::{AxResult, ax_err};
 use axhal::time::{NANOS_PER_MICROS, wall_time_nanos};
 use axsync::Mutex;
 use lazyinit::LazyInit;
-use smoltcp::iface::{Config, Interface, SocketHandle, SocketSet};
+use smoltcp::iface::{Config, Context, Interface, SocketSet};
 use smoltcp::phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken};
 use smoltcp::socket::{self, AnySocket};
 use smoltcp::time::Instant;
-use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr};
+use smoltcp::wire::{
+    EthernetAddress, EthernetFrame, EthernetProtocol, HardwareAddress, IpAddress, IpCidr,
+    Ipv4Address, Ipv6Address,
+};
 
 use self::listen_table::ListenTable;
+use self::loopback::Loopback;
 
 pub use self::dns::dns_query;
-pub use self::tcp::TcpSocket;
+pub use self::tcp::{TcpSocket, TcpSocketInfo, tcp_socket_table};
 pub use self::udp::UdpSocket;
 
 macro_rules! env_or_default {
@@ -37,66 +52,165 @@ macro_rules! env_or_default {
 
 const IP: &str = env_or_default!("AX_IP");
 const GATEWAY: &str = env_or_default!("AX_GW");
+const IP6: &str = env_or_default!("AX_IP6");
+const GATEWAY6: &str = env_or_default!("AX_GW6");
 const DNS_SEVER: &str = "8.8.8.8";
+const MAX_DNS_SERVERS: usize = 4;
 const IP_PREFIX: u8 = 24;
+const IP6_PREFIX: u8 = 64;
 
 const STANDARD_MTU: usize = 1500;
 
 const RANDOM_SEED: u64 = 0xA2CE_05A2_CE05_A2CE;
 
-const TCP_RX_BUF_LEN: usize = 64 * 1024;
-const TCP_TX_BUF_LEN: usize = 64 * 1024;
-const UDP_RX_BUF_LEN: usize = 64 * 1024;
-const UDP_TX_BUF_LEN: usize = 64 * 1024;
+const DEFAULT_BUF_LEN: usize = 64 * 1024;
+const MIN_BUF_LEN: usize = 1024;
+const MAX_BUF_LEN: usize = 16 * 1024 * 1024;
 const LISTEN_QUEUE_SIZE: usize = 512;
 
+/// Buffer lengths of new sockets, indexed by [`BufferKind`].
+static BUF_LENS: [AtomicUsize; 4] = [const { AtomicUsize::new(DEFAULT_BUF_LEN) }; 4];
+
 static LISTEN_TABLE: LazyInit<ListenTable> = LazyInit::new();
 static SOCKET_SET: LazyInit<SocketSetWrapper> = LazyInit::new();
-static ETH0: LazyInit<InterfaceWrapper> = LazyInit::new();
+static LO: LazyInit<InterfaceWrapper<Loopback>> = LazyInit::new();
+/// The interface of the NIC, if there is one.
+static ETH0: LazyInit<InterfaceWrapper<DeviceWrapper>> = LazyInit::new();
+static IPV4_CONFIG: Mutex<Option<Ipv4Config>> = Mutex::new(None);
+
+/// The network interfaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Iface {
+    Lo,
+    Eth0,
+}
 
-struct SocketSetWrapper<'a>(Mutex<SocketSet<'a>>);
+/// A handle of a socket in the socket set of an interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SocketHandle {
+    iface: Iface,
+    inner: smoltcp::iface::SocketHandle,
+}
+
+/// The socket sets of `lo` and `eth0`.
+///
+/// smoltcp sends the packets of every socket in a set through the interface
+/// that it is polled with, so each socket is added to the set of the interface
+/// that its packets are routed through.
+struct SocketSetWrapper<'a> {
+    lo: Mutex<SocketSet<'a>>,
+    eth0: Mutex<SocketSet<'a>>,
+}
 
 struct DeviceWrapper {
     inner: RefCell<AxNetDevice>, // use `RefCell` is enough since it's wrapped in `Mutex` in `InterfaceWrapper`.
+    ether_addr: EthernetAddress,
 }
 
-struct InterfaceWrapper {
+struct InterfaceWrapper<D> {
     name: &'static str,
-    ether_addr: EthernetAddress,
-    dev: Mutex<DeviceWrapper>,
+    dev: Mutex<D>,
     iface: Mutex<Interface>,
 }
 
+impl Iface {
+    /// Returns the interfaces that are up, where `lo` comes first.
+    fn all() -> impl Iterator<Item = Self> {
+        [Self::Lo]
+            .into_iter()
+            .chain(ETH0.is_inited().then_some(Self::Eth0))
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Lo => LO.name,
+            Self::Eth0 => ETH0.name,
+        }
+    }
+
+    /// Calls `f` with the context of the interface, which sockets in its set
+    /// need to connect.
+    fn with_context<R>(self, f: impl FnOnce(&mut Context) -> R) -> R {
+        match self {
+            Self::Lo => f(LO.iface.lock().context()),
+            Self::Eth0 => f(ETH0.iface.lock().context()),
+        }
+    }
+}
+
+/// Returns the interface that packets to `addr` are routed through, which is
+/// `lo` for the addresses of this host, or if there is no NIC.
+fn route(addr: &IpAddress) -> Iface {
+    if ETH0.is_inited() && !is_local_addr(addr) {
+        Iface::Eth0
+    } else {
+        Iface::Lo
+    }
+}
+
+impl fmt::Display for SocketHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.inner, self.iface.name())
+    }
+}
+
 impl<'a> SocketSetWrapper<'a> {
     fn new() -> Self {
-        Self(Mutex::new(SocketSet::new(vec![])))
+        Self {
+            lo: Mutex::new(SocketSet::new(vec![])),
+            eth0: Mutex::new(SocketSet::new(vec![])),
+        }
     }
 
-    pub fn new_tcp_socket() -> socket::tcp::Socket<'a> {
-        let tcp_rx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_RX_BUF_LEN]);
-        let tcp_tx_buffer = socket::tcp::SocketBuffer::new(vec![0; TCP_TX_BUF_LEN]);
+    fn set(&self, iface: Iface) -> &Mutex<SocketSet<'a>> {
+        match iface {
+            Iface::Lo => &self.lo,
+            Iface::Eth0 => &self.eth0,
+        }
+    }
+
+    pub fn new_tcp_socket(rx_len: usize, tx_len: usize) -> socket::tcp::Socket<'a> {
+        let tcp_rx_buffer = socket::tcp::SocketBuffer::new(vec![0; rx_len]);
+        let tcp_tx_buffer = socket::tcp::SocketBuffer::new(vec![0; tx_len]);
         socket::tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer)
     }
 
-    pub fn new_udp_socket() -> socket::udp::Socket<'a> {
+    pub fn new_udp_socket(rx_len: usize, tx_len: usize) -> socket::udp::Socket<'a> {
         let udp_rx_buffer = socket::udp::PacketBuffer::new(
             vec![socket::udp::PacketMetadata::EMPTY; 8],
-            vec![0; UDP_RX_BUF_LEN],
+            vec![0; rx_len],
         );
         let udp_tx_buffer = socket::udp::PacketBuffer::new(
             vec![socket::udp::PacketMetadata::EMPTY; 8],
-            vec![0; UDP_TX_BUF_LEN],
+            vec![0; tx_len],
         );
         socket::udp::Socket::new(udp_rx_buffer, udp_tx_buffer)
     }
 
-    pub fn new_dns_socket() -> socket::dns::Socket<'a> {
-        let server_addr = DNS_SEVER.parse().expect("invalid DNS server address");
-        socket::dns::Socket::new(&[server_addr], vec![])
+    /// Creates a DNS socket, and returns it with the interface that the route
+    /// to the first DNS server goes through.
+    pub fn new_dns_socket() -> (Iface, socket::dns::Socket<'a>) {
+        let mut servers: Vec<IpAddress> = match IPV4_CONFIG.lock().as_ref() {
+            Some(config) => config
+                .dns_servers
+                .iter()
+                .map(|&ip| addr::from_core_ipaddr(ip))
+                .take(MAX_DNS_SERVERS)
+                .collect(),
+            None => Vec::new(),
+        };
+        if servers.is_empty() {
+            servers.push(DNS_SEVER.parse().expect("invalid DNS server address"));
+        }
+        (
+            route(&servers[0]),
+            socket::dns::Socket::new(&servers, vec![]),
+        )
     }
 
-    pub fn add<T: AnySocket<'a>>(&self, socket: T) -> SocketHandle {
-        let handle = self.0.lock().add(socket);
+    pub fn add<T: AnySocket<'a>>(&self, iface: Iface, socket: T) -> SocketHandle {
+        let inner = self.set(iface).lock().add(socket);
+        let handle = SocketHandle { iface, inner };
         debug!("socket {}: created", handle);
         handle
     }
@@ -105,8 +219,8 @@ impl<'a> SocketSetWrapper<'a> {
     where
         F: FnOnce(&T) -> R,
     {
-        let set = self.0.lock();
-        let socket = set.get(handle);
+        let set = self.set(handle.iface).lock();
+        let socket = set.get(handle.inner);
         f(socket)
     }
 
@@ -114,48 +228,81 @@ impl<'a> SocketSetWrapper<'a> {
     where
         F: FnOnce(&mut T) -> R,
     {
-        let mut set = self.0.lock();
-        let socket = set.get_mut(handle);
+        let mut set = self.set(handle.iface).lock();
+        let socket = set.get_mut(handle.inner);
         f(socket)
     }
 
+    /// Calls `f` on each socket of all interfaces.
+    pub fn for_each_socket(&self, mut f: impl FnMut(&socket::Socket<'a>)) {
+        for iface in Iface::all() {
+            for (_, socket) in self.set(iface).lock().iter() {
+                f(socket);
+            }
+        }
+    }
+
     pub fn poll_interfaces(&self) {
-        ETH0.poll(&self.0);
+        if let Some(eth0) = ETH0.get() {
+            eth0.poll(&self.eth0);
+        }
+        LO.poll(&self.lo);
+    }
+
+    /// Returns how long to wait before the next poll is needed, or `None` if
+    /// no socket has pending timers.
+    #[allow(dead_code)]
+    pub fn poll_delay(&self) -> Option<Duration> {
+        let timestamp = current_time();
+        let delays = [
+            LO.poll_delay(&self.lo, timestamp),
+            ETH0.get()
+                .and_then(|eth0| eth0.poll_delay(&self.eth0, timestamp)),
+            ipv6::poll_delay(timestamp),
+            #[cfg(feature = "dhcp")]
+            dhcp::poll_delay(timestamp),
+        ];
+        let delay = delays.into_iter().flatten().min();
+        delay.map(|delay| Duration::from_micros(delay.total_micros()))
+    }
+
+    /// Returns whether any TCP connection (including the closing ones) is on
+    /// the given local port.
+    pub fn tcp_port_in_use(&self, port: u16) -> bool {
+        let mut in_use = false;
+        self.for_each_socket(|socket| {
+            if let socket::Socket::Tcp(socket) = socket {
+                in_use |= socket
+                    .local_endpoint()
+                    .is_some_and(|endpoint| endpoint.port == port);
+            }
+        });
+        in_use
     }
 
     pub fn remove(&self, handle: SocketHandle) {
-        self.0.lock().remove(handle);
+        self.set(handle.iface).lock().remove(handle.inner);
         debug!("socket {}: destroyed", handle);
     }
 }
 
-impl InterfaceWrapper {
-    fn new(name: &'static str, dev: AxNetDevice, ether_addr: EthernetAddress) -> Self {
-        let mut config = Config::new(HardwareAddress::Ethernet(ether_addr));
+fn current_time() -> Instant {
+    Instant::from_micros_const((wall_time_nanos() / NANOS_PER_MICROS) as i64)
+}
+
+impl<D: Device> InterfaceWrapper<D> {
+    fn new(name: &'static str, hardware_addr: HardwareAddress, mut dev: D) -> Self {
+        let mut config = Config::new(hardware_addr);
         config.random_seed = RANDOM_SEED;
 
-        let mut dev = DeviceWrapper::new(dev);
-        let iface = Mutex::new(Interface::new(config, &mut dev, Self::current_time()));
+        let iface = Mutex::new(Interface::new(config, &mut dev, current_time()));
         Self {
             name,
-            ether_addr,
             dev: Mutex::new(dev),
             iface,
         }
     }
 
-    fn current_time() -> Instant {
-        Instant::from_micros_const((wall_time_nanos() / NANOS_PER_MICROS) as i64)
-    }
-
-    pub fn name(&self) -> &str {
-        self.name
-    }
-
-    pub fn ethernet_address(&self) -> EthernetAddress {
-        self.ether_addr
-    }
-
     pub fn setup_ip_addr(&self, ip: IpAddress, prefix_len: u8) {
         let mut iface = self.iface.lock();
         iface.update_ip_addrs(|ip_addrs| {
@@ -167,22 +314,83 @@ impl InterfaceWrapper {
         let mut iface = self.iface.lock();
         match gateway {
             IpAddress::Ipv4(v4) => iface.routes_mut().add_default_ipv4_route(v4).unwrap(),
+            IpAddress::Ipv6(v6) => iface.routes_mut().add_default_ipv6_route(v6).unwrap(),
         };
     }
 
+    pub fn poll_delay(
+        &self,
+        sockets: &Mutex<SocketSet>,
+        timestamp: Instant,
+    ) -> Option<smoltcp::time::Duration> {
+        let mut iface = self.iface.lock();
+        let sockets = sockets.lock();
+        iface.poll_delay(timestamp, &sockets)
+    }
+
+    /// Returns the information about the interface, where `driver` is the
+    /// name of the device driver.
+    fn info(&self, driver: &str, mac_address: [u8; 6]) -> InterfaceInfo {
+        InterfaceInfo {
+            name: self.name,
+            driver: driver.into(),
+            mac_address,
+            mtu: self.dev.lock().capabilities().ip_mtu(),
+        }
+    }
+}
+
+impl InterfaceWrapper<DeviceWrapper> {
+    pub fn ethernet_address(&self) -> EthernetAddress {
+        self.dev.lock().ether_addr
+    }
+
     pub fn poll(&self, sockets: &Mutex<SocketSet>) {
         let mut dev = self.dev.lock();
         let mut iface = self.iface.lock();
         let mut sockets = sockets.lock();
-        let timestamp = Self::current_time();
-        iface.poll(timestamp, dev.deref_mut(), &mut sockets);
+        let timestamp = current_time();
+        iface.poll(timestamp, &mut *dev, &mut sockets);
+        ipv6::process_router_adverts(&mut iface, &mut sockets, dev.ether_addr, timestamp);
+        #[cfg(feature = "dhcp")]
+        dhcp::process_event(&mut iface, &mut sockets, timestamp);
+    }
+}
+
+impl InterfaceWrapper<Loopback> {
+    /// Updates the addresses of `lo` to include the current ones of `eth0`.
+    fn update_addrs(&self) {
+        let host_addrs = ETH0
+            .get()
+            .map(|eth0| eth0.iface.lock().ip_addrs().to_vec())
+            .unwrap_or_default();
+        loopback::update_addrs(&mut self.iface.lock(), &host_addrs);
+    }
+
+    pub fn poll(&self, sockets: &Mutex<SocketSet>) {
+        self.update_addrs();
+        let mut dev = self.dev.lock();
+        let mut iface = self.iface.lock();
+        let mut sockets = sockets.lock();
+        let timestamp = current_time();
+        iface.poll(timestamp, &mut *dev, &mut sockets);
+        // packets sent at the end of a poll are received in the next one
+        if !dev.is_empty() {
+            iface.poll(timestamp, &mut *dev, &mut sockets);
+        }
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        if !dev.is_empty() {
+            wait::poll_soon();
+        }
     }
 }
 
 impl DeviceWrapper {
     fn new(inner: AxNetDevice) -> Self {
+        let ether_addr = EthernetAddress(inner.mac_address().0);
         Self {
             inner: RefCell::new(inner),
+            ether_addr,
         }
     }
 }
@@ -246,7 +454,15 @@ struct AxNetTxToken<'a>(&'a RefCell<AxNetDevice>);
 
 impl RxToken for AxNetRxToken<'_> {
     fn preprocess(&self, sockets: &mut SocketSet<'_>) {
-        snoop_tcp_packet(self.1.packet(), sockets).ok();
+        let Ok(frame) = EthernetFrame::new_checked(self.1.packet()) else {
+            return;
+        };
+        if matches!(
+            frame.ethertype(),
+            EthernetProtocol::Ipv4 | EthernetProtocol::Ipv6
+        ) {
+            snoop_tcp_packet(Iface::Eth0, frame.payload(), sockets).ok();
+        }
     }
 
     fn consume<R, F>(self, f: F) -> R
@@ -279,20 +495,36 @@ impl TxToken for AxNetTxToken<'_> {
     }
 }
 
-fn snoop_tcp_packet(buf: &[u8], sockets: &mut SocketSet<'_>) -> Result<(), smoltcp::wire::Error> {
-    use smoltcp::wire::{EthernetFrame, IpProtocol, Ipv4Packet, TcpPacket};
-
-    let ether_frame = EthernetFrame::new_checked(buf)?;
-    let ipv4_packet = Ipv4Packet::new_checked(ether_frame.payload())?;
+/// Creates a socket in the socket set of `iface` for the first packet of an
+/// incoming TCP connection, which the later `accept()` returns.
+fn snoop_tcp_packet(
+    iface: Iface,
+    buf: &[u8],
+    sockets: &mut SocketSet<'_>,
+) -> Result<(), smoltcp::wire::Error> {
+    use smoltcp::wire::{IpProtocol, IpVersion, Ipv4Packet, Ipv6Packet, TcpPacket};
+
+    let (src_ip, dst_ip, next_header, payload): (IpAddress, IpAddress, _, _) =
+        match IpVersion::of_packet(buf)? {
+            IpVersion::Ipv4 => {
+                let packet = Ipv4Packet::new_checked(buf)?;
+                let (src, dst) = (packet.src_addr().into(), packet.dst_addr().into());
+                (src, dst, packet.next_header(), packet.payload())
+            }
+            IpVersion::Ipv6 => {
+                let packet = Ipv6Packet::new_checked(buf)?;
+                let (src, dst) = (packet.src_addr().into(), packet.dst_addr().into());
+                (src, dst, packet.next_header(), packet.payload())
+            }
+        };
 
-    if ipv4_packet.next_header() == IpProtocol::Tcp {
-        let tcp_packet = TcpPacket::new_checked(ipv4_packet.payload())?;
-        let src_addr = (ipv4_packet.src_addr(), tcp_packet.src_port()).into();
-        let dst_addr = (ipv4_packet.dst_addr(), tcp_packet.dst_port()).into();
+    if next_header == IpProtocol::Tcp {
+        let tcp_packet = TcpPacket::new_checked(payload)?;
+        let src_addr = (src_ip, tcp_packet.src_port()).into();
+        let dst_addr = (dst_ip, tcp_packet.dst_port()).into();
         let is_first = tcp_packet.syn() && !tcp_packet.ack();
         if is_first {
-            // create a socket for the first incoming TCP packet, as the later accept() returns.
-            LISTEN_TABLE.incoming_tcp_packet(src_addr, dst_addr, sockets);
+            LISTEN_TABLE.incoming_tcp_packet(iface, src_addr, dst_addr, sockets);
         }
     }
     Ok(())
@@ -306,31 +538,279 @@ pub fn poll_interfaces() {
     SOCKET_SET.poll_interfaces();
 }
 
+/// The kind of socket buffers, whose lengths can be tuned at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferKind {
+    /// The receive buffer of TCP sockets.
+    TcpRecv,
+    /// The send buffer of TCP sockets.
+    TcpSend,
+    /// The receive buffer of UDP sockets.
+    UdpRecv,
+    /// The send buffer of UDP sockets.
+    UdpSend,
+}
+
+/// Returns the length in bytes of the given kind of buffers of new sockets.
+pub fn buffer_len(kind: BufferKind) -> usize {
+    BUF_LENS[kind as usize].load(Ordering::Relaxed)
+}
+
+/// Sets the length in bytes of the given kind of buffers of new sockets.
+///
+/// Existing sockets are not affected. Returns
+/// [`InvalidInput`](axerrno::AxError::InvalidInput) if `len` is not between
+/// 1 KiB and 16 MiB.
+pub fn set_buffer_len(kind: BufferKind, len: usize) -> AxResult {
+    if !(MIN_BUF_LEN..=MAX_BUF_LEN).contains(&len) {
+        return ax_err!(InvalidInput, "socket buffer length out of range");
+    }
+    BUF_LENS[kind as usize].store(len, Ordering::Relaxed);
+    Ok(())
+}
+
+/// The IPv4 configuration of the network interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv4Config {
+    /// The IPv4 address.
+    pub address: Ipv4Addr,
+    /// The length of the network prefix.
+    pub prefix_len: u8,
+    /// The default gateway, if any.
+    pub gateway: Option<Ipv4Addr>,
+    /// The DNS servers, or empty to use the default one (`8.8.8.8`).
+    pub dns_servers: Vec<IpAddr>,
+    /// Whether it is leased from a DHCP server, rather than set statically.
+    pub dhcp: bool,
+}
+
+/// Returns the current IPv4 configuration of the network interface, or `None`
+/// if it has no IPv4 address.
+pub fn ipv4_config() -> Option<Ipv4Config> {
+    IPV4_CONFIG.lock().clone()
+}
+
+/// Replaces the IPv4 address and the default IPv4 route of `iface`, keeping
+/// the IPv6 ones.
+fn set_ipv4_config(iface: &mut Interface, config: Option<Ipv4Config>) {
+    iface.update_ip_addrs(|ip_addrs| {
+        ip_addrs.retain(|cidr| !matches!(cidr, IpCidr::Ipv4(_)));
+        if let Some(config) = &config {
+            let ip = IpAddress::Ipv4(Ipv4Address(config.address.octets()));
+            ip_addrs.push(IpCidr::new(ip, config.prefix_len)).unwrap();
+        }
+    });
+    match config.as_ref().and_then(|config| config.gateway) {
+        Some(gateway) => {
+            let gateway = Ipv4Address(gateway.octets());
+            iface.routes_mut().add_default_ipv4_route(gateway).unwrap();
+        }
+        None => {
+            iface.routes_mut().remove_default_ipv4_route();
+        }
+    }
+    *IPV4_CONFIG.lock() = config;
+}
+
+/// The IPv6 configuration of the network interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Config {
+    /// The IPv6 addresses with the lengths of their prefixes, including the
+    /// link-local and the autoconfigured ones.
+    pub addresses: Vec<(Ipv6Addr, u8)>,
+    /// The default gateway, either static or learned from router
+    /// advertisements, if any.
+    pub gateway: Option<Ipv6Addr>,
+}
+
+/// Returns the current IPv6 configuration of the network interface, which is
+/// empty if there is no NIC.
+pub fn ipv6_config() -> Ipv6Config {
+    let Some(eth0) = ETH0.get() else {
+        return Ipv6Config {
+            addresses: Vec::new(),
+            gateway: None,
+        };
+    };
+    let mut iface = eth0.iface.lock();
+    let addresses = iface
+        .ip_addrs()
+        .iter()
+        .filter_map(|cidr| match cidr {
+            IpCidr::Ipv6(cidr) => Some((Ipv6Addr::from(cidr.address().0), cidr.prefix_len())),
+            _ => None,
+        })
+        .collect();
+    let mut gateway = None;
+    iface.routes_mut().update(|routes| {
+        gateway = routes
+            .iter()
+            .find_map(|route| match (route.cidr, route.via_router) {
+                (IpCidr::Ipv6(cidr), IpAddress::Ipv6(router)) if cidr.prefix_len() == 0 => {
+                    Some(Ipv6Addr::from(router.0))
+                }
+                _ => None,
+            });
+    });
+    Ipv6Config { addresses, gateway }
+}
+
+/// Information about a network interface.
+#[derive(Debug, Clone)]
+pub struct InterfaceInfo {
+    /// The name of the interface (e.g., `eth0`).
+    pub name: &'static str,
+    /// The name of the device driver (`loopback` for `lo`).
+    pub driver: String,
+    /// The MAC address, which is all zeros for `lo`.
+    pub mac_address: [u8; 6],
+    /// The maximum transmission unit (the maximum length of IP packets) in
+    /// bytes.
+    pub mtu: usize,
+}
+
+/// Returns the information about all network interfaces, where `lo` comes
+/// first.
+pub fn interfaces() -> Vec<InterfaceInfo> {
+    if !LO.is_inited() {
+        return Vec::new();
+    }
+    let mut infos = vec![LO.info("loopback", [0; 6])];
+    if let Some(eth0) = ETH0.get() {
+        let driver = String::from(eth0.dev.lock().inner.borrow().device_name());
+        infos.push(eth0.info(&driver, eth0.ethernet_address().0));
+    }
+    infos
+}
+
+/// Returns whether `addr` is an address of this host, which is reached
+/// through `lo`.
+fn is_local_addr(addr: &IpAddress) -> bool {
+    let is_loopback = match addr {
+        IpAddress::Ipv4(v4) => v4.is_loopback(),
+        IpAddress::Ipv6(v6) => v6.is_loopback(),
+    };
+    is_loopback
+        || ETH0
+            .get()
+            .is_some_and(|eth0| eth0.iface.lock().has_ip_addr(*addr))
+}
+
 /// Benchmark raw socket transmit bandwidth.
 pub fn bench_transmit() {
-    ETH0.dev.lock().bench_transmit_bandwidth();
+    let Some(eth0) = ETH0.get() else {
+        warn!("no NIC to benchmark");
+        return;
+    };
+    eth0.dev.lock().bench_transmit_bandwidth();
 }
 
 /// Benchmark raw socket receive bandwidth.
 pub fn bench_receive() {
-    ETH0.dev.lock().bench_receive_bandwidth();
+    let Some(eth0) = ETH0.get() else {
+        warn!("no NIC to benchmark");
+        return;
+    };
+    eth0.dev.lock().bench_receive_bandwidth();
 }
 
-pub(crate) fn init(net_dev: AxNetDevice) {
-    let ether_addr = EthernetAddress(net_dev.mac_address().0);
-    let eth0 = InterfaceWrapper::new("eth0", net_dev, ether_addr);
-
-    let ip = IP.parse().expect("invalid IP address");
-    let gateway = GATEWAY.parse().expect("invalid gateway IP address");
-    eth0.setup_ip_addr(ip, IP_PREFIX);
-    eth0.setup_gateway(gateway);
-
-    ETH0.init_once(eth0);
+/// Creates the interface of `lo`, and of `eth0` on `net_dev` if any.
+pub(crate) fn init(net_dev: Option<AxNetDevice>, net_irq: Option<NetIrq>) {
+    LO.init_once(InterfaceWrapper::new(
+        "lo",
+        HardwareAddress::Ip,
+        Loopback::new(),
+    ));
+    let ether_addr = net_dev.map(|dev| {
+        let dev = DeviceWrapper::new(dev);
+        let ether_addr = dev.ether_addr;
+        ETH0.init_once(InterfaceWrapper::new(
+            "eth0",
+            HardwareAddress::Ethernet(ether_addr),
+            dev,
+        ));
+        ether_addr
+    });
     SOCKET_SET.init_once(SocketSetWrapper::new());
     LISTEN_TABLE.init_once(ListenTable::new());
 
-    info!("created net interface {:?}:", ETH0.name());
+    info!("created net interface \"lo\":");
+    info!("  ip:       {}", loopback::IPV4_CIDR);
+    info!("  ip6:      {}", loopback::IPV6_CIDR);
+    if let Some(ether_addr) = ether_addr {
+        ipv6::init();
+        init_eth0(ether_addr);
+    }
+    LO.update_addrs();
+    wait::init(net_irq);
+
+    if ether_addr.is_some() {
+        ipv6::solicit_routers();
+    }
+}
+
+/// Configures the addresses and routes of `eth0`.
+fn init_eth0(ether_addr: EthernetAddress) {
+    let static_config = Ipv4Config {
+        address: IP.parse().expect("invalid IP address"),
+        prefix_len: IP_PREFIX,
+        gateway: Some(GATEWAY.parse().expect("invalid gateway IP address")),
+        dns_servers: Vec::new(),
+        dhcp: false,
+    };
+
+    let link_local = ipv6::link_local_address(ether_addr);
+    ETH0.setup_ip_addr(IpAddress::Ipv6(link_local), ipv6::SLAAC_PREFIX_LEN);
+    let ip6 = (!IP6.is_empty()).then(|| parse_ipv6_cidr(IP6));
+    if let Some((ip6, prefix_len)) = ip6 {
+        ETH0.setup_ip_addr(IpAddress::Ipv6(ip6), prefix_len);
+    }
+    let gateway6 = (!GATEWAY6.is_empty()).then(|| {
+        GATEWAY6
+            .parse::<Ipv6Address>()
+            .expect("invalid IPv6 gateway address")
+    });
+    if let Some(gateway6) = gateway6 {
+        ETH0.setup_gateway(IpAddress::Ipv6(gateway6));
+        ipv6::set_static_gateway();
+    }
+
+    info!("created net interface \"eth0\":");
     info!("  ether:    {}", ETH0.ethernet_address());
-    info!("  ip:       {}/{}", ip, IP_PREFIX);
-    info!("  gateway:  {}", gateway);
+    #[cfg(feature = "dhcp")]
+    {
+        info!("  ip:       DHCP (or {})", static_config.address);
+        dhcp::start(static_config);
+    }
+    #[cfg(not(feature = "dhcp"))]
+    {
+        info!(
+            "  ip:       {}/{}",
+            static_config.address, static_config.prefix_len
+        );
+        if let Some(gateway) = static_config.gateway {
+            info!("  gateway:  {}", gateway);
+        }
+        set_ipv4_config(&mut ETH0.iface.lock(), Some(static_config));
+    }
+    info!("  ip6:      {}/{}", link_local, ipv6::SLAAC_PREFIX_LEN);
+    if let Some((ip6, prefix_len)) = ip6 {
+        info!("  ip6:      {}/{}", ip6, prefix_len);
+    }
+    if let Some(gateway6) = gateway6 {
+        info!("  gateway6: {}", gateway6);
+    }
+}
+
+/// Parses an IPv6 address with an optional prefix length, which defaults to
+/// 64 (e.g., `fd00::15/64`).
+fn parse_ipv6_cidr(s: &str) -> (Ipv6Address, u8) {
+    let (addr, prefix_len) = match s.split_once('/') {
+        Some((addr, prefix_len)) => (
+            addr,
+            prefix_len.parse().expect("invalid IPv6 prefix length"),
+        ),
+        None => (s, IP6_PREFIX),
+    };
+    (addr.parse().expect("invalid IPv6 address"), prefix_len)
 }
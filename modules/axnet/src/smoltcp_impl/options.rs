@@ -0,0 +1,210 @@
+//! Per-socket options, as set by `setsockopt()` on POSIX.
+
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axerrno::{AxResult, ax_err};
+
+use super::{BufferKind, MAX_BUF_LEN, MIN_BUF_LEN, buffer_len};
+
+/// The TTL (hop limit) of outgoing packets if not set, as in smoltcp.
+const DEFAULT_TTL: u8 = 64;
+
+/// An optional [`Duration`], which can be shared between threads.
+pub struct AtomicDuration(AtomicU64);
+
+impl AtomicDuration {
+    const NONE: u64 = u64::MAX;
+
+    pub const fn none() -> Self {
+        Self(AtomicU64::new(Self::NONE))
+    }
+
+    pub fn load(&self) -> Option<Duration> {
+        match self.0.load(Ordering::Relaxed) {
+            Self::NONE => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+
+    pub fn store(&self, dur: Option<Duration>) {
+        let nanos = dur.map_or(Self::NONE, |dur| {
+            dur.as_nanos().min((Self::NONE - 1) as u128) as u64
+        });
+        self.0.store(nanos, Ordering::Relaxed);
+    }
+}
+
+/// Converts to the duration type of smoltcp.
+pub fn to_smoltcp_duration(dur: Duration) -> smoltcp::time::Duration {
+    smoltcp::time::Duration::from_micros(dur.as_micros() as u64)
+}
+
+/// Options of TCP and UDP sockets, where `nodelay`, `keepalive` and `linger`
+/// only apply to TCP ones.
+pub struct SocketOptions {
+    /// The length of the receive buffer, or 0 for the default of new sockets.
+    recv_buf_len: AtomicUsize,
+    /// The length of the send buffer, or 0 for the default of new sockets.
+    send_buf_len: AtomicUsize,
+    read_timeout: AtomicDuration,
+    write_timeout: AtomicDuration,
+    /// The TTL of outgoing packets, or 0 for the default.
+    ttl: AtomicU8,
+    reuse_addr: AtomicBool,
+    nodelay: AtomicBool,
+    /// The interval of keep-alive packets, or `None` if disabled.
+    keepalive: AtomicDuration,
+    /// The linger time, or `None` if disabled.
+    linger: AtomicDuration,
+}
+
+impl SocketOptions {
+    pub const fn new() -> Self {
+        Self {
+            recv_buf_len: AtomicUsize::new(0),
+            send_buf_len: AtomicUsize::new(0),
+            read_timeout: AtomicDuration::none(),
+            write_timeout: AtomicDuration::none(),
+            ttl: AtomicU8::new(0),
+            reuse_addr: AtomicBool::new(false),
+            nodelay: AtomicBool::new(false),
+            keepalive: AtomicDuration::none(),
+            linger: AtomicDuration::none(),
+        }
+    }
+
+    /// Copies all options from `other`.
+    pub fn copy_from(&self, other: &Self) {
+        let relaxed = Ordering::Relaxed;
+        self.recv_buf_len
+            .store(other.recv_buf_len.load(relaxed), relaxed);
+        self.send_buf_len
+            .store(other.send_buf_len.load(relaxed), relaxed);
+        self.read_timeout.store(other.read_timeout.load());
+        self.write_timeout.store(other.write_timeout.load());
+        self.ttl.store(other.ttl.load(relaxed), relaxed);
+        self.reuse_addr
+            .store(other.reuse_addr.load(relaxed), relaxed);
+        self.nodelay.store(other.nodelay.load(relaxed), relaxed);
+        self.keepalive.store(other.keepalive.load());
+        self.linger.store(other.linger.load());
+    }
+
+    /// Returns the length of the receive buffer, which defaults to the length
+    /// of the given kind of buffers.
+    pub fn recv_buf_len(&self, default: BufferKind) -> usize {
+        match self.recv_buf_len.load(Ordering::Relaxed) {
+            0 => buffer_len(default),
+            len => len,
+        }
+    }
+
+    /// Sets the length of the receive buffer, clamped between 1 KiB and 16 MiB.
+    pub fn set_recv_buf_len(&self, len: usize) {
+        let len = len.clamp(MIN_BUF_LEN, MAX_BUF_LEN);
+        self.recv_buf_len.store(len, Ordering::Relaxed);
+    }
+
+    /// Returns the length of the send buffer, which defaults to the length of
+    /// the given kind of buffers.
+    pub fn send_buf_len(&self, default: BufferKind) -> usize {
+        match self.send_buf_len.load(Ordering::Relaxed) {
+            0 => buffer_len(default),
+            len => len,
+        }
+    }
+
+    /// Sets the length of the send buffer, clamped between 1 KiB and 16 MiB.
+    pub fn set_send_buf_len(&self, len: usize) {
+        let len = len.clamp(MIN_BUF_LEN, MAX_BUF_LEN);
+        self.send_buf_len.store(len, Ordering::Relaxed);
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout.load()
+    }
+
+    /// Sets the timeout of blocking receives, which must not be zero.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.read_timeout.store(timeout);
+        Ok(())
+    }
+
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout.load()
+    }
+
+    /// Sets the timeout of blocking sends, which must not be zero.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        if timeout == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero timeout");
+        }
+        self.write_timeout.store(timeout);
+        Ok(())
+    }
+
+    pub fn ttl(&self) -> u8 {
+        match self.ttl.load(Ordering::Relaxed) {
+            0 => DEFAULT_TTL,
+            ttl => ttl,
+        }
+    }
+
+    /// Returns the hop limit to set on smoltcp sockets.
+    pub fn hop_limit(&self) -> Option<u8> {
+        match self.ttl.load(Ordering::Relaxed) {
+            0 => None,
+            ttl => Some(ttl),
+        }
+    }
+
+    /// Sets the TTL of outgoing packets, which must not be zero.
+    pub fn set_ttl(&self, ttl: u8) -> AxResult {
+        if ttl == 0 {
+            return ax_err!(InvalidInput, "zero TTL");
+        }
+        self.ttl.store(ttl, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn reuse_addr(&self) -> bool {
+        self.reuse_addr.load(Ordering::Relaxed)
+    }
+
+    pub fn set_reuse_addr(&self, reuse: bool) {
+        self.reuse_addr.store(reuse, Ordering::Relaxed);
+    }
+
+    pub fn nodelay(&self) -> bool {
+        self.nodelay.load(Ordering::Relaxed)
+    }
+
+    pub fn set_nodelay(&self, nodelay: bool) {
+        self.nodelay.store(nodelay, Ordering::Relaxed);
+    }
+
+    pub fn keepalive(&self) -> Option<Duration> {
+        self.keepalive.load()
+    }
+
+    /// Sets the interval of keep-alive packets, which must not be zero.
+    pub fn set_keepalive(&self, interval: Option<Duration>) -> AxResult {
+        if interval == Some(Duration::ZERO) {
+            return ax_err!(InvalidInput, "zero keep-alive interval");
+        }
+        self.keepalive.store(interval);
+        Ok(())
+    }
+
+    pub fn linger(&self) -> Option<Duration> {
+        self.linger.load()
+    }
+
+    pub fn set_linger(&self, linger: Option<Duration>) {
+        self.linger.store(linger);
+    }
+}
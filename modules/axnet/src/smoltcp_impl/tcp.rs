@@ -1,17 +1,28 @@
 use core::cell::UnsafeCell;
 use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use core::task::Waker;
+use core::time::Duration;
 
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axio::PollState;
 use axsync::Mutex;
+use spin::Once;
 
-use smoltcp::iface::SocketHandle;
+use alloc::{sync::Arc, vec::Vec};
+use smoltcp::socket::Socket;
 use smoltcp::socket::tcp::{self, ConnectError, State};
-use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
-
-use super::addr::{UNSPECIFIED_ENDPOINT, from_core_sockaddr, into_core_sockaddr, is_unspecified};
-use super::{ETH0, LISTEN_TABLE, SOCKET_SET, SocketSetWrapper};
+use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
+
+use super::addr::{
+    UNSPECIFIED_ENDPOINT, UNSPECIFIED_IP, UNSPECIFIED_IP6, from_core_sockaddr, into_core_sockaddr,
+    is_unspecified,
+};
+use super::options::{SocketOptions, to_smoltcp_duration};
+use super::wait::SocketWaiter;
+use super::{
+    BufferKind, LISTEN_TABLE, SOCKET_SET, SocketHandle, SocketSetWrapper, is_local_addr, route,
+};
 
 // State transitions:
 // CLOSED -(connect)-> BUSY -> CONNECTING -> CONNECTED -(shutdown)-> BUSY -> CLOSED
@@ -41,6 +52,8 @@ pub struct TcpSocket {
     local_addr: UnsafeCell<IpEndpoint>,
     peer_addr: UnsafeCell<IpEndpoint>,
     nonblock: AtomicBool,
+    waiter: Once<Arc<SocketWaiter>>,
+    opts: SocketOptions,
 }
 
 unsafe impl Sync for TcpSocket {}
@@ -54,6 +67,8 @@ impl TcpSocket {
             local_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             peer_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             nonblock: AtomicBool::new(false),
+            waiter: Once::new(),
+            opts: SocketOptions::new(),
         }
     }
 
@@ -69,6 +84,8 @@ impl TcpSocket {
             local_addr: UnsafeCell::new(local_addr),
             peer_addr: UnsafeCell::new(peer_addr),
             nonblock: AtomicBool::new(false),
+            waiter: Once::new(),
+            opts: SocketOptions::new(),
         }
     }
 
@@ -120,18 +137,26 @@ impl TcpSocket {
     /// The local port is generated automatically.
     pub fn connect(&self, remote_addr: SocketAddr) -> AxResult {
         self.update_state(STATE_CLOSED, STATE_CONNECTING, || {
-            // SAFETY: no other threads can read or write these fields.
-            let handle = unsafe { self.handle.get().read() }
-                .unwrap_or_else(|| SOCKET_SET.add(SocketSetWrapper::new_tcp_socket()));
-
             // TODO: check remote addr unreachable
             let remote_endpoint = from_core_sockaddr(remote_addr);
-            let bound_endpoint = self.bound_endpoint()?;
-            let iface = &ETH0.iface;
+            // SAFETY: no other threads can read or write these fields.
+            let handle = unsafe { self.handle.get().read() }.unwrap_or_else(|| {
+                let (rx_len, tx_len) = self.fix_buffer_lens();
+                let socket = SocketSetWrapper::new_tcp_socket(rx_len, tx_len);
+                SOCKET_SET.add(route(&remote_endpoint.addr), socket)
+            });
+
+            let mut bound_endpoint = self.bound_endpoint()?;
+            if bound_endpoint.addr.is_none() && is_local_addr(&remote_endpoint.addr) {
+                // connect to ourselves from the same address, as Linux does
+                bound_endpoint.addr = Some(remote_endpoint.addr);
+            }
             let (local_endpoint, remote_endpoint) = SOCKET_SET
                 .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                    socket
-                        .connect(iface.lock().context(), remote_endpoint, bound_endpoint)
+                    self.apply_options(socket);
+                    handle
+                        .iface
+                        .with_context(|cx| socket.connect(cx, remote_endpoint, bound_endpoint))
                         .or_else(|e| match e {
                             ConnectError::InvalidState => {
                                 ax_err!(BadState, "socket connect() failed")
@@ -160,7 +185,12 @@ impl TcpSocket {
         if self.is_nonblocking() {
             Err(AxError::WouldBlock)
         } else {
-            self.block_on(|| {
+            // SAFETY: `self.handle` should be initialized above.
+            let handle = unsafe { self.handle.get().read().unwrap() };
+            self.block_on(None, |waker| {
+                SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                    socket.register_send_waker(waker)
+                });
                 let PollState { writable, .. } = self.poll_connect()?;
                 if !writable {
                     Err(AxError::WouldBlock)
@@ -203,13 +233,21 @@ impl TcpSocket {
     ///
     /// It's must be called after [`bind`](Self::bind) and before
     /// [`accept`](Self::accept).
+    ///
+    /// Unless [`set_reuse_address`](Self::set_reuse_address) is enabled, it
+    /// fails with [`AddrInUse`](AxError::AddrInUse) if connections (e.g.,
+    /// those accepted by a previous listener) are still on the port.
     pub fn listen(&self) -> AxResult {
         self.update_state(STATE_CLOSED, STATE_LISTENING, || {
             let bound_endpoint = self.bound_endpoint()?;
+            if !self.opts.reuse_addr() && SOCKET_SET.tcp_port_in_use(bound_endpoint.port) {
+                return ax_err!(AddrInUse, "socket listen() failed");
+            }
             unsafe {
                 (*self.local_addr.get()).port = bound_endpoint.port;
             }
-            LISTEN_TABLE.listen(bound_endpoint)?;
+            let (rx_len, tx_len) = self.fix_buffer_lens();
+            LISTEN_TABLE.listen(bound_endpoint, rx_len, tx_len)?;
             debug!("TCP socket listening on {}", bound_endpoint);
             Ok(())
         })
@@ -229,29 +267,45 @@ impl TcpSocket {
 
         // SAFETY: `self.local_addr` should be initialized after `bind()`.
         let local_port = unsafe { self.local_addr.get().read().port };
-        self.block_on(|| {
+        self.block_on(self.opts.read_timeout(), |waker| {
+            LISTEN_TABLE.register_waker(local_port, waker)?;
             let (handle, (local_addr, peer_addr)) = LISTEN_TABLE.accept(local_port)?;
             debug!("TCP socket accepted a new connection {}", peer_addr);
-            Ok(TcpSocket::new_connected(handle, local_addr, peer_addr))
+            let stream = TcpSocket::new_connected(handle, local_addr, peer_addr);
+            stream.inherit_options(self);
+            Ok(stream)
         })
     }
 
     /// Close the connection.
+    ///
+    /// If [`set_linger`](Self::set_linger) is enabled, it blocks until the
+    /// queued data is sent or the linger time elapses, or resets the connection
+    /// if the linger time is zero.
     pub fn shutdown(&self) -> AxResult {
         // stream
-        self.update_state(STATE_CONNECTED, STATE_CLOSED, || {
-            // SAFETY: `self.handle` should be initialized in a connected socket, and
-            // no other threads can read or write it.
-            let handle = unsafe { self.handle.get().read().unwrap() };
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                debug!("TCP socket {}: shutting down", handle);
-                socket.close();
-            });
-            unsafe { self.local_addr.get().write(UNSPECIFIED_ENDPOINT) }; // clear bound address
-            SOCKET_SET.poll_interfaces();
-            Ok(())
-        })
-        .unwrap_or(Ok(()))?;
+        let linger = self.opts.linger();
+        let closed = self
+            .update_state(STATE_CONNECTED, STATE_CLOSED, || {
+                // SAFETY: `self.handle` should be initialized in a connected socket, and
+                // no other threads can read or write it.
+                let handle = unsafe { self.handle.get().read().unwrap() };
+                SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                    debug!("TCP socket {}: shutting down", handle);
+                    if linger == Some(Duration::ZERO) {
+                        socket.abort();
+                    } else {
+                        socket.close();
+                    }
+                });
+                unsafe { self.local_addr.get().write(UNSPECIFIED_ENDPOINT) }; // clear bound address
+                SOCKET_SET.poll_interfaces();
+                Ok(Some(handle))
+            })
+            .unwrap_or(Ok(None))?;
+        if let (Some(handle), Some(linger)) = (closed, linger.filter(|l| !l.is_zero())) {
+            self.linger_on(handle, linger);
+        }
 
         // listener
         self.update_state(STATE_LISTENING, STATE_CLOSED, || {
@@ -279,8 +333,9 @@ impl TcpSocket {
 
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        self.block_on(|| {
+        self.block_on(self.opts.read_timeout(), |waker| {
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                socket.register_recv_waker(waker);
                 if !socket.is_active() {
                     // not open
                     ax_err!(ConnectionRefused, "socket recv() failed")
@@ -312,8 +367,9 @@ impl TcpSocket {
 
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        self.block_on(|| {
+        self.block_on(self.opts.write_timeout(), |waker| {
             SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                socket.register_send_waker(waker);
                 if !socket.is_active() || !socket.may_send() {
                     // closed by remote
                     ax_err!(ConnectionReset, "socket send() failed")
@@ -346,6 +402,129 @@ impl TcpSocket {
     }
 }
 
+/// Socket options
+impl TcpSocket {
+    /// Returns whether the Nagle algorithm is disabled.
+    pub fn nodelay(&self) -> bool {
+        self.opts.nodelay()
+    }
+
+    /// Disables or enables the Nagle algorithm (`TCP_NODELAY`).
+    pub fn set_nodelay(&self, nodelay: bool) {
+        self.opts.set_nodelay(nodelay);
+        self.update_options();
+    }
+
+    /// Returns the interval of keep-alive packets, or `None` if disabled.
+    pub fn keepalive(&self) -> Option<Duration> {
+        self.opts.keepalive()
+    }
+
+    /// Sets the interval of keep-alive packets sent on an idle connection, or
+    /// disables them with `None` (`SO_KEEPALIVE`).
+    pub fn set_keepalive(&self, interval: Option<Duration>) -> AxResult {
+        self.opts.set_keepalive(interval)?;
+        self.update_options();
+        Ok(())
+    }
+
+    /// Returns the linger time, or `None` if disabled.
+    pub fn linger(&self) -> Option<Duration> {
+        self.opts.linger()
+    }
+
+    /// Sets how long [`shutdown`](Self::shutdown) waits for the queued data
+    /// to be sent (`SO_LINGER`).
+    ///
+    /// With `Some(Duration::ZERO)`, the connection is reset instead. With
+    /// `None`, the data is sent in the background.
+    pub fn set_linger(&self, linger: Option<Duration>) {
+        self.opts.set_linger(linger);
+    }
+
+    /// Returns the length in bytes of the receive buffer.
+    pub fn recv_buffer_size(&self) -> usize {
+        self.opts.recv_buf_len(BufferKind::TcpRecv)
+    }
+
+    /// Sets the length in bytes of the receive buffer (`SO_RCVBUF`), clamped
+    /// between 1 KiB and 16 MiB.
+    ///
+    /// It takes effect on the next [`connect`](Self::connect) or
+    /// [`listen`](Self::listen).
+    pub fn set_recv_buffer_size(&self, len: usize) {
+        self.opts.set_recv_buf_len(len);
+    }
+
+    /// Returns the length in bytes of the send buffer.
+    pub fn send_buffer_size(&self) -> usize {
+        self.opts.send_buf_len(BufferKind::TcpSend)
+    }
+
+    /// Sets the length in bytes of the send buffer (`SO_SNDBUF`), clamped
+    /// between 1 KiB and 16 MiB.
+    ///
+    /// It takes effect on the next [`connect`](Self::connect) or
+    /// [`listen`](Self::listen).
+    pub fn set_send_buffer_size(&self, len: usize) {
+        self.opts.set_send_buf_len(len);
+    }
+
+    /// Returns the timeout of [`recv`](Self::recv) and
+    /// [`accept`](Self::accept), or `None` if they block indefinitely.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.opts.read_timeout()
+    }
+
+    /// Sets the timeout of [`recv`](Self::recv) and [`accept`](Self::accept)
+    /// (`SO_RCVTIMEO`), after which they fail with
+    /// [`WouldBlock`](AxError::WouldBlock).
+    ///
+    /// Returns [`InvalidInput`](AxError::InvalidInput) if the timeout is zero.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        self.opts.set_read_timeout(timeout)
+    }
+
+    /// Returns the timeout of [`send`](Self::send), or `None` if it blocks
+    /// indefinitely.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.opts.write_timeout()
+    }
+
+    /// Sets the timeout of [`send`](Self::send) (`SO_SNDTIMEO`), after which
+    /// it fails with [`WouldBlock`](AxError::WouldBlock).
+    ///
+    /// Returns [`InvalidInput`](AxError::InvalidInput) if the timeout is zero.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        self.opts.set_write_timeout(timeout)
+    }
+
+    /// Returns the TTL (or hop limit) of outgoing packets.
+    pub fn ttl(&self) -> u8 {
+        self.opts.ttl()
+    }
+
+    /// Sets the TTL (or hop limit) of outgoing packets (`IP_TTL`).
+    ///
+    /// Returns [`InvalidInput`](AxError::InvalidInput) if the TTL is zero.
+    pub fn set_ttl(&self, ttl: u8) -> AxResult {
+        self.opts.set_ttl(ttl)?;
+        self.update_options();
+        Ok(())
+    }
+
+    /// Returns whether the local address can be reused.
+    pub fn reuse_address(&self) -> bool {
+        self.opts.reuse_addr()
+    }
+
+    /// Allows [`listen`](Self::listen) on a port that connections are still
+    /// on (`SO_REUSEADDR`).
+    pub fn set_reuse_address(&self, reuse: bool) {
+        self.opts.set_reuse_addr(reuse);
+    }
+}
+
 /// Private methods
 impl TcpSocket {
     #[inline]
@@ -469,28 +648,74 @@ impl TcpSocket {
         })
     }
 
-    /// Block the current thread until the given function completes or fails.
+    /// Block the current thread until the given function completes or fails,
+    /// or the timeout elapses.
     ///
     /// If the socket is non-blocking, it calls the function once and returns
     /// immediately. Otherwise, it may call the function multiple times if it
-    /// returns [`Err(WouldBlock)`](AxError::WouldBlock).
-    fn block_on<F, T>(&self, mut f: F) -> AxResult<T>
+    /// returns [`Err(WouldBlock)`](AxError::WouldBlock), sleeping until the
+    /// waker passed to the function is woken.
+    fn block_on<F, T>(&self, timeout: Option<Duration>, f: F) -> AxResult<T>
     where
-        F: FnMut() -> AxResult<T>,
+        F: FnMut(&Waker) -> AxResult<T>,
     {
-        if self.is_nonblocking() {
-            f()
-        } else {
-            loop {
-                SOCKET_SET.poll_interfaces();
-                match f() {
-                    Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
-                    Err(e) => return Err(e),
+        self.waiter
+            .call_once(SocketWaiter::new)
+            .block_on(self.is_nonblocking(), timeout, f)
+    }
+
+    /// Waits at most `timeout` for the data queued in the closed connection to
+    /// be sent, regardless of the nonblocking mode.
+    fn linger_on(&self, handle: SocketHandle, timeout: Duration) {
+        let waiter = self.waiter.call_once(SocketWaiter::new);
+        let res = waiter.block_on(false, Some(timeout), |waker| {
+            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                socket.register_send_waker(waker);
+                if socket.send_queue() == 0 || !socket.is_active() {
+                    Ok(())
+                } else {
+                    Err(AxError::WouldBlock)
                 }
-            }
+            })
+        });
+        if res.is_err() {
+            debug!("TCP socket {}: linger timed out", handle);
+        }
+    }
+
+    /// Fixes the lengths of the receive and send buffers, as the smoltcp
+    /// socket is about to be created.
+    fn fix_buffer_lens(&self) -> (usize, usize) {
+        let rx_len = self.opts.recv_buf_len(BufferKind::TcpRecv);
+        let tx_len = self.opts.send_buf_len(BufferKind::TcpSend);
+        self.opts.set_recv_buf_len(rx_len);
+        self.opts.set_send_buf_len(tx_len);
+        (rx_len, tx_len)
+    }
+
+    /// Applies the options to the smoltcp socket.
+    fn apply_options(&self, socket: &mut tcp::Socket) {
+        socket.set_nagle_enabled(!self.opts.nodelay());
+        socket.set_keep_alive(self.opts.keepalive().map(to_smoltcp_duration));
+        socket.set_hop_limit(self.opts.hop_limit());
+    }
+
+    /// Applies the options to the smoltcp socket if connected, after they are
+    /// changed.
+    fn update_options(&self) {
+        if matches!(self.get_state(), STATE_CONNECTING | STATE_CONNECTED) {
+            // SAFETY: `self.handle` should be initialized in a connected socket.
+            let handle = unsafe { self.handle.get().read().unwrap() };
+            SOCKET_SET
+                .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| self.apply_options(socket));
         }
     }
+
+    /// Copies the options of the listening socket to the accepted one.
+    fn inherit_options(&self, listener: &TcpSocket) {
+        self.opts.copy_from(&listener.opts);
+        self.update_options();
+    }
 }
 
 impl Drop for TcpSocket {
@@ -525,3 +750,70 @@ fn get_ephemeral_port() -> AxResult<u16> {
     }
     ax_err!(AddrInUse, "no avaliable ports!")
 }
+
+/// Information of a TCP connection or listening socket, as shown in
+/// `/proc/net/tcp`.
+#[derive(Debug, Clone, Copy)]
+pub struct TcpSocketInfo {
+    /// The local address.
+    pub local_addr: SocketAddr,
+    /// The remote address, which is unspecified for listening sockets.
+    pub peer_addr: SocketAddr,
+    /// The state, numbered as the `TCP_*` states of Linux (e.g., `1` for
+    /// `ESTABLISHED`, `10` for `LISTEN`).
+    pub state: u8,
+    /// The number of bytes in the send queue.
+    pub tx_queue: usize,
+    /// The number of bytes in the receive queue.
+    pub rx_queue: usize,
+}
+
+/// Returns the information of all TCP sockets, listening sockets first.
+pub fn tcp_socket_table() -> Vec<TcpSocketInfo> {
+    const TCP_LISTEN: u8 = 10;
+    let mut table: Vec<_> = LISTEN_TABLE
+        .listen_endpoints()
+        .into_iter()
+        .map(|endpoint| {
+            let local_ip = endpoint.addr.unwrap_or(UNSPECIFIED_IP);
+            let peer_ip = match local_ip {
+                IpAddress::Ipv4(_) => UNSPECIFIED_IP,
+                IpAddress::Ipv6(_) => UNSPECIFIED_IP6,
+            };
+            TcpSocketInfo {
+                local_addr: into_core_sockaddr(IpEndpoint::new(local_ip, endpoint.port)),
+                peer_addr: into_core_sockaddr(IpEndpoint::new(peer_ip, 0)),
+                state: TCP_LISTEN,
+                tx_queue: 0,
+                rx_queue: 0,
+            }
+        })
+        .collect();
+    SOCKET_SET.for_each_socket(|socket| {
+        let Socket::Tcp(socket) = socket else {
+            return;
+        };
+        let state = match socket.state() {
+            State::Established => 1,
+            State::SynSent => 2,
+            State::SynReceived => 3,
+            State::FinWait1 => 4,
+            State::FinWait2 => 5,
+            State::TimeWait => 6,
+            State::Closed => 7,
+            State::CloseWait => 8,
+            State::LastAck => 9,
+            // waiting in the SYN queue, whose listening socket is listed above
+            State::Listen => return,
+            State::Closing => 11,
+        };
+        table.push(TcpSocketInfo {
+            local_addr: into_core_sockaddr(socket.local_endpoint().unwrap_or(UNSPECIFIED_ENDPOINT)),
+            peer_addr: into_core_sockaddr(socket.remote_endpoint().unwrap_or(UNSPECIFIED_ENDPOINT)),
+            state,
+            tx_queue: socket.send_queue(),
+            rx_queue: socket.recv_queue(),
+        });
+    });
+    table
+}
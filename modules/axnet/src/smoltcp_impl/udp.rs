@@ -1,37 +1,57 @@
+use alloc::{sync::Arc, vec::Vec};
 use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Waker;
+use core::time::Duration;
 
 use axerrno::{AxError, AxResult, ax_err, ax_err_type};
 use axio::PollState;
 use axsync::Mutex;
 use spin::RwLock;
 
-use smoltcp::iface::SocketHandle;
 use smoltcp::socket::udp::{self, BindError, SendError};
-use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
+use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
 use super::addr::{UNSPECIFIED_ENDPOINT, from_core_sockaddr, into_core_sockaddr, is_unspecified};
-use super::{SOCKET_SET, SocketSetWrapper};
+use super::options::SocketOptions;
+use super::wait::SocketWaiter;
+use super::{BufferKind, Iface, SOCKET_SET, SocketHandle, SocketSetWrapper, route};
 
 /// A UDP socket that provides POSIX-like APIs.
 pub struct UdpSocket {
-    handle: SocketHandle,
+    /// The smoltcp sockets of all interfaces, which are bound to the same
+    /// endpoint, so that datagrams are received from any of them. `lo` comes
+    /// first.
+    handles: Vec<SocketHandle>,
     local_addr: RwLock<Option<IpEndpoint>>,
     peer_addr: RwLock<Option<IpEndpoint>>,
     nonblock: AtomicBool,
+    waiter: Arc<SocketWaiter>,
+    opts: SocketOptions,
 }
 
 impl UdpSocket {
     /// Creates a new UDP socket.
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        let socket = SocketSetWrapper::new_udp_socket();
-        let handle = SOCKET_SET.add(socket);
+        let opts = SocketOptions::new();
+        let rx_len = opts.recv_buf_len(BufferKind::UdpRecv);
+        let tx_len = opts.send_buf_len(BufferKind::UdpSend);
+        opts.set_recv_buf_len(rx_len);
+        opts.set_send_buf_len(tx_len);
+        let handles = Iface::all()
+            .map(|iface| {
+                let socket = SocketSetWrapper::new_udp_socket(rx_len, tx_len);
+                SOCKET_SET.add(iface, socket)
+            })
+            .collect();
         Self {
-            handle,
+            handles,
             local_addr: RwLock::new(None),
             peer_addr: RwLock::new(None),
             nonblock: AtomicBool::new(false),
+            waiter: SocketWaiter::new(),
+            opts,
         }
     }
 
@@ -88,15 +108,17 @@ impl UdpSocket {
             addr: (!is_unspecified(local_endpoint.addr)).then_some(local_endpoint.addr),
             port: local_endpoint.port,
         };
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            socket.bind(endpoint).or_else(|e| match e {
-                BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
-                BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
-            })
-        })?;
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                socket.bind(endpoint).or_else(|e| match e {
+                    BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
+                    BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
+                })
+            })?;
+        }
 
         *self_local_addr = Some(local_endpoint);
-        debug!("UDP socket {}: bound on {}", self.handle, endpoint);
+        debug!("UDP socket {}: bound on {}", self.handles[0], endpoint);
         Ok(())
     }
 
@@ -142,7 +164,7 @@ impl UdpSocket {
         }
 
         *self_peer_addr = Some(from_core_sockaddr(addr));
-        debug!("UDP socket {}: connected to {}", self.handle, addr);
+        debug!("UDP socket {}: connected to {}", self.handles[0], addr);
         Ok(())
     }
 
@@ -154,6 +176,8 @@ impl UdpSocket {
 
     /// Receives a single datagram message on the socket from the remote address
     /// to which it is connected. On success, returns the number of bytes read.
+    ///
+    /// Datagrams from other addresses are discarded.
     pub fn recv(&self, buf: &mut [u8]) -> AxResult<usize> {
         let remote_endpoint = self.remote_endpoint()?;
         self.recv_impl(|socket| {
@@ -172,10 +196,10 @@ impl UdpSocket {
 
     /// Close the socket.
     pub fn shutdown(&self) -> AxResult {
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            debug!("UDP socket {}: shutting down", self.handle);
-            socket.close();
-        });
+        debug!("UDP socket {}: shutting down", self.handles[0]);
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| socket.close());
+        }
         SOCKET_SET.poll_interfaces();
         Ok(())
     }
@@ -188,12 +212,105 @@ impl UdpSocket {
                 writable: false,
             });
         }
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            Ok(PollState {
-                readable: socket.can_recv(),
-                writable: socket.can_send(),
-            })
-        })
+        let mut state = PollState {
+            readable: false,
+            writable: true,
+        };
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket::<udp::Socket, _, _>(handle, |socket| {
+                state.readable |= socket.can_recv();
+                state.writable &= socket.can_send();
+            });
+        }
+        Ok(state)
+    }
+}
+
+/// Socket options
+impl UdpSocket {
+    /// Returns the length in bytes of the receive buffer.
+    pub fn recv_buffer_size(&self) -> usize {
+        self.opts.recv_buf_len(BufferKind::UdpRecv)
+    }
+
+    /// Sets the length in bytes of the receive buffer (`SO_RCVBUF`), clamped
+    /// between 1 KiB and 16 MiB.
+    ///
+    /// Datagrams queued in the socket are discarded.
+    pub fn set_recv_buffer_size(&self, len: usize) -> AxResult {
+        self.opts.set_recv_buf_len(len);
+        self.rebuild_socket()
+    }
+
+    /// Returns the length in bytes of the send buffer.
+    pub fn send_buffer_size(&self) -> usize {
+        self.opts.send_buf_len(BufferKind::UdpSend)
+    }
+
+    /// Sets the length in bytes of the send buffer (`SO_SNDBUF`), clamped
+    /// between 1 KiB and 16 MiB.
+    ///
+    /// Datagrams queued in the socket are discarded.
+    pub fn set_send_buffer_size(&self, len: usize) -> AxResult {
+        self.opts.set_send_buf_len(len);
+        self.rebuild_socket()
+    }
+
+    /// Returns the timeout of receives, or `None` if they block indefinitely.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.opts.read_timeout()
+    }
+
+    /// Sets the timeout of receives (`SO_RCVTIMEO`), after which they fail
+    /// with [`WouldBlock`](AxError::WouldBlock).
+    ///
+    /// Returns [`InvalidInput`](AxError::InvalidInput) if the timeout is zero.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        self.opts.set_read_timeout(timeout)
+    }
+
+    /// Returns the timeout of sends, or `None` if they block indefinitely.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.opts.write_timeout()
+    }
+
+    /// Sets the timeout of sends (`SO_SNDTIMEO`), after which they fail with
+    /// [`WouldBlock`](AxError::WouldBlock).
+    ///
+    /// Returns [`InvalidInput`](AxError::InvalidInput) if the timeout is zero.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> AxResult {
+        self.opts.set_write_timeout(timeout)
+    }
+
+    /// Returns the TTL (or hop limit) of outgoing packets.
+    pub fn ttl(&self) -> u8 {
+        self.opts.ttl()
+    }
+
+    /// Sets the TTL (or hop limit) of outgoing packets (`IP_TTL`).
+    ///
+    /// Returns [`InvalidInput`](AxError::InvalidInput) if the TTL is zero.
+    pub fn set_ttl(&self, ttl: u8) -> AxResult {
+        self.opts.set_ttl(ttl)?;
+        for &handle in &self.handles {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                socket.set_hop_limit(self.opts.hop_limit())
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns whether the local address can be reused.
+    pub fn reuse_address(&self) -> bool {
+        self.opts.reuse_addr()
+    }
+
+    /// Sets whether the local address can be reused (`SO_REUSEADDR`).
+    ///
+    /// It has no effect yet, as the ports of UDP sockets are not checked for
+    /// conflicts.
+    pub fn set_reuse_address(&self, reuse: bool) {
+        self.opts.set_reuse_addr(reuse);
     }
 }
 
@@ -206,13 +323,23 @@ impl UdpSocket {
         }
     }
 
+    /// Returns the socket of the interface that datagrams to `addr` are
+    /// routed through.
+    fn handle_to(&self, addr: &IpAddress) -> SocketHandle {
+        let iface = route(addr);
+        let handle = self.handles.iter().find(|handle| handle.iface == iface);
+        *handle.unwrap_or(&self.handles[0])
+    }
+
     fn send_impl(&self, buf: &[u8], remote_endpoint: IpEndpoint) -> AxResult<usize> {
         if self.local_addr.read().is_none() {
             return ax_err!(NotConnected, "socket send() failed");
         }
 
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+        let handle = self.handle_to(&remote_endpoint.addr);
+        self.block_on(self.opts.write_timeout(), |waker| {
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                socket.register_send_waker(waker);
                 if socket.can_send() {
                     socket
                         .send_slice(buf, remote_endpoint)
@@ -231,6 +358,31 @@ impl UdpSocket {
         })
     }
 
+    /// Replaces the smoltcp sockets with ones of the new buffer lengths, bound
+    /// to the same endpoint.
+    fn rebuild_socket(&self) -> AxResult {
+        let rx_len = self.opts.recv_buf_len(BufferKind::UdpRecv);
+        let tx_len = self.opts.send_buf_len(BufferKind::UdpSend);
+        for &handle in &self.handles {
+            let mut new = SocketSetWrapper::new_udp_socket(rx_len, tx_len);
+            new.set_hop_limit(self.opts.hop_limit());
+            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                let endpoint = socket.endpoint();
+                if endpoint.port != 0 {
+                    new.bind(endpoint)
+                        .map_err(|_| ax_err_type!(BadState, "socket rebuild failed"))?;
+                }
+                *socket = new;
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Calls `op` on the socket of any interface to receive a queued datagram.
+    ///
+    /// `op` may return [`Err(WouldBlock)`](AxError::WouldBlock) to discard the
+    /// datagram it received, then it is called again on the next one.
     fn recv_impl<F, T>(&self, mut op: F) -> AxResult<T>
     where
         F: FnMut(&mut udp::Socket) -> AxResult<T>,
@@ -239,42 +391,45 @@ impl UdpSocket {
             return ax_err!(NotConnected, "socket send() failed");
         }
 
-        self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-                if socket.can_recv() {
+        self.block_on(self.opts.read_timeout(), |waker| {
+            for &handle in &self.handles {
+                let res = SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(handle, |socket| {
+                    socket.register_recv_waker(waker);
                     // data available
-                    op(socket)
-                } else {
+                    while socket.can_recv() {
+                        match op(socket) {
+                            Err(AxError::WouldBlock) => continue,
+                            res => return res,
+                        }
+                    }
                     // no more data
                     Err(AxError::WouldBlock)
+                });
+                if !matches!(res, Err(AxError::WouldBlock)) {
+                    return res;
                 }
-            })
+            }
+            Err(AxError::WouldBlock)
         })
     }
 
-    fn block_on<F, T>(&self, mut f: F) -> AxResult<T>
+    /// Block the current thread until the given function completes or fails.
+    ///
+    /// See `SocketWaiter::block_on` for details.
+    fn block_on<F, T>(&self, timeout: Option<Duration>, f: F) -> AxResult<T>
     where
-        F: FnMut() -> AxResult<T>,
+        F: FnMut(&Waker) -> AxResult<T>,
     {
-        if self.is_nonblocking() {
-            f()
-        } else {
-            loop {
-                SOCKET_SET.poll_interfaces();
-                match f() {
-                    Ok(t) => return Ok(t),
-                    Err(AxError::WouldBlock) => axtask::yield_now(),
-                    Err(e) => return Err(e),
-                }
-            }
-        }
+        self.waiter.block_on(self.is_nonblocking(), timeout, f)
     }
 }
 
 impl Drop for UdpSocket {
     fn drop(&mut self) {
         self.shutdown().ok();
-        SOCKET_SET.remove(self.handle);
+        for &handle in &self.handles {
+            SOCKET_SET.remove(handle);
+        }
     }
 }
 
@@ -0,0 +1,197 @@
+//! Blocking on sockets, and polling the interface in the background.
+//!
+//! With both the `multitask` and `irq` features, a task blocked on a socket
+//! sleeps until the smoltcp waker registered on the socket is woken by a poll,
+//! and the interface is polled by the `net-poll` task, which sleeps until the
+//! NIC raises an interrupt or the next timer of smoltcp expires. So an idle
+//! network stack takes no CPU time.
+//!
+//! Otherwise, blocked tasks poll the interface and yield in a loop.
+
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Waker;
+use core::time::Duration;
+
+use axerrno::{AxError, AxResult};
+use axhal::time::wall_time;
+
+use super::SOCKET_SET;
+
+#[cfg(all(feature = "multitask", feature = "irq"))]
+pub use self::poller::{init, poll_soon};
+
+/// What tasks blocked on a socket wait for.
+///
+/// Its [`Waker`] is registered on the smoltcp sockets that the blocked
+/// operation depends on.
+pub struct SocketWaiter {
+    woken: AtomicBool,
+    #[cfg(all(feature = "multitask", feature = "irq"))]
+    wq: axtask::WaitQueue,
+}
+
+impl SocketWaiter {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            woken: AtomicBool::new(false),
+            #[cfg(all(feature = "multitask", feature = "irq"))]
+            wq: axtask::WaitQueue::new(),
+        })
+    }
+
+    /// Calls `f` with the waker to register, until it completes or fails.
+    ///
+    /// If `nonblock` is true, it calls the function once and returns
+    /// immediately. Otherwise, it blocks the current task while the function
+    /// returns [`Err(WouldBlock)`](AxError::WouldBlock), until the registered
+    /// waker is woken, or until `timeout` elapses (then it returns
+    /// `Err(WouldBlock)` too).
+    ///
+    /// To not miss the wakeup, `f` should register the waker before (or while
+    /// holding the lock of the socket set) checking whether it would block.
+    pub fn block_on<F, T>(
+        self: &Arc<Self>,
+        nonblock: bool,
+        timeout: Option<Duration>,
+        mut f: F,
+    ) -> AxResult<T>
+    where
+        F: FnMut(&Waker) -> AxResult<T>,
+    {
+        let waker = Waker::from(self.clone());
+        let deadline = timeout.map(|timeout| wall_time() + timeout);
+        let res = if nonblock {
+            f(&waker)
+        } else {
+            loop {
+                SOCKET_SET.poll_interfaces();
+                self.woken.store(false, Ordering::Release);
+                match f(&waker) {
+                    Err(AxError::WouldBlock) => match deadline {
+                        Some(deadline) => {
+                            let now = wall_time();
+                            if now >= deadline {
+                                break Err(AxError::WouldBlock);
+                            }
+                            self.wait(Some(deadline - now));
+                        }
+                        None => self.wait(None),
+                    },
+                    res => break res,
+                }
+            }
+        };
+        // flush what `f` may have queued without waiting for the next timer
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        poll_soon();
+        res
+    }
+
+    #[allow(unused_variables)]
+    fn wait(&self, timeout: Option<Duration>) {
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        {
+            let woken = || self.woken.load(Ordering::Acquire);
+            match timeout {
+                Some(timeout) => {
+                    self.wq.wait_timeout_until(timeout, woken);
+                }
+                None => self.wq.wait_until(woken),
+            }
+        }
+        #[cfg(not(all(feature = "multitask", feature = "irq")))]
+        axtask::yield_now();
+    }
+}
+
+impl Wake for SocketWaiter {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        #[cfg(all(feature = "multitask", feature = "irq"))]
+        self.wq.notify_all(false);
+    }
+}
+
+/// Does nothing, as blocked tasks poll the interface by themselves without the
+/// `multitask` and `irq` features.
+#[cfg(not(all(feature = "multitask", feature = "irq")))]
+pub fn init(_net_irq: Option<axdriver::NetIrq>) {}
+
+#[cfg(all(feature = "multitask", feature = "irq"))]
+mod poller {
+    use alloc::string::String;
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use core::time::Duration;
+
+    use axdriver::NetIrq;
+    use axtask::WaitQueue;
+    use lazyinit::LazyInit;
+
+    use super::SOCKET_SET;
+
+    /// The interval of polling if the NIC raises no interrupt.
+    const NO_IRQ_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    const TASK_STACK_SIZE: usize = 0x8000;
+
+    static NET_IRQ: LazyInit<NetIrq> = LazyInit::new();
+    static POLL_WQ: WaitQueue = WaitQueue::new();
+    static POLL_PENDING: AtomicBool = AtomicBool::new(false);
+
+    /// Requests the `net-poll` task to poll the interface now.
+    pub fn poll_soon() {
+        POLL_PENDING.store(true, Ordering::Release);
+        POLL_WQ.notify_one(false);
+    }
+
+    fn net_irq_handler() {
+        if NET_IRQ.ack() {
+            poll_soon();
+        }
+    }
+
+    /// Registers the interrupt handler of the NIC, if it raises interrupts, and
+    /// spawns the `net-poll` task.
+    pub fn init(net_irq: Option<NetIrq>) {
+        let has_irq = match net_irq {
+            Some(irq) => {
+                NET_IRQ.init_once(irq);
+                let ok = axhal::irq::register_handler(irq.irq_num, net_irq_handler);
+                if ok {
+                    info!("  irq:      {}", irq.irq_num);
+                } else {
+                    warn!("failed to register the NIC interrupt, fall back to polling");
+                }
+                ok
+            }
+            None => false,
+        };
+
+        axtask::spawn_raw(
+            move || loop {
+                SOCKET_SET.poll_interfaces();
+                let delay = SOCKET_SET.poll_delay();
+                let delay = if has_irq {
+                    delay
+                } else {
+                    Some(delay.map_or(NO_IRQ_POLL_INTERVAL, |d| d.min(NO_IRQ_POLL_INTERVAL)))
+                };
+                let pending = || POLL_PENDING.swap(false, Ordering::AcqRel);
+                match delay {
+                    Some(delay) => {
+                        POLL_WQ.wait_timeout_until(delay, pending);
+                    }
+                    None => POLL_WQ.wait_until(pending),
+                }
+            },
+            String::from("net-poll"),
+            TASK_STACK_SIZE,
+        );
+    }
+}
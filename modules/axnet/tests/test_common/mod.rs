@@ -0,0 +1,110 @@
+// not every test uses all of them
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+
+use axdriver::prelude::*;
+use axdriver_net::{EthernetAddress, NetBufPtr};
+
+/// Handles a frame sent by the stack, and returns the frames to reply.
+pub type FrameHandler = Box<dyn FnMut(&[u8]) -> Vec<Vec<u8>> + Send + Sync>;
+
+/// The frames to be received by the stack.
+pub type RxQueue = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+/// A NIC that passes the frames sent by the stack to a handler, which plays
+/// the rest of the network, and receives the frames that it replies.
+pub struct FakeNic {
+    mac: [u8; 6],
+    handler: FrameHandler,
+    rx_queue: RxQueue,
+}
+
+impl FakeNic {
+    pub fn new(mac: [u8; 6], handler: FrameHandler) -> Self {
+        Self {
+            mac,
+            handler,
+            rx_queue: RxQueue::default(),
+        }
+    }
+
+    /// Returns the queue of received frames, to inject frames that are not
+    /// replies.
+    pub fn rx_queue(&self) -> RxQueue {
+        self.rx_queue.clone()
+    }
+}
+
+/// Allocates a buffer of the frame, which is freed by [`free_buf`].
+fn alloc_buf(frame: Vec<u8>) -> NetBufPtr {
+    let mut frame = Box::new(frame);
+    let buf_ptr = NonNull::new(frame.as_mut_ptr()).unwrap();
+    let len = frame.len();
+    let raw_ptr = NonNull::new(Box::into_raw(frame) as *mut u8).unwrap();
+    NetBufPtr::new(raw_ptr, buf_ptr, len)
+}
+
+fn free_buf(buf: NetBufPtr) {
+    drop(unsafe { Box::from_raw(buf.raw_ptr::<Vec<u8>>()) });
+}
+
+impl BaseDriverOps for FakeNic {
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Net
+    }
+
+    fn device_name(&self) -> &str {
+        "fake-net"
+    }
+}
+
+impl NetDriverOps for FakeNic {
+    fn mac_address(&self) -> EthernetAddress {
+        EthernetAddress(self.mac)
+    }
+
+    fn can_transmit(&self) -> bool {
+        true
+    }
+
+    fn can_receive(&self) -> bool {
+        !self.rx_queue.lock().unwrap().is_empty()
+    }
+
+    fn rx_queue_size(&self) -> usize {
+        64
+    }
+
+    fn tx_queue_size(&self) -> usize {
+        64
+    }
+
+    fn recycle_rx_buffer(&mut self, rx_buf: NetBufPtr) -> DevResult {
+        free_buf(rx_buf);
+        Ok(())
+    }
+
+    fn recycle_tx_buffers(&mut self) -> DevResult {
+        Ok(())
+    }
+
+    fn transmit(&mut self, tx_buf: NetBufPtr) -> DevResult {
+        let replies = (self.handler)(tx_buf.packet());
+        self.rx_queue.lock().unwrap().extend(replies);
+        free_buf(tx_buf);
+        Ok(())
+    }
+
+    fn receive(&mut self) -> DevResult<NetBufPtr> {
+        let frame = self.rx_queue.lock().unwrap().pop_front();
+        let frame = frame.ok_or(DevError::Again)?;
+        Ok(alloc_buf(frame))
+    }
+
+    fn alloc_tx_buffer(&mut self, size: usize) -> DevResult<NetBufPtr> {
+        Ok(alloc_buf(vec![0; size]))
+    }
+}
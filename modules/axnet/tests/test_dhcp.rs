@@ -0,0 +1,122 @@
+#![cfg(feature = "dhcp")]
+
+mod test_common;
+
+use core::net::{IpAddr, Ipv4Addr};
+
+use axdriver::{AxDeviceContainer, AxNetDevice};
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::wire::{
+    DHCP_CLIENT_PORT, DHCP_SERVER_PORT, DhcpMessageType, DhcpPacket, DhcpRepr, EthernetAddress,
+    EthernetFrame, EthernetProtocol, EthernetRepr, IpProtocol, Ipv4Address, Ipv4Packet, Ipv4Repr,
+    UdpPacket, UdpRepr,
+};
+use test_common::FakeNic;
+
+const MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+const SERVER_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x00, 0x00, 0x02];
+const SERVER: Ipv4Address = Ipv4Address::new(192, 168, 7, 1);
+const LEASED: Ipv4Address = Ipv4Address::new(192, 168, 7, 100);
+
+/// Answers a DHCP discover with an offer, and a request with an ack, of
+/// `LEASED` for an hour.
+fn dhcp_server(frame: &[u8]) -> Option<Vec<u8>> {
+    let frame = EthernetFrame::new_checked(frame).ok()?;
+    if frame.ethertype() != EthernetProtocol::Ipv4 {
+        return None;
+    }
+    let packet = Ipv4Packet::new_checked(frame.payload()).ok()?;
+    if packet.next_header() != IpProtocol::Udp {
+        return None;
+    }
+    let udp_packet = UdpPacket::new_checked(packet.payload()).ok()?;
+    if udp_packet.dst_port() != DHCP_SERVER_PORT {
+        return None;
+    }
+    let dhcp_packet = DhcpPacket::new_checked(udp_packet.payload()).ok()?;
+    let request = DhcpRepr::parse(&dhcp_packet).ok()?;
+    let message_type = match request.message_type {
+        DhcpMessageType::Discover => DhcpMessageType::Offer,
+        DhcpMessageType::Request => DhcpMessageType::Ack,
+        _ => return None,
+    };
+    let dhcp_repr = DhcpRepr {
+        message_type,
+        your_ip: LEASED,
+        server_ip: SERVER,
+        router: Some(SERVER),
+        subnet_mask: Some(Ipv4Address::new(255, 255, 255, 0)),
+        requested_ip: None,
+        client_identifier: None,
+        server_identifier: Some(SERVER),
+        parameter_request_list: None,
+        max_size: None,
+        lease_duration: Some(3600),
+        ..request
+    };
+
+    // broadcast the reply, as the client has no address yet
+    let udp_repr = UdpRepr {
+        src_port: DHCP_SERVER_PORT,
+        dst_port: DHCP_CLIENT_PORT,
+    };
+    let ip_repr = Ipv4Repr {
+        src_addr: SERVER,
+        dst_addr: Ipv4Address::BROADCAST,
+        next_header: IpProtocol::Udp,
+        payload_len: udp_repr.header_len() + dhcp_repr.buffer_len(),
+        hop_limit: 64,
+    };
+    let eth_repr = EthernetRepr {
+        src_addr: EthernetAddress(SERVER_MAC),
+        dst_addr: EthernetAddress::BROADCAST,
+        ethertype: EthernetProtocol::Ipv4,
+    };
+
+    let len = eth_repr.buffer_len() + ip_repr.buffer_len() + ip_repr.payload_len;
+    let mut buf = vec![0; len];
+    let mut frame = EthernetFrame::new_unchecked(&mut buf);
+    eth_repr.emit(&mut frame);
+    let mut packet = Ipv4Packet::new_unchecked(frame.payload_mut());
+    ip_repr.emit(&mut packet, &ChecksumCapabilities::default());
+    let mut udp_packet = UdpPacket::new_unchecked(packet.payload_mut());
+    udp_repr.emit(
+        &mut udp_packet,
+        &SERVER.into(),
+        &Ipv4Address::BROADCAST.into(),
+        dhcp_repr.buffer_len(),
+        |buf| dhcp_repr.emit(&mut DhcpPacket::new_unchecked(buf)).unwrap(),
+        &ChecksumCapabilities::default(),
+    );
+    Some(buf)
+}
+
+#[test]
+fn test_dhcp() {
+    println!("Testing DHCP ...");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+
+    let nic = FakeNic::new(
+        MAC,
+        Box::new(|frame: &[u8]| dhcp_server(frame).into_iter().collect()),
+    );
+    let nic: AxNetDevice = Box::new(nic);
+    // returns at once rather than waiting for the lease
+    axnet::init_network(AxDeviceContainer::from_one(nic), None);
+    assert_eq!(axnet::ipv4_config(), None);
+
+    // discover, offer, request and ack
+    for _ in 0..4 {
+        if axnet::ipv4_config().is_some() {
+            break;
+        }
+        axnet::poll_interfaces();
+    }
+    let config = axnet::ipv4_config().expect("no lease");
+    println!("{:?}", config);
+    assert!(config.dhcp);
+    assert_eq!(config.address, Ipv4Addr::from(LEASED.0));
+    assert_eq!(config.prefix_len, 24);
+    assert_eq!(config.gateway, Some(Ipv4Addr::from(SERVER.0)));
+    assert_eq!(config.dns_servers, Vec::<IpAddr>::new());
+}
@@ -0,0 +1,132 @@
+#![cfg(not(feature = "dhcp"))]
+
+mod test_common;
+
+use core::net::Ipv6Addr;
+
+use axdriver::{AxDeviceContainer, AxNetDevice};
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::time::Duration;
+use smoltcp::wire::{
+    EthernetAddress, EthernetFrame, EthernetProtocol, EthernetRepr, Icmpv6Message, Icmpv6Packet,
+    Icmpv6Repr, IpProtocol, Ipv6Address, Ipv6Packet, Ipv6Repr, NdiscPrefixInfoFlags,
+    NdiscPrefixInformation, NdiscRepr, NdiscRouterFlags, RawHardwareAddress,
+};
+use test_common::FakeNic;
+
+const MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+const ROUTER_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x00, 0x00, 0x01];
+const ROUTER: Ipv6Address = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+const PREFIX: Ipv6Address = Ipv6Address::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0);
+
+/// Returns whether the frame is a router solicitation.
+fn is_router_solicit(frame: &[u8]) -> bool {
+    let Ok(frame) = EthernetFrame::new_checked(frame) else {
+        return false;
+    };
+    if frame.ethertype() != EthernetProtocol::Ipv6 {
+        return false;
+    }
+    let Ok(packet) = Ipv6Packet::new_checked(frame.payload()) else {
+        return false;
+    };
+    packet.next_header() == IpProtocol::Icmpv6
+        && Icmpv6Packet::new_checked(packet.payload())
+            .is_ok_and(|icmp| icmp.msg_type() == Icmpv6Message::RouterSolicit)
+}
+
+/// Builds a router advertisement of `PREFIX` to all nodes.
+fn router_advert() -> Vec<u8> {
+    let icmp_repr = Icmpv6Repr::Ndisc(NdiscRepr::RouterAdvert {
+        hop_limit: 64,
+        flags: NdiscRouterFlags::empty(),
+        router_lifetime: Duration::from_secs(1800),
+        reachable_time: Duration::ZERO,
+        retrans_time: Duration::ZERO,
+        lladdr: Some(RawHardwareAddress::from(EthernetAddress(ROUTER_MAC))),
+        mtu: None,
+        prefix_info: Some(NdiscPrefixInformation {
+            prefix_len: 64,
+            flags: NdiscPrefixInfoFlags::ON_LINK | NdiscPrefixInfoFlags::ADDRCONF,
+            valid_lifetime: Duration::from_secs(3600),
+            preferred_lifetime: Duration::from_secs(1800),
+            prefix: PREFIX,
+        }),
+    });
+    let ip_repr = Ipv6Repr {
+        src_addr: ROUTER,
+        dst_addr: Ipv6Address::LINK_LOCAL_ALL_NODES,
+        next_header: IpProtocol::Icmpv6,
+        payload_len: icmp_repr.buffer_len(),
+        hop_limit: 255,
+    };
+    let eth_repr = EthernetRepr {
+        src_addr: EthernetAddress(ROUTER_MAC),
+        dst_addr: EthernetAddress([0x33, 0x33, 0, 0, 0, 1]),
+        ethertype: EthernetProtocol::Ipv6,
+    };
+
+    let len = eth_repr.buffer_len() + ip_repr.buffer_len() + icmp_repr.buffer_len();
+    let mut buf = vec![0; len];
+    let mut frame = EthernetFrame::new_unchecked(&mut buf);
+    eth_repr.emit(&mut frame);
+    let mut packet = Ipv6Packet::new_unchecked(frame.payload_mut());
+    ip_repr.emit(&mut packet);
+    let mut icmp_packet = Icmpv6Packet::new_unchecked(packet.payload_mut());
+    icmp_repr.emit(
+        &ROUTER.into(),
+        &Ipv6Address::LINK_LOCAL_ALL_NODES.into(),
+        &mut icmp_packet,
+        &ChecksumCapabilities::default(),
+    );
+    buf
+}
+
+#[test]
+fn test_ipv6() {
+    // the static configuration is read when `axnet` is built
+    let (Some(ip), Some(ip6), Some(gw6)) = (
+        option_env!("AX_IP").filter(|s| !s.is_empty()),
+        option_env!("AX_IP6").filter(|s| !s.is_empty()),
+        option_env!("AX_GW6").filter(|s| !s.is_empty()),
+    ) else {
+        println!("skipped: AX_IP6 and AX_GW6 are needed to test the static IPv6 config");
+        return;
+    };
+    println!("Testing IPv6 with {} and {} ...", ip, ip6);
+
+    // the router answers solicitations
+    let nic = FakeNic::new(
+        MAC,
+        Box::new(|frame: &[u8]| {
+            if is_router_solicit(frame) {
+                vec![router_advert()]
+            } else {
+                Vec::new()
+            }
+        }),
+    );
+    let nic: AxNetDevice = Box::new(nic);
+    axnet::init_network(AxDeviceContainer::from_one(nic), None);
+    // receive the advertisement to the solicitation sent at init
+    axnet::poll_interfaces();
+
+    let config = axnet::ipv6_config();
+    println!("{:?}", config);
+    let (addr, prefix_len) = match ip6.split_once('/') {
+        Some((addr, prefix_len)) => (addr, prefix_len.parse().unwrap()),
+        None => (ip6, 64),
+    };
+    let link_local: Ipv6Addr = "fe80::5054:ff:fe12:3456".parse().unwrap();
+    let autoconf: Ipv6Addr = "2001:db8::5054:ff:fe12:3456".parse().unwrap();
+    assert!(config.addresses.contains(&(link_local, 64)));
+    assert!(
+        config
+            .addresses
+            .contains(&(addr.parse().unwrap(), prefix_len))
+    );
+    assert!(config.addresses.contains(&(autoconf, 64)));
+    // the advertised router does not replace the static gateway
+    assert_eq!(config.gateway, Some(gw6.parse().unwrap()));
+    assert!(!config.addresses.contains(&(Ipv6Addr::LOCALHOST, 128)));
+}
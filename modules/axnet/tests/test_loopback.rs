@@ -0,0 +1,141 @@
+#![cfg(not(feature = "multitask"))]
+
+mod test_common;
+
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use axdriver::{AxDeviceContainer, AxNetDevice};
+use axnet::{TcpSocket, UdpSocket};
+use smoltcp::wire::{
+    ArpPacket, EthernetFrame, EthernetProtocol, Ipv4Address, Ipv4Packet, Ipv6Packet,
+};
+use test_common::FakeNic;
+
+const MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+const PORT: u16 = 7000;
+const MESSAGE: &[u8] = b"hello, loopback";
+
+/// Returns the IP address that the frame is sent to, or the one it resolves
+/// for ARP requests.
+fn frame_destination(frame: &[u8]) -> Option<IpAddr> {
+    let frame = EthernetFrame::new_checked(frame).ok()?;
+    match frame.ethertype() {
+        EthernetProtocol::Arp => {
+            let packet = ArpPacket::new_checked(frame.payload()).ok()?;
+            let target = Ipv4Address::from_bytes(packet.target_protocol_addr());
+            Some(Ipv4Addr::from(target.0).into())
+        }
+        EthernetProtocol::Ipv4 => {
+            let packet = Ipv4Packet::new_checked(frame.payload()).ok()?;
+            Some(Ipv4Addr::from(packet.dst_addr().0).into())
+        }
+        EthernetProtocol::Ipv6 => {
+            let packet = Ipv6Packet::new_checked(frame.payload()).ok()?;
+            Some(Ipv6Addr::from(packet.dst_addr().0).into())
+        }
+        _ => None,
+    }
+}
+
+/// Echoes a message from a TCP client to a server listening on `addr`.
+fn tcp_echo(addr: SocketAddr) {
+    let listener = TcpSocket::new();
+    listener.bind(addr).unwrap();
+    listener.listen().unwrap();
+    let client = TcpSocket::new();
+    client.connect(addr).unwrap();
+    let server = listener.accept().unwrap();
+    assert_eq!(server.peer_addr().unwrap(), client.local_addr().unwrap());
+    assert_eq!(client.peer_addr().unwrap(), addr);
+
+    let mut buf = [0; 64];
+    assert_eq!(client.send(MESSAGE).unwrap(), MESSAGE.len());
+    let len = server.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], MESSAGE);
+    assert_eq!(server.send(&buf[..len]).unwrap(), len);
+    let len = client.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], MESSAGE);
+
+    drop((client, server, listener));
+}
+
+/// Echoes a datagram from a UDP client to a server bound on `addr`.
+fn udp_echo(addr: SocketAddr) {
+    let server = UdpSocket::new();
+    server.bind(addr).unwrap();
+    let client = UdpSocket::new();
+    client.bind(SocketAddr::new(addr.ip(), 0)).unwrap();
+
+    let mut buf = [0; 64];
+    assert_eq!(client.send_to(MESSAGE, addr).unwrap(), MESSAGE.len());
+    let (len, from) = server.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..len], MESSAGE);
+    assert_eq!(from, client.local_addr().unwrap());
+    assert_eq!(server.send_to(&buf[..len], from).unwrap(), len);
+    let (len, from) = client.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..len], MESSAGE);
+    assert_eq!(from, addr);
+}
+
+#[test]
+fn test_loopback() {
+    // with the static configuration, `eth0` is up and local traffic must not
+    // reach its NIC
+    let has_nic = option_env!("AX_IP").is_some_and(|s| !s.is_empty());
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    if has_nic {
+        println!("Testing TCP and UDP over lo with a NIC ...");
+        let frames = sent.clone();
+        let nic: AxNetDevice = Box::new(FakeNic::new(
+            MAC,
+            Box::new(move |frame: &[u8]| {
+                frames.lock().unwrap().push(frame.to_vec());
+                Vec::new()
+            }),
+        ));
+        axnet::init_network(AxDeviceContainer::from_one(nic), None);
+    } else {
+        println!("Testing TCP and UDP over lo without a NIC ...");
+        axnet::init_network(AxDeviceContainer::default(), None);
+    }
+
+    let ifaces = axnet::interfaces();
+    assert_eq!(ifaces.len(), if has_nic { 2 } else { 1 });
+    assert_eq!(
+        (ifaces[0].name, ifaces[0].driver.as_str()),
+        ("lo", "loopback")
+    );
+    assert_eq!(ifaces[0].mac_address, [0; 6]);
+    if has_nic {
+        assert_eq!((ifaces[1].name, ifaces[1].mac_address), ("eth0", MAC));
+    }
+
+    let mut local_addrs = vec![
+        IpAddr::from(Ipv4Addr::LOCALHOST),
+        Ipv6Addr::LOCALHOST.into(),
+    ];
+    if has_nic {
+        local_addrs.push(axnet::ipv4_config().unwrap().address.into());
+        local_addrs.extend(
+            axnet::ipv6_config()
+                .addresses
+                .iter()
+                .map(|&(addr, _)| IpAddr::from(addr)),
+        );
+    }
+    for (i, &ip) in local_addrs.iter().enumerate() {
+        let port = PORT + i as u16;
+        tcp_echo(SocketAddr::new(ip, port));
+        udp_echo(SocketAddr::new(ip, port));
+    }
+
+    for frame in sent.lock().unwrap().iter() {
+        let dst = frame_destination(frame);
+        assert!(
+            !dst.is_some_and(|dst| local_addrs.contains(&dst)),
+            "frame to {:?} sent to the NIC",
+            dst
+        );
+    }
+}
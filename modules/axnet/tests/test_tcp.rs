@@ -0,0 +1,38 @@
+#![cfg(not(feature = "multitask"))]
+
+use core::net::{Ipv4Addr, SocketAddr};
+
+use axdriver::AxDeviceContainer;
+use axerrno::AxError;
+use axnet::TcpSocket;
+
+const PORT: u16 = 5555;
+
+#[test]
+fn test_listen_reuse_addr() {
+    println!("Testing listen() on a port in use ...");
+    axnet::init_network(AxDeviceContainer::default(), None);
+
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, PORT));
+    let listener = TcpSocket::new();
+    listener.bind(addr).unwrap();
+    listener.listen().unwrap();
+    let client = TcpSocket::new();
+    client.connect(addr).unwrap();
+    let server = listener.accept().unwrap();
+    listener.shutdown().unwrap();
+
+    // the accepted connection is still on the port
+    let listener = TcpSocket::new();
+    listener.bind(addr).unwrap();
+    assert_eq!(listener.listen().err(), Some(AxError::AddrInUse));
+    listener.set_reuse_address(true);
+    listener.listen().unwrap();
+
+    // the new listener accepts connections
+    let client2 = TcpSocket::new();
+    client2.connect(addr).unwrap();
+    let server2 = listener.accept().unwrap();
+    assert_eq!(server2.peer_addr().unwrap(), client2.local_addr().unwrap());
+    drop((client, server, client2, server2));
+}
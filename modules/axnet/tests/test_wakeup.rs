@@ -0,0 +1,102 @@
+#![cfg(all(feature = "multitask", feature = "irq", not(feature = "dhcp")))]
+
+mod test_common;
+
+use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use axdriver::{AxDeviceContainer, AxNetDevice};
+use axnet::UdpSocket;
+use smoltcp::phy::ChecksumCapabilities;
+use smoltcp::wire::{
+    EthernetAddress, EthernetFrame, EthernetProtocol, EthernetRepr, IpProtocol, Ipv4Address,
+    Ipv4Packet, Ipv4Repr, UdpPacket, UdpRepr,
+};
+use test_common::FakeNic;
+
+const MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+const PEER_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x00, 0x00, 0x03];
+const PEER: Ipv4Address = Ipv4Address::new(10, 0, 2, 3);
+const PEER_PORT: u16 = 5555;
+const PORT: u16 = 6000;
+const PAYLOAD: &[u8] = b"hello";
+
+/// Builds a UDP datagram of `PAYLOAD` from `PEER` to `dst_addr`.
+fn udp_frame(dst_addr: Ipv4Address) -> Vec<u8> {
+    let udp_repr = UdpRepr {
+        src_port: PEER_PORT,
+        dst_port: PORT,
+    };
+    let ip_repr = Ipv4Repr {
+        src_addr: PEER,
+        dst_addr,
+        next_header: IpProtocol::Udp,
+        payload_len: udp_repr.header_len() + PAYLOAD.len(),
+        hop_limit: 64,
+    };
+    let eth_repr = EthernetRepr {
+        src_addr: EthernetAddress(PEER_MAC),
+        dst_addr: EthernetAddress(MAC),
+        ethertype: EthernetProtocol::Ipv4,
+    };
+
+    let len = eth_repr.buffer_len() + ip_repr.buffer_len() + ip_repr.payload_len;
+    let mut buf = vec![0; len];
+    let mut frame = EthernetFrame::new_unchecked(&mut buf);
+    eth_repr.emit(&mut frame);
+    let mut packet = Ipv4Packet::new_unchecked(frame.payload_mut());
+    ip_repr.emit(&mut packet, &ChecksumCapabilities::default());
+    let mut udp_packet = UdpPacket::new_unchecked(packet.payload_mut());
+    udp_repr.emit(
+        &mut udp_packet,
+        &PEER.into(),
+        &dst_addr.into(),
+        PAYLOAD.len(),
+        |buf| buf.copy_from_slice(PAYLOAD),
+        &ChecksumCapabilities::default(),
+    );
+    buf
+}
+
+#[test]
+fn test_wakeup() {
+    let Some(ip) = option_env!("AX_IP").filter(|s| !s.is_empty()) else {
+        println!("skipped: AX_IP is needed to receive from the NIC");
+        return;
+    };
+    println!("Testing the wakeup of blocked sockets ...");
+    axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+
+    let nic = FakeNic::new(MAC, Box::new(|_: &[u8]| Vec::new()));
+    let rx_queue = nic.rx_queue();
+    let nic: AxNetDevice = Box::new(nic);
+    axnet::init_network(AxDeviceContainer::from_one(nic), None);
+
+    let socket = Arc::new(UdpSocket::new());
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), PORT);
+    socket.bind(addr).unwrap();
+    let received = Arc::new(AtomicBool::new(false));
+    let task = {
+        let (socket, received) = (socket.clone(), received.clone());
+        axtask::spawn(move || {
+            let mut buf = [0; 16];
+            let (len, from) = socket.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..len], PAYLOAD);
+            assert_eq!(from, SocketAddr::new(IpAddr::V4(PEER.0.into()), PEER_PORT));
+            received.store(true, Ordering::Release);
+        })
+    };
+    axtask::yield_now(); // let the task block on the socket
+    assert!(!received.load(Ordering::Acquire));
+
+    // the poll after the NIC raises an interrupt wakes the task
+    let ip: Ipv4Addr = ip.parse().unwrap();
+    rx_queue
+        .lock()
+        .unwrap()
+        .push_back(udp_frame(Ipv4Address(ip.octets())));
+    axnet::poll_interfaces();
+    task.join();
+    assert!(received.load(Ordering::Acquire));
+}
@@ -0,0 +1,348 @@
+//! Devices in `/dev` that are backed by the drivers of the runtime.
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axfs::devfs::register_device;
+#[cfg(any(feature = "display", feature = "rtc"))]
+use axfs_vfs::VfsError;
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsResult};
+
+/// Registers the devices in `/dev` provided by the enabled modules.
+///
+/// It should be called after the devices are initialized.
+pub(crate) fn init() {
+    let console = Arc::new(ConsoleDev);
+    register_device("console", console.clone());
+    register_device("tty", console);
+    RandomDev::seed();
+    let random = Arc::new(RandomDev);
+    register_device("random", random.clone());
+    register_device("urandom", random);
+    #[cfg(feature = "display")]
+    register_device("fb0", Arc::new(FramebufferDev));
+    #[cfg(feature = "rtc")]
+    axfs::devfs::register_device_with_ioctl("rtc", Arc::new(RtcDev), RtcDev::ioctl);
+}
+
+fn char_dev_attr(perm: u16, size: u64) -> VfsNodeAttr {
+    VfsNodeAttr::new(
+        VfsNodePerm::from_bits_truncate(perm),
+        VfsNodeType::CharDevice,
+        size,
+        0,
+    )
+}
+
+/// Copies the bytes of `src` at `offset` to `buf`, returns the number of
+/// bytes copied.
+#[cfg(feature = "display")]
+fn copy_from(src: &[u8], offset: u64, buf: &mut [u8]) -> usize {
+    let start = src.len().min(offset as usize);
+    let len = buf.len().min(src.len() - start);
+    buf[..len].copy_from_slice(&src[start..start + len]);
+    len
+}
+
+/// `/dev/console` and `/dev/tty`, which read from and write to the console
+/// of [`axhal::console`].
+struct ConsoleDev;
+
+impl VfsNodeOps for ConsoleDev {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(char_dev_attr(0o666, 0))
+    }
+
+    /// Blocks until at least one byte is read.
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        loop {
+            let len = axhal::console::read_bytes(buf);
+            if len > 0 || buf.is_empty() {
+                // the enter key is received as `\r`
+                for c in &mut buf[..len] {
+                    if *c == b'\r' {
+                        *c = b'\n';
+                    }
+                }
+                return Ok(len);
+            }
+            #[cfg(feature = "multitask")]
+            axtask::yield_now();
+            #[cfg(not(feature = "multitask"))]
+            core::hint::spin_loop();
+        }
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        axhal::console::write_bytes(buf);
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+}
+
+/// `/dev/random` and `/dev/urandom`, which never block.
+///
+/// The bytes are generated by a SplitMix64 generator. It is seeded at boot
+/// from the hardware random number generator if there is one (RDSEED or
+/// RDRAND on x86_64), or from the timer counter and the wall clock otherwise,
+/// and is mixed with the time of each read and the data written to the
+/// device.
+///
+/// The generator is **not** cryptographically secure: its state can be
+/// recovered from its output, and the seed may be guessed on platforms
+/// without a hardware generator. Do not use it for keys.
+struct RandomDev;
+
+static RANDOM_STATE: AtomicU64 = AtomicU64::new(0xA2CE_05A2_CE05_A2CE);
+
+impl RandomDev {
+    const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+    /// Seeds the generator, which is called once at boot.
+    fn seed() {
+        #[cfg(target_arch = "x86_64")]
+        let seed = hardware_random();
+        #[cfg(not(target_arch = "x86_64"))]
+        let seed = None;
+        // The time it takes to boot varies a little from boot to boot.
+        let seed = seed.unwrap_or_else(|| {
+            axhal::time::current_ticks().rotate_left(32) ^ axhal::time::wall_time_nanos()
+        });
+        RANDOM_STATE.fetch_xor(seed, Ordering::Relaxed);
+    }
+
+    fn next(time: u64) -> u64 {
+        let mut z = RANDOM_STATE.fetch_add(Self::GOLDEN_GAMMA, Ordering::Relaxed) ^ time;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+impl VfsNodeOps for RandomDev {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(char_dev_attr(0o666, 0))
+    }
+
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let time = axhal::time::monotonic_time_nanos();
+        for chunk in buf.chunks_mut(8) {
+            let bytes = Self::next(time).to_ne_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+        Ok(buf.len())
+    }
+
+    fn write_at(&self, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        for chunk in buf.chunks(8) {
+            let mut bytes = [0; 8];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            RANDOM_STATE.fetch_xor(u64::from_ne_bytes(bytes), Ordering::Relaxed);
+        }
+        Ok(buf.len())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+}
+
+/// Returns a random value from RDSEED, or from RDRAND if RDSEED is not
+/// supported. Returns `None` if neither is supported or they keep failing.
+#[cfg(target_arch = "x86_64")]
+fn hardware_random() -> Option<u64> {
+    use core::arch::x86_64::{__cpuid, __cpuid_count};
+
+    #[target_feature(enable = "rdseed")]
+    fn rdseed() -> Option<u64> {
+        let mut value = 0;
+        (0..10)
+            .any(|_| core::arch::x86_64::_rdseed64_step(&mut value) == 1)
+            .then_some(value)
+    }
+
+    #[target_feature(enable = "rdrand")]
+    fn rdrand() -> Option<u64> {
+        let mut value = 0;
+        (0..10)
+            .any(|_| core::arch::x86_64::_rdrand64_step(&mut value) == 1)
+            .then_some(value)
+    }
+
+    // CPUID.(EAX=07H,ECX=0):EBX.RDSEED[bit 18], CPUID.01H:ECX.RDRAND[bit 30]
+    let seed = if __cpuid(0).eax >= 7 && __cpuid_count(7, 0).ebx & (1 << 18) != 0 {
+        // SAFETY: the CPU supports RDSEED.
+        unsafe { rdseed() }
+    } else {
+        None
+    };
+    seed.or_else(|| {
+        // SAFETY: the CPU supports RDRAND.
+        (__cpuid(1).ecx & (1 << 30) != 0).then(|| unsafe { rdrand() })?
+    })
+}
+
+/// `/dev/fb0`, the framebuffer of the main display of [`axdisplay`].
+///
+/// Writes are shown on the screen immediately.
+#[cfg(feature = "display")]
+struct FramebufferDev;
+
+#[cfg(feature = "display")]
+impl FramebufferDev {
+    fn framebuffer() -> &'static mut [u8] {
+        let info = axdisplay::framebuffer_info();
+        // SAFETY: the framebuffer is mapped by the display driver and is
+        // never freed.
+        unsafe { core::slice::from_raw_parts_mut(info.fb_base_vaddr as *mut u8, info.fb_size) }
+    }
+}
+
+#[cfg(feature = "display")]
+impl VfsNodeOps for FramebufferDev {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let info = axdisplay::framebuffer_info();
+        Ok(char_dev_attr(0o660, info.fb_size as u64))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        Ok(copy_from(Self::framebuffer(), offset, buf))
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let fb = Self::framebuffer();
+        let start = fb.len().min(offset as usize);
+        let len = buf.len().min(fb.len() - start);
+        if len == 0 && !buf.is_empty() {
+            return Err(VfsError::StorageFull);
+        }
+        fb[start..start + len].copy_from_slice(&buf[..len]);
+        axdisplay::framebuffer_flush();
+        Ok(len)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        axdisplay::framebuffer_flush();
+        Ok(())
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Ok(())
+    }
+}
+
+/// `/dev/rtc`, the real-time clock.
+///
+/// The time is got by the `RTC_RD_TIME` ioctl, as a `struct rtc_time` (i.e.,
+/// `struct tm` without the time zone fields) in UTC.
+///
+/// The clock has no interrupts, so the update interrupts (one per second)
+/// are emulated and always enabled. Each read blocks until the next one, and
+/// returns an `unsigned long` whose low byte is `RTC_UF` and the rest is the
+/// number of update interrupts since the last read, as Linux does.
+#[cfg(feature = "rtc")]
+struct RtcDev;
+
+/// `_IOR('p', 0x09, struct rtc_time)`
+#[cfg(feature = "rtc")]
+const RTC_RD_TIME: u32 = 0x8024_7009;
+
+/// The flag of the update interrupts in the data read from `/dev/rtc`.
+#[cfg(feature = "rtc")]
+const RTC_UF: u64 = 0x10;
+
+/// The second of the update interrupt that the last read returned at.
+#[cfg(feature = "rtc")]
+static RTC_LAST_UPDATE: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "rtc")]
+impl RtcDev {
+    fn ioctl(cmd: u32, arg: usize) -> axerrno::AxResult<usize> {
+        use chrono::{Datelike, Timelike};
+
+        if cmd != RTC_RD_TIME {
+            return Err(VfsError::InvalidInput);
+        }
+        if arg == 0 {
+            return Err(VfsError::BadAddress);
+        }
+        let now = chrono::DateTime::from_timestamp_nanos(axhal::time::wall_time_nanos() as _);
+        let rtc_time = [
+            now.second() as i32,
+            now.minute() as i32,
+            now.hour() as i32,
+            now.day() as i32,
+            now.month0() as i32,
+            now.year() - 1900,
+            now.weekday().num_days_from_sunday() as i32,
+            now.ordinal0() as i32,
+            0, // tm_isdst
+        ];
+        // SAFETY: `arg` points to a `struct rtc_time` given by the caller.
+        unsafe { (arg as *mut [i32; 9]).write_unaligned(rtc_time) };
+        Ok(0)
+    }
+}
+
+#[cfg(feature = "rtc")]
+impl VfsNodeOps for RtcDev {
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
+
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(char_dev_attr(0o444, 0))
+    }
+
+    /// Blocks until the next update interrupt.
+    fn read_at(&self, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        use axhal::time::{NANOS_PER_SEC, wall_time_nanos};
+
+        // either an `unsigned int` or an `unsigned long`
+        let len = match buf.len() {
+            4 => 4,
+            n if n >= size_of::<usize>() => size_of::<usize>(),
+            _ => return Err(VfsError::InvalidInput),
+        };
+        let now = wall_time_nanos();
+        let update = now / NANOS_PER_SEC + 1;
+        let wait = core::time::Duration::from_nanos(update * NANOS_PER_SEC - now);
+        #[cfg(feature = "multitask")]
+        axtask::sleep(wait);
+        #[cfg(not(feature = "multitask"))]
+        axhal::time::busy_wait(wait);
+
+        let last = RTC_LAST_UPDATE.swap(update, Ordering::Relaxed);
+        let count = if last == 0 || last >= update {
+            1
+        } else {
+            update - last
+        };
+        let data = (count << 8) | RTC_UF;
+        if len == 4 {
+            buf[..len].copy_from_slice(&(data as u32).to_ne_bytes());
+        } else {
+            buf[..len].copy_from_slice(&(data as usize).to_ne_bytes());
+        }
+        Ok(len)
+    }
+
+    fn write_at(&self, _offset: u64, _buf: &[u8]) -> VfsResult<usize> {
+        Err(VfsError::PermissionDenied)
+    }
+}
@@ -10,7 +10,12 @@
 //! - `irq`: Enable interrupt handling support.
 //! - `multitask`: Enable multi-threading support.
 //! - `smp`: Enable SMP (symmetric multiprocessing) support.
-//! - `fs`: Enable filesystem support.
+//! - `sched-rr`: Use the round-robin scheduler, whose time slice can be tuned
+//!   in `/sys`.
+//! - `sched-rt`: Enable the real-time scheduling class, whose round-robin time
+//!   slice can be tuned in `/sys`.
+//! - `fs`: Enable filesystem support, with the devices in `/dev`, the kernel
+//!   state exported in `/proc` and the tunables in `/sys`.
 //! - `net`: Enable networking support.
 //! - `display`: Enable graphics support.
 //!
@@ -22,12 +27,25 @@
 #[macro_use]
 extern crate axlog;
 
+#[cfg(feature = "fs")]
+extern crate alloc;
+
 #[cfg(all(target_os = "none", not(test)))]
 mod lang_items;
 
 #[cfg(feature = "smp")]
 mod mp;
 
+#[cfg(feature = "fs")]
+mod devfs;
+#[cfg(feature = "fs")]
+mod procfs;
+#[cfg(feature = "fs")]
+mod sysfs;
+
+#[cfg(all(test, feature = "fs"))]
+mod tests;
+
 #[cfg(feature = "smp")]
 pub use self::mp::rust_main_secondary;
 
@@ -159,13 +177,22 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
         let all_devices = axdriver::init_drivers();
 
         #[cfg(feature = "fs")]
-        axfs::init_filesystems(all_devices.block);
+        {
+            axfs::init_filesystems(all_devices.block);
+            procfs::init();
+        }
 
         #[cfg(feature = "net")]
-        axnet::init_network(all_devices.net);
+        axnet::init_network(all_devices.net, all_devices.net_irq);
 
         #[cfg(feature = "display")]
         axdisplay::init_display(all_devices.display);
+
+        #[cfg(feature = "fs")]
+        {
+            devfs::init();
+            sysfs::init();
+        }
     }
 
     #[cfg(feature = "smp")]
@@ -195,11 +222,31 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     unsafe { main() };
 
     #[cfg(feature = "multitask")]
-    axtask::exit(0);
+    {
+        // The exit of the main task shuts down the whole system.
+        #[cfg(feature = "fs")]
+        sync_filesystems();
+        axtask::exit(0);
+    }
     #[cfg(not(feature = "multitask"))]
     {
         debug!("main task exited: exit_code={}", 0);
-        axhal::misc::terminate();
+        terminate();
+    }
+}
+
+/// Shuts down the whole system, after writing the cached data of the
+/// filesystems back to the disks.
+pub fn terminate() -> ! {
+    #[cfg(feature = "fs")]
+    sync_filesystems();
+    axhal::misc::terminate()
+}
+
+#[cfg(feature = "fs")]
+fn sync_filesystems() {
+    if let Err(e) = axfs::api::sync() {
+        warn!("failed to sync filesystems: {:?}", e);
     }
 }
 
@@ -255,7 +302,15 @@ fn init_interrupt() {
     }
 
     axhal::irq::register_handler(TIMER_IRQ_NUM, || {
-        update_timer();
+        // Do not rearm the periodic timer on an idle CPU with the tick stopped,
+        // the scheduler will restart it when there is work to do.
+        #[cfg(feature = "multitask")]
+        let tick_stopped = axtask::is_tick_stopped();
+        #[cfg(not(feature = "multitask"))]
+        let tick_stopped = false;
+        if !tick_stopped {
+            update_timer();
+        }
         #[cfg(feature = "multitask")]
         axtask::on_timer_tick();
     });
@@ -0,0 +1,228 @@
+//! Files in `/proc` that are generated from the kernel state.
+
+use alloc::{format, string::String};
+
+use axfs::procfs::register_file;
+
+/// Registers the files in `/proc` provided by the enabled modules.
+pub(crate) fn init() {
+    register_file("cpuinfo", cpuinfo);
+    register_file("uptime", uptime);
+    #[cfg(feature = "alloc")]
+    register_file("meminfo", meminfo);
+    #[cfg(feature = "irq")]
+    register_file("interrupts", interrupts);
+    #[cfg(feature = "multitask")]
+    task::init();
+    #[cfg(feature = "net")]
+    {
+        register_file("net/tcp", net_tcp);
+        register_file("net/tcp6", net_tcp6);
+    }
+}
+
+fn cpuinfo() -> String {
+    let mut content = String::new();
+    for cpu in 0..axconfig::SMP {
+        content += &format!(
+            "processor\t: {}\narch\t\t: {}\nplatform\t: {}\n\n",
+            cpu,
+            axconfig::ARCH,
+            axconfig::PLATFORM
+        );
+    }
+    content
+}
+
+/// Generates the uptime and the total idle time of all CPUs in seconds.
+fn uptime() -> String {
+    let uptime = axhal::time::monotonic_time();
+    #[cfg(feature = "multitask")]
+    let idle = (0..axconfig::SMP)
+        .filter_map(axtask::cpu_idle_time)
+        .sum::<core::time::Duration>();
+    #[cfg(not(feature = "multitask"))]
+    let idle = core::time::Duration::ZERO;
+    format!(
+        "{}.{:02} {}.{:02}\n",
+        uptime.as_secs(),
+        uptime.subsec_millis() / 10,
+        idle.as_secs(),
+        idle.subsec_millis() / 10
+    )
+}
+
+#[cfg(feature = "alloc")]
+fn meminfo() -> String {
+    use axhal::mem::PAGE_SIZE_4K;
+
+    let allocator = axalloc::global_allocator();
+    let total_pages = allocator.used_pages() + allocator.available_pages();
+    let free_kb = allocator.available_pages() * PAGE_SIZE_4K / 1024;
+    // the free space in the heap can also be allocated
+    let available_kb = free_kb + allocator.available_bytes() / 1024;
+    let mut content = String::new();
+    for (key, kb) in [
+        ("MemTotal", total_pages * PAGE_SIZE_4K / 1024),
+        ("MemFree", free_kb),
+        ("MemAvailable", available_kb),
+        ("HeapUsed", allocator.used_bytes() / 1024),
+    ] {
+        content += &format!("{:<15} {:>8} kB\n", format!("{}:", key), kb);
+    }
+    content
+}
+
+#[cfg(feature = "irq")]
+fn interrupts() -> String {
+    let mut content = String::from("          TOTAL\n");
+    axhal::irq::for_each_irq_count(|irq_num, count| {
+        content += &format!("{:>4}: {:>10}\n", irq_num, count);
+    });
+    content
+}
+
+/// Generates the IPv4 TCP sockets in the format of Linux.
+#[cfg(feature = "net")]
+fn net_tcp() -> String {
+    use core::net::SocketAddr;
+
+    // the address is printed as a native-endian integer, as Linux does
+    let addr = |addr: SocketAddr| match addr {
+        SocketAddr::V4(addr) => Some(format!(
+            "{:08X}:{:04X}",
+            u32::from_ne_bytes(addr.ip().octets()),
+            addr.port()
+        )),
+        SocketAddr::V6(_) => None,
+    };
+    tcp_table(
+        "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n",
+        addr,
+    )
+}
+
+/// Generates the IPv6 TCP sockets in the format of Linux.
+#[cfg(feature = "net")]
+fn net_tcp6() -> String {
+    use core::net::SocketAddr;
+
+    // the address is printed as four native-endian integers, as Linux does
+    let addr = |addr: SocketAddr| match addr {
+        SocketAddr::V4(_) => None,
+        SocketAddr::V6(addr) => {
+            let mut content = String::new();
+            for word in addr.ip().octets().chunks_exact(4) {
+                content += &format!("{:08X}", u32::from_ne_bytes(word.try_into().unwrap()));
+            }
+            Some(format!("{}:{:04X}", content, addr.port()))
+        }
+    };
+    tcp_table(
+        "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n",
+        addr,
+    )
+}
+
+/// Generates the TCP sockets whose addresses can be formatted by `addr`.
+#[cfg(feature = "net")]
+fn tcp_table(header: &str, addr: impl Fn(core::net::SocketAddr) -> Option<String>) -> String {
+    let mut content = String::from(header);
+    let sockets = axnet::tcp_socket_table()
+        .into_iter()
+        .filter_map(|info| Some((addr(info.local_addr)?, addr(info.peer_addr)?, info)));
+    for (i, (local, peer, info)) in sockets.enumerate() {
+        content += &format!(
+            "{:>4}: {} {} {:02X} {:08X}:{:08X} 00:00000000 00000000     0        0 0\n",
+            i, local, peer, info.state, info.tx_queue, info.rx_queue
+        );
+    }
+    content
+}
+
+/// The per-task directories `/proc/<tid>`.
+#[cfg(feature = "multitask")]
+mod task {
+    use alloc::string::{String, ToString};
+    use alloc::{format, vec::Vec};
+
+    use axfs::procfs::ProcEntryFileFn;
+    use axtask::{AxTaskRef, SchedPolicy, TaskId, TaskState};
+
+    static FILES: [(&str, ProcEntryFileFn); 2] = [("stat", stat), ("status", status)];
+
+    /// The unit of times in `/proc/<tid>/stat`.
+    const USER_HZ: u64 = 100;
+
+    pub(super) fn init() {
+        axfs::procfs::register_symlink("self", || axtask::current().id().as_u64().to_string());
+        axfs::procfs::register_dir("", list, &FILES);
+    }
+
+    fn list() -> Vec<String> {
+        let mut tids = Vec::new();
+        axtask::for_each_task(|task| tids.push(task.id().as_u64().to_string()));
+        tids
+    }
+
+    fn find(tid: &str) -> Option<AxTaskRef> {
+        let tid = tid.parse::<u64>().ok()?;
+        axtask::get_task(TaskId::from(tid))
+    }
+
+    /// Returns the state of the task as a character and a name.
+    const fn state_name(state: TaskState) -> (char, &'static str) {
+        match state {
+            TaskState::Running | TaskState::Ready => ('R', "running"),
+            TaskState::Blocked => ('S', "sleeping"),
+            TaskState::Exited => ('Z', "zombie"),
+        }
+    }
+
+    fn status(tid: &str) -> Option<String> {
+        let task = find(tid)?;
+        let (state, name) = state_name(task.state());
+        let stats = task.stats();
+        Some(format!(
+            "Name:\t{}\nState:\t{} ({})\nTgid:\t{}\nPid:\t{}\nPPid:\t0\nThreads:\t1\n\
+             voluntary_ctxt_switches:\t{}\nnonvoluntary_ctxt_switches:\t{}\n",
+            task.name(),
+            state,
+            name,
+            tid,
+            tid,
+            stats.voluntary_switches,
+            stats.involuntary_switches,
+        ))
+    }
+
+    fn stat(tid: &str) -> Option<String> {
+        let task = find(tid)?;
+        let utime = task.stats().runtime_ns * USER_HZ / axhal::time::NANOS_PER_SEC;
+        let policy = task.sched_policy();
+        let (priority, policy_num) = match policy {
+            SchedPolicy::Normal => (task.priority(), 0),
+            SchedPolicy::Fifo(prio) => (-1 - prio as isize, 1),
+            SchedPolicy::RoundRobin(prio) => (-1 - prio as isize, 2),
+        };
+        // fields 1 to 24 (pid to rss)
+        let mut content = format!(
+            "{} ({}) {} 0 {} {} 0 -1 0 0 0 0 0 {} 0 0 0 {} 0 1 0 0 0 0",
+            tid,
+            task.name(),
+            state_name(task.state()).0,
+            tid,
+            tid,
+            utime,
+            priority,
+        );
+        // fields 25 to 38 (rsslim to exit_signal)
+        content += &" 0".repeat(14);
+        // fields 39 to 41 (processor, rt_priority and policy)
+        content += &format!(" {} {} {}", task.cpu_id(), policy.rt_priority(), policy_num);
+        // fields 42 to 52 (delayacct_blkio_ticks to exit_code)
+        content += &" 0".repeat(11);
+        content.push('\n');
+        Some(content)
+    }
+}
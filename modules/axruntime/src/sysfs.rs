@@ -0,0 +1,88 @@
+//! Attributes in `/sys` that tune the kernel at runtime.
+
+use alloc::format;
+
+use axerrno::{AxError, AxResult};
+use axfs::sysfs::register_writable_attr;
+
+/// Registers the attributes in `/sys` provided by the enabled modules.
+///
+/// It should be called after the devices are initialized.
+pub(crate) fn init() {
+    register_writable_attr(
+        "module/axlog/parameters/level",
+        || format!("{}\n", axlog::max_level()),
+        set_log_level,
+    );
+    #[cfg(any(feature = "sched-rr", feature = "sched-rt"))]
+    register_writable_attr(
+        "module/axtask/parameters/time_slice",
+        || format!("{}\n", axtask::time_slice()),
+        |value| {
+            if axtask::set_time_slice(parse(value)?) {
+                Ok(())
+            } else {
+                Err(AxError::InvalidInput)
+            }
+        },
+    );
+    #[cfg(feature = "net")]
+    net::init();
+}
+
+/// Parses a decimal integer written to an attribute.
+#[cfg(any(feature = "sched-rr", feature = "sched-rt", feature = "net"))]
+fn parse(value: &str) -> AxResult<usize> {
+    value.parse().map_err(|_| AxError::InvalidInput)
+}
+
+fn set_log_level(value: &str) -> AxResult {
+    const LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+    let level = value.to_lowercase();
+    if !LEVELS.contains(&level.as_str()) {
+        return Err(AxError::InvalidInput);
+    }
+    axlog::set_max_level(&level);
+    Ok(())
+}
+
+/// The socket buffer lengths and the network interfaces.
+#[cfg(feature = "net")]
+mod net {
+    use alloc::format;
+
+    use axfs::sysfs::{register_attr, register_writable_attr};
+    use axnet::BufferKind;
+
+    use super::parse;
+
+    pub(super) fn init() {
+        for (name, kind) in [
+            ("tcp_recv_buf_len", BufferKind::TcpRecv),
+            ("tcp_send_buf_len", BufferKind::TcpSend),
+            ("udp_recv_buf_len", BufferKind::UdpRecv),
+            ("udp_send_buf_len", BufferKind::UdpSend),
+        ] {
+            register_writable_attr(
+                &format!("module/axnet/parameters/{}", name),
+                move || format!("{}\n", axnet::buffer_len(kind)),
+                move |value| axnet::set_buffer_len(kind, parse(value)?),
+            );
+        }
+
+        for iface in axnet::interfaces() {
+            let dir = format!("class/net/{}", iface.name);
+            let mac = iface.mac_address;
+            register_attr(&format!("{}/address", dir), move || {
+                format!(
+                    "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}\n",
+                    mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+                )
+            });
+            let mtu = iface.mtu;
+            register_attr(&format!("{}/mtu", dir), move || format!("{}\n", mtu));
+            let driver = iface.driver;
+            register_attr(&format!("{}/driver", dir), move || format!("{}\n", driver));
+        }
+    }
+}
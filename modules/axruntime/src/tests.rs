@@ -0,0 +1,129 @@
+use std::sync::{Mutex, Once};
+
+use axdriver::AxDeviceContainer;
+use axdriver_block::ramdisk::RamDisk;
+use axerrno::AxError;
+use axfs::api as fs;
+
+static INIT: Once = Once::new();
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// Mounts the filesystems on a FAT image, and registers the entries in
+/// `/dev` and `/sys` as the runtime does.
+fn init() {
+    INIT.call_once(|| {
+        let data = std::fs::read("../axfs/resources/fat16.img").expect("failed to load disk image");
+        axtask::init_scheduler(); // call this to use `axsync::Mutex`.
+        axfs::init_filesystems(AxDeviceContainer::from_one(RamDisk::from(&data)));
+        crate::devfs::init();
+        crate::sysfs::init();
+    });
+}
+
+#[test]
+fn test_sysfs_log_level() {
+    let _lock = SERIAL.lock();
+    init();
+    let fname = "/sys/module/axlog/parameters/level";
+    fs::write(fname, "warn\n").unwrap();
+    assert_eq!(fs::read_to_string(fname).unwrap(), "warn\n");
+    assert_eq!(axlog::max_level(), "warn");
+    assert_eq!(fs::write(fname, "loud"), Err(AxError::InvalidInput));
+    assert_eq!(axlog::max_level(), "warn");
+}
+
+#[cfg(any(feature = "sched-rr", feature = "sched-rt"))]
+#[test]
+fn test_sysfs_time_slice() {
+    let _lock = SERIAL.lock();
+    init();
+    let fname = "/sys/module/axtask/parameters/time_slice";
+    assert_eq!(fs::read_to_string(fname).unwrap(), "5\n");
+    fs::write(fname, "10").unwrap();
+    assert_eq!(axtask::time_slice(), 10);
+    assert_eq!(fs::read_to_string(fname).unwrap(), "10\n");
+    assert_eq!(fs::write(fname, "0"), Err(AxError::InvalidInput));
+    assert_eq!(fs::write(fname, "-1"), Err(AxError::InvalidInput));
+    assert_eq!(axtask::time_slice(), 10);
+    fs::write(fname, "5").unwrap();
+}
+
+/// Opens the device `/dev/<name>` for reading and writing.
+fn open_dev(name: &str) -> axfs::fops::File {
+    let mut opts = axfs::fops::OpenOptions::new();
+    opts.read(true);
+    opts.write(true);
+    axfs::fops::File::open(&format!("/dev/{}", name), &opts).unwrap()
+}
+
+#[test]
+fn test_devfs_random() {
+    let _lock = SERIAL.lock();
+    init();
+    for name in ["random", "urandom"] {
+        let mut dev = open_dev(name);
+        // reads never block, and fill the whole buffer with new bytes
+        let (mut buf1, mut buf2) = ([0; 61], [0; 61]);
+        assert_eq!(dev.read(&mut buf1), Ok(61));
+        assert_eq!(dev.read(&mut buf2), Ok(61));
+        assert_ne!(buf1, buf2);
+        assert_ne!(buf1, [0; 61]);
+        // writes are mixed into the state, and are all accepted
+        assert_eq!(dev.write(&buf1[..13]), Ok(13));
+        assert_eq!(dev.read(&mut buf2), Ok(61));
+        assert_ne!(buf1, buf2);
+    }
+}
+
+#[test]
+fn test_devfs_null_zero() {
+    let _lock = SERIAL.lock();
+    init();
+    let mut buf = [0xa5; 16];
+    let mut null = open_dev("null");
+    assert_eq!(null.read(&mut buf), Ok(0));
+    assert_eq!(null.write(&buf), Ok(16));
+    let mut zero = open_dev("zero");
+    assert_eq!(zero.read(&mut buf), Ok(16));
+    assert_eq!(buf, [0; 16]);
+    assert_eq!(zero.write(&[0xa5; 16]), Ok(16));
+}
+
+#[cfg(feature = "rtc")]
+#[test]
+fn test_devfs_rtc_ioctl() {
+    use chrono::{Datelike, Timelike};
+
+    const RTC_RD_TIME: u32 = 0x8024_7009;
+
+    /// Returns the wall time as a `struct rtc_time` in UTC, without the time
+    /// zone fields.
+    fn rtc_time() -> [i32; 9] {
+        let now = chrono::DateTime::from_timestamp_nanos(axhal::time::wall_time_nanos() as _);
+        [
+            now.second() as i32,
+            now.minute() as i32,
+            now.hour() as i32,
+            now.day() as i32,
+            now.month0() as i32,
+            now.year() - 1900,
+            now.weekday().num_days_from_sunday() as i32,
+            now.ordinal0() as i32,
+            0,
+        ]
+    }
+
+    let _lock = SERIAL.lock();
+    init();
+    let mut opts = axfs::fops::OpenOptions::new();
+    opts.read(true);
+    let rtc = axfs::fops::File::open("/dev/rtc", &opts).unwrap();
+    let mut tm = [-1; 9];
+    let before = rtc_time();
+    assert_eq!(rtc.ioctl(RTC_RD_TIME, tm.as_mut_ptr() as usize), Ok(0));
+    let after = rtc_time();
+    assert!(tm == before || tm == after);
+
+    assert_eq!(rtc.ioctl(RTC_RD_TIME, 0), Err(AxError::BadAddress));
+    assert_eq!(rtc.ioctl(0x7001, 0), Err(AxError::InvalidInput));
+}
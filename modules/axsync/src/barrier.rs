@@ -0,0 +1,78 @@
+//! A barrier to synchronize a number of tasks.
+
+use kspin::SpinNoIrq;
+
+use crate::wait::WaitQueue;
+
+struct BarrierState {
+    /// Number of tasks that have arrived in the current generation.
+    count: usize,
+    /// Incremented each time all tasks have arrived.
+    generation: usize,
+}
+
+/// A barrier enables multiple tasks to synchronize the beginning of some
+/// computation.
+///
+/// Without the `multitask` feature, there is only one task, so waiting on a
+/// barrier for more than one task spins forever.
+pub struct Barrier {
+    state: SpinNoIrq<BarrierState>,
+    wq: WaitQueue,
+    num_tasks: usize,
+}
+
+/// A `BarrierWaitResult` is returned by [`Barrier::wait`] when all tasks in
+/// the [`Barrier`] have rendezvoused.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    /// Returns `true` if this task is the "leader task" for the call to
+    /// [`Barrier::wait`].
+    ///
+    /// Only one task will have `true` returned from their result, all other
+    /// tasks will have `false` returned.
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+impl Barrier {
+    /// Creates a new barrier that can block a given number of tasks.
+    ///
+    /// A barrier will block `n - 1` tasks which call [`Barrier::wait`] and
+    /// then wake up all tasks at once when the `n`th task calls it.
+    pub const fn new(n: usize) -> Self {
+        Self {
+            state: SpinNoIrq::new(BarrierState {
+                count: 0,
+                generation: 0,
+            }),
+            wq: WaitQueue::new(),
+            num_tasks: n,
+        }
+    }
+
+    /// Blocks the current task until all tasks have rendezvoused here.
+    ///
+    /// Barriers are re-usable after all tasks have rendezvoused once, and can
+    /// be used continuously.
+    pub fn wait(&self) -> BarrierWaitResult {
+        let mut state = self.state.lock();
+        let generation = state.generation;
+        state.count += 1;
+        if state.count < self.num_tasks {
+            drop(state);
+            self.wq
+                .wait_until(|| self.state.lock().generation != generation);
+            BarrierWaitResult(false)
+        } else {
+            state.count = 0;
+            state.generation = state.generation.wrapping_add(1);
+            drop(state);
+            self.wq.notify_all(true);
+            BarrierWaitResult(true)
+        }
+    }
+}
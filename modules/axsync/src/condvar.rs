@@ -0,0 +1,174 @@
+//! A condition variable working with [`Mutex`].
+
+use core::sync::atomic::{AtomicU32, Ordering};
+#[cfg(feature = "irq")]
+use core::time::Duration;
+
+use crate::MutexGuard;
+use crate::wait::WaitQueue;
+
+#[cfg(doc)]
+use crate::Mutex;
+
+/// A type indicating whether a timed wait on a condition variable returned
+/// due to a time out or not.
+///
+/// It is returned by the [`Condvar::wait_timeout`] method.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    /// Returns `true` if the wait was known to have timed out.
+    pub fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+/// A condition variable, which blocks tasks while waiting for an event to
+/// occur.
+///
+/// It is used with a [`Mutex`], which is released while waiting, and
+/// re-acquired before the wait functions return. Like other condition
+/// variables, it is subject to spurious wakeups, so the protected condition
+/// should be checked in a loop, or use [`Condvar::wait_while`].
+///
+/// Without the `multitask` feature, there are no other tasks to notify the
+/// condition variable, so waiting just returns immediately with the lock still
+/// held.
+pub struct Condvar {
+    /// Incremented on each notification, so that a waiter will not miss the
+    /// notifications after it releases the mutex.
+    seq: AtomicU32,
+    wq: WaitQueue,
+}
+
+impl Condvar {
+    /// Creates a new condition variable.
+    pub const fn new() -> Self {
+        Self {
+            seq: AtomicU32::new(0),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// Blocks the current task until this condition variable receives a
+    /// notification.
+    ///
+    /// The mutex guarded by `guard` is released while blocking, and is
+    /// re-acquired before returning.
+    #[cfg(feature = "multitask")]
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let seq = self.seq.load(Ordering::Acquire);
+        let mutex = MutexGuard::mutex(&guard);
+        drop(guard);
+        self.wq
+            .wait_until(|| self.seq.load(Ordering::Acquire) != seq);
+        mutex.lock()
+    }
+
+    /// Blocks the current task until this condition variable receives a
+    /// notification.
+    ///
+    /// Without the `multitask` feature, it is always a spurious wakeup.
+    #[cfg(not(feature = "multitask"))]
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        core::hint::spin_loop();
+        guard
+    }
+
+    /// Blocks the current task until this condition variable receives a
+    /// notification, or the given duration has elapsed.
+    ///
+    /// The mutex guarded by `guard` is released while blocking, and is
+    /// re-acquired before returning.
+    #[cfg(all(feature = "multitask", feature = "irq"))]
+    pub fn wait_timeout<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        dur: Duration,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        let seq = self.seq.load(Ordering::Acquire);
+        let mutex = MutexGuard::mutex(&guard);
+        drop(guard);
+        let timed_out = self
+            .wq
+            .wait_timeout_until(dur, || self.seq.load(Ordering::Acquire) != seq);
+        (mutex.lock(), WaitTimeoutResult(timed_out))
+    }
+
+    /// Blocks the current task until this condition variable receives a
+    /// notification, or the given duration has elapsed.
+    ///
+    /// Without the `multitask` feature, it is always a spurious wakeup.
+    #[cfg(all(not(feature = "multitask"), feature = "irq"))]
+    pub fn wait_timeout<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        dur: Duration,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult) {
+        let _ = dur;
+        core::hint::spin_loop();
+        (guard, WaitTimeoutResult(false))
+    }
+
+    /// Blocks the current task while `condition` returns `true`.
+    pub fn wait_while<'a, T, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Blocks the current task while `condition` returns `true`, or until the
+    /// given duration has elapsed.
+    ///
+    /// The returned [`WaitTimeoutResult`] indicates whether the duration has
+    /// elapsed with the condition still being `true`.
+    #[cfg(feature = "irq")]
+    pub fn wait_timeout_while<'a, T, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        dur: Duration,
+        mut condition: F,
+    ) -> (MutexGuard<'a, T>, WaitTimeoutResult)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let deadline = axhal::time::wall_time() + dur;
+        loop {
+            if !condition(&mut *guard) {
+                return (guard, WaitTimeoutResult(false));
+            }
+            let now = axhal::time::wall_time();
+            if now >= deadline {
+                return (guard, WaitTimeoutResult(true));
+            }
+            guard = self.wait_timeout(guard, deadline - now).0;
+        }
+    }
+
+    /// Wakes up one task blocked on this condition variable.
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        self.wq.notify_one(true);
+    }
+
+    /// Wakes up all tasks blocked on this condition variable.
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Release);
+        self.wq.notify_all(true);
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -3,25 +3,49 @@
 //! Currently supported primitives:
 //!
 //! - [`Mutex`]: A mutual exclusion primitive.
+//! - [`PiMutex`]: A mutex with priority inheritance (only with the `multitask`
+//!   feature).
+//! - [`Condvar`]: A condition variable.
+//! - [`RwLock`]: A reader-writer lock.
+//! - [`Semaphore`]: A counting semaphore.
+//! - [`Barrier`]: A barrier to synchronize a number of tasks.
 //! - mod [`spin`]: spinlocks imported from the [`kspin`] crate.
 //!
 //! # Cargo Features
 //!
 //! - `multitask`: For use in the multi-threaded environments. If the feature is
-//!   not enabled, [`Mutex`] will be an alias of [`spin::SpinNoIrq`]. This
-//!   feature is enabled by default.
+//!   not enabled, [`Mutex`] will be an alias of [`spin::SpinNoIrq`], and other
+//!   primitives will spin instead of blocking. This feature is enabled by
+//!   default.
+//! - `irq`: Interrupts are enabled. If this feature is enabled, waiting with
+//!   timeouts can be used, such as [`Condvar::wait_timeout`] and
+//!   [`Semaphore::acquire_timeout`].
 
 #![cfg_attr(not(test), no_std)]
 #![feature(doc_cfg)]
 
 pub use kspin as spin;
 
+mod barrier;
+mod condvar;
+mod rwlock;
+mod semaphore;
+mod wait;
+
 #[cfg(feature = "multitask")]
 mod mutex;
 
+#[cfg(test)]
+mod tests;
+
+pub use self::barrier::{Barrier, BarrierWaitResult};
+pub use self::condvar::{Condvar, WaitTimeoutResult};
+pub use self::rwlock::{RawRwLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use self::semaphore::{Semaphore, SemaphoreGuard};
+
 #[cfg(feature = "multitask")]
 #[doc(cfg(feature = "multitask"))]
-pub use self::mutex::{Mutex, MutexGuard, RawMutex};
+pub use self::mutex::{Mutex, MutexGuard, PiMutex, RawMutex};
 
 #[cfg(not(feature = "multitask"))]
 #[doc(cfg(not(feature = "multitask")))]
@@ -1,5 +1,6 @@
 //! A naïve sleeping mutex.
 
+use core::ops::Deref;
 use core::sync::atomic::{AtomicU64, Ordering};
 
 use axtask::{WaitQueue, current};
@@ -9,9 +10,14 @@ use axtask::{WaitQueue, current};
 /// When the mutex is locked, the current task will block and be put into the
 /// wait queue. When the mutex is unlocked, all tasks waiting on the queue
 /// will be woken up.
+///
+/// If priority inheritance is enabled (see [`RawMutex::new_pi`]), the owner
+/// of the mutex inherits the priority of a more important task that blocks
+/// on it, until the owner releases all its priority inheritance mutexes.
 pub struct RawMutex {
     wq: WaitQueue,
     owner_id: AtomicU64,
+    pi: bool,
 }
 
 impl RawMutex {
@@ -21,6 +27,36 @@ impl RawMutex {
         Self {
             wq: WaitQueue::new(),
             owner_id: AtomicU64::new(0),
+            pi: false,
+        }
+    }
+
+    /// Creates a [`RawMutex`] with priority inheritance enabled.
+    #[inline(always)]
+    pub const fn new_pi() -> Self {
+        Self {
+            wq: WaitQueue::new(),
+            owner_id: AtomicU64::new(0),
+            pi: true,
+        }
+    }
+
+    /// Returns whether priority inheritance is enabled.
+    #[inline(always)]
+    pub const fn is_pi(&self) -> bool {
+        self.pi
+    }
+
+    /// Lets the owner inherit the priority of the current task, which is
+    /// going to block on this mutex.
+    ///
+    /// The owner may release the mutex at any time, so it is boosted only if
+    /// it still owns the mutex (checked with its PI state locked).
+    fn boost_owner(&self, owner_id: u64) {
+        if let Some(owner) = axtask::get_task(owner_id.into()) {
+            axtask::pi_inherit(&owner, current().as_task_ref(), || {
+                self.owner_id.load(Ordering::Acquire) == owner_id
+            });
         }
     }
 }
@@ -50,11 +86,17 @@ unsafe impl lock_api::RawMutex for RawMutex {
                         "{} tried to acquire mutex it already owns.",
                         current().id_name()
                     );
+                    if self.pi && owner_id != 0 {
+                        self.boost_owner(owner_id);
+                    }
                     // Wait until the lock looks unlocked before retrying
                     self.wq.wait_until(|| !self.is_locked());
                 }
             }
         }
+        if self.pi {
+            axtask::pi_lock_acquired(current().as_task_ref());
+        }
     }
 
     #[inline(always)]
@@ -62,9 +104,14 @@ unsafe impl lock_api::RawMutex for RawMutex {
         let current_id = current().id().as_u64();
         // The reason for using a strong compare_exchange is explained here:
         // https://github.com/Amanieu/parking_lot/pull/207#issuecomment-575869107
-        self.owner_id
+        let locked = self
+            .owner_id
             .compare_exchange(0, current_id, Ordering::Acquire, Ordering::Relaxed)
-            .is_ok()
+            .is_ok();
+        if locked && self.pi {
+            axtask::pi_lock_acquired(current().as_task_ref());
+        }
+        locked
     }
 
     #[inline(always)]
@@ -76,6 +123,9 @@ unsafe impl lock_api::RawMutex for RawMutex {
             "{} tried to release mutex it doesn't own",
             current().id_name()
         );
+        if self.pi {
+            axtask::pi_lock_released(current().as_task_ref());
+        }
         self.wq.notify_one(true);
     }
 
@@ -90,13 +140,39 @@ pub type Mutex<T> = lock_api::Mutex<RawMutex, T>;
 /// An alias of [`lock_api::MutexGuard`].
 pub type MutexGuard<'a, T> = lock_api::MutexGuard<'a, RawMutex, T>;
 
+/// A [`Mutex`] with priority inheritance enabled.
+///
+/// It dereferences to the [`Mutex`], so it can be used in the same way.
+pub struct PiMutex<T: ?Sized>(Mutex<T>);
+
+impl<T> PiMutex<T> {
+    /// Creates a new mutex with priority inheritance enabled.
+    #[inline(always)]
+    pub const fn new(val: T) -> Self {
+        Self(Mutex::const_new(RawMutex::new_pi(), val))
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+impl<T: ?Sized> Deref for PiMutex<T> {
+    type Target = Mutex<T>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Mutex<T> {
+        &self.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::Mutex;
+    use crate::tests::{INIT, SERIAL};
     use axtask as thread;
-    use std::sync::Once;
-
-    static INIT: Once = Once::new();
 
     fn may_interrupt() {
         // simulate interrupts
@@ -107,6 +183,7 @@ mod tests {
 
     #[test]
     fn lots_and_lots() {
+        let _lock = SERIAL.lock();
         INIT.call_once(thread::init_scheduler);
 
         const NUM_TASKS: u32 = 10;
@@ -0,0 +1,115 @@
+//! A reader-writer lock.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::wait::WaitQueue;
+
+/// The lock state when it is held by a writer.
+const WRITER: usize = usize::MAX;
+
+/// A [`lock_api::RawRwLock`] implementation.
+///
+/// When the lock can not be acquired, the current task will block and be put
+/// into the wait queue. Readers are preferred: new readers can acquire the lock
+/// as long as it is not held by a writer, even if there are writers waiting.
+///
+/// Without the `multitask` feature, it just spins while the lock is held.
+pub struct RawRwLock {
+    /// [`WRITER`] if the lock is held by a writer, otherwise the number of
+    /// readers holding the lock.
+    state: AtomicUsize,
+    wq: WaitQueue,
+}
+
+impl RawRwLock {
+    /// Creates a [`RawRwLock`].
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicUsize::new(0),
+            wq: WaitQueue::new(),
+        }
+    }
+}
+
+unsafe impl lock_api::RawRwLock for RawRwLock {
+    const INIT: Self = RawRwLock::new();
+
+    type GuardMarker = lock_api::GuardSend;
+
+    #[inline(always)]
+    fn lock_shared(&self) {
+        while !self.try_lock_shared() {
+            // Wait until the writer releases the lock before retrying.
+            self.wq
+                .wait_until(|| self.state.load(Ordering::Relaxed) != WRITER);
+        }
+    }
+
+    #[inline(always)]
+    fn try_lock_shared(&self) -> bool {
+        let mut state = self.state.load(Ordering::Relaxed);
+        while state < WRITER - 1 {
+            match self.state.compare_exchange_weak(
+                state,
+                state + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(s) => state = s,
+            }
+        }
+        false
+    }
+
+    #[inline(always)]
+    unsafe fn unlock_shared(&self) {
+        let state = self.state.fetch_sub(1, Ordering::Release);
+        debug_assert!(state != 0 && state != WRITER);
+        if state == 1 {
+            // Only writers can be waiting, since readers wait only when the
+            // lock is held by a writer.
+            self.wq.notify_one(true);
+        }
+    }
+
+    #[inline(always)]
+    fn lock_exclusive(&self) {
+        while !self.try_lock_exclusive() {
+            // Wait until the lock looks unlocked before retrying.
+            self.wq
+                .wait_until(|| self.state.load(Ordering::Relaxed) == 0);
+        }
+    }
+
+    #[inline(always)]
+    fn try_lock_exclusive(&self) -> bool {
+        self.state
+            .compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    #[inline(always)]
+    unsafe fn unlock_exclusive(&self) {
+        self.state.store(0, Ordering::Release);
+        self.wq.notify_all(true);
+    }
+
+    #[inline(always)]
+    fn is_locked(&self) -> bool {
+        self.state.load(Ordering::Relaxed) != 0
+    }
+
+    #[inline(always)]
+    fn is_locked_exclusive(&self) -> bool {
+        self.state.load(Ordering::Relaxed) == WRITER
+    }
+}
+
+/// An alias of [`lock_api::RwLock`].
+pub type RwLock<T> = lock_api::RwLock<RawRwLock, T>;
+/// An alias of [`lock_api::RwLockReadGuard`].
+pub type RwLockReadGuard<'a, T> = lock_api::RwLockReadGuard<'a, RawRwLock, T>;
+/// An alias of [`lock_api::RwLockWriteGuard`].
+pub type RwLockWriteGuard<'a, T> = lock_api::RwLockWriteGuard<'a, RawRwLock, T>;
@@ -0,0 +1,100 @@
+//! A counting semaphore.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "irq")]
+use core::time::Duration;
+
+use crate::wait::WaitQueue;
+
+/// A counting semaphore, which controls the access to a shared resource with
+/// a number of permits.
+///
+/// When there are no permits available, [`Semaphore::acquire`] blocks the
+/// current task and puts it into the wait queue, until a permit is released
+/// by another task.
+///
+/// Without the `multitask` feature, it just spins while no permits are
+/// available.
+pub struct Semaphore {
+    permits: AtomicUsize,
+    wq: WaitQueue,
+}
+
+/// An RAII guard which releases a permit of the [`Semaphore`] when dropped.
+///
+/// It is returned by [`Semaphore::access`].
+pub struct SemaphoreGuard<'a> {
+    sem: &'a Semaphore,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with the given number of permits.
+    pub const fn new(permits: usize) -> Self {
+        Self {
+            permits: AtomicUsize::new(permits),
+            wq: WaitQueue::new(),
+        }
+    }
+
+    /// Returns the number of available permits.
+    pub fn available_permits(&self) -> usize {
+        self.permits.load(Ordering::Acquire)
+    }
+
+    /// Acquires a permit, blocking the current task until one is available.
+    pub fn acquire(&self) {
+        while !self.try_acquire() {
+            self.wq.wait_until(|| self.available_permits() > 0);
+        }
+    }
+
+    /// Tries to acquire a permit without blocking.
+    ///
+    /// Returns `true` if a permit is acquired.
+    pub fn try_acquire(&self) -> bool {
+        self.permits
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok()
+    }
+
+    /// Acquires a permit, blocking the current task until one is available,
+    /// or the given duration has elapsed.
+    ///
+    /// Returns `true` if a permit is acquired, or `false` if timed out.
+    #[cfg(feature = "irq")]
+    pub fn acquire_timeout(&self, dur: Duration) -> bool {
+        let deadline = axhal::time::wall_time() + dur;
+        loop {
+            if self.try_acquire() {
+                return true;
+            }
+            let now = axhal::time::wall_time();
+            if now >= deadline
+                || self
+                    .wq
+                    .wait_timeout_until(deadline - now, || self.available_permits() > 0)
+            {
+                return self.try_acquire();
+            }
+        }
+    }
+
+    /// Acquires a permit, and returns an RAII guard which releases the permit
+    /// when dropped.
+    pub fn access(&self) -> SemaphoreGuard<'_> {
+        self.acquire();
+        SemaphoreGuard { sem: self }
+    }
+
+    /// Releases a permit, waking up one task waiting for it.
+    pub fn release(&self) {
+        self.permits.fetch_add(1, Ordering::Release);
+        self.wq.notify_one(true);
+    }
+}
+
+impl Drop for SemaphoreGuard<'_> {
+    fn drop(&mut self) {
+        self.sem.release();
+    }
+}
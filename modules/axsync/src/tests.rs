@@ -0,0 +1,179 @@
+use core::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use std::sync::{Mutex as StdMutex, Once};
+
+use axtask as thread;
+
+use crate::{Barrier, Condvar, Mutex, PiMutex, RwLock, Semaphore};
+
+pub(crate) static INIT: Once = Once::new();
+/// Tests share the same scheduler, so they must not run in parallel.
+pub(crate) static SERIAL: StdMutex<()> = StdMutex::new(());
+
+#[test]
+fn test_condvar() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    const NUM_TASKS: usize = 5;
+    static READY: Mutex<usize> = Mutex::new(0);
+    static CV: Condvar = Condvar::new();
+
+    let tasks: Vec<_> = (0..NUM_TASKS)
+        .map(|_| {
+            thread::spawn(|| {
+                let mut ready = READY.lock();
+                *ready += 1;
+                CV.notify_all();
+                // Wait until the main task resets the counter.
+                let _ready = CV.wait_while(ready, |ready| *ready != 0);
+            })
+        })
+        .collect();
+
+    let mut ready = CV.wait_while(READY.lock(), |ready| *ready < NUM_TASKS);
+    *ready = 0;
+    CV.notify_all();
+    drop(ready);
+
+    for task in tasks {
+        assert_eq!(task.join(), Some(0));
+    }
+}
+
+#[test]
+fn test_rwlock() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    const NUM_TASKS: usize = 10;
+    const NUM_ITERS: usize = 100;
+    static LOCK: RwLock<(usize, usize)> = RwLock::new((0, 0));
+
+    let tasks: Vec<_> = (0..NUM_TASKS)
+        .map(|i| {
+            thread::spawn(move || {
+                for _ in 0..NUM_ITERS {
+                    if i % 2 == 0 {
+                        let mut val = LOCK.write();
+                        val.0 += 1;
+                        thread::yield_now();
+                        val.1 += 1;
+                    } else {
+                        let val = LOCK.read();
+                        thread::yield_now();
+                        assert_eq!(val.0, val.1);
+                    }
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        assert_eq!(task.join(), Some(0));
+    }
+    assert_eq!(
+        *LOCK.read(),
+        (NUM_TASKS / 2 * NUM_ITERS, NUM_TASKS / 2 * NUM_ITERS)
+    );
+}
+
+#[test]
+fn test_semaphore_and_barrier() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    const NUM_TASKS: usize = 6;
+    const NUM_PERMITS: usize = 2;
+    static SEM: Semaphore = Semaphore::new(NUM_PERMITS);
+    static BARRIER: Barrier = Barrier::new(NUM_TASKS);
+    static IN_USE: AtomicUsize = AtomicUsize::new(0);
+    static LEADERS: AtomicUsize = AtomicUsize::new(0);
+
+    let tasks: Vec<_> = (0..NUM_TASKS)
+        .map(|_| {
+            thread::spawn(|| {
+                {
+                    let _guard = SEM.access();
+                    let n = IN_USE.fetch_add(1, Ordering::SeqCst) + 1;
+                    assert!(n <= NUM_PERMITS);
+                    thread::yield_now();
+                    IN_USE.fetch_sub(1, Ordering::SeqCst);
+                }
+                if BARRIER.wait().is_leader() {
+                    LEADERS.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        assert_eq!(task.join(), Some(0));
+    }
+    assert_eq!(LEADERS.load(Ordering::SeqCst), 1);
+    assert_eq!(SEM.available_permits(), NUM_PERMITS);
+    assert!(SEM.try_acquire());
+    SEM.release();
+}
+
+#[test]
+fn test_pi_mutex() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(thread::init_scheduler);
+
+    const NUM_TASKS: usize = 5;
+    const NUM_ITERS: usize = 100;
+    static M: PiMutex<usize> = PiMutex::new(0);
+
+    assert!(unsafe { M.raw() }.is_pi());
+    let tasks: Vec<_> = (0..NUM_TASKS)
+        .map(|_| {
+            thread::spawn(|| {
+                for _ in 0..NUM_ITERS {
+                    let mut val = M.lock();
+                    thread::yield_now();
+                    *val += 1;
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        assert_eq!(task.join(), Some(0));
+    }
+    assert_eq!(*M.lock(), NUM_TASKS * NUM_ITERS);
+    // The priority is restored after releasing the mutex.
+    assert_eq!(thread::current().priority(), 0);
+
+    // A less important owner inherits the priority of the current task while
+    // it blocks on the mutex, and gets its own back after releasing it.
+    const OWNER_PRIO: isize = 10;
+    static LOCKED: AtomicUsize = AtomicUsize::new(0);
+    static WAITING: AtomicUsize = AtomicUsize::new(0);
+    static OWNER_PRIO_LOCKED: AtomicIsize = AtomicIsize::new(isize::MIN);
+
+    let owner = thread::spawn(|| {
+        let _val = M.lock();
+        LOCKED.store(1, Ordering::Release);
+        // Tasks are not preempted in unit tests, so the main task has
+        // boosted us when we run again after it starts waiting.
+        while WAITING.load(Ordering::Acquire) == 0 {
+            thread::yield_now();
+        }
+        let prio = thread::current().priority();
+        OWNER_PRIO_LOCKED.store(prio, Ordering::Release);
+    });
+    // The FIFO scheduler does not support priorities.
+    let has_prio = thread::set_priority(&owner, OWNER_PRIO);
+    while LOCKED.load(Ordering::Acquire) == 0 {
+        thread::yield_now();
+    }
+    WAITING.store(1, Ordering::Release);
+    drop(M.lock());
+    assert_eq!(owner.join(), Some(0));
+
+    let boosted = OWNER_PRIO_LOCKED.load(Ordering::Acquire);
+    if has_prio {
+        assert_eq!(boosted, thread::current().priority());
+        assert_eq!(owner.priority(), OWNER_PRIO);
+    } else {
+        assert_eq!(boosted, 0);
+        assert_eq!(owner.priority(), 0);
+    }
+}
@@ -0,0 +1,49 @@
+//! The wait queue used by the blocking primitives.
+//!
+//! With the `multitask` feature, it is [`axtask::WaitQueue`]. Otherwise,
+//! there is only one task in the system, so waiting is just busy spinning
+//! until the condition is met (e.g., changed by interrupt handlers).
+
+#[cfg(feature = "multitask")]
+pub(crate) use axtask::WaitQueue;
+
+#[cfg(not(feature = "multitask"))]
+pub(crate) struct WaitQueue;
+
+#[cfg(not(feature = "multitask"))]
+impl WaitQueue {
+    pub const fn new() -> Self {
+        Self
+    }
+
+    pub fn wait_until<F>(&self, condition: F)
+    where
+        F: Fn() -> bool,
+    {
+        while !condition() {
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Returns `true` if the condition is still not met after `dur`.
+    #[cfg(feature = "irq")]
+    pub fn wait_timeout_until<F>(&self, dur: core::time::Duration, condition: F) -> bool
+    where
+        F: Fn() -> bool,
+    {
+        let deadline = axhal::time::wall_time() + dur;
+        while !condition() {
+            if axhal::time::wall_time() >= deadline {
+                return true;
+            }
+            core::hint::spin_loop();
+        }
+        false
+    }
+
+    pub fn notify_one(&self, _resched: bool) -> bool {
+        false
+    }
+
+    pub fn notify_all(&self, _resched: bool) {}
+}
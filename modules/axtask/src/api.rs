@@ -4,15 +4,30 @@ use alloc::{string::String, sync::Arc};
 
 use kernel_guard::NoPreemptIrqSave;
 
-pub(crate) use crate::run_queue::{current_run_queue, select_run_queue};
+pub(crate) use crate::run_queue::{current_run_queue, select_run_queue, task_run_queue};
 
 #[doc(cfg(feature = "multitask"))]
-pub use crate::task::{CurrentTask, TaskId, TaskInner};
+pub use crate::pi::{pi_inherit, pi_lock_acquired, pi_lock_released};
+#[doc(cfg(feature = "multitask"))]
+pub use crate::registry::{for_each_task, get_task, task_count};
+#[doc(cfg(feature = "multitask"))]
+pub use crate::run_queue::{MigrationStats, cpu_idle_time, migration_stats};
+#[cfg(any(feature = "sched-rr", feature = "sched-rt"))]
+#[doc(cfg(any(feature = "sched-rr", feature = "sched-rt")))]
+pub use crate::sched_rr::{set_time_slice, time_slice};
+#[doc(cfg(feature = "multitask"))]
+pub use crate::task::{
+    CurrentTask, RT_PRIO_MAX, SchedPolicy, TaskId, TaskInner, TaskState, TaskStats,
+};
 #[doc(cfg(feature = "multitask"))]
 pub use crate::task_ext::{TaskExtMut, TaskExtRef};
 #[doc(cfg(feature = "multitask"))]
 pub use crate::wait_queue::WaitQueue;
 
+#[cfg(feature = "irq")]
+#[doc(cfg(feature = "irq"))]
+pub use crate::timers::Timer;
+
 /// The reference type of a task.
 pub type AxTaskRef = Arc<AxTask>;
 
@@ -21,16 +36,26 @@ pub type AxCpuMask = cpumask::CpuMask<{ axconfig::SMP }>;
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "sched-rr")] {
-        const MAX_TIME_SLICE: usize = 5;
-        pub(crate) type AxTask = scheduler::RRTask<TaskInner, MAX_TIME_SLICE>;
-        pub(crate) type Scheduler = scheduler::RRScheduler<TaskInner, MAX_TIME_SLICE>;
+        // The time slice is tunable at runtime, so the round-robin scheduler
+        // is implemented in this crate rather than by `scheduler::RRScheduler`.
+        pub(crate) type AxTask = scheduler::FifoTask<TaskInner>;
+        pub(crate) type NormalScheduler = crate::sched_rr::RRScheduler;
     } else if #[cfg(feature = "sched-cfs")] {
         pub(crate) type AxTask = scheduler::CFSTask<TaskInner>;
-        pub(crate) type Scheduler = scheduler::CFScheduler<TaskInner>;
+        pub(crate) type NormalScheduler = scheduler::CFScheduler<TaskInner>;
     } else {
         // If no scheduler features are set, use FIFO as the default.
         pub(crate) type AxTask = scheduler::FifoTask<TaskInner>;
-        pub(crate) type Scheduler = scheduler::FifoScheduler<TaskInner>;
+        pub(crate) type NormalScheduler = scheduler::FifoScheduler<TaskInner>;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "sched-rt")] {
+        // Real-time tasks are scheduled before normal tasks.
+        pub(crate) type Scheduler = crate::sched_rt::RtScheduler;
+    } else {
+        pub(crate) type Scheduler = NormalScheduler;
     }
 }
 
@@ -77,6 +102,11 @@ pub fn init_scheduler() {
     crate::timers::init();
 
     info!("  use {} scheduler.", Scheduler::scheduler_name());
+    #[cfg(feature = "sched-rt")]
+    info!(
+        "  use {} scheduler for normal tasks.",
+        NormalScheduler::scheduler_name()
+    );
 }
 
 /// Initializes the task scheduler for secondary CPUs.
@@ -129,7 +159,9 @@ where
     spawn_raw(f, "".into(), axconfig::TASK_STACK_SIZE)
 }
 
-/// Set the priority for current task.
+/// Set the priority for the given task.
+///
+/// The task can be in any state (running on any CPU, ready or blocked).
 ///
 /// The range of the priority is dependent on the underlying scheduler. For
 /// example, in the [CFS] scheduler, the priority is the nice value, ranging from
@@ -138,44 +170,84 @@ where
 /// Returns `true` if the priority is set successfully.
 ///
 /// [CFS]: https://en.wikipedia.org/wiki/Completely_Fair_Scheduler
-pub fn set_priority(prio: isize) -> bool {
-    current_run_queue::<NoPreemptIrqSave>().set_current_priority(prio)
+pub fn set_priority(task: &AxTaskRef, prio: isize) -> bool {
+    loop {
+        // Look up the run queue again if the task has been migrated.
+        if let Some(ok) = task_run_queue::<NoPreemptIrqSave>(task).set_task_priority(task, prio) {
+            return ok;
+        }
+    }
+}
+
+/// Set the scheduling policy for the given task.
+///
+/// The task can be in any state (running on any CPU, ready or blocked). If the
+/// task is ready, it will be re-queued according to the new policy.
+///
+/// Real-time policies ([`SchedPolicy::Fifo`] and [`SchedPolicy::RoundRobin`])
+/// are only available with the `sched-rt` feature.
+///
+/// Returns `true` if the policy is set successfully.
+pub fn set_sched_policy(task: &AxTaskRef, policy: SchedPolicy) -> bool {
+    if !policy.is_valid() {
+        return false;
+    }
+    // Look up the run queue again if the task has been migrated.
+    #[cfg(feature = "sched-rt")]
+    while !task_run_queue::<NoPreemptIrqSave>(task).set_task_sched_policy(task, policy) {}
+    #[cfg(not(feature = "sched-rt"))]
+    task.store_sched_policy(policy);
+    true
 }
 
 /// Set the affinity for the current task.
 /// [`AxCpuMask`] is used to specify the CPU affinity.
 /// Returns `true` if the affinity is set successfully.
-///
-/// TODO: support set the affinity for other tasks.
 pub fn set_current_affinity(cpumask: AxCpuMask) -> bool {
     if cpumask.is_empty() {
         false
     } else {
-        let curr = current().clone();
-
-        curr.set_cpumask(cpumask);
+        current().set_cpumask(cpumask);
         // After setting the affinity, we need to check if current cpu matches
         // the affinity. If not, we need to migrate the task to the correct CPU.
         #[cfg(feature = "smp")]
         if !cpumask.get(axhal::cpu::this_cpu_id()) {
-            const MIGRATION_TASK_STACK_SIZE: usize = 4096;
-            // Spawn a new migration task for migrating.
-            let migration_task = TaskInner::new(
-                move || crate::run_queue::migrate_entry(curr),
-                "migration-task".into(),
-                MIGRATION_TASK_STACK_SIZE,
-            )
-            .into_arc();
-
-            // Migrate the current task to the correct CPU using the migration task.
-            current_run_queue::<NoPreemptIrqSave>().migrate_current(migration_task);
-
+            current_run_queue::<NoPreemptIrqSave>().migrate_current(false);
             assert!(cpumask.get(axhal::cpu::this_cpu_id()), "Migration failed");
         }
         true
     }
 }
 
+/// Set the affinity for the given task.
+/// [`AxCpuMask`] is used to specify the CPU affinity.
+/// Returns `true` if the affinity is set successfully.
+///
+/// If the task is the current task, it is the same as [`set_current_affinity`].
+/// Otherwise, the task is migrated lazily when it is not allowed to run on its
+/// current CPU any more:
+///
+/// - A blocked task will be put into a run queue matching the new affinity
+///   when it is woken up.
+/// - A ready task will be forwarded to a run queue matching the new affinity
+///   when it is picked by the scheduler of its current CPU.
+/// - A task running on another CPU will migrate itself at its next scheduling
+///   point (it is requested to be preempted if preemption is enabled).
+pub fn set_affinity(task: &AxTaskRef, cpumask: AxCpuMask) -> bool {
+    if cpumask.is_empty() {
+        return false;
+    }
+    if current().ptr_eq(task) {
+        return set_current_affinity(cpumask);
+    }
+    task.set_cpumask(cpumask);
+    #[cfg(all(feature = "smp", feature = "preempt"))]
+    if task.is_running() && !cpumask.get(task.cpu_id()) {
+        task.set_preempt_pending(true);
+    }
+    true
+}
+
 /// Current task gives up the CPU time voluntarily, and switches to another
 /// ready task.
 pub fn yield_now() {
@@ -204,6 +276,23 @@ pub fn exit(exit_code: i32) -> ! {
     current_run_queue::<NoPreemptIrqSave>().exit_current(exit_code)
 }
 
+/// Returns whether the periodic timer tick is stopped on the current CPU.
+///
+/// With the `tickless` feature, the tick is stopped when the CPU becomes idle,
+/// and the timer interrupt handler should not rearm the periodic timer until
+/// the tick is restarted by the scheduler. It always returns `false` if the
+/// `tickless` feature is not enabled.
+#[cfg(feature = "irq")]
+#[doc(cfg(feature = "irq"))]
+pub fn is_tick_stopped() -> bool {
+    #[cfg(feature = "tickless")]
+    {
+        current_run_queue::<kernel_guard::NoOp>().is_tick_stopped()
+    }
+    #[cfg(not(feature = "tickless"))]
+    false
+}
+
 /// The idle task routine.
 ///
 /// It runs an infinite loop that keeps calling [`yield_now()`].
@@ -211,6 +300,8 @@ pub fn run_idle() -> ! {
     loop {
         yield_now();
         debug!("idle task: waiting for IRQs...");
+        #[cfg(feature = "tickless")]
+        current_run_queue::<NoPreemptIrqSave>().stop_tick_if_idle();
         #[cfg(feature = "irq")]
         axhal::asm::wait_for_irqs();
     }
@@ -13,13 +13,27 @@
 //!   APIs can be used, such as [`sleep`], [`sleep_until`], and
 //!   [`WaitQueue::wait_timeout`].
 //! - `preempt`: Enable preemptive scheduling.
+//! - `tickless`: Stop the periodic timer tick when a CPU is idle (NO_HZ idle).
+//!   The next timer interrupt is programmed at the earliest timer event of
+//!   that CPU instead. It also enables the `multitask` and `irq` features if
+//!   it is enabled.
+//! - `paging`: Unmap a guard page below each kernel stack from the linear
+//!   mapping to catch stack overflows, which are reported on a per-CPU
+//!   overflow stack. Without this feature, overflows are detected by a canary
+//!   at the bottom of the stack, which is checked on every context switch.
 //! - `sched-fifo`: Use the [FIFO cooperative scheduler][1]. It also enables the
 //!   `multitask` feature if it is enabled. This feature is enabled by default,
 //!   and it can be overriden by other scheduler features.
 //! - `sched-rr`: Use the [Round-robin preemptive scheduler][2]. It also enables
-//!   the `multitask` and `preempt` features if it is enabled.
+//!   the `multitask` and `preempt` features if it is enabled. The time slice
+//!   can be changed at runtime by [`set_time_slice`].
 //! - `sched-cfs`: Use the [Completely Fair Scheduler][3]. It also enables the
 //!   the `multitask` and `preempt` features if it is enabled.
+//! - `sched-rt`: Add a fixed-priority real-time scheduling class on top of the
+//!   scheduler selected above. Tasks with the [`SchedPolicy::Fifo`] or
+//!   [`SchedPolicy::RoundRobin`] policy always preempt normal tasks. The
+//!   round-robin tasks use the same time slice as `sched-rr`. It also enables
+//!   the `multitask` and `preempt` features if it is enabled.
 //!
 //! [1]: scheduler::FifoScheduler
 //! [2]: scheduler::RRScheduler
@@ -44,8 +58,15 @@ cfg_if::cfg_if! {
         mod task;
         mod task_ext;
         mod api;
+        mod pi;
+        mod registry;
         mod wait_queue;
 
+        #[cfg(any(feature = "sched-rr", feature = "sched-rt"))]
+        mod sched_rr;
+        #[cfg(feature = "sched-rt")]
+        mod sched_rt;
+
         #[cfg(feature = "irq")]
         mod timers;
 
@@ -0,0 +1,160 @@
+//! Priority inheritance for blocking locks.
+//!
+//! A task holding priority inheritance (PI) locks inherits the priority of a
+//! more important task that is going to block on one of them, so that a task
+//! with a medium priority can not starve the high-priority waiter by
+//! preempting the low-priority lock owner. The original priority is restored
+//! when the owner releases all its PI locks.
+//!
+//! The inheritance is not transitive: if the owner is itself blocked on a PI
+//! lock held by another task, the latter is not boosted.
+
+use crate::{AxTaskRef, SchedPolicy, set_priority, set_sched_policy};
+
+/// A scheduling policy and a priority.
+type SchedParams = (SchedPolicy, isize);
+
+/// Priority inheritance state of a task.
+pub(crate) struct PiState {
+    /// Number of PI locks held by the task.
+    held: usize,
+    /// The scheduling policy and priority of the task before it inherited
+    /// any priorities.
+    base: Option<SchedParams>,
+    /// The scheduling policy and priority that the task should have, which
+    /// are applied after this state is unlocked.
+    target: Option<SchedParams>,
+    /// Incremented whenever `target` is changed.
+    seq: u64,
+}
+
+impl PiState {
+    pub(crate) const fn new() -> Self {
+        Self {
+            held: 0,
+            base: None,
+            target: None,
+            seq: 0,
+        }
+    }
+
+    /// Sets the scheduling policy and priority that the task should have,
+    /// returns the sequence number to pass to [`apply_target`].
+    fn set_target(&mut self, target: SchedParams) -> u64 {
+        self.target = Some(target);
+        self.seq += 1;
+        self.seq
+    }
+}
+
+/// Returns the current scheduling policy and priority of `task`.
+fn sched_params(task: &AxTaskRef) -> SchedParams {
+    (task.sched_policy(), task.priority())
+}
+
+/// Returns whether a task with scheduling parameters `a` is more important
+/// than one with `b`.
+///
+/// Real-time tasks are compared by their real-time priorities first. Normal
+/// tasks are compared by their priority values, and a smaller value is more
+/// important (e.g., the nice value of CFS).
+fn more_important(a: SchedParams, b: SchedParams) -> bool {
+    let (rt_a, rt_b) = (a.0.rt_priority(), b.0.rt_priority());
+    if rt_a != rt_b {
+        rt_a > rt_b
+    } else {
+        rt_a == 0 && a.1 < b.1
+    }
+}
+
+/// Applies the target scheduling policy and priority of `task` set with
+/// sequence number `seq`.
+///
+/// They are applied without the PI state locked, since changing them locks
+/// the run queue of the task. If the target is changed meanwhile, e.g., the
+/// task releases its PI locks while it is being boosted, the latest one is
+/// applied again, so that the task never ends up with a stale one.
+fn apply_target(task: &AxTaskRef, mut seq: u64, (mut policy, mut prio): SchedParams) {
+    loop {
+        if task.sched_policy() != policy {
+            set_sched_policy(task, policy);
+        }
+        if task.priority() != prio {
+            set_priority(task, prio);
+        }
+        let pi = task.pi_state().lock();
+        if pi.seq == seq {
+            return;
+        }
+        seq = pi.seq;
+        (policy, prio) = pi.target.expect("PI target changed without being set");
+    }
+}
+
+/// Records that `task` has acquired a PI lock.
+pub fn pi_lock_acquired(task: &AxTaskRef) {
+    task.pi_state().lock().held += 1;
+}
+
+/// Records that `task` has released a PI lock.
+///
+/// If it does not hold any PI locks anymore, its original scheduling policy
+/// and priority are restored.
+pub fn pi_lock_released(task: &AxTaskRef) {
+    let mut pi = task.pi_state().lock();
+    assert!(pi.held > 0, "{} released an unheld PI lock", task.id_name());
+    pi.held -= 1;
+    if pi.held > 0 {
+        return;
+    }
+    if let Some(base) = pi.base.take() {
+        debug!("task {}: restore priority", task.id_name());
+        let seq = pi.set_target(base);
+        drop(pi);
+        apply_target(task, seq, base);
+    }
+}
+
+/// Lets `owner` inherit the priority of `waiter`, which is going to block on
+/// a PI lock held by `owner`.
+///
+/// `still_owns` tells whether `owner` still holds the lock, and is called
+/// with the PI state of `owner` locked, which must be also locked to release
+/// its PI locks (see [`pi_lock_released`]). So `owner` is not boosted after
+/// it has released the lock.
+///
+/// It does nothing if `owner` is at least as important as `waiter`, or it
+/// does not hold the lock any more.
+pub fn pi_inherit<F>(owner: &AxTaskRef, waiter: &AxTaskRef, still_owns: F)
+where
+    F: FnOnce() -> bool,
+{
+    let mut pi = owner.pi_state().lock();
+    if pi.held == 0 || !still_owns() {
+        return;
+    }
+    // The owner may have been boosted by another waiter, with the new
+    // priority not applied yet.
+    let current = match pi.base {
+        Some(_) => pi.target.unwrap(),
+        None => sched_params(owner),
+    };
+    let inherited = sched_params(waiter);
+    if !more_important(inherited, current) {
+        return;
+    }
+    debug!(
+        "task {}: inherit priority from {}",
+        owner.id_name(),
+        waiter.id_name()
+    );
+    pi.base.get_or_insert(current);
+    let target = if inherited.0.rt_priority() > current.0.rt_priority() {
+        (inherited.0, current.1)
+    } else {
+        (current.0, inherited.1)
+    };
+    let seq = pi.set_target(target);
+    drop(pi);
+    apply_target(owner, seq, target);
+}
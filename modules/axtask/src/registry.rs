@@ -0,0 +1,74 @@
+//! A global registry of all tasks in the system.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+
+use kspin::SpinNoIrq;
+
+use crate::{AxTask, AxTaskRef, TaskId};
+
+/// All tasks that have been created but not yet dropped, indexed by the
+/// task ID.
+///
+/// Only weak references are stored, so the registry does not extend the
+/// lifetime of any task. Entries are removed when the task is dropped.
+static TASK_REGISTRY: SpinNoIrq<BTreeMap<u64, Weak<AxTask>>> = SpinNoIrq::new(BTreeMap::new());
+
+/// Adds a newly created task to the registry.
+pub(crate) fn register(task: &AxTaskRef) {
+    TASK_REGISTRY
+        .lock()
+        .insert(task.id().as_u64(), Arc::downgrade(task));
+}
+
+/// Removes the task with the given ID from the registry.
+pub(crate) fn unregister(id: TaskId) {
+    TASK_REGISTRY.lock().remove(&id.as_u64());
+}
+
+/// Takes a snapshot of all alive tasks, ordered by task ID.
+///
+/// Note: the strong references are collected while holding the lock, but
+/// must be dropped after the lock is released, because dropping the last
+/// reference to a task will call [`unregister`].
+fn snapshot() -> Vec<AxTaskRef> {
+    TASK_REGISTRY
+        .lock()
+        .values()
+        .filter_map(Weak::upgrade)
+        .collect()
+}
+
+/// Calls the given function on every task in the system, in the order of
+/// task IDs.
+///
+/// The tasks include all tasks that have been created but not dropped yet,
+/// including idle tasks, gc tasks and exited tasks that have not been
+/// recycled.
+///
+/// The task list is a snapshot taken before the first call to `f`. Tasks
+/// spawned during the iteration are not visited.
+pub fn for_each_task<F>(mut f: F)
+where
+    F: FnMut(&AxTaskRef),
+{
+    for task in snapshot() {
+        f(&task);
+    }
+}
+
+/// Finds a task by its ID.
+///
+/// Returns [`None`] if no such task exists or it has been dropped.
+pub fn get_task(id: TaskId) -> Option<AxTaskRef> {
+    TASK_REGISTRY
+        .lock()
+        .get(&id.as_u64())
+        .and_then(Weak::upgrade)
+}
+
+/// Returns the number of tasks in the system.
+pub fn task_count() -> usize {
+    TASK_REGISTRY.lock().len()
+}
@@ -1,9 +1,11 @@
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
 
 #[cfg(feature = "smp")]
-use alloc::sync::Weak;
+use alloc::{sync::Weak, vec::Vec};
 
 use kernel_guard::BaseGuard;
 use kspin::SpinRaw;
@@ -37,6 +39,10 @@ percpu_static! {
     /// Stores the weak reference to the previous task that is running on this CPU.
     #[cfg(feature = "smp")]
     PREV_TASK: Weak<crate::AxTask> = Weak::new(),
+    /// Stores the previous task that is switched out for migrating to another CPU.
+    /// It will be put into the target run queue after its scheduling process is finished.
+    #[cfg(feature = "smp")]
+    MIGRATING_TASK: Option<AxTaskRef> = None,
 }
 
 /// An array of references to run queues, one for each CPU, indexed by cpu_id.
@@ -53,6 +59,111 @@ static mut RUN_QUEUES: [MaybeUninit<&'static mut AxRunQueue>; axconfig::SMP] =
 #[allow(clippy::declare_interior_mutable_const)] // It's ok because it's used only for initialization `RUN_QUEUES`.
 const ARRAY_REPEAT_VALUE: MaybeUninit<&'static mut AxRunQueue> = MaybeUninit::uninit();
 
+/// Whether the run queue of each CPU has been initialized in [`RUN_QUEUES`].
+///
+/// Load balancing only looks at the run queues that are ready, since secondary
+/// CPUs may still be booting when other CPUs start scheduling.
+#[cfg(feature = "smp")]
+static RUN_QUEUE_READY: [core::sync::atomic::AtomicBool; axconfig::SMP] =
+    [const { core::sync::atomic::AtomicBool::new(false) }; axconfig::SMP];
+
+/// Interval (in timer ticks) of the periodic load balancing on each CPU.
+#[cfg(all(feature = "smp", feature = "irq"))]
+const LOAD_BALANCE_INTERVAL_TICKS: usize = 10;
+
+/// Interval of the periodic timer tick.
+#[cfg(any(feature = "tickless", feature = "smp"))]
+pub(crate) const TICK_INTERVAL_NANOS: u64 =
+    axhal::time::NANOS_PER_SEC / axconfig::TICKS_PER_SEC as u64;
+
+/// The longest time an idle CPU can sleep with the tick stopped.
+///
+/// There is no way to wake up a remote idle CPU when tasks are put into its
+/// run queue, so in SMP systems idle CPUs wake up as often as the periodic
+/// load balancing, to run such tasks or pull tasks from busy CPUs.
+#[cfg(all(feature = "tickless", feature = "smp"))]
+pub(crate) const NOHZ_MAX_IDLE_NANOS: u64 =
+    TICK_INTERVAL_NANOS * LOAD_BALANCE_INTERVAL_TICKS as u64;
+#[cfg(all(feature = "tickless", not(feature = "smp")))]
+pub(crate) const NOHZ_MAX_IDLE_NANOS: u64 = axhal::time::NANOS_PER_SEC;
+
+/// Returns the monotonic time (in nanoseconds) at which an idle CPU with the
+/// tick stopped at `now` should be woken up, if the earliest timer event on
+/// it expires after `next_event`.
+///
+/// It is the expiry of the event, no matter how many tick periods away, but
+/// no later than [`NOHZ_MAX_IDLE_NANOS`] from now.
+#[cfg(feature = "tickless")]
+pub(crate) fn nohz_deadline(now: u64, next_event: Option<Duration>) -> u64 {
+    let max_deadline = now + NOHZ_MAX_IDLE_NANOS;
+    match next_event {
+        Some(delta) => max_deadline.min(now.saturating_add(delta.as_nanos() as u64)),
+        None => max_deadline,
+    }
+}
+
+/// The shortest time between two failed attempts of an idle CPU to pull tasks
+/// from other CPUs, so that it does not keep locking their run queues.
+#[cfg(feature = "smp")]
+const IDLE_PULL_INTERVAL_NANOS: u64 = TICK_INTERVAL_NANOS;
+
+/// The most pullable tasks that are looked at when pulling from a run queue,
+/// so that the lock of the remote scheduler is held for a bounded time.
+#[cfg(feature = "smp")]
+const MAX_PULL_CANDIDATES: usize = 4;
+
+static IDLE_PULLS: AtomicU64 = AtomicU64::new(0);
+static PERIODIC_PULLS: AtomicU64 = AtomicU64::new(0);
+static AFFINITY_MIGRATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Statistics of task migrations between run queues of different CPUs.
+///
+/// All counters are always zero if the `smp` feature is not enabled.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct MigrationStats {
+    /// Number of tasks pulled by CPUs that would otherwise be idle.
+    pub idle_pulls: u64,
+    /// Number of tasks pulled by the periodic load balancing.
+    pub periodic_pulls: u64,
+    /// Number of tasks moved because their CPU affinity no longer allowed
+    /// them to run on their previous CPU.
+    pub affinity_migrations: u64,
+}
+
+impl MigrationStats {
+    /// Returns the total number of migrations.
+    pub const fn total(&self) -> u64 {
+        self.idle_pulls + self.periodic_pulls + self.affinity_migrations
+    }
+}
+
+/// Returns the statistics of task migrations between CPUs since boot.
+pub fn migration_stats() -> MigrationStats {
+    MigrationStats {
+        idle_pulls: IDLE_PULLS.load(Ordering::Relaxed),
+        periodic_pulls: PERIODIC_PULLS.load(Ordering::Relaxed),
+        affinity_migrations: AFFINITY_MIGRATIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// Returns the time that the CPU `cpu_id` has spent running its idle task
+/// since boot, or `None` if the CPU does not exist or is not brought up yet.
+pub fn cpu_idle_time(cpu_id: usize) -> Option<Duration> {
+    if cpu_id >= axconfig::SMP {
+        return None;
+    }
+    // SAFETY: the idle task of each CPU is only initialized once.
+    let idle_task = unsafe { IDLE_TASK.remote_ref_raw(cpu_id) }.get()?;
+    Some(Duration::from_nanos(idle_task.stats().runtime_ns))
+}
+
+/// Records a migration of `task` to another CPU in `counter`.
+#[cfg(feature = "smp")]
+fn record_migration(task: &AxTaskRef, counter: &AtomicU64) {
+    task.account_migration();
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
 /// Returns a reference to the current run queue in [`CurrentRunQueueRef`].
 ///
 /// ## Safety
@@ -98,11 +209,23 @@ pub(crate) fn current_run_queue<G: BaseGuard>() -> CurrentRunQueueRef<'static, G
 #[allow(clippy::modulo_one)]
 #[inline]
 fn select_run_queue_index(cpumask: AxCpuMask) -> usize {
-    use core::sync::atomic::{AtomicUsize, Ordering};
     static RUN_QUEUE_INDEX: AtomicUsize = AtomicUsize::new(0);
 
     assert!(!cpumask.is_empty(), "No available CPU for task execution");
 
+    // Prefer CPUs with the periodic tick running, since an idle CPU with the
+    // tick stopped will not notice the new task until its next timer interrupt.
+    #[cfg(feature = "tickless")]
+    for _ in 0..axconfig::SMP {
+        let index = RUN_QUEUE_INDEX.fetch_add(1, Ordering::SeqCst) % axconfig::SMP;
+        if cpumask.get(index)
+            && !(RUN_QUEUE_READY[index].load(Ordering::Acquire)
+                && get_run_queue(index).tick_stopped.load(Ordering::Acquire))
+        {
+            return index;
+        }
+    }
+
     // Round-robin selection of the run queue index.
     loop {
         let index = RUN_QUEUE_INDEX.fetch_add(1, Ordering::SeqCst) % axconfig::SMP;
@@ -135,6 +258,40 @@ fn get_run_queue(index: usize) -> &'static mut AxRunQueue {
     unsafe { RUN_QUEUES[index].assume_init_mut() }
 }
 
+/// Retrieves the run queue that the provided task currently belongs to.
+///
+/// It is the run queue of the CPU that the task is running on, or the CPU
+/// whose run queue the task was last put into (see [`TaskInner::cpu_id`]).
+///
+/// Nothing pins the task to the returned run queue, it may be migrated to
+/// another one right after its CPU ID is read. The operations on it check the
+/// CPU ID again after locking the scheduler, and fail if it has changed, in
+/// which case the caller should retry with a new lookup.
+///
+/// ## Arguments
+///
+/// * `task` - A reference to the task whose run queue is being retrieved.
+///
+/// ## Returns
+///
+/// * [`AxRunQueueRef`] - a static reference to the [`AxRunQueue`] (current or remote).
+#[inline]
+pub(crate) fn task_run_queue<G: BaseGuard>(task: &AxTaskRef) -> AxRunQueueRef<'static, G> {
+    let irq_state = G::acquire();
+    #[cfg(not(feature = "smp"))]
+    let inner = {
+        let _ = task;
+        unsafe { RUN_QUEUE.current_ref_mut_raw() }
+    };
+    #[cfg(feature = "smp")]
+    let inner = get_run_queue(task.cpu_id());
+    AxRunQueueRef {
+        inner,
+        state: irq_state,
+        _phantom: core::marker::PhantomData,
+    }
+}
+
 /// Selects the appropriate run queue for the provided task.
 ///
 /// * In a single-core system, this function always returns a reference to the global run queue.
@@ -148,10 +305,13 @@ fn get_run_queue(index: usize) -> &'static mut AxRunQueue {
 ///
 /// * [`AxRunQueueRef`] - a static reference to the selected [`AxRunQueue`] (current or remote).
 ///
+/// Note that this is only the initial placement. Ready tasks may be pulled to
+/// other CPUs later by the idle-time and periodic load balancing, see
+/// `AxRunQueue::pull_task()`.
+///
 /// ## TODO
 ///
-/// 1. Implement better load balancing across CPUs for more efficient task distribution.
-/// 2. Use a more generic load balancing algorithm that can be customized or replaced.
+/// 1. Use a more generic load balancing algorithm that can be customized or replaced.
 ///
 #[inline]
 pub(crate) fn select_run_queue<G: BaseGuard>(task: &AxTaskRef) -> AxRunQueueRef<'static, G> {
@@ -186,6 +346,67 @@ pub(crate) struct AxRunQueue {
     /// Since irq and preempt are preserved by the kernel guard hold by `AxRunQueueRef`,
     /// we just use a simple raw spin lock here.
     scheduler: SpinRaw<Scheduler>,
+    /// The number of tasks in the scheduler, which can be read by other CPUs
+    /// without holding the lock of the scheduler for load balancing.
+    nr_queued: AtomicUsize,
+    /// The tasks in the scheduler that other CPUs can pull.
+    ///
+    /// It is only locked while holding the lock of the scheduler.
+    #[cfg(feature = "smp")]
+    pullable: SpinRaw<PullableTasks>,
+    /// Timer ticks since the last periodic load balancing.
+    #[cfg(all(feature = "smp", feature = "irq"))]
+    balance_ticks: usize,
+    /// The monotonic time (in nanoseconds) before which this CPU does not try
+    /// to pull tasks when it becomes idle, since the last attempt failed.
+    #[cfg(feature = "smp")]
+    next_idle_pull_nanos: u64,
+    /// Whether the periodic tick is stopped on this CPU since it is idle.
+    #[cfg(feature = "tickless")]
+    tick_stopped: core::sync::atomic::AtomicBool,
+}
+
+/// The tasks in the scheduler of a run queue that are allowed to run on other
+/// CPUs, from which other CPUs pull tasks for load balancing.
+///
+/// The scheduler has no way to peek its tasks, so they are tracked here when
+/// they enter and leave the scheduler. Each task records its index in the
+/// list (see [`TaskInner::pull_slot`]), so both take O(1). The affinity is
+/// checked when a task enters the scheduler, and again when it is pulled.
+#[cfg(feature = "smp")]
+struct PullableTasks(Vec<AxTaskRef>);
+
+#[cfg(feature = "smp")]
+impl PullableTasks {
+    fn push(&mut self, task: &AxTaskRef) {
+        task.set_pull_slot(Some(self.0.len()));
+        self.0.push(task.clone());
+    }
+
+    /// Removes the task if it is in the list.
+    ///
+    /// The caller holds a reference to the task, so it is never freed here.
+    fn remove(&mut self, task: &AxTaskRef) {
+        let Some(slot) = task.pull_slot() else {
+            return;
+        };
+        task.set_pull_slot(None);
+        self.0.swap_remove(slot);
+        if let Some(moved) = self.0.get(slot) {
+            moved.set_pull_slot(Some(slot));
+        }
+    }
+
+    /// Returns the most recently queued task that can be pulled to the CPU
+    /// `cpu_id`, among the last [`MAX_PULL_CANDIDATES`] tasks.
+    fn find(&self, cpu_id: usize) -> Option<AxTaskRef> {
+        self.0
+            .iter()
+            .rev()
+            .take(MAX_PULL_CANDIDATES)
+            .find(|task| task.cpumask().get(cpu_id) && !task.on_cpu())
+            .cloned()
+    }
 }
 
 /// A reference to the run queue with specific guard.
@@ -237,7 +458,12 @@ impl<G: BaseGuard> AxRunQueueRef<'_, G> {
             self.inner.cpu_id
         );
         assert!(task.is_ready());
-        self.inner.scheduler.lock().add_task(task);
+        let mut scheduler = self.inner.scheduler.lock();
+        self.inner.mark_queued(&task);
+        scheduler.add_task(task);
+        drop(scheduler);
+        #[cfg(feature = "sched-rt")]
+        self.inner.check_preempt_current();
     }
 
     /// Unblock one task by inserting it into the run queue.
@@ -264,7 +490,68 @@ impl<G: BaseGuard> AxRunQueueRef<'_, G> {
                 #[cfg(feature = "preempt")]
                 crate::current().set_preempt_pending(true);
             }
+            #[cfg(feature = "sched-rt")]
+            self.inner.check_preempt_current();
+        }
+    }
+
+    /// Locks the scheduler of this run queue if the given task still belongs
+    /// to it, otherwise returns `None`.
+    ///
+    /// The CPU ID of a task in a scheduler is only changed with holding the
+    /// lock of that scheduler, so the task can not leave this run queue until
+    /// the lock is released.
+    fn lock_scheduler_of(&self, task: &AxTaskRef) -> Option<kspin::SpinRawGuard<'_, Scheduler>> {
+        let scheduler = self.inner.scheduler.lock();
+        (task.cpu_id() == self.inner.cpu_id).then_some(scheduler)
+    }
+
+    /// Sets the priority of the given task, which belongs to this run queue.
+    ///
+    /// Returns whether the priority is accepted by the scheduler, or `None` if
+    /// the task has been migrated to another run queue.
+    pub fn set_task_priority(&mut self, task: &AxTaskRef, prio: isize) -> Option<bool> {
+        if self.lock_scheduler_of(task)?.set_priority(task, prio) {
+            task.set_priority_value(prio);
+            Some(true)
+        } else {
+            Some(false)
+        }
+    }
+
+    /// Sets the scheduling policy of the given task, which belongs to this run queue.
+    ///
+    /// If the task is in the scheduler of this run queue, it will be removed and
+    /// re-inserted according to the new policy.
+    ///
+    /// Returns `false` if the task has been migrated to another run queue.
+    #[cfg(feature = "sched-rt")]
+    pub fn set_task_sched_policy(&mut self, task: &AxTaskRef, policy: crate::SchedPolicy) -> bool {
+        let Some(mut scheduler) = self.lock_scheduler_of(task) else {
+            return false;
+        };
+        // The task can only be in the scheduler of the run queue it belongs to,
+        // and the flag is only updated with holding the lock of that scheduler.
+        let removed = if task.in_run_queue() {
+            scheduler.remove_task(task)
+        } else {
+            None
+        };
+        task.store_sched_policy(policy);
+        if let Some(task) = removed {
+            scheduler.add_task(task);
+        }
+        drop(scheduler);
+
+        if self.inner.cpu_id == this_cpu_id() && crate::current().ptr_eq(task) {
+            // The current task may have been lowered below other ready tasks.
+            if self.inner.scheduler.lock().need_preempt(task) {
+                task.set_preempt_pending(true);
+            }
+        } else {
+            self.inner.check_preempt_current();
         }
+        true
     }
 }
 
@@ -277,41 +564,85 @@ impl<G: BaseGuard> CurrentRunQueueRef<'_, G> {
             #[cfg(feature = "preempt")]
             curr.set_preempt_pending(true);
         }
+        #[cfg(feature = "smp")]
+        self.inner.periodic_balance();
+    }
+
+    /// Returns whether the periodic tick is stopped on this CPU.
+    #[cfg(feature = "tickless")]
+    pub fn is_tick_stopped(&self) -> bool {
+        self.inner.tick_stopped.load(Ordering::Acquire)
+    }
+
+    /// Stops the periodic tick on this CPU if the idle task is running and
+    /// there is nothing else to run (NO_HZ idle).
+    ///
+    /// The next timer interrupt is programmed at the earliest deadline of the
+    /// timer events on this CPU, but no later than [`NOHZ_MAX_IDLE_NANOS`] from
+    /// now. The tick is restarted once a task is put into this run queue on
+    /// this CPU, or this CPU switches from the idle task to another task.
+    #[cfg(feature = "tickless")]
+    pub fn stop_tick_if_idle(&mut self) {
+        if !self.current_task.is_idle() || self.inner.nr_queued.load(Ordering::Relaxed) != 0 {
+            return;
+        }
+        // Timer events use the wall time, convert it to the monotonic time.
+        let next_event = crate::timers::next_deadline()
+            .map(|next| next.saturating_sub(axhal::time::wall_time()));
+        let deadline = nohz_deadline(axhal::time::monotonic_time_nanos(), next_event);
+        if !self.inner.tick_stopped.swap(true, Ordering::AcqRel) {
+            trace!("tick stopped on CPU {}", self.inner.cpu_id);
+        }
+        axhal::time::set_oneshot_timer(deadline);
     }
 
     /// Yield the current task and reschedule.
     /// This function will put the current task into this run queue with `Ready` state,
     /// and reschedule to the next task on this run queue.
+    ///
+    /// If the current task is no longer allowed to run on this CPU (its affinity
+    /// has been changed by other tasks), it will be migrated instead.
     pub fn yield_current(&mut self) {
         let curr = &self.current_task;
         trace!("task yield: {}", curr.id_name());
         assert!(curr.is_running());
 
+        #[cfg(feature = "smp")]
+        if !curr.cpumask().get(self.inner.cpu_id) {
+            self.migrate_current(false);
+            return;
+        }
+
         self.inner
             .put_task_with_state(curr.clone(), TaskState::Running, false);
 
-        self.inner.resched();
+        self.inner.resched(false);
     }
 
     /// Migrate the current task to a new run queue matching its CPU affinity and reschedule.
-    /// This function will spawn a new `migration_task` to perform the migration, which will set
-    /// current task to `Ready` state and select a proper run queue for it according to its CPU affinity,
-    /// switch to the migration task immediately after migration task is prepared.
+    /// This function will set current task to `Ready` state and store it in the per-CPU
+    /// `MIGRATING_TASK`, then reschedule to the next task on this run queue. After the
+    /// current task is switched out, it is put into a proper run queue selected according to
+    /// its CPU affinity, see `clear_prev_task_on_cpu()`.
     ///
-    /// Note: the ownership if migrating task (which is current task) is handed over to the migration task,
-    /// before the migration task inserted it into the target run queue.
+    /// Note: the ownership of migrating task (which is current task) is handed over to `MIGRATING_TASK`,
+    /// before it is inserted into the target run queue.
     #[cfg(feature = "smp")]
-    pub fn migrate_current(&mut self, migration_task: AxTaskRef) {
+    pub fn migrate_current(&mut self, preempted: bool) {
         let curr = &self.current_task;
         trace!("task migrate: {}", curr.id_name());
         assert!(curr.is_running());
+        assert!(!curr.is_idle());
 
         // Mark current task's state as `Ready`,
         // but, do not put current task to the scheduler of this run queue.
         curr.set_state(TaskState::Ready);
+        // Safety: IRQs and preemption are disabled by the guard of `CurrentRunQueueRef`.
+        unsafe {
+            *MIGRATING_TASK.current_ref_mut_raw() = Some(curr.clone());
+        }
 
-        // Call `switch_to` to reschedule to the migration task that performs the migration directly.
-        self.inner.switch_to(crate::current(), migration_task);
+        self.inner.resched(preempted);
     }
 
     /// Preempts the current task and reschedules.
@@ -341,10 +672,15 @@ impl<G: BaseGuard> CurrentRunQueueRef<'_, G> {
             curr.id_name(),
             can_preempt
         );
+        #[cfg(feature = "smp")]
+        if can_preempt && !curr.cpumask().get(self.inner.cpu_id) {
+            self.migrate_current(true);
+            return;
+        }
         if can_preempt {
             self.inner
                 .put_task_with_state(curr.clone(), TaskState::Running, true);
-            self.inner.resched();
+            self.inner.resched(true);
         } else {
             curr.set_preempt_pending(true);
         }
@@ -380,7 +716,7 @@ impl<G: BaseGuard> CurrentRunQueueRef<'_, G> {
             }
 
             // Schedule to next task.
-            self.inner.resched();
+            self.inner.resched(false);
         }
         unreachable!("task exited!");
     }
@@ -416,7 +752,7 @@ impl<G: BaseGuard> CurrentRunQueueRef<'_, G> {
         // see `unblock_task()` for details.
 
         debug!("task block: {}", curr.id_name());
-        self.inner.resched();
+        self.inner.resched(false);
     }
 
     #[cfg(feature = "irq")]
@@ -430,16 +766,9 @@ impl<G: BaseGuard> CurrentRunQueueRef<'_, G> {
         if now < deadline {
             crate::timers::set_alarm_wakeup(deadline, curr.clone());
             curr.set_state(TaskState::Blocked);
-            self.inner.resched();
+            self.inner.resched(false);
         }
     }
-
-    pub fn set_current_priority(&mut self, prio: isize) -> bool {
-        self.inner
-            .scheduler
-            .lock()
-            .set_priority(self.current_task.as_task_ref(), prio)
-    }
 }
 
 impl AxRunQueue {
@@ -449,15 +778,71 @@ impl AxRunQueue {
         let gc_task = TaskInner::new(gc_entry, "gc".into(), axconfig::TASK_STACK_SIZE).into_arc();
         // gc task should be pinned to the current CPU.
         gc_task.set_cpumask(AxCpuMask::one_shot(cpu_id));
+        gc_task.set_cpu_id(cpu_id);
 
         let mut scheduler = Scheduler::new();
+        gc_task.set_in_run_queue(true);
         scheduler.add_task(gc_task);
         Self {
             cpu_id,
             scheduler: SpinRaw::new(scheduler),
+            nr_queued: AtomicUsize::new(1),
+            #[cfg(feature = "smp")]
+            pullable: SpinRaw::new(PullableTasks(Vec::new())),
+            #[cfg(all(feature = "smp", feature = "irq"))]
+            balance_ticks: 0,
+            #[cfg(feature = "smp")]
+            next_idle_pull_nanos: 0,
+            #[cfg(feature = "tickless")]
+            tick_stopped: core::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    /// Marks the task as being in the scheduler of this run queue.
+    ///
+    /// Must be called with holding the lock of the scheduler.
+    fn mark_queued(&self, task: &AxTaskRef) {
+        task.set_cpu_id(self.cpu_id);
+        task.set_in_run_queue(true);
+        self.nr_queued.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "smp")]
+        {
+            let cpumask = task.cpumask();
+            if (0..axconfig::SMP).any(|i| i != self.cpu_id && cpumask.get(i)) {
+                self.pullable.lock().push(task);
+            }
+        }
+        // There is work to do on this CPU, e.g., a task is woken up in an IRQ
+        // handler while the idle task is about to wait for IRQs.
+        #[cfg(feature = "tickless")]
+        if self.cpu_id == this_cpu_id() {
+            self.restart_tick();
         }
     }
 
+    /// Restarts the periodic tick if it is stopped on this CPU.
+    ///
+    /// Must be called on the CPU of this run queue with IRQs disabled.
+    #[cfg(feature = "tickless")]
+    fn restart_tick(&self) {
+        if self.tick_stopped.swap(false, Ordering::AcqRel) {
+            trace!("tick restarted on CPU {}", self.cpu_id);
+            axhal::time::set_oneshot_timer(
+                axhal::time::monotonic_time_nanos() + TICK_INTERVAL_NANOS,
+            );
+        }
+    }
+
+    /// Marks the task as having been taken out of the scheduler of this run queue.
+    ///
+    /// Must be called with holding the lock of the scheduler.
+    fn mark_dequeued(&self, task: &AxTaskRef) {
+        task.set_in_run_queue(false);
+        self.nr_queued.fetch_sub(1, Ordering::Relaxed);
+        #[cfg(feature = "smp")]
+        self.pullable.lock().remove(task);
+    }
+
     /// Puts target task into current run queue with `Ready` state
     /// if its state matches `current_state` (except idle task).
     ///
@@ -493,8 +878,9 @@ impl AxRunQueue {
                     core::hint::spin_loop();
                 }
             }
-            // TODO: priority
-            self.scheduler.lock().put_prev_task(task, preempt);
+            let mut scheduler = self.scheduler.lock();
+            self.mark_queued(&task);
+            scheduler.put_prev_task(task, preempt);
             true
         } else {
             false
@@ -503,25 +889,148 @@ impl AxRunQueue {
 
     /// Core reschedule subroutine.
     /// Pick the next task to run and switch to it.
-    fn resched(&mut self) {
-        let next = self
-            .scheduler
-            .lock()
-            .pick_next_task()
-            .unwrap_or_else(|| unsafe {
+    ///
+    /// `preempted` indicates whether the current task is forced to give up
+    /// the CPU, which is used for context switch accounting.
+    fn resched(&mut self, preempted: bool) {
+        let next = loop {
+            let mut scheduler = self.scheduler.lock();
+            let Some(task) = scheduler.pick_next_task() else {
+                drop(scheduler);
+                // Nothing to run on this CPU, try to pull a task from other CPUs
+                // before going idle.
+                #[cfg(feature = "smp")]
+                if let Some(task) = self.idle_pull() {
+                    record_migration(&task, &IDLE_PULLS);
+                    break task;
+                }
                 // Safety: IRQs must be disabled at this time.
-                IDLE_TASK.current_ref_raw().get_unchecked().clone()
-            });
+                break unsafe { IDLE_TASK.current_ref_raw().get_unchecked().clone() };
+            };
+            self.mark_dequeued(&task);
+            drop(scheduler);
+            // The affinity of the picked task may have been changed after it was
+            // put into this run queue, forward it to a run queue matching its affinity.
+            // Note: the current task is still running on this CPU, it will be
+            // migrated at its next scheduling point.
+            #[cfg(feature = "smp")]
+            if !task.cpumask().get(self.cpu_id) && !crate::current().ptr_eq(&task) {
+                record_migration(&task, &AFFINITY_MIGRATIONS);
+                select_run_queue::<kernel_guard::NoOp>(&task).add_task(task);
+                continue;
+            }
+            break task;
+        };
         assert!(
             next.is_ready(),
             "next {} is not ready: {:?}",
             next.id_name(),
             next.state()
         );
-        self.switch_to(crate::current(), next);
+        self.switch_to(crate::current(), next, preempted);
+    }
+
+    /// Tries to pull a ready task from the busiest run queue of other CPUs,
+    /// which must have at least `min_queued` tasks in its scheduler.
+    ///
+    /// The candidates are the pullable tasks of that run queue (see
+    /// [`PullableTasks`]) that are allowed to run on this CPU and have finished
+    /// their scheduling process on that CPU (e.g., they have not just
+    /// yielded). We must not wait for the latter, since the remote CPU may be
+    /// pulling from this run queue at the same time. The candidate is then
+    /// removed from the remote scheduler, leaving the order of the other tasks
+    /// there untouched.
+    ///
+    /// The returned task is in `Ready` state and belongs to this run queue, but
+    /// is not in its scheduler.
+    #[cfg(feature = "smp")]
+    fn pull_task(&self, min_queued: usize) -> Option<AxTaskRef> {
+        let (busiest, _) = (0..axconfig::SMP)
+            .filter(|&i| i != self.cpu_id && RUN_QUEUE_READY[i].load(Ordering::Acquire))
+            .map(|i| (i, get_run_queue(i).nr_queued.load(Ordering::Relaxed)))
+            .filter(|&(_, nr_queued)| nr_queued >= min_queued.max(1))
+            .max_by_key(|&(_, nr_queued)| nr_queued)?;
+
+        let src = get_run_queue(busiest);
+        let mut scheduler = src.scheduler.lock();
+        // The scheduler holds a reference to the candidate, so dropping the
+        // one taken here never frees the task under the lock.
+        let candidate = src.pullable.lock().find(self.cpu_id)?;
+        let task = scheduler.remove_task(&candidate)?;
+        src.mark_dequeued(&task);
+        // Changed before the lock is released, see `lock_scheduler_of()`.
+        task.set_cpu_id(self.cpu_id);
+        drop(scheduler);
+
+        debug!(
+            "task pull: {} from run_queue {} to {}",
+            task.id_name(),
+            busiest,
+            self.cpu_id
+        );
+        Some(task)
+    }
+
+    /// Tries to pull a task from other CPUs when this CPU has nothing to run.
+    ///
+    /// After a failed attempt, no further attempts are made in the next
+    /// [`IDLE_PULL_INTERVAL_NANOS`], as the idle task keeps rescheduling.
+    #[cfg(feature = "smp")]
+    fn idle_pull(&mut self) -> Option<AxTaskRef> {
+        let now = axhal::time::monotonic_time_nanos();
+        if now < self.next_idle_pull_nanos {
+            return None;
+        }
+        let task = self.pull_task(1);
+        self.next_idle_pull_nanos = match task {
+            Some(_) => 0,
+            None => now + IDLE_PULL_INTERVAL_NANOS,
+        };
+        task
+    }
+
+    /// Periodic load balancing, called on every timer tick.
+    ///
+    /// Every [`LOAD_BALANCE_INTERVAL_TICKS`] ticks, a ready task is pulled from
+    /// the busiest run queue into this one, if the former has at least two
+    /// more tasks waiting in its scheduler.
+    #[cfg(all(feature = "smp", feature = "irq"))]
+    fn periodic_balance(&mut self) {
+        self.balance_ticks += 1;
+        if self.balance_ticks < LOAD_BALANCE_INTERVAL_TICKS {
+            return;
+        }
+        self.balance_ticks = 0;
+
+        let min_queued = self.nr_queued.load(Ordering::Relaxed) + 2;
+        if let Some(task) = self.pull_task(min_queued) {
+            record_migration(&task, &PERIODIC_PULLS);
+            let mut scheduler = self.scheduler.lock();
+            self.mark_queued(&task);
+            scheduler.add_task(task);
+            drop(scheduler);
+            #[cfg(feature = "sched-rt")]
+            self.check_preempt_current();
+        }
     }
 
-    fn switch_to(&mut self, prev_task: CurrentTask, next_task: AxTaskRef) {
+    /// Requests rescheduling of the current task if it should be preempted by
+    /// a ready real-time task in this run queue.
+    ///
+    /// It does nothing if this run queue is not on the current CPU. Remote CPUs
+    /// will check it on their next timer tick (see `RtScheduler::task_tick`).
+    #[cfg(feature = "sched-rt")]
+    fn check_preempt_current(&self) {
+        if self.cpu_id != this_cpu_id() {
+            return;
+        }
+        let curr = crate::current();
+        if !curr.is_idle() && self.scheduler.lock().need_preempt(curr.as_task_ref()) {
+            curr.set_preempt_pending(true);
+        }
+    }
+
+    fn switch_to(&mut self, prev_task: CurrentTask, next_task: AxTaskRef, preempted: bool) {
         // Make sure that IRQs are disabled by kernel guard or other means.
         #[cfg(all(not(test), feature = "irq"))] // Note: irq is faked under unit tests.
         assert!(
@@ -539,6 +1048,16 @@ impl AxRunQueue {
         if prev_task.ptr_eq(&next_task) {
             return;
         }
+        prev_task.account_switch_out(preempted);
+        // The stack of an exited task is freed after switching out, so it
+        // should be checked here.
+        #[cfg(not(feature = "paging"))]
+        prev_task.check_stack_overflow();
+        // Leaving the idle task, the tick is needed for scheduling again.
+        #[cfg(feature = "tickless")]
+        if prev_task.is_idle() {
+            self.restart_tick();
+        }
 
         // Claim the task as running, we do this before switching to it
         // such that any running task will have this set.
@@ -597,20 +1116,10 @@ fn gc_entry() {
     }
 }
 
-/// The task routine for migrating the current task to the correct CPU.
-///
-/// It calls `select_run_queue` to get the correct run queue for the task, and
-/// then puts the task to the scheduler of target run queue.
-#[cfg(feature = "smp")]
-pub(crate) fn migrate_entry(migrated_task: AxTaskRef) {
-    select_run_queue::<kernel_guard::NoPreemptIrqSave>(&migrated_task)
-        .inner
-        .scheduler
-        .lock()
-        .put_prev_task(migrated_task, false)
-}
-
 /// Clear the `on_cpu` field of previous task running on this CPU.
+///
+/// If the previous task is migrating (see `migrate_current()`), it is put into
+/// a run queue matching its CPU affinity since it has finished its scheduling process.
 #[cfg(feature = "smp")]
 pub(crate) unsafe fn clear_prev_task_on_cpu() {
     unsafe {
@@ -619,8 +1128,21 @@ pub(crate) unsafe fn clear_prev_task_on_cpu() {
             .upgrade()
             .expect("Invalid prev_task pointer or prev_task has been dropped")
             .set_on_cpu(false);
+        // IRQs are disabled at this time, so we can use the `NoOp` guard.
+        if let Some(task) = MIGRATING_TASK.current_ref_mut_raw().take() {
+            record_migration(&task, &AFFINITY_MIGRATIONS);
+            select_run_queue::<kernel_guard::NoOp>(&task).add_task(task);
+        }
     }
 }
+/// Restarts the periodic tick on the current CPU if it is stopped.
+///
+/// Must be called with IRQs disabled.
+#[cfg(feature = "tickless")]
+pub(crate) fn restart_current_tick() {
+    unsafe { RUN_QUEUE.current_ref_raw() }.restart_tick();
+}
+
 pub(crate) fn init() {
     let cpu_id = this_cpu_id();
 
@@ -629,6 +1151,7 @@ pub(crate) fn init() {
     let idle_task = TaskInner::new(|| crate::run_idle(), "idle".into(), IDLE_TASK_STACK_SIZE);
     // idle task should be pinned to the current CPU.
     idle_task.set_cpumask(AxCpuMask::one_shot(cpu_id));
+    idle_task.set_cpu_id(cpu_id);
     IDLE_TASK.with_current(|i| {
         i.init_once(idle_task.into_arc());
     });
@@ -636,6 +1159,7 @@ pub(crate) fn init() {
     // Put the subsequent execution into the `main` task.
     let main_task = TaskInner::new_init("main".into()).into_arc();
     main_task.set_state(TaskState::Running);
+    main_task.set_cpu_id(cpu_id);
     unsafe { CurrentTask::init_current(main_task) }
 
     RUN_QUEUE.with_current(|rq| {
@@ -644,6 +1168,8 @@ pub(crate) fn init() {
     unsafe {
         RUN_QUEUES[cpu_id].write(RUN_QUEUE.current_ref_mut_raw());
     }
+    #[cfg(feature = "smp")]
+    RUN_QUEUE_READY[cpu_id].store(true, Ordering::Release);
 }
 
 pub(crate) fn init_secondary() {
@@ -652,6 +1178,7 @@ pub(crate) fn init_secondary() {
     // Put the subsequent execution into the `idle` task.
     let idle_task = TaskInner::new_init("idle".into()).into_arc();
     idle_task.set_state(TaskState::Running);
+    idle_task.set_cpu_id(cpu_id);
     IDLE_TASK.with_current(|i| {
         i.init_once(idle_task.clone());
     });
@@ -663,4 +1190,18 @@ pub(crate) fn init_secondary() {
     unsafe {
         RUN_QUEUES[cpu_id].write(RUN_QUEUE.current_ref_mut_raw());
     }
+    #[cfg(feature = "smp")]
+    RUN_QUEUE_READY[cpu_id].store(true, Ordering::Release);
+}
+
+/// Brings up the run queue of the CPU `cpu_id` without running the CPU, so
+/// that the unit tests, which run on one CPU, can check that the tasks queued
+/// on it are pulled by the running CPU.
+#[cfg(all(test, feature = "smp"))]
+pub(crate) fn init_offline_run_queue(cpu_id: usize) {
+    let rq = alloc::boxed::Box::leak(alloc::boxed::Box::new(AxRunQueue::new(cpu_id)));
+    unsafe {
+        RUN_QUEUES[cpu_id].write(rq);
+    }
+    RUN_QUEUE_READY[cpu_id].store(true, Ordering::Release);
 }
@@ -0,0 +1,116 @@
+//! Round-robin scheduling with a time slice that is tunable at runtime.
+//!
+//! The same time slice is used by the normal tasks with the `sched-rr`
+//! feature, and by the [`SchedPolicy::RoundRobin`] real-time tasks with the
+//! `sched-rt` feature. It is read from [`time_slice`] whenever a task gets a
+//! new slice, so it can be changed by [`set_time_slice`] while tasks run.
+//!
+//! [`SchedPolicy::RoundRobin`]: crate::SchedPolicy::RoundRobin
+
+use core::sync::atomic::{AtomicIsize, Ordering};
+
+use crate::AxTaskRef;
+
+/// Time slice (in timer ticks) of the round-robin tasks.
+static TIME_SLICE: AtomicIsize = AtomicIsize::new(5);
+
+/// Returns the time slice (in timer ticks) of the round-robin tasks.
+pub fn time_slice() -> usize {
+    TIME_SLICE.load(Ordering::Relaxed) as usize
+}
+
+/// Sets the time slice (in timer ticks) of the round-robin tasks.
+///
+/// The new time slice takes effect when the running tasks use up their
+/// current slices. Returns `false` if `ticks` is zero or too large.
+pub fn set_time_slice(ticks: usize) -> bool {
+    if ticks == 0 || ticks > isize::MAX as usize {
+        return false;
+    }
+    TIME_SLICE.store(ticks as isize, Ordering::Relaxed);
+    true
+}
+
+/// Returns whether a round-robin task that is put back to its ready queue
+/// keeps its position at the head of the queue.
+///
+/// A preempted task that still has time slice left keeps its position.
+/// Otherwise, it gets a new time slice and should go to the tail.
+pub(crate) fn keeps_head(prev: &AxTaskRef, preempt: bool) -> bool {
+    if prev.time_slice() > 0 && preempt {
+        true
+    } else {
+        prev.reset_time_slice();
+        false
+    }
+}
+
+/// Consumes one tick of the time slice of the running round-robin task, and
+/// returns whether the time slice is used up.
+pub(crate) fn tick(current: &AxTaskRef) -> bool {
+    current.consume_time_slice() <= 0
+}
+
+/// A simple round-robin scheduler for normal tasks.
+///
+/// It works like [`scheduler::RRScheduler`], but with the time slice that is
+/// tunable at runtime.
+#[cfg(feature = "sched-rr")]
+pub(crate) struct RRScheduler {
+    ready_queue: alloc::collections::VecDeque<AxTaskRef>,
+}
+
+#[cfg(feature = "sched-rr")]
+impl RRScheduler {
+    /// Creates a new empty scheduler.
+    pub fn new() -> Self {
+        Self {
+            ready_queue: alloc::collections::VecDeque::new(),
+        }
+    }
+
+    /// Returns the name of the scheduler.
+    pub fn scheduler_name() -> &'static str {
+        "Round-robin"
+    }
+}
+
+#[cfg(feature = "sched-rr")]
+impl scheduler::BaseScheduler for RRScheduler {
+    type SchedItem = AxTaskRef;
+
+    fn init(&mut self) {}
+
+    fn add_task(&mut self, task: Self::SchedItem) {
+        task.reset_time_slice();
+        self.ready_queue.push_back(task);
+    }
+
+    fn remove_task(&mut self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        let index = self
+            .ready_queue
+            .iter()
+            .position(|t| alloc::sync::Arc::ptr_eq(t, task))?;
+        self.ready_queue.remove(index)
+    }
+
+    fn pick_next_task(&mut self) -> Option<Self::SchedItem> {
+        self.ready_queue.pop_front()
+    }
+
+    fn put_prev_task(&mut self, prev: Self::SchedItem, preempt: bool) {
+        if keeps_head(&prev, preempt) {
+            self.ready_queue.push_front(prev);
+        } else {
+            self.ready_queue.push_back(prev);
+        }
+    }
+
+    fn task_tick(&mut self, current: &Self::SchedItem) -> bool {
+        tick(current)
+    }
+
+    fn set_priority(&mut self, _task: &Self::SchedItem, _prio: isize) -> bool {
+        false
+    }
+}
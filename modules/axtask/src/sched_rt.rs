@@ -0,0 +1,152 @@
+//! The layered scheduler with a real-time scheduling class.
+//!
+//! Real-time tasks ([`SchedPolicy::Fifo`] and [`SchedPolicy::RoundRobin`])
+//! are always picked before normal tasks. Among real-time tasks, the one with
+//! the highest priority is picked first, and tasks with the same priority are
+//! scheduled in FIFO order. Normal tasks ([`SchedPolicy::Normal`]) are
+//! scheduled by the underlying [`NormalScheduler`] when there are no ready
+//! real-time tasks.
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use scheduler::BaseScheduler;
+
+use crate::task::{RT_PRIO_MAX, SchedPolicy};
+use crate::{AxTaskRef, NormalScheduler, sched_rr};
+
+const NUM_RT_QUEUES: usize = RT_PRIO_MAX as usize + 1;
+
+/// A scheduler that layers a fixed-priority real-time class on top of the
+/// normal scheduler.
+pub(crate) struct RtScheduler {
+    /// Ready queues of real-time tasks, indexed by the real-time priority.
+    /// Index `0` is not used.
+    rt_queues: [VecDeque<AxTaskRef>; NUM_RT_QUEUES],
+    /// Bit `i` is set if `rt_queues[i]` is not empty.
+    rt_bitmap: u128,
+    /// The scheduler for normal tasks.
+    normal: NormalScheduler,
+}
+
+impl RtScheduler {
+    /// Creates a new empty scheduler.
+    pub fn new() -> Self {
+        Self {
+            rt_queues: [const { VecDeque::new() }; NUM_RT_QUEUES],
+            rt_bitmap: 0,
+            normal: NormalScheduler::new(),
+        }
+    }
+
+    /// Returns the name of the scheduler.
+    pub fn scheduler_name() -> &'static str {
+        "Real-time"
+    }
+
+    /// Returns the highest priority of ready real-time tasks, or `0` if there
+    /// are no ready real-time tasks.
+    fn highest_rt_prio(&self) -> u8 {
+        if self.rt_bitmap == 0 {
+            0
+        } else {
+            (u128::BITS - 1 - self.rt_bitmap.leading_zeros()) as u8
+        }
+    }
+
+    /// Returns whether a ready task in this scheduler should preempt the given
+    /// task, i.e., there is a ready real-time task with a higher priority.
+    pub fn need_preempt(&self, curr: &AxTaskRef) -> bool {
+        self.highest_rt_prio() > curr.sched_policy().rt_priority()
+    }
+
+    fn push_rt(&mut self, task: AxTaskRef, prio: u8, front: bool) {
+        let queue = &mut self.rt_queues[prio as usize];
+        if front {
+            queue.push_front(task);
+        } else {
+            queue.push_back(task);
+        }
+        self.rt_bitmap |= 1 << prio;
+    }
+
+    fn pop_rt(&mut self) -> Option<AxTaskRef> {
+        let prio = self.highest_rt_prio();
+        if prio == 0 {
+            return None;
+        }
+        let queue = &mut self.rt_queues[prio as usize];
+        let task = queue.pop_front();
+        if queue.is_empty() {
+            self.rt_bitmap &= !(1 << prio);
+        }
+        task
+    }
+}
+
+impl BaseScheduler for RtScheduler {
+    type SchedItem = AxTaskRef;
+
+    fn init(&mut self) {
+        self.normal.init();
+    }
+
+    fn add_task(&mut self, task: Self::SchedItem) {
+        match task.sched_policy() {
+            SchedPolicy::Normal => self.normal.add_task(task),
+            SchedPolicy::Fifo(prio) => self.push_rt(task, prio, false),
+            SchedPolicy::RoundRobin(prio) => {
+                task.reset_time_slice();
+                self.push_rt(task, prio, false);
+            }
+        }
+    }
+
+    fn remove_task(&mut self, task: &Self::SchedItem) -> Option<Self::SchedItem> {
+        let prio = task.sched_policy().rt_priority();
+        if prio == 0 {
+            return self.normal.remove_task(task);
+        }
+        let queue = &mut self.rt_queues[prio as usize];
+        let index = queue.iter().position(|t| Arc::ptr_eq(t, task))?;
+        let task = queue.remove(index);
+        if queue.is_empty() {
+            self.rt_bitmap &= !(1 << prio);
+        }
+        task
+    }
+
+    fn pick_next_task(&mut self) -> Option<Self::SchedItem> {
+        self.pop_rt().or_else(|| self.normal.pick_next_task())
+    }
+
+    fn put_prev_task(&mut self, prev: Self::SchedItem, preempt: bool) {
+        match prev.sched_policy() {
+            SchedPolicy::Normal => self.normal.put_prev_task(prev, preempt),
+            // A preempted real-time task keeps its position at the head of its
+            // priority queue, otherwise (e.g., yielded) it goes to the tail.
+            SchedPolicy::Fifo(prio) => self.push_rt(prev, prio, preempt),
+            SchedPolicy::RoundRobin(prio) => {
+                let front = sched_rr::keeps_head(&prev, preempt);
+                self.push_rt(prev, prio, front);
+            }
+        }
+    }
+
+    fn task_tick(&mut self, current: &Self::SchedItem) -> bool {
+        let policy = current.sched_policy();
+        let expired = match policy {
+            SchedPolicy::Normal => self.normal.task_tick(current),
+            SchedPolicy::Fifo(_) => false,
+            SchedPolicy::RoundRobin(_) => sched_rr::tick(current),
+        };
+        expired || self.need_preempt(current)
+    }
+
+    fn set_priority(&mut self, task: &Self::SchedItem, prio: isize) -> bool {
+        // The priority of real-time tasks can only be changed by `set_sched_policy`.
+        match task.sched_policy() {
+            SchedPolicy::Normal => self.normal.set_priority(task, prio),
+            _ => false,
+        }
+    }
+}
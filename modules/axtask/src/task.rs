@@ -1,11 +1,10 @@
 use alloc::{boxed::Box, string::String, sync::Arc};
 use core::ops::Deref;
-use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU8, AtomicU64, Ordering};
+use core::sync::atomic::{
+    AtomicBool, AtomicI32, AtomicIsize, AtomicU8, AtomicU16, AtomicU64, AtomicUsize, Ordering,
+};
 use core::{alloc::Layout, cell::UnsafeCell, fmt, ptr::NonNull};
 
-#[cfg(feature = "preempt")]
-use core::sync::atomic::AtomicUsize;
-
 use kspin::SpinNoIrq;
 use memory_addr::{VirtAddr, align_up_4k};
 
@@ -13,17 +12,18 @@ use axhal::context::TaskContext;
 #[cfg(feature = "tls")]
 use axhal::tls::TlsArea;
 
+use crate::pi::PiState;
 use crate::task_ext::AxTaskExt;
 use crate::{AxCpuMask, AxTask, AxTaskRef, WaitQueue};
 
 /// A unique identifier for a thread.
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct TaskId(u64);
 
 /// The possible states of a task.
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub(crate) enum TaskState {
+pub enum TaskState {
     /// Task is running on some CPU.
     Running = 1,
     /// Task is ready to run on some scheduler's ready queue.
@@ -35,6 +35,102 @@ pub(crate) enum TaskState {
     Exited = 4,
 }
 
+/// The maximum real-time priority. Valid real-time priorities are
+/// `1..=RT_PRIO_MAX`, a larger value means a higher priority.
+pub const RT_PRIO_MAX: u8 = 99;
+
+/// The scheduling policy of a task.
+///
+/// Real-time policies are only available with the `sched-rt` feature. A
+/// real-time task always preempts normal tasks, and a real-time task with a
+/// higher priority always preempts the ones with lower priorities.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum SchedPolicy {
+    /// The normal (time-sharing) policy. Tasks are scheduled by the scheduler
+    /// selected by the `sched-fifo`, `sched-rr` or `sched-cfs` feature.
+    #[default]
+    Normal,
+    /// The real-time first-in-first-out policy with the given priority
+    /// (`1..=99`, higher is more urgent). The task runs until it blocks,
+    /// yields, or is preempted by a higher priority real-time task.
+    Fifo(u8),
+    /// The real-time round-robin policy with the given priority (`1..=99`).
+    /// It is the same as [`SchedPolicy::Fifo`], except that tasks with the
+    /// same priority share the CPU by time slices.
+    RoundRobin(u8),
+}
+
+impl SchedPolicy {
+    /// Returns the real-time priority, or `0` for [`SchedPolicy::Normal`].
+    pub const fn rt_priority(&self) -> u8 {
+        match self {
+            Self::Normal => 0,
+            Self::Fifo(prio) | Self::RoundRobin(prio) => *prio,
+        }
+    }
+
+    /// Returns whether the policy is supported in the current configuration.
+    pub const fn is_valid(&self) -> bool {
+        match self {
+            Self::Normal => true,
+            Self::Fifo(prio) | Self::RoundRobin(prio) => {
+                cfg!(feature = "sched-rt") && *prio >= 1 && *prio <= RT_PRIO_MAX
+            }
+        }
+    }
+
+    const fn encode(&self) -> u16 {
+        match self {
+            Self::Normal => 0,
+            Self::Fifo(prio) => (1 << 8) | *prio as u16,
+            Self::RoundRobin(prio) => (2 << 8) | *prio as u16,
+        }
+    }
+
+    const fn decode(value: u16) -> Self {
+        let prio = value as u8;
+        match value >> 8 {
+            1 => Self::Fifo(prio),
+            2 => Self::RoundRobin(prio),
+            _ => Self::Normal,
+        }
+    }
+}
+
+/// CPU accounting statistics of a task.
+///
+/// All times are in nanoseconds, measured by [`axhal::time::monotonic_time_nanos`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct TaskStats {
+    /// Total time spent running on a CPU.
+    pub runtime_ns: u64,
+    /// Total time spent in [`TaskState::Ready`], waiting to be scheduled.
+    pub ready_ns: u64,
+    /// Total time spent in [`TaskState::Blocked`], e.g., sleeping or waiting
+    /// in a wait queue.
+    pub blocked_ns: u64,
+    /// Number of context switches in which the task gave up the CPU by itself
+    /// (yield, block, sleep, exit, etc).
+    pub voluntary_switches: u64,
+    /// Number of context switches in which the task was preempted.
+    pub involuntary_switches: u64,
+    /// Number of times the task was moved from one CPU's run queue to
+    /// another's, by load balancing or affinity changes.
+    pub migrations: u64,
+}
+
+/// The raw counters of [`TaskStats`].
+struct TaskAccounting {
+    /// The timestamp of the last state change.
+    last_update_ns: AtomicU64,
+    runtime_ns: AtomicU64,
+    ready_ns: AtomicU64,
+    blocked_ns: AtomicU64,
+    voluntary_switches: AtomicU64,
+    involuntary_switches: AtomicU64,
+    migrations: AtomicU64,
+}
+
 /// The inner task structure.
 pub struct TaskInner {
     id: TaskId,
@@ -47,6 +143,24 @@ pub struct TaskInner {
 
     /// CPU affinity mask.
     cpumask: SpinNoIrq<AxCpuMask>,
+    /// The ID of the CPU whose run queue the task belongs to.
+    cpu_id: AtomicUsize,
+    /// The priority of the task, last accepted by the scheduler.
+    priority: AtomicIsize,
+    /// The scheduling policy of the task, encoded from [`SchedPolicy`].
+    sched_policy: AtomicU16,
+    /// The remaining time slice (in timer ticks) of a round-robin task.
+    #[cfg(any(feature = "sched-rr", feature = "sched-rt"))]
+    time_slice: AtomicIsize,
+
+    /// Mark whether the task is in the scheduler of a run queue.
+    in_run_queue: AtomicBool,
+
+    /// Priority inheritance state.
+    pi: SpinNoIrq<PiState>,
+
+    /// CPU time accounting.
+    acct: TaskAccounting,
 
     /// Mark whether the task is in the wait queue.
     in_wait_queue: AtomicBool,
@@ -54,6 +168,10 @@ pub struct TaskInner {
     /// Used to indicate whether the task is running on a CPU.
     #[cfg(feature = "smp")]
     on_cpu: AtomicBool,
+    /// The index of the task in the tasks that other CPUs can pull from its
+    /// run queue, or `usize::MAX` if it is not one of them.
+    #[cfg(feature = "smp")]
+    pull_slot: AtomicUsize,
 
     /// A ticket ID used to identify the timer event.
     /// Set by `set_timer_ticket()` when creating a timer event in `set_alarm_wakeup()`,
@@ -89,6 +207,67 @@ impl TaskId {
     }
 }
 
+impl From<u64> for TaskId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl TaskAccounting {
+    fn new() -> Self {
+        Self {
+            last_update_ns: AtomicU64::new(axhal::time::monotonic_time_nanos()),
+            runtime_ns: AtomicU64::new(0),
+            ready_ns: AtomicU64::new(0),
+            blocked_ns: AtomicU64::new(0),
+            voluntary_switches: AtomicU64::new(0),
+            involuntary_switches: AtomicU64::new(0),
+            migrations: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the counter that accumulates the time spent in `state`.
+    fn counter(&self, state: TaskState) -> Option<&AtomicU64> {
+        match state {
+            TaskState::Running => Some(&self.runtime_ns),
+            TaskState::Ready => Some(&self.ready_ns),
+            TaskState::Blocked => Some(&self.blocked_ns),
+            TaskState::Exited => None,
+        }
+    }
+
+    /// Charges the time elapsed since the last state change to `old_state`.
+    fn on_state_change(&self, old_state: TaskState) {
+        let now = axhal::time::monotonic_time_nanos();
+        let last = self.last_update_ns.swap(now, Ordering::AcqRel);
+        if let Some(counter) = self.counter(old_state) {
+            counter.fetch_add(now.saturating_sub(last), Ordering::Relaxed);
+        }
+    }
+
+    fn snapshot(&self, state: TaskState) -> TaskStats {
+        let mut stats = TaskStats {
+            runtime_ns: self.runtime_ns.load(Ordering::Relaxed),
+            ready_ns: self.ready_ns.load(Ordering::Relaxed),
+            blocked_ns: self.blocked_ns.load(Ordering::Relaxed),
+            voluntary_switches: self.voluntary_switches.load(Ordering::Relaxed),
+            involuntary_switches: self.involuntary_switches.load(Ordering::Relaxed),
+            migrations: self.migrations.load(Ordering::Relaxed),
+        };
+        // Include the time spent in the current state, which has not been
+        // charged yet.
+        let pending = axhal::time::monotonic_time_nanos()
+            .saturating_sub(self.last_update_ns.load(Ordering::Acquire));
+        match state {
+            TaskState::Running => stats.runtime_ns += pending,
+            TaskState::Ready => stats.ready_ns += pending,
+            TaskState::Blocked => stats.blocked_ns += pending,
+            TaskState::Exited => {}
+        }
+        stats
+    }
+}
+
 impl From<u8> for TaskState {
     #[inline]
     fn from(state: u8) -> Self {
@@ -139,6 +318,86 @@ impl TaskInner {
         self.name.as_str()
     }
 
+    /// Gets the current state of the task.
+    #[inline]
+    pub fn state(&self) -> TaskState {
+        self.state.load(Ordering::Acquire).into()
+    }
+
+    /// Gets the ID of the CPU that the task is running on, or the CPU whose
+    /// run queue the task was last put into.
+    #[inline]
+    pub fn cpu_id(&self) -> usize {
+        self.cpu_id.load(Ordering::Acquire)
+    }
+
+    /// Gets the priority of the task.
+    ///
+    /// It is the last priority successfully set by [`set_priority`], or `0` if
+    /// it has never been set.
+    ///
+    /// [`set_priority`]: crate::set_priority
+    #[inline]
+    pub fn priority(&self) -> isize {
+        self.priority.load(Ordering::Acquire)
+    }
+
+    /// Gets the scheduling policy of the task.
+    #[inline]
+    pub fn sched_policy(&self) -> SchedPolicy {
+        SchedPolicy::decode(self.sched_policy.load(Ordering::Acquire))
+    }
+
+    /// Sets the scheduling policy of a task that has not been spawned.
+    ///
+    /// Returns `false` if the policy is not valid (see [`SchedPolicy::is_valid`]).
+    /// To change the policy of a spawned task, use [`set_sched_policy`].
+    ///
+    /// [`set_sched_policy`]: crate::set_sched_policy
+    pub fn set_sched_policy(&mut self, policy: SchedPolicy) -> bool {
+        if policy.is_valid() {
+            self.store_sched_policy(policy);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gets the CPU accounting statistics of the task.
+    ///
+    /// The time spent in the current state is included, so it can be called on
+    /// a running task (e.g., the current task) to get its up-to-date runtime.
+    pub fn stats(&self) -> TaskStats {
+        self.acct.snapshot(self.state())
+    }
+
+    /// Gets the size of the kernel stack of the task.
+    ///
+    /// Returns `0` for the init tasks (e.g., `main` task), whose stacks are not
+    /// allocated by the task module.
+    #[inline]
+    pub fn kernel_stack_size(&self) -> usize {
+        self.kstack.as_ref().map_or(0, |s| s.size())
+    }
+
+    /// Panics if the kernel stack of the task has overflowed, i.e., the canary
+    /// at the bottom of the stack is overwritten.
+    ///
+    /// It is checked on every context switch. With the `paging` feature,
+    /// overflows are caught by the guard page instead.
+    #[cfg(not(feature = "paging"))]
+    pub(crate) fn check_stack_overflow(&self) {
+        if let Some(kstack) = &self.kstack {
+            if !kstack.canary_intact() {
+                panic!(
+                    "stack overflow in task {} ({:?})",
+                    self.id.as_u64(),
+                    self.name
+                );
+            }
+        }
+    }
+
     /// Get a combined string of the task ID and name.
     pub fn id_name(&self) -> alloc::string::String {
         alloc::format!("Task({}, {:?})", self.id.as_u64(), self.name)
@@ -223,11 +482,21 @@ impl TaskInner {
             state: AtomicU8::new(TaskState::Ready as u8),
             // By default, the task is allowed to run on all CPUs.
             cpumask: SpinNoIrq::new(AxCpuMask::full()),
+            cpu_id: AtomicUsize::new(0),
+            priority: AtomicIsize::new(0),
+            sched_policy: AtomicU16::new(SchedPolicy::Normal.encode()),
+            #[cfg(any(feature = "sched-rr", feature = "sched-rt"))]
+            time_slice: AtomicIsize::new(0),
+            in_run_queue: AtomicBool::new(false),
+            pi: SpinNoIrq::new(PiState::new()),
+            acct: TaskAccounting::new(),
             in_wait_queue: AtomicBool::new(false),
             #[cfg(feature = "irq")]
             timer_ticket_id: AtomicU64::new(0),
             #[cfg(feature = "smp")]
             on_cpu: AtomicBool::new(false),
+            #[cfg(feature = "smp")]
+            pull_slot: AtomicUsize::new(usize::MAX),
             #[cfg(feature = "preempt")]
             need_resched: AtomicBool::new(false),
             #[cfg(feature = "preempt")]
@@ -262,17 +531,71 @@ impl TaskInner {
     }
 
     pub(crate) fn into_arc(self) -> AxTaskRef {
-        Arc::new(AxTask::new(self))
+        let task = Arc::new(AxTask::new(self));
+        crate::registry::register(&task);
+        task
     }
 
     #[inline]
-    pub(crate) fn state(&self) -> TaskState {
-        self.state.load(Ordering::Acquire).into()
+    pub(crate) fn set_cpu_id(&self, cpu_id: usize) {
+        self.cpu_id.store(cpu_id, Ordering::Release)
+    }
+
+    #[inline]
+    pub(crate) fn set_priority_value(&self, prio: isize) {
+        self.priority.store(prio, Ordering::Release)
+    }
+
+    #[inline]
+    pub(crate) fn store_sched_policy(&self, policy: SchedPolicy) {
+        self.sched_policy.store(policy.encode(), Ordering::Release)
+    }
+
+    #[inline]
+    #[cfg(any(feature = "sched-rr", feature = "sched-rt"))]
+    pub(crate) fn time_slice(&self) -> isize {
+        self.time_slice.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    #[cfg(any(feature = "sched-rr", feature = "sched-rt"))]
+    pub(crate) fn reset_time_slice(&self) {
+        self.time_slice
+            .store(crate::sched_rr::time_slice() as isize, Ordering::Release)
+    }
+
+    /// Consumes one tick of the time slice, returns the remaining.
+    #[inline]
+    #[cfg(any(feature = "sched-rr", feature = "sched-rt"))]
+    pub(crate) fn consume_time_slice(&self) -> isize {
+        self.time_slice.fetch_sub(1, Ordering::AcqRel) - 1
+    }
+
+    /// Returns whether the task is in the scheduler of the run queue of
+    /// [`cpu_id`](Self::cpu_id).
+    ///
+    /// It is only updated while holding the lock of that scheduler.
+    #[inline]
+    pub(crate) fn in_run_queue(&self) -> bool {
+        self.in_run_queue.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub(crate) fn set_in_run_queue(&self, in_run_queue: bool) {
+        self.in_run_queue.store(in_run_queue, Ordering::Release)
+    }
+
+    #[inline]
+    pub(crate) fn pi_state(&self) -> &SpinNoIrq<PiState> {
+        &self.pi
     }
 
     #[inline]
     pub(crate) fn set_state(&self, state: TaskState) {
-        self.state.store(state as u8, Ordering::Release)
+        let old_state: TaskState = self.state.swap(state as u8, Ordering::AcqRel).into();
+        if old_state != state {
+            self.acct.on_state_change(old_state);
+        }
     }
 
     /// Transition the task state from `current_state` to `new_state`,
@@ -280,14 +603,38 @@ impl TaskInner {
     /// otherwise returns `false`.
     #[inline]
     pub(crate) fn transition_state(&self, current_state: TaskState, new_state: TaskState) -> bool {
-        self.state
+        let ok = self
+            .state
             .compare_exchange(
                 current_state as u8,
                 new_state as u8,
                 Ordering::AcqRel,
                 Ordering::Acquire,
             )
-            .is_ok()
+            .is_ok();
+        if ok && current_state != new_state {
+            self.acct.on_state_change(current_state);
+        }
+        ok
+    }
+
+    /// Records a context switch that switches out this task.
+    ///
+    /// `preempted` indicates whether the task is forced to give up the CPU.
+    #[inline]
+    pub(crate) fn account_switch_out(&self, preempted: bool) {
+        let counter = if preempted {
+            &self.acct.involuntary_switches
+        } else {
+            &self.acct.voluntary_switches
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a migration of this task to another CPU's run queue.
+    #[inline]
+    pub(crate) fn account_migration(&self) {
+        self.acct.migrations.fetch_add(1, Ordering::Relaxed);
     }
 
     #[inline]
@@ -300,8 +647,10 @@ impl TaskInner {
         matches!(self.state(), TaskState::Ready)
     }
 
+    /// Whether the task is the initial task (e.g., the `main` task of the
+    /// primary CPU), whose exit shuts down the whole system.
     #[inline]
-    pub(crate) const fn is_init(&self) -> bool {
+    pub const fn is_init(&self) -> bool {
         self.is_init
     }
 
@@ -416,6 +765,27 @@ impl TaskInner {
     pub(crate) fn set_on_cpu(&self, on_cpu: bool) {
         self.on_cpu.store(on_cpu, Ordering::Release)
     }
+
+    /// Returns the index of the task in the tasks that other CPUs can pull
+    /// from its run queue, if it is one of them.
+    ///
+    /// It is only updated while holding the lock of the scheduler of that run
+    /// queue.
+    #[cfg(feature = "smp")]
+    #[inline]
+    pub(crate) fn pull_slot(&self) -> Option<usize> {
+        match self.pull_slot.load(Ordering::Acquire) {
+            usize::MAX => None,
+            slot => Some(slot),
+        }
+    }
+
+    #[cfg(feature = "smp")]
+    #[inline]
+    pub(crate) fn set_pull_slot(&self, slot: Option<usize>) {
+        self.pull_slot
+            .store(slot.unwrap_or(usize::MAX), Ordering::Release)
+    }
 }
 
 impl fmt::Debug for TaskInner {
@@ -424,6 +794,7 @@ impl fmt::Debug for TaskInner {
             .field("id", &self.id)
             .field("name", &self.name)
             .field("state", &self.state())
+            .field("cpu_id", &self.cpu_id())
             .finish()
     }
 }
@@ -431,31 +802,254 @@ impl fmt::Debug for TaskInner {
 impl Drop for TaskInner {
     fn drop(&mut self) {
         debug!("task drop: {}", self.id_name());
+        crate::registry::unregister(self.id);
     }
 }
 
-struct TaskStack {
+/// The magic value at the bottom of the kernel stacks, which is overwritten
+/// if the stack overflows.
+#[cfg(not(feature = "paging"))]
+const STACK_CANARY: u64 = 0xdead_beef_cafe_f00d;
+
+/// Size of the guard page below the kernel stacks.
+#[cfg(feature = "paging")]
+const STACK_GUARD_SIZE: usize = memory_addr::PAGE_SIZE_4K;
+#[cfg(not(feature = "paging"))]
+const STACK_GUARD_SIZE: usize = 0;
+
+/// A kernel stack.
+///
+/// Without the `paging` feature, the stack is allocated from the global
+/// allocator, and a canary is written at its bottom.
+///
+/// With the `paging` feature, the stack is mapped in the kernel address space
+/// out of the linear mapping, with a guard page below it that is never
+/// mapped, so an overflow faults on every CPU. Note that `virt_to_phys` does
+/// not work on buffers on such stacks, so they can not be used for DMA.
+pub(crate) struct TaskStack {
     ptr: NonNull<u8>,
     layout: Layout,
 }
 
 impl TaskStack {
     pub fn alloc(size: usize) -> Self {
-        let layout = Layout::from_size_align(size, 16).unwrap();
-        Self {
-            ptr: NonNull::new(unsafe { alloc::alloc::alloc(layout) }).unwrap(),
-            layout,
-        }
+        let align = if cfg!(feature = "paging") {
+            memory_addr::PAGE_SIZE_4K
+        } else {
+            16
+        };
+        let layout = Layout::from_size_align(STACK_GUARD_SIZE + size, align).unwrap();
+        #[cfg(feature = "paging")]
+        let ptr = {
+            let start = FREE_STACKS.lock().take(size);
+            NonNull::new(start.unwrap_or_else(|| map_stack(size)) as *mut u8).unwrap()
+        };
+        #[cfg(not(feature = "paging"))]
+        let ptr = {
+            let ptr = NonNull::new(unsafe { alloc::alloc::alloc(layout) }).unwrap();
+            unsafe { ptr.cast::<u64>().write(STACK_CANARY) };
+            ptr
+        };
+        Self { ptr, layout }
+    }
+
+    pub const fn size(&self) -> usize {
+        self.layout.size() - STACK_GUARD_SIZE
     }
 
     pub const fn top(&self) -> VirtAddr {
         unsafe { core::mem::transmute(self.ptr.as_ptr().add(self.layout.size())) }
     }
+
+    /// Returns whether the canary at the bottom of the stack is intact.
+    #[cfg(not(feature = "paging"))]
+    pub fn canary_intact(&self) -> bool {
+        unsafe { self.ptr.cast::<u64>().read_volatile() == STACK_CANARY }
+    }
+
+    /// Returns whether `vaddr` is in the guard page of the stack.
+    #[cfg(feature = "paging")]
+    pub fn guard_contains(&self, vaddr: VirtAddr) -> bool {
+        let start = self.ptr.as_ptr() as usize;
+        (start..start + STACK_GUARD_SIZE).contains(&vaddr.as_usize())
+    }
 }
 
 impl Drop for TaskStack {
     fn drop(&mut self) {
-        unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+        // The stack stays mapped for reuse, as unmapping it would need a TLB
+        // shootdown on all CPUs.
+        #[cfg(feature = "paging")]
+        FREE_STACKS
+            .lock()
+            .put(self.ptr.as_ptr() as usize, self.size());
+        #[cfg(not(feature = "paging"))]
+        unsafe {
+            alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout)
+        }
+    }
+}
+
+/// Kernel stacks that are freed but still mapped, to be reused by new tasks.
+///
+/// The stacks are never unmapped, so the memory used by them is bounded by
+/// the most kernel stacks ever in use at the same time.
+#[cfg(feature = "paging")]
+static FREE_STACKS: SpinNoIrq<StackCache> = SpinNoIrq::new(StackCache::new());
+
+/// Freed kernel stacks, by the start addresses of their guard pages and the
+/// sizes of the stacks.
+#[cfg(any(feature = "paging", test))]
+pub(crate) struct StackCache(alloc::vec::Vec<(usize, usize)>);
+
+#[cfg(any(feature = "paging", test))]
+impl StackCache {
+    pub const fn new() -> Self {
+        Self(alloc::vec::Vec::new())
+    }
+
+    /// Takes a freed stack of `size` bytes, and returns the start address of
+    /// its guard page.
+    pub fn take(&mut self, size: usize) -> Option<usize> {
+        let idx = self.0.iter().rposition(|&(_, s)| s == size)?;
+        Some(self.0.swap_remove(idx).0)
+    }
+
+    /// Puts back a freed stack of `size` bytes with the guard page at `start`.
+    pub fn put(&mut self, start: usize, size: usize) {
+        self.0.push((start, size));
+    }
+}
+
+/// Maps a new kernel stack of `size` bytes with a guard page below it, and
+/// returns the start address of the guard page.
+///
+/// The stacks are mapped from the middle of the kernel address space, away
+/// from the linear mapping at its bottom.
+#[cfg(feature = "paging")]
+fn map_stack(size: usize) -> usize {
+    use axhal::paging::MappingFlags;
+    use memory_addr::VirtAddrRange;
+
+    let mut aspace = axmm::kernel_aspace().lock();
+    let limit = VirtAddrRange::new(aspace.base(), aspace.end());
+    let hint = aspace.base() + aspace.size() / 2;
+    let start = aspace
+        .find_free_area(hint, STACK_GUARD_SIZE + size, limit)
+        .expect("no virtual address space for kernel stacks");
+    // The guard page only reserves the addresses, and is never present.
+    aspace
+        .map_alloc(start, STACK_GUARD_SIZE, MappingFlags::empty(), false)
+        .expect("failed to map kernel stack guard page");
+    aspace
+        .map_alloc(
+            start + STACK_GUARD_SIZE,
+            size,
+            MappingFlags::READ | MappingFlags::WRITE,
+            true,
+        )
+        .expect("failed to map kernel stack");
+    start.as_usize()
+}
+
+/// Size of the stacks to report kernel stack overflows on.
+#[cfg(feature = "paging")]
+const OVERFLOW_STACK_SIZE: usize = 0x4000;
+
+#[cfg(feature = "paging")]
+#[repr(C, align(16))]
+struct OverflowStack([u8; OVERFLOW_STACK_SIZE]);
+
+/// The stacks to report kernel stack overflows on, one for each CPU, since
+/// the overflowed stack can not be used any more.
+#[cfg(feature = "paging")]
+static mut OVERFLOW_STACKS: [OverflowStack; axconfig::SMP] =
+    [const { OverflowStack([0; OVERFLOW_STACK_SIZE]) }; axconfig::SMP];
+
+/// Reports the kernel stack overflow of the current task, if the page fault
+/// occurs in the guard page of its stack.
+///
+/// The handler is running on the overflowed stack, so it switches to the
+/// overflow stack of this CPU before reporting.
+#[cfg(feature = "paging")]
+#[axhal::trap::register_trap_handler(axhal::trap::PAGE_FAULT)]
+fn handle_stack_guard_fault(
+    vaddr: VirtAddr,
+    _access_flags: axhal::paging::MappingFlags,
+    is_user: bool,
+) -> bool {
+    if !is_user {
+        if let Some(curr) = crate::current_may_uninit() {
+            if curr
+                .kstack
+                .as_ref()
+                .is_some_and(|s| s.guard_contains(vaddr))
+            {
+                let cpu_id = axhal::cpu::this_cpu_id();
+                let stack_top =
+                    (&raw const OVERFLOW_STACKS) as usize + (cpu_id + 1) * OVERFLOW_STACK_SIZE;
+                unsafe { call_on_stack(stack_top, report_stack_overflow, vaddr.as_usize()) }
+            }
+        }
+    }
+    false
+}
+
+#[cfg(feature = "paging")]
+extern "C" fn report_stack_overflow(vaddr: usize) -> ! {
+    let curr = crate::current();
+    panic!(
+        "stack overflow in task {} ({:?}) at {:#x}",
+        curr.id.as_u64(),
+        curr.name,
+        vaddr
+    );
+}
+
+/// Calls `f(arg)` with the stack pointer set to `stack_top`.
+///
+/// # Safety
+///
+/// `stack_top` must be the 16-byte aligned top of a stack that is not in use.
+#[cfg(feature = "paging")]
+unsafe fn call_on_stack(stack_top: usize, f: extern "C" fn(usize) -> !, arg: usize) -> ! {
+    unsafe {
+        #[cfg(target_arch = "x86_64")]
+        core::arch::asm!(
+            "mov rsp, {stack_top}",
+            "call {f}",
+            stack_top = in(reg) stack_top,
+            f = in(reg) f,
+            in("rdi") arg,
+            options(noreturn),
+        );
+        #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+        core::arch::asm!(
+            "mv sp, {stack_top}",
+            "jalr {f}",
+            stack_top = in(reg) stack_top,
+            f = in(reg) f,
+            in("a0") arg,
+            options(noreturn),
+        );
+        #[cfg(target_arch = "aarch64")]
+        core::arch::asm!(
+            "mov sp, {stack_top}",
+            "blr {f}",
+            stack_top = in(reg) stack_top,
+            f = in(reg) f,
+            in("x0") arg,
+            options(noreturn),
+        );
+        #[cfg(target_arch = "loongarch64")]
+        core::arch::asm!(
+            "move $sp, {stack_top}",
+            "jirl $ra, {f}, 0",
+            stack_top = in(reg) stack_top,
+            f = in(reg) f,
+            in("$a0") arg,
+            options(noreturn),
+        );
     }
 }
 
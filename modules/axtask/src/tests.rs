@@ -5,6 +5,9 @@ use crate::{WaitQueue, api as axtask, current};
 
 static INIT: Once = Once::new();
 static SERIAL: Mutex<()> = Mutex::new(());
+/// Brings up the run queue of CPU 1 for the SMP tests, but only CPU 0 runs.
+#[cfg(feature = "smp")]
+static OFFLINE_CPU: Once = Once::new();
 
 #[test]
 fn test_sched_fifo() {
@@ -128,3 +131,458 @@ fn test_task_join() {
         assert_eq!(tasks[i].join(), Some(i as _));
     }
 }
+
+#[test]
+fn test_task_registry() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static STARTED: AtomicUsize = AtomicUsize::new(0);
+    static WQ: WaitQueue = WaitQueue::new();
+
+    let task = axtask::spawn_raw(
+        || {
+            STARTED.store(1, Ordering::Release);
+            WQ.notify_one(true); // WQ.wait_until() in the main task
+            WQ.wait_until(|| STARTED.load(Ordering::Acquire) == 2);
+        },
+        "registry".into(),
+        0x2000,
+    );
+    WQ.wait_until(|| STARTED.load(Ordering::Acquire) == 1);
+
+    let found = axtask::get_task(task.id()).expect("task not registered");
+    assert!(std::sync::Arc::ptr_eq(&found, &task));
+    assert_eq!(found.name(), "registry");
+    assert_eq!(found.kernel_stack_size(), 0x2000);
+    assert_eq!(found.state(), axtask::TaskState::Blocked);
+
+    let mut names = Vec::new();
+    axtask::for_each_task(|t| names.push(t.name().to_string()));
+    assert!(names.iter().any(|n| n == "registry"));
+    assert!(names.iter().any(|n| n == "main"));
+
+    STARTED.store(2, Ordering::Release);
+    WQ.notify_all(true);
+    assert_eq!(task.join(), Some(0));
+}
+
+#[test]
+fn test_task_stats() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const NUM_YIELDS: u64 = 5;
+    static DONE: AtomicUsize = AtomicUsize::new(0);
+
+    let task = axtask::spawn(|| {
+        for _ in 0..NUM_YIELDS {
+            axtask::yield_now();
+        }
+        DONE.store(1, Ordering::Release);
+    });
+
+    let before = current().stats();
+    while DONE.load(Ordering::Acquire) == 0 {
+        axtask::yield_now();
+    }
+    let after = current().stats();
+    assert!(after.voluntary_switches >= before.voluntary_switches + NUM_YIELDS);
+    assert_eq!(after.involuntary_switches, before.involuntary_switches);
+
+    task.join();
+    let stats = task.stats();
+    assert!(stats.voluntary_switches >= NUM_YIELDS + 1); // yields and exit
+    assert_eq!(stats.involuntary_switches, 0);
+}
+
+#[test]
+fn test_set_other_task() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    let task = axtask::spawn(|| axtask::yield_now());
+
+    // FIFO scheduler does not support priorities.
+    assert!(!axtask::set_priority(&task, 1));
+    assert_eq!(task.priority(), 0);
+
+    assert!(!axtask::set_affinity(&task, axtask::AxCpuMask::new()));
+    assert!(axtask::set_affinity(&task, axtask::AxCpuMask::one_shot(0)));
+    assert!(task.cpumask().get(0));
+    assert_eq!(task.join(), Some(0));
+}
+
+#[test]
+fn test_sched_policy() {
+    use axtask::SchedPolicy;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    let task = axtask::spawn(|| axtask::yield_now());
+    assert_eq!(task.sched_policy(), SchedPolicy::Normal);
+
+    assert!(!axtask::set_sched_policy(&task, SchedPolicy::Fifo(0)));
+    assert!(!axtask::set_sched_policy(
+        &task,
+        SchedPolicy::RoundRobin(axtask::RT_PRIO_MAX + 1)
+    ));
+    // Real-time policies are not available without the `sched-rt` feature.
+    assert_eq!(
+        axtask::set_sched_policy(&task, SchedPolicy::Fifo(10)),
+        cfg!(feature = "sched-rt")
+    );
+    assert!(axtask::set_sched_policy(&task, SchedPolicy::Normal));
+    assert_eq!(task.sched_policy(), SchedPolicy::Normal);
+    assert_eq!(task.join(), Some(0));
+}
+
+/// Runs the current task at `policy`, and returns it.
+#[cfg(feature = "sched-rt")]
+fn set_current_policy(policy: axtask::SchedPolicy) -> crate::AxTaskRef {
+    let curr = current().as_task_ref().clone();
+    assert!(axtask::set_sched_policy(&curr, policy));
+    curr
+}
+
+#[cfg(feature = "sched-rt")]
+#[test]
+fn test_rt_priority_order() {
+    use axtask::SchedPolicy;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static ORDER: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+    // Nothing else runs until the current task gives up the CPU.
+    let curr = set_current_policy(SchedPolicy::Fifo(axtask::RT_PRIO_MAX));
+    let normal = axtask::spawn(|| ORDER.lock().unwrap().push(0));
+    let tasks: Vec<_> = [10, 30, 20]
+        .into_iter()
+        .map(|prio| {
+            let task = axtask::spawn(move || ORDER.lock().unwrap().push(prio));
+            assert!(axtask::set_sched_policy(&task, SchedPolicy::Fifo(prio)));
+            task
+        })
+        .collect();
+    assert!(ORDER.lock().unwrap().is_empty());
+
+    // The real-time tasks run by priority, all before the normal tasks.
+    assert!(axtask::set_sched_policy(&curr, SchedPolicy::Normal));
+    for task in tasks.into_iter().chain([normal]) {
+        assert_eq!(task.join(), Some(0));
+    }
+    assert_eq!(*ORDER.lock().unwrap(), [30, 20, 10, 0]);
+}
+
+#[cfg(feature = "sched-rt")]
+#[test]
+fn test_rt_preempt_lower_priority() {
+    use axtask::SchedPolicy;
+    use core::sync::atomic::AtomicBool;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static LOW_RAN: AtomicBool = AtomicBool::new(false);
+    static HIGH_RAN: AtomicBool = AtomicBool::new(false);
+    let curr = set_current_policy(SchedPolicy::Fifo(10));
+
+    // A task with a lower priority waits for the current task.
+    let low = axtask::spawn(|| LOW_RAN.store(true, Ordering::Release));
+    assert!(axtask::set_sched_policy(&low, SchedPolicy::Fifo(5)));
+    axtask::yield_now();
+    assert!(!LOW_RAN.load(Ordering::Acquire));
+
+    // A task with a higher priority preempts the current task as soon as it
+    // is ready.
+    let high = axtask::spawn(|| HIGH_RAN.store(true, Ordering::Release));
+    assert!(!HIGH_RAN.load(Ordering::Acquire));
+    assert!(axtask::set_sched_policy(&high, SchedPolicy::RoundRobin(20)));
+    assert!(HIGH_RAN.load(Ordering::Acquire));
+    assert!(!LOW_RAN.load(Ordering::Acquire));
+
+    assert!(axtask::set_sched_policy(&curr, SchedPolicy::Normal));
+    assert!(LOW_RAN.load(Ordering::Acquire));
+    assert_eq!(high.join(), Some(0));
+    assert_eq!(low.join(), Some(0));
+}
+
+#[cfg(feature = "sched-rt")]
+#[test]
+fn test_rt_round_robin() {
+    use axtask::SchedPolicy;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    static ORDER: Mutex<String> = Mutex::new(String::new());
+    /// Emulates a timer tick, after which the current task is preempted if
+    /// its time slice is used up.
+    fn tick() {
+        axtask::on_timer_tick();
+        drop(kernel_guard::NoPreempt::new());
+    }
+
+    let curr = set_current_policy(SchedPolicy::Fifo(axtask::RT_PRIO_MAX));
+    assert!(axtask::set_time_slice(2));
+    let tasks: Vec<_> = ['a', 'b']
+        .into_iter()
+        .map(|name| {
+            let task = axtask::spawn(move || {
+                for _ in 0..4 {
+                    ORDER.lock().unwrap().push(name);
+                    tick();
+                }
+            });
+            assert!(axtask::set_sched_policy(&task, SchedPolicy::RoundRobin(10)));
+            task
+        })
+        .collect();
+
+    // The tasks with the same priority take turns when their time slices
+    // are used up.
+    for task in tasks {
+        assert_eq!(task.join(), Some(0));
+    }
+    assert_eq!(*ORDER.lock().unwrap(), "aabbaabb");
+    assert!(axtask::set_time_slice(5));
+    assert!(axtask::set_sched_policy(&curr, SchedPolicy::Normal));
+}
+
+#[cfg(feature = "smp")]
+#[test]
+fn test_smp_pull_to_idle_cpu() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+    OFFLINE_CPU.call_once(|| crate::run_queue::init_offline_run_queue(1));
+
+    let before = axtask::migration_stats();
+    // The tasks are spread over the run queues of both CPUs, but CPU 1 never
+    // runs, so its task only runs after CPU 0 pulls it when it becomes idle.
+    let tasks: Vec<_> = (0..2)
+        .map(|_| axtask::spawn(|| axtask::exit(current().cpu_id() as _)))
+        .collect();
+    let pulled = tasks
+        .iter()
+        .find(|task| task.cpu_id() == 1)
+        .expect("no task is queued on CPU 1")
+        .clone();
+    for task in tasks {
+        assert_eq!(task.join(), Some(0));
+    }
+    assert_eq!(pulled.cpu_id(), 0);
+    assert_eq!(pulled.stats().migrations, 1);
+    let after = axtask::migration_stats();
+    assert_eq!(after.idle_pulls, before.idle_pulls + 1);
+    assert_eq!(
+        after.total(),
+        after.idle_pulls + after.periodic_pulls + after.affinity_migrations
+    );
+}
+
+#[cfg(feature = "smp")]
+#[test]
+fn test_smp_set_other_task_while_migrating() {
+    use crate::run_queue::task_run_queue;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+    OFFLINE_CPU.call_once(|| crate::run_queue::init_offline_run_queue(1));
+
+    let tasks: Vec<_> = (0..2)
+        .map(|_| axtask::spawn(|| axtask::exit(current().cpu_id() as _)))
+        .collect();
+    let task = tasks
+        .iter()
+        .find(|task| task.cpu_id() == 1)
+        .expect("no task is queued on CPU 1")
+        .clone();
+    // The run queue of CPU 1 is looked up, then the task is pinned to CPU 0
+    // and pulled there before the lookup is used.
+    let mut stale = task_run_queue::<kernel_guard::NoOp>(&task);
+    assert!(axtask::set_affinity(&task, axtask::AxCpuMask::one_shot(0)));
+    for task in tasks {
+        assert_eq!(task.join(), Some(0));
+    }
+    assert_eq!(task.cpu_id(), 0);
+    assert_eq!(stale.set_task_priority(&task, 1), None);
+    drop(stale);
+
+    // A new lookup finds the run queue of CPU 0. FIFO scheduler does not
+    // support priorities.
+    assert_eq!(
+        task_run_queue::<kernel_guard::NoOp>(&task).set_task_priority(&task, 1),
+        Some(false)
+    );
+    assert!(!axtask::set_priority(&task, 1));
+    assert_eq!(task.priority(), 0);
+}
+
+#[test]
+fn test_cpu_idle_time() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    // The idle time is accounted to the idle task of each CPU, which is not
+    // found by its name.
+    assert!(axtask::cpu_idle_time(0).is_some());
+    assert_eq!(axtask::cpu_idle_time(axconfig::SMP), None);
+    let idle = axtask::cpu_idle_time(0).unwrap();
+    axtask::yield_now();
+    assert!(axtask::cpu_idle_time(0).unwrap() >= idle);
+}
+
+#[cfg(feature = "tickless")]
+#[test]
+fn test_tickless_idle_deadline() {
+    use crate::run_queue::{NOHZ_MAX_IDLE_NANOS, TICK_INTERVAL_NANOS, nohz_deadline};
+    use core::time::Duration;
+
+    let now = 42 * TICK_INTERVAL_NANOS + 123;
+
+    // A task sleeps across several tick periods: the idle CPU is woken up
+    // right at its wakeup time, without any tick in between.
+    let sleep = Duration::from_nanos(3 * TICK_INTERVAL_NANOS + 7);
+    if sleep.as_nanos() as u64 <= NOHZ_MAX_IDLE_NANOS {
+        let deadline = nohz_deadline(now, Some(sleep));
+        assert_eq!(deadline, now + sleep.as_nanos() as u64);
+        assert!(deadline > now + TICK_INTERVAL_NANOS);
+    }
+
+    // Nothing to wait for, or a far event: sleep as long as possible.
+    assert_eq!(nohz_deadline(now, None), now + NOHZ_MAX_IDLE_NANOS);
+    assert_eq!(
+        nohz_deadline(now, Some(Duration::from_secs(3600))),
+        now + NOHZ_MAX_IDLE_NANOS
+    );
+
+    // An event that has already expired fires immediately.
+    assert_eq!(nohz_deadline(now, Some(Duration::ZERO)), now);
+}
+
+#[cfg(feature = "irq")]
+#[test]
+fn test_timer() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::timers::expire_events;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    let fired = Arc::new(Mutex::new(Vec::new()));
+    let new_timer = |id: usize| {
+        let fired = fired.clone();
+        axtask::Timer::new(move |_now| fired.lock().unwrap().push(id))
+    };
+    let fired_ids = || core::mem::take(&mut *fired.lock().unwrap());
+    // The clock does not advance in unit tests, timer interrupts are
+    // simulated by expiring the events at given times.
+    let start = axhal::time::wall_time();
+    let at = |millis: u64| start + Duration::from_millis(millis);
+    let _guard = kernel_guard::NoPreemptIrqSave::new();
+
+    // One-shot timers fire in the order of their deadlines, not the order in
+    // which they are armed.
+    let timers: Vec<_> = (0..3).map(&new_timer).collect();
+    timers[1].arm(at(20));
+    timers[2].arm(at(30));
+    timers[0].arm(at(10));
+    expire_events(at(25));
+    assert_eq!(fired_ids(), [0, 1]);
+    assert!(!timers[0].is_armed() && !timers[1].is_armed());
+    assert!(timers[2].is_armed());
+    expire_events(at(30));
+    assert_eq!(fired_ids(), [2]);
+    assert!(!timers[2].is_armed());
+
+    // A timer cancelled before its deadline never fires, and re-arming a
+    // timer discards its previous deadline.
+    let cancelled = new_timer(3);
+    cancelled.arm(at(40));
+    assert!(cancelled.cancel());
+    assert!(!cancelled.cancel());
+    let rearmed = new_timer(4);
+    rearmed.arm(at(40));
+    rearmed.arm(at(60));
+    expire_events(at(50));
+    assert!(fired_ids().is_empty());
+    expire_events(at(60));
+    assert_eq!(fired_ids(), [4]);
+
+    // A periodic timer re-arms itself after each expiry, without catching up
+    // on missed periods, until it is cancelled.
+    let periodic = new_timer(5);
+    periodic.arm_periodic(Duration::from_millis(100));
+    expire_events(at(100));
+    expire_events(at(200));
+    assert_eq!(fired_ids(), [5, 5]);
+    expire_events(at(450));
+    assert_eq!(fired_ids(), [5]);
+    assert!(periodic.is_armed());
+    expire_events(at(500));
+    assert!(fired_ids().is_empty());
+    expire_events(at(550));
+    assert_eq!(fired_ids(), [5]);
+    assert!(periodic.cancel());
+    expire_events(at(1000));
+    assert!(fired_ids().is_empty());
+}
+
+#[test]
+fn test_stack_cache() {
+    use crate::task::StackCache;
+
+    let mut cache = StackCache::new();
+    assert_eq!(cache.take(0x4000), None);
+    cache.put(0x10000, 0x4000);
+    cache.put(0x20000, 0x8000);
+    cache.put(0x30000, 0x4000);
+    // Only the stacks of the same size are reused, the last freed first.
+    assert_eq!(cache.take(0x2000), None);
+    assert_eq!(cache.take(0x4000), Some(0x30000));
+    assert_eq!(cache.take(0x8000), Some(0x20000));
+    assert_eq!(cache.take(0x4000), Some(0x10000));
+    assert_eq!(cache.take(0x4000), None);
+}
+
+#[cfg(not(feature = "paging"))]
+#[test]
+fn test_task_stack() {
+    use crate::task::TaskStack;
+
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const STACK_SIZE: usize = 0x4000;
+    let stack = TaskStack::alloc(STACK_SIZE);
+    assert_eq!(stack.size(), STACK_SIZE);
+    assert!(stack.top().is_aligned(16usize));
+    assert!(stack.canary_intact());
+
+    // Using the stack does not touch the canary at its bottom.
+    let bottom = (stack.top() - STACK_SIZE).as_mut_ptr();
+    unsafe { bottom.add(8).write_bytes(0xaa, STACK_SIZE - 8) };
+    assert!(stack.canary_intact());
+    // Overflowing it does.
+    unsafe { bottom.write_bytes(0xaa, 8) };
+    assert!(!stack.canary_intact());
+
+    // Task stacks are rounded up to pages, and tasks run on them.
+    let task = axtask::spawn_raw(
+        || {
+            let local = 0u8;
+            let stack_top = current().kernel_stack_top().unwrap();
+            let sp = &local as *const u8 as usize;
+            assert!(sp < stack_top.as_usize());
+            assert!(sp >= stack_top.as_usize() - current().kernel_stack_size());
+        },
+        "stack".into(),
+        STACK_SIZE - 1,
+    );
+    assert_eq!(task.kernel_stack_size(), STACK_SIZE);
+    assert_eq!(task.join(), Some(0));
+}
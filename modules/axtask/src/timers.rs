@@ -1,5 +1,9 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
 use core::sync::atomic::{AtomicU64, Ordering};
 
+use kspin::SpinNoIrq;
+
 use kernel_guard::NoOp;
 use lazyinit::LazyInit;
 use timer_list::{TimeValue, TimerEvent, TimerList};
@@ -11,7 +15,24 @@ use crate::{AxTaskRef, select_run_queue};
 static TIMER_TICKET_ID: AtomicU64 = AtomicU64::new(1);
 
 percpu_static! {
-    TIMER_LIST: LazyInit<TimerList<TaskWakeupEvent>> = LazyInit::new(),
+    TIMER_LIST: LazyInit<TimerList<AxTimerEvent>> = LazyInit::new(),
+}
+
+/// Events in the per-CPU timer list.
+enum AxTimerEvent {
+    /// Wakes up a sleeping task.
+    WakeupTask(TaskWakeupEvent),
+    /// Calls the callback of a [`Timer`].
+    Callback(TimerCallbackEvent),
+}
+
+impl TimerEvent for AxTimerEvent {
+    fn callback(self, now: TimeValue) {
+        match self {
+            Self::WakeupTask(event) => event.callback(now),
+            Self::Callback(event) => event.callback(now),
+        }
+    }
 }
 
 struct TaskWakeupEvent {
@@ -35,17 +56,190 @@ impl TimerEvent for TaskWakeupEvent {
     }
 }
 
-pub fn set_alarm_wakeup(deadline: TimeValue, task: AxTaskRef) {
-    TIMER_LIST.with_current(|timer_list| {
+struct TimerCallbackEvent {
+    ticket_id: u64,
+    deadline: TimeValue,
+    timer: Arc<TimerInner>,
+}
+
+impl TimerEvent for TimerCallbackEvent {
+    fn callback(self, now: TimeValue) {
+        // Ignore the timer event if the timer has been cancelled or re-armed.
+        if self.timer.ticket_id.load(Ordering::Acquire) != self.ticket_id {
+            return;
+        }
+
+        // Re-arm a periodic timer before calling the callback, so that the
+        // callback can cancel it.
+        if let Some(interval) = *self.timer.interval.lock() {
+            let mut deadline = self.deadline + interval;
+            if deadline <= now {
+                // Missed some periods, do not try to catch up.
+                deadline = now + interval;
+            }
+            set_event(
+                deadline,
+                AxTimerEvent::Callback(TimerCallbackEvent {
+                    ticket_id: self.ticket_id,
+                    deadline,
+                    timer: self.timer.clone(),
+                }),
+            );
+        } else {
+            // One-shot timer, mark it as not armed.
+            let _ = self.timer.ticket_id.compare_exchange(
+                self.ticket_id,
+                0,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            );
+        }
+
+        (self.timer.callback)(now);
+    }
+}
+
+struct TimerInner {
+    callback: Box<dyn Fn(TimeValue) + Send + Sync>,
+    /// The ticket ID of the currently armed timer event, or `0` if the timer
+    /// is not armed.
+    ticket_id: AtomicU64,
+    /// The interval of a periodic timer, or `None` for a one-shot timer.
+    interval: SpinNoIrq<Option<TimeValue>>,
+}
+
+/// A kernel timer that calls a callback function at a given time, once or
+/// periodically.
+///
+/// Timer events are kept in the timer list of the CPU that arms the timer,
+/// and the callback is called on that CPU in the timer interrupt handler, with
+/// IRQs disabled. So the callback must be short and must not block. To do more
+/// work, it can wake up a task (e.g., with [`WaitQueue::notify_one`]).
+///
+/// Cancelling or re-arming a timer simply invalidates its pending event, which
+/// is ignored when it expires. Dropping a timer cancels it.
+///
+/// [`WaitQueue::notify_one`]: crate::WaitQueue::notify_one
+pub struct Timer {
+    inner: Arc<TimerInner>,
+}
+
+impl Timer {
+    /// Creates a new timer with the given callback, which is not armed.
+    ///
+    /// The callback receives the current time (wall time, see
+    /// [`axhal::time::wall_time`]) when it is called.
+    pub fn new<F>(callback: F) -> Self
+    where
+        F: Fn(TimeValue) + Send + Sync + 'static,
+    {
+        Self {
+            inner: Arc::new(TimerInner {
+                callback: Box::new(callback),
+                ticket_id: AtomicU64::new(0),
+                interval: SpinNoIrq::new(None),
+            }),
+        }
+    }
+
+    /// Arms the timer to expire once at the given `deadline` (in wall time).
+    ///
+    /// If the timer is already armed, the previous deadline is discarded.
+    pub fn arm(&self, deadline: TimeValue) {
+        *self.inner.interval.lock() = None;
+        self.set(deadline);
+    }
+
+    /// Arms the timer to expire periodically, first after `interval` from
+    /// now, and then every `interval`.
+    ///
+    /// If the timer is already armed, the previous deadline is discarded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn arm_periodic(&self, interval: TimeValue) {
+        assert!(!interval.is_zero(), "timer interval must not be zero");
+        *self.inner.interval.lock() = Some(interval);
+        self.set(wall_time() + interval);
+    }
+
+    /// Cancels the timer.
+    ///
+    /// Returns `true` if the timer was armed. Note that the callback may be
+    /// still running on another CPU when this function returns.
+    pub fn cancel(&self) -> bool {
+        self.inner.ticket_id.swap(0, Ordering::AcqRel) != 0
+    }
+
+    /// Returns whether the timer is armed and has not expired yet (for a
+    /// one-shot timer) or has not been cancelled (for a periodic timer).
+    pub fn is_armed(&self) -> bool {
+        self.inner.ticket_id.load(Ordering::Acquire) != 0
+    }
+
+    fn set(&self, deadline: TimeValue) {
         let ticket_id = TIMER_TICKET_ID.fetch_add(1, Ordering::AcqRel);
-        task.set_timer_ticket(ticket_id);
-        timer_list.set(deadline, TaskWakeupEvent { ticket_id, task });
-    })
+        self.inner.ticket_id.store(ticket_id, Ordering::Release);
+        set_event(
+            deadline,
+            AxTimerEvent::Callback(TimerCallbackEvent {
+                ticket_id,
+                deadline,
+                timer: self.inner.clone(),
+            }),
+        );
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Adds an event to the timer list of the current CPU.
+fn set_event(deadline: TimeValue, event: AxTimerEvent) {
+    // The timer list is also accessed in the timer interrupt handler.
+    let _guard = kernel_guard::NoPreemptIrqSave::new();
+    unsafe { TIMER_LIST.current_ref_mut_raw() }.set(deadline, event);
+    // The CPU may be idle with the tick stopped and the timer programmed
+    // for a later event (e.g., a timer is armed in an IRQ handler).
+    #[cfg(feature = "tickless")]
+    crate::run_queue::restart_current_tick();
+}
+
+pub fn set_alarm_wakeup(deadline: TimeValue, task: AxTaskRef) {
+    let ticket_id = TIMER_TICKET_ID.fetch_add(1, Ordering::AcqRel);
+    task.set_timer_ticket(ticket_id);
+    set_event(
+        deadline,
+        AxTimerEvent::WakeupTask(TaskWakeupEvent { ticket_id, task }),
+    );
+}
+
+/// Returns the earliest deadline of the timer events on the current CPU.
+///
+/// Note: the deadline is in wall time, as the events are checked against
+/// [`wall_time`].
+#[cfg(feature = "tickless")]
+pub fn next_deadline() -> Option<TimeValue> {
+    unsafe {
+        // Safety: IRQs are disabled at this time.
+        TIMER_LIST.current_ref_raw()
+    }
+    .next_deadline()
 }
 
 pub fn check_events() {
+    expire_events(wall_time());
+}
+
+/// Calls the callbacks of all events on the current CPU that expire at `now`.
+///
+/// Must be called with IRQs disabled.
+pub(crate) fn expire_events(now: TimeValue) {
     loop {
-        let now = wall_time();
         let event = unsafe {
             // Safety: IRQs are disabled at this time.
             TIMER_LIST.current_ref_mut_raw()
@@ -51,5 +51,19 @@ endef
 define unit_test
   $(call run_cmd,cargo test,-p axfs $(1) $(verbose) -- --nocapture)
   $(call run_cmd,cargo test,-p axfs $(1) --features "myfs" $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p axfs $(1) --features "ext2fs" $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p axfs $(1) --features "overlayfs" $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p axfs $(1) --features "ext2fs overlayfs" $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p axfs $(1) --features "multitask" $(verbose) -- --nocapture)
   $(call run_cmd,cargo test,--workspace --exclude axfs $(1) $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p axtask $(1) --features "tickless" $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p axtask $(1) --features "sched-rt" $(verbose) -- --nocapture test_rt)
+  $(call run_cmd,axconfig-gen,configs/dummy.toml -w smp=2 -o $(TARGET_DIR)/dummy-smp.toml)
+  $(call run_cmd,AX_CONFIG_PATH=$(TARGET_DIR)/dummy-smp.toml cargo test,-p axtask $(1) --features "smp" $(verbose) -- --nocapture test_smp)
+  $(call run_cmd,cargo test,-p axsync $(1) --features "axtask/sched-cfs" $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p axruntime $(1) --features "fs sched-rr rtc" $(verbose) -- --nocapture)
+  $(call run_cmd,AX_IP6=fd00::15/64 AX_GW6=fd00::2 cargo test,-p axnet $(1) $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p axnet $(1) --features "multitask irq" $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p axnet $(1) --features "dhcp" $(verbose) -- --nocapture)
+  $(call run_cmd,cargo test,-p arceos_posix_api $(1) --features "net" $(verbose) -- --nocapture)
 endef
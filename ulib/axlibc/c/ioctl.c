@@ -1,9 +1,30 @@
+#include <stdarg.h>
 #include <stdio.h>
 #include <sys/ioctl.h>
 
+#ifdef AX_CONFIG_FS
+
+// TODO: remove this function in future work
+int ax_ioctl(int fd, int request, size_t arg);
+
+int ioctl(int __fd, int __request, ...)
+{
+    unsigned long arg;
+    va_list ap;
+    va_start(ap, __request);
+    arg = va_arg(ap, unsigned long);
+    va_end(ap);
+
+    return ax_ioctl(__fd, __request, arg);
+}
+
+#else
+
 // TODO
 int ioctl(int __fd, int __request, ...)
 {
     unimplemented();
     return 0;
 }
+
+#endif // AX_CONFIG_FS
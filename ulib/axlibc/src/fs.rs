@@ -1,7 +1,8 @@
 use core::ffi::{c_char, c_int};
 
 use arceos_posix_api::{
-    sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat,
+    sys_flock, sys_fstat, sys_getcwd, sys_ioctl, sys_link, sys_lseek, sys_lstat, sys_open,
+    sys_readlink, sys_rename, sys_stat, sys_symlink,
 };
 
 use crate::{ctypes, utils::e};
@@ -65,3 +66,47 @@ pub unsafe extern "C" fn getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
 pub unsafe extern "C" fn rename(old: *const c_char, new: *const c_char) -> c_int {
     e(sys_rename(old, new))
 }
+
+/// Create a symbolic link `linkpath` which contains the string `target`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn symlink(target: *const c_char, linkpath: *const c_char) -> c_int {
+    e(sys_symlink(target, linkpath))
+}
+
+/// Read the target of the symbolic link `path` into `buf`.
+///
+/// Return the number of bytes placed in `buf` if success.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn readlink(
+    path: *const c_char,
+    buf: *mut c_char,
+    bufsiz: usize,
+) -> ctypes::ssize_t {
+    e(sys_readlink(path, buf, bufsiz) as _) as _
+}
+
+/// Create a new hard link `new` to the existing file `old`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn link(old: *const c_char, new: *const c_char) -> c_int {
+    e(sys_link(old, new))
+}
+
+/// Apply or remove an advisory lock on the whole file `fd`.
+///
+/// Return 0 if the operation succeeds, otherwise return -1.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn flock(fd: c_int, operation: c_int) -> c_int {
+    e(sys_flock(fd, operation))
+}
+
+/// Perform the device-specific request `request` on the file `fd`.
+///
+/// Return the result of the request, or -1 on error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ax_ioctl(fd: c_int, request: c_int, arg: usize) -> c_int {
+    e(sys_ioctl(fd, request, arg))
+}
@@ -1,7 +1,7 @@
 use arceos_posix_api::{
     sys_accept, sys_bind, sys_connect, sys_freeaddrinfo, sys_getaddrinfo, sys_getpeername,
-    sys_getsockname, sys_listen, sys_recv, sys_recvfrom, sys_send, sys_sendto, sys_shutdown,
-    sys_socket,
+    sys_getsockname, sys_getsockopt, sys_listen, sys_recv, sys_recvfrom, sys_send, sys_sendto,
+    sys_setsockopt, sys_shutdown, sys_socket,
 };
 use core::ffi::{c_char, c_int, c_void};
 
@@ -178,3 +178,31 @@ pub unsafe extern "C" fn getpeername(
 ) -> c_int {
     e(sys_getpeername(sock_fd, addr, addrlen))
 }
+
+/// Set options on sockets.
+///
+/// Return 0 if success.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn setsockopt(
+    sock_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *const c_void,
+    optlen: ctypes::socklen_t,
+) -> c_int {
+    e(sys_setsockopt(sock_fd, level, optname, optval, optlen))
+}
+
+/// Get options on sockets.
+///
+/// Return 0 if success.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn getsockopt(
+    sock_fd: c_int,
+    level: c_int,
+    optname: c_int,
+    optval: *mut c_void,
+    optlen: *mut ctypes::socklen_t,
+) -> c_int {
+    e(sys_getsockopt(sock_fd, level, optname, optval, optlen))
+}
@@ -46,6 +46,38 @@ pub unsafe extern "C" fn pthread_mutex_init(
     e(api::sys_pthread_mutex_init(mutex, attr))
 }
 
+/// Initialize a mutex attributes object.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_mutexattr_init(attr: *mut ctypes::pthread_mutexattr_t) -> c_int {
+    e(api::sys_pthread_mutexattr_init(attr))
+}
+
+/// Destroy a mutex attributes object.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_mutexattr_destroy(
+    _attr: *mut ctypes::pthread_mutexattr_t,
+) -> c_int {
+    0
+}
+
+/// Get the protocol attribute of a mutex attributes object.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_mutexattr_getprotocol(
+    attr: *const ctypes::pthread_mutexattr_t,
+    protocol: *mut c_int,
+) -> c_int {
+    e(api::sys_pthread_mutexattr_getprotocol(attr, protocol))
+}
+
+/// Set the protocol attribute of a mutex attributes object.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn pthread_mutexattr_setprotocol(
+    attr: *mut ctypes::pthread_mutexattr_t,
+    protocol: c_int,
+) -> c_int {
+    e(api::sys_pthread_mutexattr_setprotocol(attr, protocol))
+}
+
 /// Lock the given mutex.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn pthread_mutex_lock(mutex: *mut ctypes::pthread_mutex_t) -> c_int {
@@ -3,6 +3,8 @@ use core::fmt;
 
 use arceos_api::fs as api;
 
+use crate::time::{Duration, SystemTime, UNIX_EPOCH};
+
 /// A structure representing a type of file with accessors for each file type.
 /// It is returned by [`Metadata::file_type`] method.
 pub type FileType = api::AxFileType;
@@ -16,7 +18,7 @@ pub struct File {
 }
 
 /// Metadata information about a file.
-pub struct Metadata(api::AxFileAttr);
+pub struct Metadata(pub(super) api::AxFileAttr);
 
 /// Options and flags which can be used to configure how a file is opened.
 #[derive(Clone, Debug)]
@@ -90,6 +92,12 @@ impl Metadata {
         self.0.is_file()
     }
 
+    /// Returns `true` if this metadata is for a symbolic link. It can only be
+    /// `true` for metadata returned by [`symlink_metadata`](super::symlink_metadata).
+    pub const fn is_symlink(&self) -> bool {
+        self.0.is_symlink()
+    }
+
     /// Returns the size of the file, in bytes, this metadata is for.
     #[allow(clippy::len_without_is_empty)]
     pub const fn len(&self) -> u64 {
@@ -110,6 +118,36 @@ impl Metadata {
     pub const fn blocks(&self) -> u64 {
         self.0.blocks()
     }
+
+    /// Returns the last modification time listed in this metadata.
+    ///
+    /// Returns an [`Unsupported`](crate::io::Error::Unsupported) error if the
+    /// filesystem does not record it.
+    pub fn modified(&self) -> Result<SystemTime> {
+        to_system_time(self.0.modified())
+    }
+
+    /// Returns the last access time of this metadata.
+    ///
+    /// Returns an [`Unsupported`](crate::io::Error::Unsupported) error if the
+    /// filesystem does not record it.
+    pub fn accessed(&self) -> Result<SystemTime> {
+        to_system_time(self.0.accessed())
+    }
+
+    /// Returns the creation time listed in this metadata.
+    ///
+    /// Returns an [`Unsupported`](crate::io::Error::Unsupported) error if the
+    /// filesystem does not record it.
+    pub fn created(&self) -> Result<SystemTime> {
+        to_system_time(self.0.created())
+    }
+}
+
+fn to_system_time(since_epoch: Option<Duration>) -> Result<SystemTime> {
+    since_epoch
+        .map(|dur| UNIX_EPOCH + dur)
+        .ok_or(crate::io::Error::Unsupported)
 }
 
 impl fmt::Debug for Metadata {
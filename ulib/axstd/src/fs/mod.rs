@@ -42,6 +42,11 @@ pub fn metadata(path: &str) -> io::Result<Metadata> {
     File::open(path)?.metadata()
 }
 
+/// Query the metadata about a file without following symbolic links.
+pub fn symlink_metadata(path: &str) -> io::Result<Metadata> {
+    arceos_api::fs::ax_symlink_attr(path).map(Metadata)
+}
+
 /// Returns an iterator over the entries within a directory.
 pub fn read_dir(path: &str) -> io::Result<ReadDir> {
     ReadDir::new(path)
@@ -75,3 +80,25 @@ pub fn remove_file(path: &str) -> io::Result<()> {
 pub fn rename(old: &str, new: &str) -> io::Result<()> {
     arceos_api::fs::ax_rename(old, new)
 }
+
+/// Creates a new symbolic link on the filesystem.
+///
+/// The `link` path will be a symbolic link pointing to the `original` path.
+/// The `original` path is not required to exist.
+pub fn symlink(original: &str, link: &str) -> io::Result<()> {
+    arceos_api::fs::ax_symlink(original, link)
+}
+
+/// Reads a symbolic link, returning the path that the link points to.
+#[cfg(feature = "alloc")]
+pub fn read_link(path: &str) -> io::Result<String> {
+    arceos_api::fs::ax_read_link(path)
+}
+
+/// Creates a new hard link on the filesystem.
+///
+/// The `link` path will be a link pointing to the `original` path. Both paths
+/// must be in the same mounted fs, and `original` can not be a directory.
+pub fn hard_link(original: &str, link: &str) -> io::Result<()> {
+    arceos_api::fs::ax_hard_link(original, link)
+}
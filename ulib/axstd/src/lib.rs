@@ -15,6 +15,7 @@
 //!     - `fp-simd`: Enable floating point and SIMD support.
 //! - Interrupts:
 //!     - `irq`: Enable interrupt handling support.
+//!     - `tickless`: Stop the periodic timer tick on idle CPUs.
 //! - Memory
 //!     - `alloc`: Enable dynamic memory allocation.
 //!     - `alloc-tlsf`: Use the TLSF allocator.
@@ -27,11 +28,17 @@
 //!     - `sched-fifo`: Use the FIFO cooperative scheduler.
 //!     - `sched-rr`: Use the Round-robin preemptive scheduler.
 //!     - `sched-cfs`: Use the Completely Fair Scheduler (CFS) preemptive scheduler.
+//!     - `sched-rt`: Add a fixed-priority real-time scheduling class on top of the
+//!       selected scheduler.
 //! - Upperlayer stacks
 //!     - `fs`: Enable file system support.
 //!     - `myfs`: Allow users to define their custom filesystems to override the default.
+//!     - `ext2fs`: Use ext2 as the root filesystem instead of FAT.
+//!     - `overlayfs`: Keep the root filesystem read-only, with the changes kept
+//!       in memory.
 //!     - `net`: Enable networking support.
 //!     - `dns`: Enable DNS lookup support.
+//!     - `dhcp`: Configure the IPv4 address, gateway and DNS servers by DHCP.
 //!     - `display`: Enable graphics support.
 //! - Device drivers
 //!     - `bus-mmio`: Use device tree to probe all MMIO devices.
@@ -1,5 +1,6 @@
 use super::{SocketAddr, ToSocketAddrs};
 use crate::io::{self, prelude::*};
+use core::time::Duration;
 
 use arceos_api::net::{self as api, AxTcpSocketHandle};
 
@@ -43,6 +44,84 @@ impl TcpStream {
     pub fn shutdown(&self) -> io::Result<()> {
         api::ax_tcp_shutdown(&self.0)
     }
+
+    /// Sets the value of the `TCP_NODELAY` option on this socket.
+    ///
+    /// If set, this option disables the Nagle algorithm. This means that
+    /// segments are always sent as soon as possible, even if there is only a
+    /// small amount of data.
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        api::ax_tcp_set_nodelay(&self.0, nodelay)
+    }
+
+    /// Gets the value of the `TCP_NODELAY` option on this socket.
+    pub fn nodelay(&self) -> io::Result<bool> {
+        api::ax_tcp_nodelay(&self.0)
+    }
+
+    /// Sets the interval of keep-alive packets sent on this connection when it
+    /// is idle, or disables them if `None` is given.
+    pub fn set_keepalive(&self, interval: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_keepalive(&self.0, interval)
+    }
+
+    /// Gets the interval of keep-alive packets, or `None` if disabled.
+    pub fn keepalive(&self) -> io::Result<Option<Duration>> {
+        api::ax_tcp_keepalive(&self.0)
+    }
+
+    /// Sets the value of the `SO_LINGER` option on this socket.
+    ///
+    /// This value controls how the socket is closed when data remains to be
+    /// sent. If `SO_LINGER` is set, closing the socket blocks until the data
+    /// is sent or the timeout elapses. A zero timeout resets the connection.
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_linger(&self.0, linger)
+    }
+
+    /// Gets the value of the `SO_LINGER` option on this socket.
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        api::ax_tcp_linger(&self.0)
+    }
+
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This value sets the time-to-live field that is used in every packet
+    /// sent from this socket.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        api::ax_tcp_set_ttl(&self.0, ttl)
+    }
+
+    /// Gets the value of the `IP_TTL` option for this socket.
+    pub fn ttl(&self) -> io::Result<u32> {
+        api::ax_tcp_ttl(&self.0)
+    }
+
+    /// Sets the read timeout to the timeout specified.
+    ///
+    /// If the value specified is `None`, then `read` calls will block
+    /// indefinitely. An error is returned if the zero `Duration` is passed.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_read_timeout(&self.0, timeout)
+    }
+
+    /// Returns the read timeout of this socket.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        api::ax_tcp_read_timeout(&self.0)
+    }
+
+    /// Sets the write timeout to the timeout specified.
+    ///
+    /// If the value specified is `None`, then `write` calls will block
+    /// indefinitely. An error is returned if the zero `Duration` is passed.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        api::ax_tcp_set_write_timeout(&self.0, timeout)
+    }
+
+    /// Returns the write timeout of this socket.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        api::ax_tcp_write_timeout(&self.0)
+    }
 }
 
 impl Read for TcpStream {
@@ -83,6 +162,8 @@ impl TcpListener {
             let addr = addr?;
             let backlog = 128;
             let socket = api::ax_tcp_socket();
+            // as `std` does on Unix
+            api::ax_tcp_set_reuse_address(&socket, true)?;
             api::ax_tcp_bind(&socket, *addr)?;
             api::ax_tcp_listen(&socket, backlog)?;
             Ok(TcpListener(socket))
@@ -102,4 +183,17 @@ impl TcpListener {
     pub fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
         api::ax_tcp_accept(&self.0).map(|(a, b)| (TcpStream(a), b))
     }
+
+    /// Sets the value for the `IP_TTL` option on this socket.
+    ///
+    /// This value sets the time-to-live field that is used in every packet
+    /// sent from this socket.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        api::ax_tcp_set_ttl(&self.0, ttl)
+    }
+
+    /// Gets the value of the `IP_TTL` option for this socket.
+    pub fn ttl(&self) -> io::Result<u32> {
+        api::ax_tcp_ttl(&self.0)
+    }
 }
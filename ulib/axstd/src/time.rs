@@ -1,6 +1,7 @@
 //! Temporal quantification.
 
 use arceos_api::time::AxTimeValue;
+use core::fmt;
 use core::ops::{Add, AddAssign, Sub, SubAssign};
 
 pub use core::time::Duration;
@@ -95,3 +96,107 @@ impl Sub<Instant> for Instant {
         self.duration_since(other)
     }
 }
+
+/// A measurement of the system clock, useful for talking to external entities
+/// like the file system or other processes.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SystemTime(Duration);
+
+/// An anchor in time which can be used to create new [`SystemTime`]
+/// instances or learn about where in time a [`SystemTime`] lies.
+///
+/// It is defined to be "1970-01-01 00:00:00 UTC".
+pub const UNIX_EPOCH: SystemTime = SystemTime(Duration::ZERO);
+
+/// An error returned from the `duration_since` and `elapsed` methods on
+/// [`SystemTime`], used to learn how far in the opposite direction a system
+/// time lies.
+#[derive(Clone, Debug)]
+pub struct SystemTimeError(Duration);
+
+impl SystemTimeError {
+    /// Returns the positive duration which represents how far forward the
+    /// second system time was from the first.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+}
+
+impl fmt::Display for SystemTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "second time provided was later than self")
+    }
+}
+
+impl SystemTime {
+    /// An anchor in time which can be used to create new [`SystemTime`]
+    /// instances or learn about where in time a [`SystemTime`] lies.
+    pub const UNIX_EPOCH: SystemTime = UNIX_EPOCH;
+
+    /// Returns the system time corresponding to "now".
+    pub fn now() -> SystemTime {
+        SystemTime(arceos_api::time::ax_wall_time())
+    }
+
+    /// Returns the amount of time elapsed from an earlier point in time.
+    ///
+    /// Returns an error if `earlier` is later than `self`, and the error
+    /// contains how far from `self` the time is.
+    pub fn duration_since(&self, earlier: SystemTime) -> Result<Duration, SystemTimeError> {
+        self.0
+            .checked_sub(earlier.0)
+            .ok_or_else(|| SystemTimeError(earlier.0 - self.0))
+    }
+
+    /// Returns the difference from this system time to the current system
+    /// time.
+    pub fn elapsed(&self) -> Result<Duration, SystemTimeError> {
+        SystemTime::now().duration_since(*self)
+    }
+
+    /// Returns `Some(t)` where `t` is the time `self + duration` if `t` can be
+    /// represented as `SystemTime`, `None` otherwise.
+    pub fn checked_add(&self, duration: Duration) -> Option<SystemTime> {
+        self.0.checked_add(duration).map(SystemTime)
+    }
+
+    /// Returns `Some(t)` where `t` is the time `self - duration` if `t` can be
+    /// represented as `SystemTime`, `None` otherwise.
+    pub fn checked_sub(&self, duration: Duration) -> Option<SystemTime> {
+        self.0.checked_sub(duration).map(SystemTime)
+    }
+}
+
+impl Add<Duration> for SystemTime {
+    type Output = SystemTime;
+
+    /// # Panics
+    ///
+    /// This function may panic if the resulting point in time cannot be represented by the
+    /// underlying data structure.
+    fn add(self, dur: Duration) -> SystemTime {
+        self.checked_add(dur)
+            .expect("overflow when adding duration to system time")
+    }
+}
+
+impl AddAssign<Duration> for SystemTime {
+    fn add_assign(&mut self, other: Duration) {
+        *self = *self + other;
+    }
+}
+
+impl Sub<Duration> for SystemTime {
+    type Output = SystemTime;
+
+    fn sub(self, dur: Duration) -> SystemTime {
+        self.checked_sub(dur)
+            .expect("overflow when subtracting duration from system time")
+    }
+}
+
+impl SubAssign<Duration> for SystemTime {
+    fn sub_assign(&mut self, other: Duration) {
+        *self = *self - other;
+    }
+}